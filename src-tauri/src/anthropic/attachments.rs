@@ -0,0 +1,137 @@
+use crate::anthropic::context_builder::TokenCounter;
+use crate::anthropic::models::{ContentBlock, MediaSource, MessageContent};
+use base64::Engine;
+use std::fs;
+use std::path::Path;
+
+/// Largest image the API takes
+const MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+/// Largest PDF the API takes
+const MAX_DOCUMENT_BYTES: u64 = 32 * 1024 * 1024;
+/// Tokens an image is counted as; larger images are scaled down to about
+/// this much by the API
+const IMAGE_TOKENS: usize = 1_600;
+/// Tokens a PDF page takes, its text and an image of it together, and
+/// roughly how many bytes of PDF a page is
+const PDF_PAGE_TOKENS: usize = 2_500;
+const PDF_PAGE_BYTES: usize = 50_000;
+
+/// Read a file into a block to send along with a message: PNG, JPEG, GIF
+/// and WebP files as images, PDFs and text files as documents
+pub fn load_attachment(path: &str) -> Result<ContentBlock, String> {
+    let file_path = Path::new(path);
+    let extension = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    let title = file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string());
+
+    let media_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "txt" | "md" | "log" | "csv" => "text/plain",
+        _ => return Err(format!("Unsupported attachment type: {}", path)),
+    };
+    let max_bytes = if media_type.starts_with("image/") {
+        MAX_IMAGE_BYTES
+    } else {
+        MAX_DOCUMENT_BYTES
+    };
+
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read attachment {}: {}", path, e))?
+        .len();
+    if size > max_bytes {
+        return Err(format!(
+            "Attachment {} is {} MB; the limit is {} MB",
+            path,
+            size / (1024 * 1024),
+            max_bytes / (1024 * 1024)
+        ));
+    }
+
+    if media_type == "text/plain" {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read attachment {}: {}", path, e))?;
+        return Ok(ContentBlock::Document {
+            source: MediaSource::Text {
+                media_type: media_type.to_string(),
+                data,
+            },
+            title,
+        });
+    }
+
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read attachment {}: {}", path, e))?;
+    let source = MediaSource::Base64 {
+        media_type: media_type.to_string(),
+        data: base64::engine::general_purpose::STANDARD.encode(bytes),
+    };
+    Ok(if media_type == "application/pdf" {
+        ContentBlock::Document { source, title }
+    } else {
+        ContentBlock::Image { source }
+    })
+}
+
+pub fn load_attachments(paths: &[String]) -> Result<Vec<ContentBlock>, String> {
+    paths.iter().map(|path| load_attachment(path)).collect()
+}
+
+/// Content of a message with text and attachments; attachments go first, as
+/// the model does best with images ahead of what's asked about them
+pub fn with_attachments(text: String, attachments: Vec<ContentBlock>) -> MessageContent {
+    if attachments.is_empty() {
+        return text.into();
+    }
+    let mut blocks = attachments;
+    blocks.push(ContentBlock::Text { text });
+    blocks.into()
+}
+
+/// Tokens an image or document is expected to take, which has little to do
+/// with the length of its encoded data; None for other blocks
+pub fn media_tokens(block: &ContentBlock, counter: &TokenCounter) -> Option<usize> {
+    match block {
+        ContentBlock::Image { .. } => Some(IMAGE_TOKENS),
+        ContentBlock::Document { source, .. } => Some(match source {
+            MediaSource::Text { data, .. } => counter.count(data),
+            MediaSource::Base64 { data, .. } => {
+                let bytes = data.len() / 4 * 3;
+                bytes.div_ceil(PDF_PAGE_BYTES).max(1) * PDF_PAGE_TOKENS
+            }
+            MediaSource::Url { .. } => PDF_PAGE_TOKENS,
+        }),
+        _ => None,
+    }
+}
+
+/// Media as a URL, data URLs included, for providers that take images and
+/// files by URL
+pub fn image_url(source: &MediaSource) -> String {
+    match source {
+        MediaSource::Base64 { media_type, data } => format!("data:{};base64,{}", media_type, data),
+        MediaSource::Text { media_type, data } => format!(
+            "data:{};base64,{}",
+            media_type,
+            base64::engine::general_purpose::STANDARD.encode(data)
+        ),
+        MediaSource::Url { url } => url.clone(),
+    }
+}
+
+/// A document as text, for providers that can't read documents: text files
+/// as they are, anything else as a note that it was left out
+pub fn document_text(source: &MediaSource, title: Option<&str>) -> String {
+    let title = title.unwrap_or("Attached document");
+    match source {
+        MediaSource::Text { data, .. } => format!("{}:\n{}", title, data),
+        _ => format!("[{} left out: this model can't read it]", title),
+    }
+}
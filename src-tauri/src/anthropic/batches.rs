@@ -0,0 +1,258 @@
+use crate::anthropic::error::ApiError;
+use crate::anthropic::http::{send_json, send_text};
+use crate::anthropic::models::{ContentBlock, Message, MessageRequest, MessageResponse};
+use crate::anthropic::AnthropicClient;
+use crate::models::code_index::{CodeSymbol, SymbolDoc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const BATCHES_URL: &str = "https://api.anthropic.com/v1/messages/batches";
+/// Symbols sent in one job; the API takes more, but results come back as
+/// one download
+pub const MAX_BATCH_SYMBOLS: usize = 10_000;
+/// Source sent per symbol; longer bodies are cut off
+const MAX_SYMBOL_SOURCE_CHARS: usize = 6_000;
+
+/// What a batch job writes for each symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchJobKind {
+    /// A doc comment's text, in the conventions of the symbol's language
+    Docstrings,
+    /// A one or two sentence plain summary
+    Summaries,
+}
+
+impl BatchJobKind {
+    fn system_prompt(self) -> &'static str {
+        match self {
+            BatchJobKind::Docstrings => "You write documentation comments for code. Reply with only the text of a doc comment for the given symbol: what it does, its parameters and return value where not obvious, and errors it can return. Follow the conventions of its language, but leave out comment markers such as /// or /** */.",
+            BatchJobKind::Summaries => "You summarize code for a search index. Reply with only one or two plain sentences saying what the given symbol does and what it is for.",
+        }
+    }
+
+    fn max_tokens(self) -> u32 {
+        match self {
+            BatchJobKind::Docstrings => 512,
+            BatchJobKind::Summaries => 150,
+        }
+    }
+}
+
+/// One request in a batch, identified by `custom_id` in the results
+#[derive(Debug, Serialize)]
+pub struct BatchRequest {
+    pub custom_id: String,
+    pub params: MessageRequest,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchRequestCounts {
+    pub processing: u32,
+    pub succeeded: u32,
+    pub errored: u32,
+    pub canceled: u32,
+    pub expired: u32,
+}
+
+/// A message batch as the API describes it
+#[derive(Debug, Deserialize)]
+pub struct Batch {
+    pub id: String,
+    /// `in_progress`, `canceling` or `ended`
+    pub processing_status: String,
+    pub request_counts: BatchRequestCounts,
+    /// Where results can be downloaded once the batch has ended
+    pub results_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResult {
+    custom_id: String,
+    result: BatchOutcome,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BatchOutcome {
+    Succeeded { message: MessageResponse },
+    Errored,
+    Canceled,
+    Expired,
+}
+
+/// A reply that came back from a batch, by request
+pub struct BatchReply {
+    pub custom_id: String,
+    pub message: MessageResponse,
+}
+
+impl AnthropicClient {
+    /// Submit requests to be processed in the background, at half the
+    /// usual price, within 24 hours
+    pub async fn create_batch(&self, requests: &[BatchRequest]) -> Result<Batch, ApiError> {
+        let body = serde_json::json!({ "requests": requests });
+        send_json(|| self.post(BATCHES_URL, &body)).await
+    }
+
+    pub async fn get_batch(&self, batch_id: &str) -> Result<Batch, ApiError> {
+        let url = format!("{}/{}", BATCHES_URL, batch_id);
+        send_json(|| self.get(&url)).await
+    }
+
+    /// Stop a batch; requests already processed keep their results
+    pub async fn cancel_batch(&self, batch_id: &str) -> Result<Batch, ApiError> {
+        let url = format!("{}/{}/cancel", BATCHES_URL, batch_id);
+        send_json(|| self.post(&url, &serde_json::json!({}))).await
+    }
+
+    /// Replies of an ended batch's succeeded requests. Failed requests are
+    /// left out; the batch's counts say how many.
+    pub async fn batch_results(&self, batch: &Batch) -> Result<Vec<BatchReply>, ApiError> {
+        let url = batch
+            .results_url
+            .as_deref()
+            .ok_or_else(|| ApiError::InvalidResponse("batch has no results yet".to_string()))?;
+        let body = send_text(|| self.get(url)).await?;
+
+        let mut replies = Vec::new();
+        for line in body.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<BatchResult>(line) {
+                Ok(BatchResult {
+                    custom_id,
+                    result: BatchOutcome::Succeeded { message },
+                }) => replies.push(BatchReply { custom_id, message }),
+                Ok(_) => {}
+                Err(e) => eprintln!("Skipping unreadable batch result: {}", e),
+            }
+        }
+        Ok(replies)
+    }
+}
+
+/// A symbol a batch request was made for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSymbol {
+    pub file_path: String,
+    pub name: String,
+    pub start_line: usize,
+}
+
+/// A batch job for a project, kept so it can be polled after a restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchJob {
+    /// The API's batch ID
+    pub id: String,
+    pub project_path: String,
+    pub kind: BatchJobKind,
+    pub model: String,
+    pub created_at: u64,
+    /// `in_progress`, `canceling` or `ended`
+    pub status: String,
+    pub counts: BatchRequestCounts,
+    /// Request `sym-{i}` is for `symbols[i]`
+    pub symbols: Vec<BatchSymbol>,
+    /// When the results were written into the index
+    pub ingested_at: Option<u64>,
+    /// Symbols given a doc from the results
+    pub ingested: usize,
+}
+
+impl BatchJob {
+    /// Requests for symbols, each given with the source of its file, and
+    /// the symbols in request order
+    pub fn requests(
+        kind: BatchJobKind,
+        model: &str,
+        symbols: &[(&CodeSymbol, &str)],
+    ) -> (Vec<BatchRequest>, Vec<BatchSymbol>) {
+        symbols
+            .iter()
+            .enumerate()
+            .map(|(i, (symbol, source))| {
+                let body: String = source
+                    .lines()
+                    .skip(symbol.start_line.saturating_sub(1))
+                    .take((symbol.end_line + 1).saturating_sub(symbol.start_line.max(1)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+                    .chars()
+                    .take(MAX_SYMBOL_SOURCE_CHARS)
+                    .collect();
+                let kind_name = format!("{:?}", symbol.kind).to_lowercase();
+                let prompt = format!(
+                    "{} `{}` in {}:\n\n{}",
+                    kind_name,
+                    symbol.name,
+                    symbol.file_path,
+                    body
+                );
+                let request = BatchRequest {
+                    custom_id: format!("sym-{}", i),
+                    params: MessageRequest {
+                        model: model.to_string(),
+                        max_tokens: kind.max_tokens(),
+                        messages: vec![Message {
+                            role: "user".to_string(),
+                            content: prompt.into(),
+                        }],
+                        system: Some(kind.system_prompt().to_string()),
+                        temperature: Some(0.3),
+                        top_p: None,
+                        tools: Vec::new(),
+                    },
+                };
+                let target = BatchSymbol {
+                    file_path: symbol.file_path.clone(),
+                    name: symbol.name.clone(),
+                    start_line: symbol.start_line,
+                };
+                (request, target)
+            })
+            .unzip()
+    }
+
+    /// Docs for the symbols of the replies that came back
+    pub fn docs(&self, replies: &[BatchReply]) -> Vec<SymbolDoc> {
+        replies
+            .iter()
+            .filter_map(|reply| {
+                let i: usize = reply.custom_id.strip_prefix("sym-")?.parse().ok()?;
+                let symbol = self.symbols.get(i)?;
+                let doc = reply.message.content.iter().find_map(|block| match block {
+                    ContentBlock::Text { text } if !text.trim().is_empty() => Some(text.clone()),
+                    _ => None,
+                })?;
+                Some(SymbolDoc {
+                    file_path: symbol.file_path.clone(),
+                    name: symbol.name.clone(),
+                    start_line: symbol.start_line,
+                    doc,
+                })
+            })
+            .collect()
+    }
+
+    /// A project's jobs, newest first
+    pub fn load_all(path: &Path) -> Result<Vec<BatchJob>, String> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let json =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read batch jobs: {}", e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse batch jobs: {}", e))
+    }
+
+    pub fn save_all(path: &Path, jobs: &[BatchJob]) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(jobs)
+            .map_err(|e| format!("Failed to serialize batch jobs: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write batch jobs: {}", e))
+    }
+
+    /// Take in the batch's latest status and counts
+    pub fn update(&mut self, batch: &Batch) {
+        self.status = batch.processing_status.clone();
+        self.counts = batch.request_counts.clone();
+    }
+}
@@ -0,0 +1,211 @@
+use crate::anthropic::catalog::DEFAULT_BEDROCK_MODEL;
+use crate::anthropic::error::ApiError;
+use crate::anthropic::http::{http_client, send_json};
+use crate::anthropic::models::{Message, MessageRequest, MessageResponse, ToolDefinition};
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::provider::{LlmProvider, Reply};
+use crate::anthropic::sigv4::{sign_post, uri_encode, AwsCredentials};
+use crate::anthropic::response_cache::ResponseCache;
+use crate::anthropic::usage::UsageRecorder;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+/// Messages API version Bedrock expects in the request body
+const BEDROCK_ANTHROPIC_VERSION: &str = "bedrock-2023-05-31";
+pub const DEFAULT_BEDROCK_REGION: &str = "us-east-1";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BedrockConfig {
+    pub region: String,
+    /// Bedrock model or inference profile ID, e.g.
+    /// `us.anthropic.claude-sonnet-4-5-20250929-v1:0`
+    pub model: String,
+    /// Empty to use `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN` from the environment
+    pub access_key_id: String,
+    /// Kept in the OS keyring, not the settings file
+    pub secret_access_key: String,
+}
+
+impl Default for BedrockConfig {
+    fn default() -> Self {
+        Self {
+            region: DEFAULT_BEDROCK_REGION.to_string(),
+            model: DEFAULT_BEDROCK_MODEL.to_string(),
+            access_key_id: String::new(),
+            secret_access_key: String::new(),
+        }
+    }
+}
+
+impl BedrockConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let region = self.region.trim();
+        if region.is_empty()
+            || !region
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(format!("Invalid AWS region: {}", self.region));
+        }
+        if self.model.trim().is_empty() {
+            return Err("Bedrock model must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Credentials from settings, or else from the environment
+    fn credentials(&self) -> Result<AwsCredentials, String> {
+        if !self.access_key_id.is_empty() {
+            if self.secret_access_key.is_empty() {
+                return Err("No AWS secret access key set".to_string());
+            }
+            return Ok(AwsCredentials {
+                access_key_id: self.access_key_id.clone(),
+                secret_access_key: self.secret_access_key.clone(),
+                session_token: None,
+            });
+        }
+
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        match (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key_id), Some(secret_access_key)) => Ok(AwsCredentials {
+                access_key_id,
+                secret_access_key,
+                session_token: var("AWS_SESSION_TOKEN"),
+            }),
+            _ => Err("No AWS credentials set in settings or the environment".to_string()),
+        }
+    }
+}
+
+/// Client for Anthropic models on AWS Bedrock, for accounts that can only
+/// reach AWS. Requests are the Messages API's, signed with SigV4.
+pub struct BedrockClient {
+    client: Client,
+    config: BedrockConfig,
+    credentials: AwsCredentials,
+    prompts: SystemPrompts,
+    usage: Option<UsageRecorder>,
+    cache: Option<ResponseCache>,
+}
+
+impl BedrockClient {
+    pub fn new(config: BedrockConfig) -> Result<Self, String> {
+        Ok(Self {
+            client: http_client()?,
+            credentials: config.credentials()?,
+            config,
+            prompts: SystemPrompts::default(),
+            usage: None,
+            cache: None,
+        })
+    }
+
+    /// Use customized system prompts in place of the built-in ones
+    pub fn with_system_prompts(mut self, prompts: SystemPrompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Record each request's token usage with `recorder`
+    pub fn with_usage_recorder(mut self, recorder: UsageRecorder) -> Self {
+        self.usage = Some(recorder);
+        self
+    }
+
+    /// Reuse replies to repeated requests from `cache`; None always asks
+    /// the model
+    pub fn with_response_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+}
+
+#[async_trait]
+impl LlmProvider for BedrockClient {
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    fn system_prompts(&self) -> &SystemPrompts {
+        &self.prompts
+    }
+
+    fn usage_recorder(&self) -> Option<&UsageRecorder> {
+        self.usage.as_ref()
+    }
+
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    async fn respond(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<Reply, ApiError> {
+        let request = MessageRequest {
+            model: self.config.model.clone(),
+            max_tokens,
+            messages: messages.to_vec(),
+            system: system.map(str::to_string),
+            temperature,
+            top_p: None,
+            tools: tools.to_vec(),
+        };
+        // The model goes in the URL; the body names the API version instead
+        let mut body =
+            serde_json::to_value(&request).map_err(|e| ApiError::InvalidResponse(e.to_string()))?;
+        if let Some(body) = body.as_object_mut() {
+            body.remove("model");
+            body.insert(
+                "anthropic_version".to_string(),
+                BEDROCK_ANTHROPIC_VERSION.into(),
+            );
+        }
+        let body = body.to_string().into_bytes();
+
+        let region = self.config.region.trim();
+        let host = format!("bedrock-runtime.{}.amazonaws.com", region);
+        let path = format!("/model/{}/invoke", uri_encode(self.config.model.trim()));
+        let url = format!("https://{}{}", host, path);
+
+        // Signed per attempt, since the signature covers the time
+        let response: MessageResponse = send_json(|| {
+            let headers = sign_post(
+                &self.credentials,
+                region,
+                "bedrock",
+                &host,
+                &path,
+                "application/json",
+                &body,
+            );
+            headers
+                .into_iter()
+                .fold(self.client.post(&url), |request, (name, value)| {
+                    request.header(name, value)
+                })
+                .body(body.clone())
+        })
+        .await?;
+
+        Ok(Reply {
+            wants_tools: response.stop_reason.as_deref() == Some("tool_use"),
+            content: response.content,
+            usage: response.usage,
+        })
+    }
+
+    async fn count_tokens(&self, _text: &str, _model: &str) -> Result<usize, ApiError> {
+        Err(ApiError::Unsupported(
+            "Token counting isn't available for Bedrock".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,214 @@
+use crate::anthropic::http::{http_client, send_json};
+use crate::anthropic::ollama::OllamaClient;
+use crate::anthropic::openai::OpenAiCompatibleConfig;
+use crate::anthropic::provider::LlmProviderConfig;
+use serde::{Deserialize, Serialize};
+
+/// Anthropic model used when settings don't pick another
+pub const DEFAULT_MODEL: &str = "claude-sonnet-4-5-20250929";
+/// Bedrock's ID for the default model
+pub const DEFAULT_BEDROCK_MODEL: &str = "anthropic.claude-sonnet-4-5-20250929-v1:0";
+
+/// What a model is a good fit for in the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelUse {
+    /// Intent analysis and query rewriting: short, structured, frequent
+    Intent,
+    /// Prompt enhancement and pattern extraction
+    Enhancement,
+    /// Conversations with tool use over the index
+    Chat,
+}
+
+/// A model a provider offers, with what's known about it. Metadata is None
+/// for models the catalog doesn't know, such as ones listed by a server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelInfo {
+    /// ID to put in settings
+    pub id: String,
+    pub name: String,
+    /// Tokens of prompt and reply the model takes in total
+    pub context_window: Option<u32>,
+    pub max_output_tokens: Option<u32>,
+    /// USD per million input tokens; 0 for local models
+    pub input_price_per_mtok: Option<f64>,
+    /// USD per million output tokens; 0 for local models
+    pub output_price_per_mtok: Option<f64>,
+    pub recommended_for: Vec<ModelUse>,
+}
+
+/// Anthropic models and their Bedrock IDs, with list prices
+struct KnownModel {
+    id: &'static str,
+    bedrock_id: &'static str,
+    name: &'static str,
+    context_window: u32,
+    max_output_tokens: u32,
+    input_price: f64,
+    output_price: f64,
+    recommended_for: &'static [ModelUse],
+}
+
+const ANTHROPIC_MODELS: &[KnownModel] = &[
+    KnownModel {
+        id: DEFAULT_MODEL,
+        bedrock_id: DEFAULT_BEDROCK_MODEL,
+        name: "Claude Sonnet 4.5",
+        context_window: 200_000,
+        max_output_tokens: 64_000,
+        input_price: 3.0,
+        output_price: 15.0,
+        recommended_for: &[ModelUse::Enhancement, ModelUse::Chat],
+    },
+    KnownModel {
+        id: "claude-haiku-4-5-20251001",
+        bedrock_id: "anthropic.claude-haiku-4-5-20251001-v1:0",
+        name: "Claude Haiku 4.5",
+        context_window: 200_000,
+        max_output_tokens: 64_000,
+        input_price: 1.0,
+        output_price: 5.0,
+        recommended_for: &[ModelUse::Intent],
+    },
+    KnownModel {
+        id: "claude-opus-4-1-20250805",
+        bedrock_id: "anthropic.claude-opus-4-1-20250805-v1:0",
+        name: "Claude Opus 4.1",
+        context_window: 200_000,
+        max_output_tokens: 32_000,
+        input_price: 15.0,
+        output_price: 75.0,
+        recommended_for: &[ModelUse::Chat],
+    },
+];
+
+impl KnownModel {
+    fn info(&self, id: &str) -> ModelInfo {
+        ModelInfo {
+            id: id.to_string(),
+            name: self.name.to_string(),
+            context_window: Some(self.context_window),
+            max_output_tokens: Some(self.max_output_tokens),
+            input_price_per_mtok: Some(self.input_price),
+            output_price_per_mtok: Some(self.output_price),
+            recommended_for: self.recommended_for.to_vec(),
+        }
+    }
+}
+
+/// Catalog entry for a model by its Anthropic or Bedrock ID. Bedrock's
+/// cross-region inference profiles (`us.anthropic...`) match too.
+pub fn known_model(id: &str) -> Option<ModelInfo> {
+    ANTHROPIC_MODELS.iter().find_map(|model| {
+        let matches = model.id == id
+            || model.bedrock_id == id
+            || id
+                .split_once('.')
+                .is_some_and(|(_, rest)| rest == model.bedrock_id);
+        matches.then(|| model.info(id))
+    })
+}
+
+/// USD per million input and output tokens for the configured model, where
+/// known. `llm_model` is the Anthropic model from settings.
+pub fn model_prices(provider: &LlmProviderConfig, llm_model: &str) -> Option<(f64, f64)> {
+    let model = match provider {
+        LlmProviderConfig::Anthropic => llm_model,
+        LlmProviderConfig::Bedrock(config) => config.model.as_str(),
+        LlmProviderConfig::OpenAiCompatible(config) => config.model.as_str(),
+        LlmProviderConfig::Ollama(_) => return Some((0.0, 0.0)),
+        LlmProviderConfig::AzureOpenAi(_) => return None,
+    };
+    let info = known_model(model)?;
+    info.input_price_per_mtok.zip(info.output_price_per_mtok)
+}
+
+fn unknown_model(id: &str) -> ModelInfo {
+    ModelInfo {
+        id: id.to_string(),
+        name: id.to_string(),
+        context_window: None,
+        max_output_tokens: None,
+        input_price_per_mtok: None,
+        output_price_per_mtok: None,
+        recommended_for: Vec::new(),
+    }
+}
+
+/// Models the configured provider offers. `llm_model` is the Anthropic
+/// model from settings, listed even when the catalog doesn't know it.
+pub async fn available_models(
+    provider: &LlmProviderConfig,
+    llm_model: &str,
+) -> Result<Vec<ModelInfo>, String> {
+    let (mut models, configured) = match provider {
+        LlmProviderConfig::Anthropic => (
+            ANTHROPIC_MODELS.iter().map(|m| m.info(m.id)).collect(),
+            llm_model,
+        ),
+        LlmProviderConfig::Bedrock(config) => (
+            ANTHROPIC_MODELS
+                .iter()
+                .map(|m| m.info(m.bedrock_id))
+                .collect(),
+            config.model.as_str(),
+        ),
+        LlmProviderConfig::Ollama(config) => {
+            let models = OllamaClient::new(config.clone())?
+                .list_models()
+                .await?
+                .into_iter()
+                .map(|model| ModelInfo {
+                    input_price_per_mtok: Some(0.0),
+                    output_price_per_mtok: Some(0.0),
+                    ..unknown_model(&model.name)
+                })
+                .collect();
+            (models, config.model.as_str())
+        }
+        LlmProviderConfig::OpenAiCompatible(config) => (
+            list_openai_models(config)
+                .await?
+                .iter()
+                .map(|id| known_model(id).unwrap_or_else(|| unknown_model(id)))
+                .collect(),
+            config.model.as_str(),
+        ),
+        // Deployments are listed by Azure's management API, not the endpoint
+        LlmProviderConfig::AzureOpenAi(config) => (Vec::new(), config.deployment.as_str()),
+    };
+
+    if !models.iter().any(|m: &ModelInfo| m.id == configured) {
+        models.push(known_model(configured).unwrap_or_else(|| unknown_model(configured)));
+    }
+    Ok(models)
+}
+
+#[derive(Deserialize)]
+struct ModelList {
+    data: Vec<ListedModel>,
+}
+
+#[derive(Deserialize)]
+struct ListedModel {
+    id: String,
+}
+
+async fn list_openai_models(config: &OpenAiCompatibleConfig) -> Result<Vec<String>, String> {
+    let client = http_client()?;
+    let url = format!("{}/models", config.base_url.trim_end_matches('/'));
+    let list: ModelList = send_json(|| {
+        let request = client.get(&url);
+        if config.api_key.is_empty() {
+            request
+        } else {
+            request.bearer_auth(&config.api_key)
+        }
+    })
+    .await?;
+
+    let mut ids: Vec<String> = list.data.into_iter().map(|model| model.id).collect();
+    ids.sort();
+    Ok(ids)
+}
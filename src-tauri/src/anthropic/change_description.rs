@@ -0,0 +1,139 @@
+use crate::indexing::git_changes::{DiffTarget, GitDiff};
+use crate::models::code_index::{CodeSymbol, CodebaseIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// Diff sent to the model; longer diffs are cut off
+const MAX_DIFF_CHARS: usize = 60_000;
+/// Symbols touched by a change that are described to the model
+const MAX_CHANGED_SYMBOLS: usize = 40;
+
+/// What to write about a change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeDescriptionKind {
+    /// For what the next commit would hold
+    CommitMessage,
+    /// For everything on the branch
+    PullRequest,
+}
+
+impl ChangeDescriptionKind {
+    pub fn diff_target(self) -> DiffTarget {
+        match self {
+            ChangeDescriptionKind::CommitMessage => DiffTarget::Commit,
+            ChangeDescriptionKind::PullRequest => DiffTarget::Branch,
+        }
+    }
+
+    pub fn system_prompt(self) -> &'static str {
+        match self {
+            ChangeDescriptionKind::CommitMessage => "You write git commit messages. Reply with only the message: a subject line of at most 72 characters in the imperative mood, then a blank line and a short body saying what changed and why, wrapped at 72 characters. Leave the body out for trivial changes. Describe the change itself, not the files it touches.",
+            ChangeDescriptionKind::PullRequest => "You write pull request descriptions. Reply with only the description in markdown: a title line, one or two sentences on what the change does and why, the notable changes as a short list, and anything reviewers should check. Describe behavior, not file by file.",
+        }
+    }
+
+    pub fn max_tokens(self) -> u32 {
+        match self {
+            ChangeDescriptionKind::CommitMessage => 512,
+            ChangeDescriptionKind::PullRequest => 1536,
+        }
+    }
+}
+
+/// A commit message or pull request description, with what it was written
+/// from
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangeDescription {
+    pub kind: ChangeDescriptionKind,
+    pub text: String,
+    /// Files the diff changes
+    pub files: Vec<String>,
+    /// Indexed symbols the diff touches
+    pub symbols: Vec<CodeSymbol>,
+    /// Whether the diff was too long to send whole
+    pub diff_truncated: bool,
+}
+
+/// Symbols whose lines the diff changes: the innermost symbol around each
+/// hunk and those inside it
+pub fn changed_symbols(index: &CodebaseIndex, diff: &GitDiff) -> Vec<CodeSymbol> {
+    let mut seen = HashSet::new();
+    let mut symbols = Vec::new();
+    for (path, ranges) in &diff.files {
+        for &(start, end) in ranges {
+            let Some(selection) = index.symbols_in_range(path, start, end) else {
+                continue;
+            };
+            for symbol in selection
+                .enclosing
+                .last()
+                .into_iter()
+                .chain(&selection.contained)
+            {
+                if seen.insert((symbol.file_path.clone(), symbol.start_line)) {
+                    symbols.push(symbol.clone());
+                }
+            }
+        }
+    }
+    symbols.truncate(MAX_CHANGED_SYMBOLS);
+    symbols
+}
+
+/// The diff and the symbols it touches as a prompt, and whether the diff
+/// had to be cut off
+pub fn change_prompt(
+    kind: ChangeDescriptionKind,
+    diff: &GitDiff,
+    symbols: &[CodeSymbol],
+) -> (String, bool) {
+    let what = match kind {
+        ChangeDescriptionKind::CommitMessage => "a commit message",
+        ChangeDescriptionKind::PullRequest => "a pull request description",
+    };
+    let mut prompt = format!("Write {} for this change.\n", what);
+
+    if !symbols.is_empty() {
+        let _ = writeln!(prompt, "\nCode it touches:");
+        for symbol in symbols {
+            let signature = symbol
+                .signature
+                .as_deref()
+                .and_then(|signature| signature.lines().next())
+                .unwrap_or(&symbol.name);
+            let kind = format!("{:?}", symbol.kind).to_lowercase();
+            let _ = write!(
+                prompt,
+                "- {} `{}` in {}",
+                kind,
+                signature.trim(),
+                symbol.file_path
+            );
+            match symbol
+                .doc_comment
+                .as_deref()
+                .and_then(|doc| doc.lines().map(str::trim).find(|line| !line.is_empty()))
+            {
+                Some(doc) => {
+                    let _ = writeln!(prompt, ": {}", doc);
+                }
+                None => prompt.push('\n'),
+            }
+        }
+    }
+
+    let truncated = diff.patch.len() > MAX_DIFF_CHARS;
+    let patch = if truncated {
+        let mut end = MAX_DIFF_CHARS;
+        while !diff.patch.is_char_boundary(end) {
+            end -= 1;
+        }
+        format!("{}\n[diff cut off]", &diff.patch[..end])
+    } else {
+        diff.patch.clone()
+    };
+    let _ = write!(prompt, "\nThe diff:\n```diff\n{}\n```", patch);
+    (prompt, truncated)
+}
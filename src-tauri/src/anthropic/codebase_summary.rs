@@ -0,0 +1,234 @@
+use crate::models::code_index::{CodeSymbol, CodebaseIndex, SymbolKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Outlines are cut off here, leaving room for the reply
+const MAX_OUTLINE_CHARS: usize = 60_000;
+/// Directories deeper than this are counted with their ancestor
+const MAX_OUTLINE_DEPTH: usize = 4;
+/// Files described in detail, those imported by the most others
+const KEY_MODULES: usize = 40;
+/// Symbols listed per key module, the most called first
+const SYMBOLS_PER_MODULE: usize = 8;
+/// File stems shared by more files than this are too common to tell which
+/// one an import means
+const MAX_FILES_PER_STEM: usize = 2;
+
+/// An LLM-written overview of a project, kept in the project's cache until
+/// the project is indexed again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodebaseSummary {
+    pub project_path: String,
+    /// Markdown, from the project as a whole down to its key modules
+    pub summary: String,
+    pub model: String,
+    pub generated_at: u64,
+    /// `indexed_at` of the index summarized
+    pub indexed_at: u64,
+    pub files: usize,
+}
+
+impl CodebaseSummary {
+    pub fn load(path: &Path) -> Result<Option<CodebaseSummary>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read codebase summary: {}", e))?;
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse codebase summary: {}", e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize codebase summary: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write codebase summary: {}", e))
+    }
+
+    /// The summary as a section to add to a system prompt
+    pub fn preamble(&self) -> String {
+        format!("Overview of the codebase:\n{}", self.summary)
+    }
+}
+
+/// What the model is given to summarize a project from: its directories,
+/// the modules most imported by others with their most called symbols, and
+/// which modules import which
+pub fn codebase_outline(index: &CodebaseIndex) -> String {
+    let relative = |path: &str| {
+        Path::new(path)
+            .strip_prefix(&index.root_path)
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string())
+    };
+
+    let mut dirs: BTreeMap<String, (usize, BTreeSet<&str>)> = BTreeMap::new();
+    for file in index.files.values() {
+        let path = relative(&file.path);
+        let components: Vec<&str> = Path::new(&path)
+            .parent()
+            .map(|dir| dir.iter().filter_map(|c| c.to_str()).collect())
+            .unwrap_or_default();
+        let dir = components
+            .iter()
+            .take(MAX_OUTLINE_DEPTH)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("/");
+        let entry = dirs.entry(dir).or_default();
+        entry.0 += 1;
+        entry.1.insert(&file.language);
+    }
+
+    let imports = import_graph(index);
+    let mut imported_by: HashMap<&str, usize> = HashMap::new();
+    for targets in imports.values() {
+        for target in targets {
+            *imported_by.entry(target).or_default() += 1;
+        }
+    }
+    let mut key_modules: Vec<&str> = index.files.keys().map(|path| path.as_str()).collect();
+    key_modules.sort_by(|a, b| {
+        let count = |path: &str| imported_by.get(path).copied().unwrap_or(0);
+        let symbols = |path: &str| index.files[path].symbols.len();
+        count(b)
+            .cmp(&count(a))
+            .then_with(|| symbols(b).cmp(&symbols(a)))
+            .then_with(|| a.cmp(b))
+    });
+    key_modules.truncate(KEY_MODULES);
+
+    let mut outline = String::new();
+    let mut languages: Vec<_> = index.language_stats.iter().collect();
+    languages.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let _ = writeln!(outline, "Files: {}", index.files.len());
+    let languages: Vec<String> = languages
+        .iter()
+        .map(|(language, count)| format!("{} ({})", language, count))
+        .collect();
+    let _ = writeln!(outline, "Languages: {}\n", languages.join(", "));
+
+    let _ = writeln!(outline, "Directories:");
+    for (dir, (count, languages)) in &dirs {
+        let name = if dir.is_empty() { "." } else { dir.as_str() };
+        let languages: Vec<&str> = languages.iter().copied().collect();
+        let _ = writeln!(
+            outline,
+            "- {}/ ({} files: {})",
+            name,
+            count,
+            languages.join(", ")
+        );
+    }
+
+    let _ = writeln!(outline, "\nKey modules:");
+    for path in &key_modules {
+        let file = &index.files[*path];
+        let _ = writeln!(
+            outline,
+            "\n{} ({}, imported by {})",
+            relative(path),
+            file.language,
+            imported_by.get(path).copied().unwrap_or(0)
+        );
+        for symbol in top_symbols(index, &file.symbols) {
+            let description = symbol
+                .signature
+                .as_deref()
+                .unwrap_or(&symbol.name)
+                .lines()
+                .next()
+                .unwrap_or_default();
+            let doc = symbol
+                .doc_comment
+                .as_deref()
+                .and_then(|doc| doc.lines().map(str::trim).find(|line| !line.is_empty()));
+            let _ = match doc {
+                Some(doc) => writeln!(outline, "  - {} — {}", description.trim(), doc),
+                None => writeln!(outline, "  - {}", description.trim()),
+            };
+        }
+        if let Some(targets) = imports.get(path).filter(|targets| !targets.is_empty()) {
+            let targets: Vec<String> = targets.iter().map(|target| relative(target)).collect();
+            let _ = writeln!(outline, "  imports: {}", targets.join(", "));
+        }
+    }
+
+    if outline.len() > MAX_OUTLINE_CHARS {
+        let mut end = MAX_OUTLINE_CHARS;
+        while !outline.is_char_boundary(end) {
+            end -= 1;
+        }
+        outline.truncate(end);
+        outline.push_str("\n[outline cut off]");
+    }
+    outline
+}
+
+/// Which indexed files each file imports. Imports are matched to files by
+/// module name, so only stems naming few files are followed.
+fn import_graph(index: &CodebaseIndex) -> HashMap<&str, BTreeSet<&str>> {
+    let mut by_stem: HashMap<String, Vec<&str>> = HashMap::new();
+    for path in index.files.keys() {
+        let file_path = Path::new(path);
+        let stem = match file_path.file_stem().and_then(|stem| stem.to_str()) {
+            Some("mod" | "index" | "__init__" | "lib") => file_path
+                .parent()
+                .and_then(|dir| dir.file_name())
+                .and_then(|name| name.to_str()),
+            stem => stem,
+        };
+        if let Some(stem) = stem.filter(|stem| stem.len() > 2) {
+            by_stem.entry(stem.to_lowercase()).or_default().push(path);
+        }
+    }
+
+    let mut graph = HashMap::new();
+    for (path, file) in &index.files {
+        let targets: BTreeSet<&str> = file
+            .imports
+            .iter()
+            .flat_map(|import| {
+                import
+                    .split(|c: char| !c.is_alphanumeric() && c != '_')
+                    .map(|word| word.to_lowercase())
+            })
+            .filter_map(|word| by_stem.get(&word))
+            .filter(|files| files.len() <= MAX_FILES_PER_STEM)
+            .flatten()
+            .copied()
+            .filter(|target| *target != path.as_str())
+            .collect();
+        graph.insert(path.as_str(), targets);
+    }
+    graph
+}
+
+/// A file's types and functions, those called from the most places first
+fn top_symbols<'a>(index: &CodebaseIndex, symbols: &'a [CodeSymbol]) -> Vec<&'a CodeSymbol> {
+    let mut top: Vec<&CodeSymbol> = symbols
+        .iter()
+        .filter(|symbol| {
+            !matches!(
+                symbol.kind,
+                SymbolKind::Import | SymbolKind::Export | SymbolKind::Variable
+            )
+        })
+        .collect();
+    let callers = |symbol: &CodeSymbol| index.callers.get(&symbol.name).map_or(0, |c| c.len());
+    top.sort_by(|a, b| {
+        callers(b)
+            .cmp(&callers(a))
+            .then_with(|| a.start_line.cmp(&b.start_line))
+    });
+    top.truncate(SYMBOLS_PER_MODULE);
+    top
+}
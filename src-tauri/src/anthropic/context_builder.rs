@@ -0,0 +1,157 @@
+use crate::anthropic::models::{ContentBlock, PromptIntent};
+use crate::anthropic::provider::LlmProvider;
+use crate::models::code_index::CodeChunk;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Tokens of code added to a prompt unless the caller sets a budget
+pub const DEFAULT_CONTEXT_TOKENS: usize = 8000;
+
+/// A prompt enriched with code from the indexed project
+#[derive(Debug, Clone, Serialize)]
+pub struct EnhancedPrompt {
+    pub original: String,
+    pub prompt: String,
+    pub intent: PromptIntent,
+    /// Code included in the prompt, most relevant first
+    pub chunks: Vec<CodeChunk>,
+    /// Tokens of the included code for the model the prompt is for
+    pub context_tokens: usize,
+    /// Whether tokens were guessed from characters because the model's
+    /// tokenizer couldn't be reached
+    pub tokens_estimated: bool,
+    /// Images and documents to send with the prompt, ahead of its text
+    pub attachments: Vec<ContentBlock>,
+    /// Tokens the attachments are expected to take, left out of the budget
+    /// for code
+    pub attachment_tokens: usize,
+}
+
+/// Measures text in tokens of a model
+#[derive(Debug, Clone, Copy)]
+pub struct TokenCounter {
+    tokens_per_char: f64,
+    estimated: bool,
+}
+
+impl TokenCounter {
+    /// About four characters per token of code, for when the model's
+    /// tokenizer can't be reached
+    pub fn estimate() -> Self {
+        Self {
+            tokens_per_char: 0.25,
+            estimated: true,
+        }
+    }
+
+    /// Counter scaled to match `tokens`, the model's count for `sample`
+    pub fn calibrated(sample: &str, tokens: usize) -> Self {
+        let chars = sample.chars().count();
+        if chars == 0 || tokens == 0 {
+            return Self::estimate();
+        }
+        Self {
+            tokens_per_char: tokens as f64 / chars as f64,
+            estimated: false,
+        }
+    }
+
+    /// Counter for the client's model, calibrated by having the API count
+    /// `chunks` in one request rather than one per chunk
+    pub async fn for_chunks(client: &dyn LlmProvider, chunks: &[CodeChunk]) -> Self {
+        if chunks.is_empty() {
+            return Self::estimate();
+        }
+        let sample: String = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        match client.count_tokens(&sample, client.model()).await {
+            Ok(tokens) => Self::calibrated(&sample, tokens),
+            Err(e) => {
+                eprintln!("Token counting failed, estimating instead: {}", e);
+                Self::estimate()
+            }
+        }
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        (text.chars().count() as f64 * self.tokens_per_char).ceil() as usize
+    }
+
+    pub fn is_estimate(&self) -> bool {
+        self.estimated
+    }
+}
+
+/// The most relevant chunks that fit in `max_tokens`, with their total
+/// tokens. A chunk overlapping a more relevant one of the same file is
+/// dropped; the most relevant chunk is kept even if it alone is over budget.
+pub fn select_context(
+    mut chunks: Vec<CodeChunk>,
+    max_tokens: usize,
+    counter: &TokenCounter,
+) -> (Vec<CodeChunk>, usize) {
+    chunks.sort_by(|a, b| {
+        b.relevance_score
+            .partial_cmp(&a.relevance_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut selected: Vec<CodeChunk> = Vec::new();
+    let mut used = 0;
+    for chunk in chunks {
+        let overlaps = selected.iter().any(|kept| {
+            kept.file_path == chunk.file_path
+                && kept.start_line <= chunk.end_line
+                && chunk.start_line <= kept.end_line
+        });
+        if overlaps {
+            continue;
+        }
+
+        // A smaller chunk further down may still fit
+        let tokens = counter.count(&chunk.content);
+        if used + tokens > max_tokens && !selected.is_empty() {
+            continue;
+        }
+        used += tokens;
+        selected.push(chunk);
+    }
+    (selected, used)
+}
+
+/// The prompt followed by its analyzed intent and the selected code
+pub fn build_prompt(prompt: &str, intent: &PromptIntent, chunks: &[CodeChunk]) -> String {
+    let mut out = format!("<task>\n{}\n</task>\n\n", prompt.trim());
+
+    let _ = writeln!(out, "<intent action=\"{}\" scope=\"{}\">", intent.action.as_str(), intent.scope.as_str());
+    if !intent.keywords.is_empty() {
+        let _ = writeln!(out, "Keywords: {}", intent.keywords.join(", "));
+    }
+    if !intent.entities.is_empty() {
+        let _ = writeln!(out, "Entities: {}", intent.entities.join(", "));
+    }
+    out.push_str("</intent>\n\n<codebase_context>\n");
+
+    if chunks.is_empty() {
+        out.push_str("No relevant code was found in the indexed codebase.\n");
+    }
+    out.push_str(&format_chunks(chunks));
+    out.push_str("</codebase_context>");
+    out
+}
+
+/// Chunks as `<file>` elements, the way the model is shown code
+pub fn format_chunks(chunks: &[CodeChunk]) -> String {
+    let mut out = String::new();
+    for chunk in chunks {
+        let _ = writeln!(
+            out,
+            "<file path=\"{}\" lines=\"{}-{}\" language=\"{}\">\n{}\n</file>\n",
+            chunk.file_path,
+            chunk.start_line,
+            chunk.end_line,
+            chunk.language,
+            chunk.content.trim_end()
+        );
+    }
+    out
+}
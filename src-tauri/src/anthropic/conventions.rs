@@ -0,0 +1,174 @@
+use crate::anthropic::context_builder::TokenCounter;
+use crate::models::code_index::{CodebaseIndex, IndexedFile};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// Code sampled from a project for pattern extraction, when not given
+pub const DEFAULT_PATTERN_SAMPLE_TOKENS: usize = 60_000;
+/// Code analyzed in one request; results of the requests are merged
+pub const PATTERN_CHUNK_TOKENS: usize = 15_000;
+/// Sampled files are cut off after this many characters
+const MAX_SAMPLE_FILE_CHARS: usize = 8_000;
+/// Files sampled from one directory of one language, so large directories
+/// don't crowd out the rest
+const MAX_FILES_PER_GROUP: usize = 3;
+
+/// A file picked to represent its language and directory
+#[derive(Debug, Clone)]
+pub struct SampledFile {
+    /// Relative to the project root
+    pub path: String,
+    pub language: String,
+    pub content: String,
+    pub tokens: usize,
+}
+
+/// The conventions of a project, merged from analyses of samples of it.
+/// The latest is kept in the project's cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConventionsProfile {
+    pub project_path: String,
+    /// Markdown, by topic
+    pub profile: String,
+    pub languages: Vec<String>,
+    /// Relative paths of the files analyzed
+    pub files_sampled: Vec<String>,
+    /// Requests the analysis was split into
+    pub chunks: usize,
+    pub sample_tokens: usize,
+}
+
+impl ConventionsProfile {
+    pub fn load(path: &Path) -> Result<Option<ConventionsProfile>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read conventions profile: {}", e))?;
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse conventions profile: {}", e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize conventions profile: {}", e))?;
+        fs::write(path, json).map_err(|e| format!("Failed to write conventions profile: {}", e))
+    }
+
+    /// The profile's section on testing: the first markdown heading
+    /// mentioning tests and what's under it, up to the next heading at the
+    /// same level or above
+    pub fn testing_section(&self) -> Option<String> {
+        let level = |line: &str| {
+            let hashes = line.chars().take_while(|&c| c == '#').count();
+            (hashes > 0 && line[hashes..].starts_with(' ')).then_some(hashes)
+        };
+        let mut lines = self.profile.lines();
+        let (heading_level, heading) = lines.by_ref().find_map(|line| {
+            let level = level(line.trim_start())?;
+            line.to_lowercase().contains("test").then_some((level, line))
+        })?;
+        let mut section = heading.to_string();
+        for line in lines {
+            if level(line.trim_start()).is_some_and(|level| level <= heading_level) {
+                break;
+            }
+            section.push('\n');
+            section.push_str(line);
+        }
+        Some(section.trim().to_string())
+    }
+}
+
+/// Files representing each language and directory of the index, within
+/// `budget` tokens. Directories take turns, each giving its files with the
+/// most symbols first, so every part of the project is seen before any is
+/// seen twice.
+pub fn sample_files(
+    index: &CodebaseIndex,
+    budget: usize,
+    counter: &TokenCounter,
+) -> Vec<SampledFile> {
+    let mut groups: BTreeMap<(&str, &str), Vec<&IndexedFile>> = BTreeMap::new();
+    for file in index.files.values().filter(|file| !file.symbols.is_empty()) {
+        let dir = Path::new(&file.path)
+            .parent()
+            .and_then(|dir| dir.to_str())
+            .unwrap_or("");
+        groups
+            .entry((file.language.as_str(), dir))
+            .or_default()
+            .push(file);
+    }
+
+    let mut groups: Vec<Vec<&IndexedFile>> = groups.into_values().collect();
+    for files in &mut groups {
+        files.sort_by(|a, b| {
+            b.symbols
+                .len()
+                .cmp(&a.symbols.len())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        files.truncate(MAX_FILES_PER_GROUP);
+    }
+    groups.sort_by_key(|files| std::cmp::Reverse(files[0].symbols.len()));
+
+    let mut samples = Vec::new();
+    let mut used = 0;
+    for round in 0..MAX_FILES_PER_GROUP {
+        for file in groups.iter().filter_map(|files| files.get(round)) {
+            let Ok(source) = fs::read_to_string(&file.path) else {
+                continue;
+            };
+            let content: String = source.chars().take(MAX_SAMPLE_FILE_CHARS).collect();
+            let tokens = counter.count(&content);
+            // A smaller file further on may still fit
+            if content.trim().is_empty() || used + tokens > budget {
+                continue;
+            }
+            used += tokens;
+
+            let path = Path::new(&file.path)
+                .strip_prefix(&index.root_path)
+                .map(|path| path.to_string_lossy().to_string())
+                .unwrap_or_else(|_| file.path.clone());
+            samples.push(SampledFile {
+                path,
+                language: file.language.clone(),
+                content,
+                tokens,
+            });
+        }
+    }
+    samples
+}
+
+/// Samples packed into snippets of up to `chunk_tokens` each, one request's
+/// worth of code apiece
+pub fn chunk_samples(samples: &[SampledFile], chunk_tokens: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut used = 0;
+    for sample in samples {
+        if used + sample.tokens > chunk_tokens && !chunk.is_empty() {
+            chunks.push(std::mem::take(&mut chunk));
+            used = 0;
+        }
+        chunk.push_str(&format!(
+            "File: {}\n```{}\n{}\n```\n\n",
+            sample.path, sample.language, sample.content
+        ));
+        used += sample.tokens;
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
@@ -0,0 +1,81 @@
+use keyring::Entry;
+
+const KEYRING_SERVICE: &str = "prompto";
+const ANTHROPIC_KEY_USER: &str = "anthropic-api-key";
+const EMBEDDING_KEY_USER: &str = "embedding-api-key";
+const LLM_PROVIDER_KEY_USER: &str = "llm-provider-api-key";
+
+fn entry(user: &str) -> Result<Entry, String> {
+    Entry::new(KEYRING_SERVICE, user).map_err(|e| format!("Failed to open keyring: {}", e))
+}
+
+fn load_secret(user: &str) -> Result<Option<String>, String> {
+    match entry(user)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("Failed to read API key: {}", e)),
+    }
+}
+
+fn store_secret(user: &str, secret: &str) -> Result<(), String> {
+    entry(user)?
+        .set_password(secret)
+        .map_err(|e| format!("Failed to store API key: {}", e))
+}
+
+fn delete_secret(user: &str) -> Result<(), String> {
+    match entry(user)?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(format!("Failed to delete API key: {}", e)),
+    }
+}
+
+/// The API key saved in the OS keyring, if any
+pub fn load_api_key() -> Result<Option<String>, String> {
+    load_secret(ANTHROPIC_KEY_USER)
+}
+
+pub fn store_api_key(api_key: &str) -> Result<(), String> {
+    store_secret(ANTHROPIC_KEY_USER, api_key)
+}
+
+pub fn delete_api_key() -> Result<(), String> {
+    delete_secret(ANTHROPIC_KEY_USER)
+}
+
+/// The key Anthropic clients use. It's only ever read here, so it stays
+/// out of command arguments and logs.
+pub fn anthropic_api_key() -> Result<String, String> {
+    load_api_key()?
+        .filter(|key| !key.trim().is_empty())
+        .ok_or_else(|| "No Anthropic API key set; add one in settings".to_string())
+}
+
+/// Key of the hosted embedding API, kept out of the settings file
+pub fn load_embedding_api_key() -> Result<Option<String>, String> {
+    load_secret(EMBEDDING_KEY_USER)
+}
+
+/// Save the hosted embedding API key; an empty key removes it
+pub fn store_embedding_api_key(api_key: &str) -> Result<(), String> {
+    if api_key.is_empty() {
+        delete_secret(EMBEDDING_KEY_USER)
+    } else {
+        store_secret(EMBEDDING_KEY_USER, api_key)
+    }
+}
+
+/// API key or secret of the configured LLM provider, kept out of the
+/// settings file
+pub fn load_llm_provider_api_key() -> Result<Option<String>, String> {
+    load_secret(LLM_PROVIDER_KEY_USER)
+}
+
+/// Save the LLM provider's API key or secret; an empty one removes it
+pub fn store_llm_provider_api_key(api_key: &str) -> Result<(), String> {
+    if api_key.is_empty() {
+        delete_secret(LLM_PROVIDER_KEY_USER)
+    } else {
+        store_secret(LLM_PROVIDER_KEY_USER, api_key)
+    }
+}
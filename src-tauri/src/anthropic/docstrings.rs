@@ -0,0 +1,227 @@
+use crate::models::code_index::CodeSymbol;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// Callers shown to the model as examples of how a symbol is used
+pub const MAX_DOCSTRING_CALLERS: usize = 5;
+/// Source of the symbol sent; longer bodies are cut off
+const MAX_SYMBOL_SOURCE_CHARS: usize = 12_000;
+/// Lines sent of each caller
+const CALLER_LINES: usize = 20;
+
+/// A doc comment proposed for a file: `replaced_lines` lines from `line`
+/// (1-based) are replaced with `text`, or none when there's no doc yet
+#[derive(Debug, Clone, Serialize)]
+pub struct DocstringEdit {
+    pub file_path: String,
+    pub line: usize,
+    pub replaced_lines: usize,
+    /// The lines replaced, for showing the edit as a diff
+    pub original: String,
+    /// The comment as it goes in the file: markers, indentation and a
+    /// trailing newline included
+    pub text: String,
+    /// The doc's text alone
+    pub doc: String,
+}
+
+/// Lines `start_line..=end_line` of a symbol in its file's source
+pub fn symbol_source(source: &str, symbol: &CodeSymbol, max_chars: usize) -> String {
+    source
+        .lines()
+        .skip(symbol.start_line.saturating_sub(1))
+        .take((symbol.end_line + 1).saturating_sub(symbol.start_line.max(1)))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .chars()
+        .take(max_chars)
+        .collect()
+}
+
+/// What the model is asked to document a symbol from: its source and the
+/// start of some callers, each given with the source of its file
+pub fn docstring_prompt(
+    symbol: &CodeSymbol,
+    language: &str,
+    source: &str,
+    callers: &[(CodeSymbol, String)],
+) -> String {
+    let kind = format!("{:?}", symbol.kind).to_lowercase();
+    let mut prompt = format!(
+        "Write the doc comment for {} `{}` in {} ({}):\n\n```{}\n{}\n```\n",
+        kind,
+        symbol.name,
+        symbol.file_path,
+        language,
+        language,
+        symbol_source(source, symbol, MAX_SYMBOL_SOURCE_CHARS)
+    );
+    if !callers.is_empty() {
+        let _ = writeln!(prompt, "\nIt's called from:");
+        for (caller, caller_source) in callers {
+            let mut lines = symbol_source(caller_source, caller, usize::MAX)
+                .lines()
+                .take(CALLER_LINES)
+                .collect::<Vec<_>>()
+                .join("\n");
+            if (caller.end_line + 1).saturating_sub(caller.start_line) > CALLER_LINES {
+                lines.push_str("\n...");
+            }
+            let _ = writeln!(
+                prompt,
+                "\n{} in {}:\n```{}\n{}\n```",
+                caller.name, caller.file_path, language, lines
+            );
+        }
+    }
+    prompt
+}
+
+/// Where `doc` goes for a symbol and how it's written, replacing the doc
+/// comment it already has, if any. Rust, JavaScript and TypeScript docs go
+/// above the symbol and its attributes or decorators; Python docstrings go
+/// first in the body.
+pub fn docstring_edit(
+    symbol: &CodeSymbol,
+    language: &str,
+    source: &str,
+    doc: &str,
+) -> DocstringEdit {
+    let lines: Vec<&str> = source.lines().collect();
+    let item = symbol.start_line.saturating_sub(1).min(lines.len());
+    let indent_of = |i: usize| -> String {
+        lines
+            .get(i)
+            .map(|line| line.chars().take_while(|c| c.is_whitespace()).collect())
+            .unwrap_or_default()
+    };
+    let doc_lines: Vec<&str> = doc.trim().lines().map(str::trim_end).collect();
+
+    let (start, end, text) = if language == "python" {
+        let (start, end, indent) = python_docstring_range(&lines, item);
+        let indent = indent.unwrap_or_else(|| format!("{}    ", indent_of(item)));
+        let mut text = format!("{}\"\"\"", indent);
+        for (i, line) in doc_lines.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+                if !line.is_empty() {
+                    text.push_str(&indent);
+                }
+            }
+            text.push_str(line);
+        }
+        if doc_lines.len() > 1 {
+            let _ = write!(text, "\n{}", indent);
+        }
+        text.push_str("\"\"\"\n");
+        (start, end, text)
+    } else {
+        // Attributes and decorators stay with the symbol, below its doc
+        let mut top = item;
+        while top > 0 {
+            let above = lines[top - 1].trim_start();
+            if above.starts_with("#[") || above.starts_with('@') {
+                top -= 1;
+            } else {
+                break;
+            }
+        }
+        let indent = indent_of(item);
+        let (start, text) = match language {
+            "rust" => {
+                let mut start = top;
+                while start > 0 && lines[start - 1].trim_start().starts_with("///") {
+                    start -= 1;
+                }
+                let text: String = doc_lines
+                    .iter()
+                    .map(|line| {
+                        if line.is_empty() {
+                            format!("{}///\n", indent)
+                        } else {
+                            format!("{}/// {}\n", indent, line)
+                        }
+                    })
+                    .collect();
+                (start, text)
+            }
+            _ => {
+                let mut start = top;
+                if top > 0 && lines[top - 1].trim_end().ends_with("*/") {
+                    for i in (0..top).rev() {
+                        let line = lines[i].trim_start();
+                        if line.starts_with("/**") {
+                            start = i;
+                            break;
+                        }
+                        if !line.starts_with('*') && !line.starts_with("/*") {
+                            break;
+                        }
+                    }
+                }
+                let text = if doc_lines.len() == 1 {
+                    format!("{}/** {} */\n", indent, doc_lines[0])
+                } else {
+                    let mut text = format!("{}/**\n", indent);
+                    for line in &doc_lines {
+                        if line.is_empty() {
+                            let _ = writeln!(text, "{} *", indent);
+                        } else {
+                            let _ = writeln!(text, "{} * {}", indent, line);
+                        }
+                    }
+                    let _ = writeln!(text, "{} */", indent);
+                    text
+                };
+                (start, text)
+            }
+        };
+        (start, top, text)
+    };
+
+    DocstringEdit {
+        file_path: symbol.file_path.clone(),
+        line: start + 1,
+        replaced_lines: end - start,
+        original: lines[start..end]
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect(),
+        text,
+        doc: doc_lines.join("\n"),
+    }
+}
+
+/// Lines `start..end` of a Python function's or class's existing docstring,
+/// empty at the start of the body when it has none, with the body's
+/// indentation when known
+fn python_docstring_range(lines: &[&str], item: usize) -> (usize, usize, Option<String>) {
+    let body = (item..lines.len())
+        .find(|&i| {
+            lines[i]
+                .split('#')
+                .next()
+                .unwrap_or("")
+                .trim_end()
+                .ends_with(':')
+        })
+        .map_or(lines.len(), |i| i + 1);
+    let Some(first) = (body..lines.len()).find(|&i| !lines[i].trim().is_empty()) else {
+        return (body, body, None);
+    };
+    let line = lines[first];
+    let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+    let text = line.trim_start().trim_start_matches(['r', 'R']);
+    let Some(quote) = ["\"\"\"", "'''"].into_iter().find(|q| text.starts_with(q)) else {
+        return (body, body, Some(indent));
+    };
+
+    let end = if text[3..].contains(quote) {
+        first + 1
+    } else {
+        (first + 1..lines.len())
+            .find(|&i| lines[i].contains(quote))
+            .map_or(lines.len(), |i| i + 1)
+    };
+    (first, end, Some(indent))
+}
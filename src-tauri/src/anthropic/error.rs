@@ -0,0 +1,70 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Why a request to the Anthropic API failed
+#[derive(Debug)]
+pub enum ApiError {
+    /// The request never got a response: connection failure or timeout
+    Network { message: String, timed_out: bool },
+    /// The API answered with an error status
+    Status {
+        status: u16,
+        message: String,
+        /// How long the API asked us to wait before trying again
+        retry_after: Option<Duration>,
+    },
+    /// The response body wasn't what the API documents
+    InvalidResponse(String),
+    /// The provider doesn't offer what was asked of it
+    Unsupported(String),
+}
+
+impl ApiError {
+    /// Whether the same request may succeed later: rate limits, overload,
+    /// server errors and timeouts are transient, bad requests and bad keys
+    /// are not
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::Network { .. } => true,
+            ApiError::Status { status, .. } => {
+                matches!(status, 408 | 409 | 429) || *status >= 500
+            }
+            ApiError::InvalidResponse(_) | ApiError::Unsupported(_) => false,
+        }
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ApiError::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Network { message, timed_out: true } => {
+                write!(f, "Request timed out: {}", message)
+            }
+            ApiError::Network { message, .. } => write!(f, "Failed to send request: {}", message),
+            ApiError::Status { status: 429, message, .. } => {
+                write!(f, "Rate limited by the API: {}", message)
+            }
+            ApiError::Status { status, message, .. } => {
+                write!(f, "API request failed with status {}: {}", status, message)
+            }
+            ApiError::InvalidResponse(message) => write!(f, "Failed to parse response: {}", message),
+            ApiError::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+// Commands report errors as strings
+impl From<ApiError> for String {
+    fn from(error: ApiError) -> Self {
+        error.to_string()
+    }
+}
@@ -0,0 +1,178 @@
+use crate::anthropic::docstrings::symbol_source;
+use crate::models::code_index::{CodeSymbol, CodebaseIndex, SymbolKind};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+
+/// Functions and types the symbol calls that are shown with their source
+const MAX_EXPLAIN_CALLEES: usize = 12;
+/// Callers listed by signature
+const MAX_EXPLAIN_CALLERS: usize = 8;
+/// Imports of the symbol's file listed
+const MAX_EXPLAIN_IMPORTS: usize = 30;
+/// Source of the symbol sent; longer bodies are cut off
+const MAX_SYMBOL_SOURCE_CHARS: usize = 16_000;
+/// Lines sent of each callee
+const CALLEE_LINES: usize = 25;
+
+/// A symbol with what it depends on and what depends on it, for explaining
+/// it
+#[derive(Debug, Clone)]
+pub struct SymbolContext {
+    pub symbol: CodeSymbol,
+    pub language: String,
+    /// The type or module it's declared in, when indexed
+    pub parent: Option<CodeSymbol>,
+    pub imports: Vec<String>,
+    /// Definitions of what it calls, nearest the symbol's file first
+    pub callees: Vec<CodeSymbol>,
+    pub callers: Vec<CodeSymbol>,
+}
+
+impl SymbolContext {
+    /// Look up a symbol's parent, callees and callers in the call graph and
+    /// its file's imports
+    pub fn gather(index: &CodebaseIndex, symbol: CodeSymbol, language: String) -> Self {
+        let parent = symbol.parent.as_ref().and_then(|parent| {
+            index
+                .find_definitions(parent, Some(&symbol.file_path), None)
+                .into_iter()
+                .next()
+        });
+        let imports = index
+            .files
+            .get(&symbol.file_path)
+            .map(|file| {
+                file.imports
+                    .iter()
+                    .take(MAX_EXPLAIN_IMPORTS)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut callees: Vec<CodeSymbol> = Vec::new();
+        for call in &symbol.calls {
+            if callees.len() >= MAX_EXPLAIN_CALLEES {
+                break;
+            }
+            let definition = index
+                .find_definitions(call, Some(&symbol.file_path), None)
+                .into_iter()
+                .find(|s| !matches!(s.kind, SymbolKind::Import | SymbolKind::Export));
+            if let Some(definition) = definition {
+                let seen = callees.iter().any(|c| {
+                    c.file_path == definition.file_path && c.start_line == definition.start_line
+                });
+                let is_self = definition.file_path == symbol.file_path
+                    && definition.start_line == symbol.start_line;
+                if !seen && !is_self {
+                    callees.push(definition);
+                }
+            }
+        }
+
+        let mut callers = index.direct_callers(&symbol);
+        callers.truncate(MAX_EXPLAIN_CALLERS);
+
+        Self {
+            symbol,
+            language,
+            parent,
+            imports,
+            callees,
+            callers,
+        }
+    }
+
+    /// The context as a prompt, with source read from the files. Files that
+    /// can't be read are left out.
+    pub fn prompt(&self) -> String {
+        let mut sources: HashMap<String, Option<String>> = HashMap::new();
+        let mut source_of = |path: &str| -> Option<String> {
+            sources
+                .entry(path.to_string())
+                .or_insert_with(|| fs::read_to_string(path).ok())
+                .clone()
+        };
+        let kind = |symbol: &CodeSymbol| format!("{:?}", symbol.kind).to_lowercase();
+        let lang = &self.language;
+
+        let source = source_of(&self.symbol.file_path).unwrap_or_default();
+        let mut prompt = format!(
+            "Explain {} `{}` in {} (line {}):\n\n```{}\n{}\n```\n",
+            kind(&self.symbol),
+            self.symbol.name,
+            self.symbol.file_path,
+            self.symbol.start_line,
+            lang,
+            symbol_source(&source, &self.symbol, MAX_SYMBOL_SOURCE_CHARS)
+        );
+        if let Some(doc) = &self.symbol.doc_comment {
+            let _ = writeln!(prompt, "\nIts documentation:\n{}", doc);
+        }
+
+        if let Some(parent) = &self.parent {
+            let _ = write!(prompt, "\nIt belongs to {} `{}`", kind(parent), parent.name);
+            match &parent.doc_comment {
+                Some(doc) => {
+                    let _ = writeln!(prompt, ", documented as:\n{}", doc);
+                }
+                None => prompt.push_str(".\n"),
+            }
+        }
+
+        if !self.imports.is_empty() {
+            let _ = writeln!(prompt, "\nIts file imports:");
+            for import in &self.imports {
+                let _ = writeln!(prompt, "- {}", import.lines().next().unwrap_or_default());
+            }
+        }
+
+        if !self.callees.is_empty() {
+            let _ = writeln!(prompt, "\nWhat it calls:");
+            for callee in &self.callees {
+                let _ = writeln!(
+                    prompt,
+                    "\n{} `{}` in {}:",
+                    kind(callee),
+                    callee.name,
+                    callee.file_path
+                );
+                if let Some(doc) = &callee.doc_comment {
+                    let _ = writeln!(prompt, "{}", doc);
+                }
+                if let Some(source) = source_of(&callee.file_path) {
+                    let mut lines = symbol_source(&source, callee, usize::MAX)
+                        .lines()
+                        .take(CALLEE_LINES)
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    if (callee.end_line + 1).saturating_sub(callee.start_line) > CALLEE_LINES {
+                        lines.push_str("\n...");
+                    }
+                    let _ = writeln!(prompt, "```{}\n{}\n```", lang, lines);
+                }
+            }
+        }
+
+        if !self.callers.is_empty() {
+            let _ = writeln!(prompt, "\nWhere it's called from:");
+            for caller in &self.callers {
+                let signature = caller
+                    .signature
+                    .as_deref()
+                    .and_then(|signature| signature.lines().next())
+                    .unwrap_or(&caller.name);
+                let _ = writeln!(
+                    prompt,
+                    "- {} in {} (line {})",
+                    signature.trim(),
+                    caller.file_path,
+                    caller.start_line
+                );
+            }
+        }
+        prompt
+    }
+}
@@ -1,129 +1,238 @@
+pub mod attachments;
+pub mod batches;
+pub mod bedrock;
+pub mod catalog;
+pub mod change_description;
+pub mod codebase_summary;
+pub mod context_builder;
+pub mod conventions;
+pub mod credentials;
+pub mod docstrings;
+pub mod error;
+pub mod explain;
+pub mod http;
 pub mod models;
-
-use models::{Message, MessageRequest, MessageResponse};
-use reqwest::Client;
+pub mod ollama;
+pub mod openai;
+pub mod prompts;
+pub mod provider;
+pub mod response_cache;
+pub mod sessions;
+pub mod sigv4;
+pub mod test_suggestions;
+pub mod tools;
+pub mod usage;
+
+use async_trait::async_trait;
+use catalog::DEFAULT_MODEL;
+use error::ApiError;
+use http::{http_client, send_json, send_streaming};
+use models::{
+    ContentBlock, CountTokensRequest, CountTokensResponse, Message, MessageRequest,
+    MessageResponse, StreamDelta, StreamEvent, ToolDefinition, Usage,
+};
+use prompts::SystemPrompts;
+use provider::{LlmProvider, Reply, TextSink};
+use response_cache::ResponseCache;
+use usage::UsageRecorder;
+use reqwest::{Client, RequestBuilder};
+use serde::Serialize;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
+const COUNT_TOKENS_URL: &str = "https://api.anthropic.com/v1/messages/count_tokens";
 const ANTHROPIC_VERSION: &str = "2023-06-01";
 
 pub struct AnthropicClient {
     client: Client,
     api_key: String,
+    model: String,
+    prompts: SystemPrompts,
+    usage: Option<UsageRecorder>,
+    cache: Option<ResponseCache>,
 }
 
 impl AnthropicClient {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            client: Client::new(),
+    pub fn new(api_key: String) -> Result<Self, String> {
+        Ok(Self {
+            client: http_client()?,
             api_key,
-        }
+            model: DEFAULT_MODEL.to_string(),
+            prompts: SystemPrompts::default(),
+            usage: None,
+            cache: None,
+        })
     }
 
-    pub async fn create_message(
-        &self,
-        model: &str,
-        max_tokens: u32,
-        messages: Vec<Message>,
-        system: Option<String>,
-        temperature: Option<f32>,
-    ) -> Result<MessageResponse, String> {
-        let request = MessageRequest {
-            model: model.to_string(),
-            max_tokens,
-            messages,
-            system,
-            temperature,
-            top_p: None,
-        };
+    /// Use a different model for every request
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Use customized system prompts in place of the built-in ones
+    pub fn with_system_prompts(mut self, prompts: SystemPrompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Record each request's token usage with `recorder`
+    pub fn with_usage_recorder(mut self, recorder: UsageRecorder) -> Self {
+        self.usage = Some(recorder);
+        self
+    }
 
-        let response = self
-            .client
-            .post(ANTHROPIC_API_URL)
+    /// Reuse replies to repeated requests from `cache`; None always asks
+    /// the model
+    pub fn with_response_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    fn get(&self, url: &str) -> RequestBuilder {
+        self.client
+            .get(url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", ANTHROPIC_VERSION)
-            .header("content-type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to send request: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(format!("API request failed with status {}: {}", status, error_text));
-        }
+    }
 
-        response
-            .json::<MessageResponse>()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))
+    fn post(&self, url: &str, body: &impl Serialize) -> RequestBuilder {
+        self.client
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("content-type", "application/json")
+            .json(body)
     }
+}
 
-    pub async fn analyze_intent(&self, prompt: &str) -> Result<String, String> {
-        let system_prompt = r#"You are an expert at analyzing user intent for code-related tasks. Extract structured information from prompts and return ONLY valid JSON with no markdown formatting.
+#[async_trait]
+impl LlmProvider for AnthropicClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
 
-Return a JSON object with:
-- "action": The primary action (create/modify/fix/explain/refactor/other)
-- "keywords": Array of key technical terms (3-8 terms)
-- "scope": The scope level (file/function/class/module/project)
-- "entities": Array of specific names mentioned (files, functions, classes, variables)"#;
+    fn system_prompts(&self) -> &SystemPrompts {
+        &self.prompts
+    }
 
-        let messages = vec![
-            Message {
-                role: "user".to_string(),
-                content: format!("Analyze this prompt and extract intent:\n\n{}", prompt),
-            },
-        ];
-
-        let response = self
-            .create_message("claude-sonnet-4-5-20250929", 1024, messages, Some(system_prompt.to_string()), Some(0.3))
-            .await?;
-
-        // Extract text from first content block
-        if let Some(content_block) = response.content.first() {
-            if let Some(text) = &content_block.text {
-                return Ok(text.clone());
-            }
-        }
+    fn usage_recorder(&self) -> Option<&UsageRecorder> {
+        self.usage.as_ref()
+    }
 
-        Err("No content in response".to_string())
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
     }
 
-    pub async fn extract_patterns(&self, code_snippets: &str) -> Result<String, String> {
-        let system_prompt = r#"You are an expert code analyst. Analyze code to identify patterns, conventions, and architectural insights that would help a developer write consistent code.
+    async fn respond(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<Reply, ApiError> {
+        let request = MessageRequest {
+            model: self.model.clone(),
+            max_tokens,
+            messages: messages.to_vec(),
+            system: system.map(str::to_string),
+            temperature,
+            top_p: None,
+            tools: tools.to_vec(),
+        };
 
-Focus on:
-- Naming conventions (variables, functions, classes)
-- Code organization patterns
-- Error handling approaches
-- Common design patterns used
-- Testing strategies
-- Documentation style"#;
+        let response: MessageResponse =
+            send_json(|| self.post(ANTHROPIC_API_URL, &request)).await?;
+        Ok(Reply {
+            wants_tools: response.stop_reason.as_deref() == Some("tool_use"),
+            content: response.content,
+            usage: response.usage,
+        })
+    }
 
-        let messages = vec![
-            Message {
+    /// Counted by the API with the model's own tokenizer. Includes the few
+    /// tokens of message framing.
+    async fn count_tokens(&self, text: &str, model: &str) -> Result<usize, ApiError> {
+        let request = CountTokensRequest {
+            model: model.to_string(),
+            messages: vec![Message {
                 role: "user".to_string(),
-                content: format!(
-                    "Analyze the following code and extract common patterns and conventions:\n\n{}",
-                    code_snippets
-                ),
-            },
-        ];
-
-        let response = self
-            .create_message("claude-sonnet-4-5-20250929", 2048, messages, Some(system_prompt.to_string()), Some(0.5))
-            .await?;
-
-        // Extract text from first content block
-        if let Some(content_block) = response.content.first() {
-            if let Some(text) = &content_block.text {
-                return Ok(text.clone());
+                content: text.to_string().into(),
+            }],
+        };
+        let response: CountTokensResponse =
+            send_json(|| self.post(COUNT_TOKENS_URL, &request)).await?;
+        Ok(response.input_tokens)
+    }
+
+    /// Read as server-sent events, text deltas given to `on_text` as they
+    /// arrive
+    async fn respond_streaming(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        max_tokens: u32,
+        temperature: Option<f32>,
+        on_text: &mut TextSink<'_>,
+    ) -> Result<Reply, ApiError> {
+        let request = MessageRequest {
+            model: self.model.clone(),
+            max_tokens,
+            messages: messages.to_vec(),
+            system: system.map(str::to_string),
+            temperature,
+            top_p: None,
+            tools: Vec::new(),
+        };
+        let mut body = serde_json::to_value(&request)
+            .map_err(|e| ApiError::InvalidResponse(e.to_string()))?;
+        body["stream"] = serde_json::Value::Bool(true);
+
+        let mut response = send_streaming(|| self.post(ANTHROPIC_API_URL, &body)).await?;
+        let mut pending: Vec<u8> = Vec::new();
+        let mut text = String::new();
+        let mut usage = Usage::default();
+        loop {
+            let chunk = response.chunk().await.map_err(|e| ApiError::Network {
+                message: e.to_string(),
+                timed_out: e.is_timeout(),
+            })?;
+            let Some(chunk) = chunk else {
+                break;
+            };
+            pending.extend_from_slice(&chunk);
+
+            // Events may be split across chunks, so only whole lines are read
+            while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                match serde_json::from_str::<StreamEvent>(data.trim()) {
+                    Ok(StreamEvent::MessageStart { message }) => usage = message.usage,
+                    Ok(StreamEvent::ContentBlockDelta {
+                        delta: StreamDelta::TextDelta { text: delta },
+                    }) => {
+                        on_text(&delta);
+                        text.push_str(&delta);
+                    }
+                    Ok(StreamEvent::MessageDelta { usage: delta }) => {
+                        usage.output_tokens = delta.output_tokens
+                    }
+                    Ok(StreamEvent::Error { error }) => {
+                        return Err(ApiError::InvalidResponse(error.message))
+                    }
+                    Ok(_) => {}
+                    Err(e) => eprintln!("Skipping unreadable stream event: {}", e),
+                }
             }
         }
 
-        Err("No content in response".to_string())
+        Ok(Reply {
+            content: vec![ContentBlock::Text { text }],
+            wants_tools: false,
+            usage,
+        })
     }
 }
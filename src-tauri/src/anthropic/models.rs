@@ -1,9 +1,30 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: String,
-    pub content: String,
+    pub content: MessageContent,
+}
+
+/// Plain text, or blocks when a turn carries tool calls, their results, or
+/// images and documents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        MessageContent::Text(text)
+    }
+}
+
+impl From<Vec<ContentBlock>> for MessageContent {
+    fn from(blocks: Vec<ContentBlock>) -> Self {
+        MessageContent::Blocks(blocks)
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -17,12 +38,71 @@ pub struct MessageRequest {
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
+    /// Tools the model may call instead of answering directly
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<ToolDefinition>,
+}
+
+/// A tool offered to the model, with a JSON Schema for its input
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    pub description: String,
+    pub input_schema: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CountTokensRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct ContentBlock {
-    pub r#type: String,
-    pub text: Option<String>,
+pub struct CountTokensResponse {
+    pub input_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentBlock {
+    Text {
+        text: String,
+    },
+    /// The model calling a tool; answered by a ToolResult with the same id
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        is_error: bool,
+    },
+    /// A picture for the model to look at, such as a screenshot
+    Image {
+        source: MediaSource,
+    },
+    /// A PDF or text file for the model to read
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    /// Block types this client doesn't use
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Where an image's or document's data comes from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MediaSource {
+    Base64 { media_type: String, data: String },
+    /// Plain text documents, as is
+    Text { media_type: String, data: String },
+    Url { url: String },
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,16 +116,119 @@ pub struct MessageResponse {
     pub usage: Usage,
 }
 
+/// An event of a streamed response, from a `data:` line
 #[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    MessageStart { message: StreamMessage },
+    ContentBlockDelta { delta: StreamDelta },
+    MessageDelta { usage: StreamUsage },
+    Error { error: StreamError },
+    /// Events carrying nothing this client uses, such as pings
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamMessage {
+    pub usage: Usage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamDelta {
+    TextDelta {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Output tokens so far, in a `message_delta` event
+#[derive(Debug, Deserialize)]
+pub struct StreamUsage {
+    pub output_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamError {
+    pub message: String,
+}
+
+/// Tokens a request took, as the provider reports them
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
 pub struct Usage {
     pub input_tokens: u32,
     pub output_tokens: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptIntent {
-    pub action: String,
+    pub action: IntentAction,
     pub keywords: Vec<String>,
-    pub scope: String,
+    pub scope: IntentScope,
     pub entities: Vec<String>,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntentAction {
+    Create,
+    Modify,
+    Fix,
+    Explain,
+    Refactor,
+    Other,
+}
+
+impl IntentAction {
+    pub const ALL: [IntentAction; 6] = [
+        IntentAction::Create,
+        IntentAction::Modify,
+        IntentAction::Fix,
+        IntentAction::Explain,
+        IntentAction::Refactor,
+        IntentAction::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntentAction::Create => "create",
+            IntentAction::Modify => "modify",
+            IntentAction::Fix => "fix",
+            IntentAction::Explain => "explain",
+            IntentAction::Refactor => "refactor",
+            IntentAction::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IntentScope {
+    File,
+    Function,
+    Class,
+    Module,
+    Project,
+}
+
+impl IntentScope {
+    pub const ALL: [IntentScope; 5] = [
+        IntentScope::File,
+        IntentScope::Function,
+        IntentScope::Class,
+        IntentScope::Module,
+        IntentScope::Project,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntentScope::File => "file",
+            IntentScope::Function => "function",
+            IntentScope::Class => "class",
+            IntentScope::Module => "module",
+            IntentScope::Project => "project",
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use crate::anthropic::attachments::{document_text, image_url};
+use crate::anthropic::error::ApiError;
+use crate::anthropic::http::{http_client, send_json};
+use crate::anthropic::models::{
+    ContentBlock, MediaSource, Message, MessageContent, ToolDefinition, Usage,
+};
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::provider::{LlmProvider, Reply};
+use crate::anthropic::response_cache::ResponseCache;
+use crate::anthropic::usage::UsageRecorder;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const DEFAULT_OLLAMA_URL: &str = "http://localhost:11434";
+pub const DEFAULT_OLLAMA_MODEL: &str = "llama3.2";
+/// Short, so a server that isn't running is reported quickly
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OllamaConfig {
+    /// Where the Ollama server listens
+    pub base_url: String,
+    /// A model pulled into Ollama, e.g. `llama3.2` or `qwen2.5-coder:7b`
+    pub model: String,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_OLLAMA_URL.to_string(),
+            model: DEFAULT_OLLAMA_MODEL.to_string(),
+        }
+    }
+}
+
+impl OllamaConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.base_url.starts_with("http://") && !self.base_url.starts_with("https://") {
+            return Err("Ollama URL must start with http:// or https://".to_string());
+        }
+        if self.model.trim().is_empty() {
+            return Err("Ollama model must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A model pulled into Ollama
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    /// Bytes on disk
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: String,
+}
+
+/// Whether Ollama can serve requests, as shown in settings
+#[derive(Debug, Clone, Serialize)]
+pub struct OllamaStatus {
+    pub running: bool,
+    pub version: Option<String>,
+    /// Whether the configured model has been pulled
+    pub model_available: bool,
+    /// Why Ollama can't be used, when it can't
+    pub error: Option<String>,
+}
+
+/// Client for a local Ollama server, so prompts never leave the machine
+pub struct OllamaClient {
+    client: Client,
+    config: OllamaConfig,
+    prompts: SystemPrompts,
+    usage: Option<UsageRecorder>,
+    cache: Option<ResponseCache>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    message: ChatMessage,
+    #[serde(default)]
+    prompt_eval_count: u32,
+    #[serde(default)]
+    eval_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    tool_calls: Vec<ToolCall>,
+}
+
+#[derive(Deserialize)]
+struct ToolCall {
+    function: FunctionCall,
+}
+
+#[derive(Deserialize)]
+struct FunctionCall {
+    name: String,
+    /// Already JSON, unlike the OpenAI API's string
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+impl OllamaClient {
+    pub fn new(config: OllamaConfig) -> Result<Self, String> {
+        Ok(Self {
+            client: http_client()?,
+            config,
+            prompts: SystemPrompts::default(),
+            usage: None,
+            cache: None,
+        })
+    }
+
+    /// Use customized system prompts in place of the built-in ones
+    pub fn with_system_prompts(mut self, prompts: SystemPrompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Record each request's token usage with `recorder`
+    pub fn with_usage_recorder(mut self, recorder: UsageRecorder) -> Self {
+        self.usage = Some(recorder);
+        self
+    }
+
+    /// Reuse replies to repeated requests from `cache`; None always asks
+    /// the model
+    pub fn with_response_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Models pulled into the server, by name
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>, ApiError> {
+        let response = self.probe("api/tags").await?;
+        let mut models = response
+            .json::<TagsResponse>()
+            .await
+            .map_err(|e| ApiError::InvalidResponse(e.to_string()))?
+            .models;
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(models)
+    }
+
+    /// Whether the server answers and has the configured model. Never fails;
+    /// what went wrong is in the status.
+    pub async fn status(&self) -> OllamaStatus {
+        let version = match self.probe("api/version").await {
+            Ok(response) => response
+                .json::<VersionResponse>()
+                .await
+                .ok()
+                .map(|v| v.version),
+            Err(e) => {
+                return OllamaStatus {
+                    running: false,
+                    version: None,
+                    model_available: false,
+                    error: Some(format!(
+                        "Ollama isn't reachable at {}: {}",
+                        self.config.base_url, e
+                    )),
+                }
+            }
+        };
+
+        let (model_available, error) = match self.list_models().await {
+            Ok(models) if models.iter().any(|m| self.is_configured_model(&m.name)) => (true, None),
+            Ok(_) => (
+                false,
+                Some(format!(
+                    "Model {} isn't pulled; run `ollama pull {}`",
+                    self.config.model, self.config.model
+                )),
+            ),
+            Err(e) => (false, Some(e.to_string())),
+        };
+
+        OllamaStatus {
+            running: true,
+            version,
+            model_available,
+            error,
+        }
+    }
+
+    /// Ollama lists models with their tag; `llama3.2` means `llama3.2:latest`
+    fn is_configured_model(&self, name: &str) -> bool {
+        let model = self.config.model.trim();
+        name == model || (!model.contains(':') && name == format!("{}:latest", model))
+    }
+
+    /// A single GET without retries, for checks that should fail fast
+    async fn probe(&self, path: &str) -> Result<reqwest::Response, ApiError> {
+        let response = self
+            .client
+            .get(self.url(path))
+            .timeout(PROBE_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| ApiError::Network {
+                message: e.to_string(),
+                timed_out: e.is_timeout(),
+            })?;
+        if !response.status().is_success() {
+            return Err(ApiError::Status {
+                status: response.status().as_u16(),
+                message: response.text().await.unwrap_or_default(),
+                retry_after: None,
+            });
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaClient {
+    fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    fn system_prompts(&self) -> &SystemPrompts {
+        &self.prompts
+    }
+
+    fn usage_recorder(&self) -> Option<&UsageRecorder> {
+        self.usage.as_ref()
+    }
+
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    async fn respond(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<Reply, ApiError> {
+        let mut options = json!({ "num_predict": max_tokens });
+        if let Some(temperature) = temperature {
+            options["temperature"] = json!(temperature);
+        }
+        let mut request = json!({
+            "model": self.config.model,
+            "messages": chat_messages(system, messages),
+            "stream": false,
+            "options": options,
+        });
+        if !tools.is_empty() {
+            let tools: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.input_schema,
+                        }
+                    })
+                })
+                .collect();
+            request["tools"] = json!(tools);
+        }
+
+        let url = self.url("api/chat");
+        let response: ChatResponse = send_json(|| self.client.post(&url).json(&request)).await?;
+        let usage = Usage {
+            input_tokens: response.prompt_eval_count,
+            output_tokens: response.eval_count,
+        };
+        let message = response.message;
+
+        let mut content = Vec::new();
+        if !message.content.is_empty() {
+            content.push(ContentBlock::Text {
+                text: message.content,
+            });
+        }
+        let wants_tools = !message.tool_calls.is_empty();
+        // Ollama doesn't id tool calls; results are matched back by position
+        content.extend(message.tool_calls.into_iter().enumerate().map(|(i, call)| {
+            ContentBlock::ToolUse {
+                id: format!("call_{}", i),
+                name: call.function.name,
+                input: call.function.arguments,
+            }
+        }));
+
+        Ok(Reply {
+            content,
+            wants_tools,
+            usage,
+        })
+    }
+
+    async fn count_tokens(&self, _text: &str, _model: &str) -> Result<usize, ApiError> {
+        Err(ApiError::Unsupported(
+            "Token counting isn't available for Ollama".to_string(),
+        ))
+    }
+}
+
+/// Messages in Ollama's chat format: the system prompt as the first message,
+/// tool calls on the assistant message and each tool result as a message of
+/// its own. Images go on the message as base64; Ollama can't fetch them by
+/// URL or read PDFs, so those become notes in the text.
+fn chat_messages(system: Option<&str>, messages: &[Message]) -> Vec<Value> {
+    let mut chat = Vec::new();
+    if let Some(system) = system {
+        chat.push(json!({ "role": "system", "content": system }));
+    }
+
+    for message in messages {
+        let blocks = match &message.content {
+            MessageContent::Text(text) => {
+                chat.push(json!({ "role": message.role, "content": text }));
+                continue;
+            }
+            MessageContent::Blocks(blocks) => blocks,
+        };
+
+        let mut texts = Vec::new();
+        let mut images = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block {
+                ContentBlock::Text { text } => texts.push(text.clone()),
+                ContentBlock::Image {
+                    source: MediaSource::Base64 { data, .. },
+                } => images.push(data.as_str()),
+                ContentBlock::Image { source } => {
+                    texts.push(format!("[Image left out: {}]", image_url(source)))
+                }
+                ContentBlock::Document { source, title } => {
+                    texts.push(document_text(source, title.as_deref()))
+                }
+                ContentBlock::ToolUse { name, input, .. } => tool_calls.push(json!({
+                    "function": { "name": name, "arguments": input },
+                })),
+                ContentBlock::ToolResult { content, .. } => {
+                    chat.push(json!({ "role": "tool", "content": content }))
+                }
+                ContentBlock::Unsupported => {}
+            }
+        }
+
+        if texts.is_empty() && images.is_empty() && tool_calls.is_empty() {
+            continue;
+        }
+        let mut turn = json!({ "role": message.role, "content": texts.join("\n") });
+        if !images.is_empty() {
+            turn["images"] = json!(images);
+        }
+        if !tool_calls.is_empty() {
+            turn["tool_calls"] = json!(tool_calls);
+        }
+        chat.push(turn);
+    }
+    chat
+}
@@ -0,0 +1,371 @@
+use crate::anthropic::attachments::{document_text, image_url};
+use crate::anthropic::error::ApiError;
+use crate::anthropic::http::{http_client, send_json};
+use crate::anthropic::models::{
+    ContentBlock, MediaSource, Message, MessageContent, ToolDefinition, Usage,
+};
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::provider::{LlmProvider, Reply};
+use crate::anthropic::response_cache::ResponseCache;
+use crate::anthropic::usage::UsageRecorder;
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenAiCompatibleConfig {
+    /// API root the chat completions path goes under, e.g.
+    /// `https://api.openai.com/v1` or `http://localhost:8080/v1`
+    pub base_url: String,
+    /// Kept in the OS keyring, not the settings file. Empty for servers
+    /// without authentication.
+    #[serde(default)]
+    pub api_key: String,
+    pub model: String,
+}
+
+impl OpenAiCompatibleConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.base_url.starts_with("http://") && !self.base_url.starts_with("https://") {
+            return Err("Provider URL must start with http:// or https://".to_string());
+        }
+        if self.model.trim().is_empty() {
+            return Err("Provider model must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Azure OpenAI API version used when settings don't pick another
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// An Azure OpenAI resource, where models are reached through the
+/// deployments made of them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AzureOpenAiConfig {
+    /// The resource's endpoint, e.g. `https://my-resource.openai.azure.com`
+    pub endpoint: String,
+    /// Name of the model deployment requests go to
+    pub deployment: String,
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+    /// Kept in the OS keyring, not the settings file
+    #[serde(default)]
+    pub api_key: String,
+}
+
+fn default_azure_api_version() -> String {
+    DEFAULT_AZURE_API_VERSION.to_string()
+}
+
+impl AzureOpenAiConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.endpoint.starts_with("https://") {
+            return Err("Azure OpenAI endpoint must start with https://".to_string());
+        }
+        if self.deployment.trim().is_empty() {
+            return Err("Azure OpenAI deployment must not be empty".to_string());
+        }
+        if self.api_version.trim().is_empty() {
+            return Err("Azure OpenAI API version must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+enum Auth {
+    None,
+    Bearer(String),
+    /// Azure's `api-key` header
+    ApiKey(String),
+}
+
+/// Client for any server speaking the OpenAI chat completions API, Azure
+/// OpenAI included
+pub struct OpenAiClient {
+    client: Client,
+    /// Full chat completions URL
+    url: String,
+    auth: Auth,
+    model: String,
+    prompts: SystemPrompts,
+    usage: Option<UsageRecorder>,
+    cache: Option<ResponseCache>,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCall>>,
+}
+
+#[derive(Deserialize)]
+struct ToolCall {
+    id: String,
+    function: FunctionCall,
+}
+
+#[derive(Deserialize)]
+struct FunctionCall {
+    name: String,
+    /// JSON, as a string
+    arguments: String,
+}
+
+impl OpenAiClient {
+    pub fn new(config: OpenAiCompatibleConfig) -> Result<Self, String> {
+        Ok(Self {
+            client: http_client()?,
+            url: format!("{}/chat/completions", config.base_url.trim_end_matches('/')),
+            auth: if config.api_key.is_empty() {
+                Auth::None
+            } else {
+                Auth::Bearer(config.api_key)
+            },
+            model: config.model,
+            prompts: SystemPrompts::default(),
+            usage: None,
+            cache: None,
+        })
+    }
+
+    /// Client for an Azure OpenAI deployment. The deployment stands in for
+    /// the model, which Azure ignores in the request.
+    pub fn azure(config: AzureOpenAiConfig) -> Result<Self, String> {
+        Ok(Self {
+            client: http_client()?,
+            url: format!(
+                "{}/openai/deployments/{}/chat/completions?api-version={}",
+                config.endpoint.trim_end_matches('/'),
+                config.deployment.trim(),
+                config.api_version.trim()
+            ),
+            auth: Auth::ApiKey(config.api_key),
+            model: config.deployment,
+            prompts: SystemPrompts::default(),
+            usage: None,
+            cache: None,
+        })
+    }
+
+    /// Use customized system prompts in place of the built-in ones
+    pub fn with_system_prompts(mut self, prompts: SystemPrompts) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Record each request's token usage with `recorder`
+    pub fn with_usage_recorder(mut self, recorder: UsageRecorder) -> Self {
+        self.usage = Some(recorder);
+        self
+    }
+
+    /// Reuse replies to repeated requests from `cache`; None always asks
+    /// the model
+    pub fn with_response_cache(mut self, cache: Option<ResponseCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    fn post(&self, body: &Value) -> RequestBuilder {
+        let request = self.client.post(&self.url).json(body);
+        match &self.auth {
+            Auth::None => request,
+            Auth::Bearer(api_key) => request.bearer_auth(api_key),
+            Auth::ApiKey(api_key) => request.header("api-key", api_key),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn system_prompts(&self) -> &SystemPrompts {
+        &self.prompts
+    }
+
+    fn usage_recorder(&self) -> Option<&UsageRecorder> {
+        self.usage.as_ref()
+    }
+
+    fn response_cache(&self) -> Option<&ResponseCache> {
+        self.cache.as_ref()
+    }
+
+    async fn respond(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<Reply, ApiError> {
+        let mut request = json!({
+            "model": self.model,
+            "messages": chat_messages(system, messages),
+            "max_tokens": max_tokens,
+        });
+        if let Some(temperature) = temperature {
+            request["temperature"] = json!(temperature);
+        }
+        if !tools.is_empty() {
+            let tools: Vec<Value> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.input_schema,
+                        }
+                    })
+                })
+                .collect();
+            request["tools"] = json!(tools);
+        }
+
+        let response: ChatResponse = send_json(|| self.post(&request)).await?;
+        let usage = response
+            .usage
+            .map(|usage| Usage {
+                input_tokens: usage.prompt_tokens,
+                output_tokens: usage.completion_tokens,
+            })
+            .unwrap_or_default();
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| ApiError::InvalidResponse("no choices in response".to_string()))?
+            .message;
+
+        let mut content = Vec::new();
+        if let Some(text) = message.content.filter(|text| !text.is_empty()) {
+            content.push(ContentBlock::Text { text });
+        }
+        let tool_calls = message.tool_calls.unwrap_or_default();
+        let wants_tools = !tool_calls.is_empty();
+        // Arguments that aren't JSON are passed on, for the tool to reject
+        content.extend(tool_calls.into_iter().map(|call| ContentBlock::ToolUse {
+            id: call.id,
+            name: call.function.name,
+            input: serde_json::from_str(&call.function.arguments)
+                .unwrap_or(Value::String(call.function.arguments)),
+        }));
+
+        Ok(Reply {
+            content,
+            wants_tools,
+            usage,
+        })
+    }
+
+    async fn count_tokens(&self, _text: &str, _model: &str) -> Result<usize, ApiError> {
+        Err(ApiError::Unsupported(
+            "Token counting isn't available for OpenAI-compatible providers".to_string(),
+        ))
+    }
+}
+
+/// Messages in the chat completions format: the system prompt as the first
+/// message, tool calls on the assistant message and each tool result as a
+/// message of its own. Images and PDFs make the content a list of parts.
+fn chat_messages(system: Option<&str>, messages: &[Message]) -> Vec<Value> {
+    let mut chat = Vec::new();
+    if let Some(system) = system {
+        chat.push(json!({ "role": "system", "content": system }));
+    }
+
+    for message in messages {
+        let blocks = match &message.content {
+            MessageContent::Text(text) => {
+                chat.push(json!({ "role": message.role, "content": text }));
+                continue;
+            }
+            MessageContent::Blocks(blocks) => blocks,
+        };
+
+        let mut parts = Vec::new();
+        let mut tool_calls = Vec::new();
+        for block in blocks {
+            match block {
+                ContentBlock::Text { text } => parts.push(json!({ "type": "text", "text": text })),
+                ContentBlock::Image { source } => parts.push(json!({
+                    "type": "image_url",
+                    "image_url": { "url": image_url(source) },
+                })),
+                ContentBlock::Document {
+                    source: source @ MediaSource::Base64 { .. },
+                    title,
+                } => parts.push(json!({
+                    "type": "file",
+                    "file": {
+                        "filename": title.as_deref().unwrap_or("document.pdf"),
+                        "file_data": image_url(source),
+                    },
+                })),
+                ContentBlock::Document { source, title } => parts.push(json!({
+                    "type": "text",
+                    "text": document_text(source, title.as_deref()),
+                })),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(json!({
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": input.to_string() },
+                })),
+                ContentBlock::ToolResult {
+                    tool_use_id,
+                    content,
+                    ..
+                } => chat.push(json!({
+                    "role": "tool",
+                    "tool_call_id": tool_use_id,
+                    "content": content,
+                })),
+                ContentBlock::Unsupported => {}
+            }
+        }
+
+        if parts.is_empty() && tool_calls.is_empty() {
+            continue;
+        }
+        let content = if parts.is_empty() {
+            Value::Null
+        } else if parts.iter().all(|part| part["type"] == "text") {
+            let texts: Vec<&str> = parts.iter().filter_map(|part| part["text"].as_str()).collect();
+            json!(texts.join("\n"))
+        } else {
+            json!(parts)
+        };
+        let mut turn = json!({ "role": message.role, "content": content });
+        if !tool_calls.is_empty() {
+            turn["tool_calls"] = json!(tool_calls);
+        }
+        chat.push(turn);
+    }
+    chat
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Built-in system prompt for intent analysis
+pub const DEFAULT_INTENT_ANALYSIS_PROMPT: &str = r#"You are an expert at analyzing user intent for code-related tasks. Extract structured information from prompts and return ONLY valid JSON with no markdown formatting.
+
+Return a JSON object with:
+- "action": The primary action (create/modify/fix/explain/refactor/other)
+- "keywords": Array of key technical terms (3-8 terms)
+- "scope": The scope level (file/function/class/module/project)
+- "entities": Array of specific names mentioned (files, functions, classes, variables)"#;
+
+/// Built-in system prompt for pattern extraction
+pub const DEFAULT_PATTERN_EXTRACTION_PROMPT: &str = r#"You are an expert code analyst. Analyze code to identify patterns, conventions, and architectural insights that would help a developer write consistent code.
+
+Focus on:
+- Naming conventions (variables, functions, classes)
+- Code organization patterns
+- Error handling approaches
+- Common design patterns used
+- Testing strategies
+- Documentation style"#;
+
+/// System prompts customized in settings; None keeps the built-in one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemPrompts {
+    pub intent_analysis: Option<String>,
+    pub pattern_extraction: Option<String>,
+}
+
+impl SystemPrompts {
+    /// The built-in prompts, spelled out
+    pub fn defaults() -> Self {
+        Self {
+            intent_analysis: Some(DEFAULT_INTENT_ANALYSIS_PROMPT.to_string()),
+            pattern_extraction: Some(DEFAULT_PATTERN_EXTRACTION_PROMPT.to_string()),
+        }
+    }
+
+    pub fn intent_analysis(&self) -> &str {
+        self.intent_analysis
+            .as_deref()
+            .unwrap_or(DEFAULT_INTENT_ANALYSIS_PROMPT)
+    }
+
+    pub fn pattern_extraction(&self) -> &str {
+        self.pattern_extraction
+            .as_deref()
+            .unwrap_or(DEFAULT_PATTERN_EXTRACTION_PROMPT)
+    }
+
+    /// These prompts with those `overrides` customizes replacing them, as a
+    /// project's prompts replace the app-wide ones
+    pub fn overridden_by(&self, overrides: &SystemPrompts) -> Self {
+        Self {
+            intent_analysis: overrides
+                .intent_analysis
+                .clone()
+                .or_else(|| self.intent_analysis.clone()),
+            pattern_extraction: overrides
+                .pattern_extraction
+                .clone()
+                .or_else(|| self.pattern_extraction.clone()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let prompts = [&self.intent_analysis, &self.pattern_extraction];
+        if prompts
+            .iter()
+            .any(|prompt| prompt.as_deref().is_some_and(|p| p.trim().is_empty()))
+        {
+            return Err("System prompts must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
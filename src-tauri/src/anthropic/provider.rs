@@ -0,0 +1,587 @@
+use crate::anthropic::bedrock::BedrockConfig;
+use crate::anthropic::change_description::ChangeDescriptionKind;
+use crate::anthropic::error::ApiError;
+use crate::anthropic::models::{
+    ContentBlock, IntentAction, IntentScope, Message, PromptIntent, ToolDefinition, Usage,
+};
+use crate::anthropic::ollama::OllamaConfig;
+use crate::anthropic::openai::{AzureOpenAiConfig, OpenAiCompatibleConfig};
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::response_cache::ResponseCache;
+use crate::anthropic::test_suggestions::TestSuggestion;
+use crate::anthropic::usage::{UsageFeature, UsageRecorder};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Tool calls answered before giving up on a model that keeps calling more
+const MAX_TOOL_ROUNDS: usize = 8;
+const TOOL_USE_MAX_TOKENS: u32 = 4096;
+
+/// Which service intent analysis, prompt enhancement and chat go to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LlmProviderConfig {
+    /// Anthropic's API, with the `llm_model` setting
+    #[default]
+    Anthropic,
+    /// A server speaking the OpenAI chat completions API: OpenAI itself,
+    /// OpenRouter, Groq or a local one
+    OpenAiCompatible(OpenAiCompatibleConfig),
+    /// A local Ollama server, keeping prompts and code on this machine
+    Ollama(OllamaConfig),
+    /// Anthropic models on AWS Bedrock
+    Bedrock(BedrockConfig),
+    /// A model deployment on Azure OpenAI
+    AzureOpenAi(AzureOpenAiConfig),
+}
+
+impl LlmProviderConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            LlmProviderConfig::Anthropic => Ok(()),
+            LlmProviderConfig::OpenAiCompatible(config) => config.validate(),
+            LlmProviderConfig::Ollama(config) => config.validate(),
+            LlmProviderConfig::Bedrock(config) => config.validate(),
+            LlmProviderConfig::AzureOpenAi(config) => config.validate(),
+        }
+    }
+
+    /// The provider's API key or secret, which is kept in the OS keyring.
+    /// None for providers without one in settings.
+    pub fn secret_mut(&mut self) -> Option<&mut String> {
+        match self {
+            LlmProviderConfig::Anthropic | LlmProviderConfig::Ollama(_) => None,
+            LlmProviderConfig::OpenAiCompatible(config) => Some(&mut config.api_key),
+            LlmProviderConfig::Bedrock(config) => Some(&mut config.secret_access_key),
+            LlmProviderConfig::AzureOpenAi(config) => Some(&mut config.api_key),
+        }
+    }
+}
+
+/// Runs a tool call by name with the model's input, returning its output
+pub type ToolRunner<'a> = dyn FnMut(&str, &serde_json::Value) -> Result<String, String> + Send + 'a;
+
+/// Takes a reply's text a piece at a time, as the model writes it
+pub type TextSink<'a> = dyn FnMut(&str) + Send + 'a;
+
+/// What a model said in reply, with any tools it called
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub content: Vec<ContentBlock>,
+    /// Whether the model stopped to wait for tool results
+    pub wants_tools: bool,
+    /// Zero where the provider doesn't report it
+    pub usage: Usage,
+}
+
+impl Reply {
+    /// The reply's text, from its first text block
+    pub fn text(&self) -> Option<&str> {
+        self.content.iter().find_map(|block| match block {
+            ContentBlock::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    fn into_text(self) -> Result<String, String> {
+        self.text()
+            .map(str::to_string)
+            .ok_or_else(|| "No content in response".to_string())
+    }
+}
+
+/// A chat model the app can send prompts to. Providers implement the
+/// request itself; the tasks built on it are shared.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Model requests are sent to
+    fn model(&self) -> &str;
+
+    fn system_prompts(&self) -> &SystemPrompts;
+
+    /// Where requests' token usage is recorded, if anywhere
+    fn usage_recorder(&self) -> Option<&UsageRecorder>;
+
+    /// Where replies are cached for reuse, if anywhere
+    fn response_cache(&self) -> Option<&ResponseCache>;
+
+    /// Send a conversation, with `tools` the model may call instead of
+    /// answering directly
+    async fn respond(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<Reply, ApiError>;
+
+    /// Tokens `text` takes as a user message to `model`, by the provider's
+    /// own tokenizer
+    async fn count_tokens(&self, text: &str, model: &str) -> Result<usize, ApiError>;
+
+    /// `respond` without tools, giving the reply's text to `on_text` as it's
+    /// written. Providers that can't stream give it all at once.
+    async fn respond_streaming(
+        &self,
+        system: Option<&str>,
+        messages: &[Message],
+        max_tokens: u32,
+        temperature: Option<f32>,
+        on_text: &mut TextSink<'_>,
+    ) -> Result<Reply, ApiError> {
+        let reply = self
+            .respond(system, messages, &[], max_tokens, temperature)
+            .await?;
+        if let Some(text) = reply.text() {
+            on_text(text);
+        }
+        Ok(reply)
+    }
+
+    /// `respond`, recording the tokens used against `feature`. A cached
+    /// reply to the same request is returned without asking the model.
+    async fn respond_for(
+        &self,
+        feature: UsageFeature,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<Reply, ApiError> {
+        let cache_key = self.response_cache().map(|_| {
+            ResponseCache::key(self.model(), system, messages, tools, max_tokens, temperature)
+        });
+        if let (Some(cache), Some(key)) = (self.response_cache(), &cache_key) {
+            if let Some(reply) = cache.get(key) {
+                return Ok(reply);
+            }
+        }
+
+        let reply = self
+            .respond(system, messages, tools, max_tokens, temperature)
+            .await?;
+        if let Some(recorder) = self.usage_recorder() {
+            recorder.record(feature, self.model(), reply.usage);
+        }
+        if let (Some(cache), Some(key)) = (self.response_cache(), &cache_key) {
+            cache.put(key, &reply);
+        }
+        Ok(reply)
+    }
+
+    /// `respond_streaming`, recording usage and reusing cached replies as
+    /// `respond_for` does. A cached reply's text is given all at once.
+    async fn stream_for(
+        &self,
+        feature: UsageFeature,
+        system: Option<&str>,
+        messages: &[Message],
+        max_tokens: u32,
+        temperature: Option<f32>,
+        on_text: &mut TextSink<'_>,
+    ) -> Result<Reply, ApiError> {
+        let cache_key = self.response_cache().map(|_| {
+            ResponseCache::key(self.model(), system, messages, &[], max_tokens, temperature)
+        });
+        if let (Some(cache), Some(key)) = (self.response_cache(), &cache_key) {
+            if let Some(reply) = cache.get(key) {
+                if let Some(text) = reply.text() {
+                    on_text(text);
+                }
+                return Ok(reply);
+            }
+        }
+
+        let reply = self
+            .respond_streaming(system, messages, max_tokens, temperature, on_text)
+            .await?;
+        if let Some(recorder) = self.usage_recorder() {
+            recorder.record(feature, self.model(), reply.usage);
+        }
+        if let (Some(cache), Some(key)) = (self.response_cache(), &cache_key) {
+            cache.put(key, &reply);
+        }
+        Ok(reply)
+    }
+
+    /// Reply to a single prompt
+    async fn complete(
+        &self,
+        feature: UsageFeature,
+        system: &str,
+        prompt: String,
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> Result<String, String> {
+        let messages = [Message {
+            role: "user".to_string(),
+            content: prompt.into(),
+        }];
+        self.respond_for(feature, Some(system), &messages, &[], max_tokens, temperature)
+            .await?
+            .into_text()
+    }
+
+    /// Intent of a prompt. A reply that isn't valid intent JSON is sent
+    /// back to the model once with what was wrong before giving up.
+    async fn analyze_intent(&self, prompt: &str) -> Result<PromptIntent, String> {
+        let system = self.system_prompts().intent_analysis().to_string();
+        let mut messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: format!("Analyze this prompt and extract intent:\n\n{}", prompt).into(),
+            },
+        ];
+
+        let mut retried = false;
+        loop {
+            let text = self
+                .respond_for(UsageFeature::Intent, Some(&system), &messages, &[], 1024, Some(0.3))
+                .await?
+                .into_text()?;
+
+            match parse_intent(&text) {
+                Ok(intent) => return Ok(intent),
+                Err(e) if retried => return Err(format!("Failed to parse prompt intent: {}", e)),
+                Err(e) => {
+                    eprintln!("Malformed intent, asking again: {}", e);
+                    messages.push(Message {
+                        role: "assistant".to_string(),
+                        content: text.into(),
+                    });
+                    messages.push(Message {
+                        role: "user".to_string(),
+                        content: format!(
+                            "That reply was not valid: {}. Reply with only the corrected JSON object.",
+                            e
+                        )
+                        .into(),
+                    });
+                    retried = true;
+                }
+            }
+        }
+    }
+
+    /// Summary of the conversation in `transcript`, folding in the summary
+    /// of what came before it, if any
+    async fn summarize_conversation(
+        &self,
+        previous: Option<&str>,
+        transcript: &str,
+    ) -> Result<String, String> {
+        let system_prompt = "You summarize conversations between a developer and an assistant about their codebase. Keep decisions, conclusions, open questions and the files, functions and identifiers discussed. Be concise; reply with only the summary.";
+
+        let content = match previous {
+            Some(previous) => format!(
+                "Summary of the conversation so far:\n{}\n\nIt continued:\n{}",
+                previous, transcript
+            ),
+            None => transcript.to_string(),
+        };
+        self.complete(UsageFeature::Chat, system_prompt, content, 1024, Some(0.3))
+            .await
+    }
+
+    /// Rephrase a code search query `count` ways, for multi-query retrieval
+    async fn rewrite_query(&self, query: &str, count: usize) -> Result<Vec<String>, String> {
+        let system_prompt = r#"You rewrite code search queries to improve recall. Return ONLY a JSON array of strings with no markdown formatting.
+
+Each rewrite should:
+- Keep the original meaning
+- Use different vocabulary: likely identifier names, synonyms, or the underlying technical concept
+- Be a short keyword-style query (2-8 words)"#;
+
+        let prompt = format!("Rewrite this code search query {} different ways:\n\n{}", count, query);
+        let text = self
+            .complete(UsageFeature::QueryRewrite, system_prompt, prompt, 512, Some(0.7))
+            .await?;
+
+        let rewrites: Vec<String> = serde_json::from_str(strip_code_fence(&text))
+            .map_err(|e| format!("Failed to parse query rewrites: {}", e))?;
+
+        Ok(rewrites
+            .into_iter()
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty())
+            .take(count)
+            .collect())
+    }
+
+    async fn extract_patterns(&self, code_snippets: &str) -> Result<String, String> {
+        let system = self.system_prompts().pattern_extraction().to_string();
+        let prompt = format!(
+            "Analyze the following code and extract common patterns and conventions:\n\n{}",
+            code_snippets
+        );
+        self.complete(UsageFeature::Patterns, &system, prompt, 2048, Some(0.5))
+            .await
+    }
+
+    /// Text of a doc comment for the symbol described in `prompt`, without
+    /// comment markers
+    async fn write_docstring(&self, language: &str, prompt: String) -> Result<String, String> {
+        let system_prompt = format!(
+            "You write documentation comments for {} code. Reply with only the text of a doc comment for the given symbol: what it does, its parameters and return value where not obvious, and errors it can return. Use the callers shown to say what it's for, not how they use it. Follow the conventions of the language, but leave out comment markers such as /// or /** */ and quotes.",
+            language
+        );
+        let text = self
+            .complete(UsageFeature::Docstring, &system_prompt, prompt, 1024, Some(0.3))
+            .await?;
+        Ok(strip_code_fence(&text).to_string())
+    }
+
+    /// What a symbol does, from its source and the code around it in
+    /// `prompt`, given to `on_text` as it's written. `background` is added
+    /// to the system prompt, such as an overview of the codebase.
+    async fn explain_symbol(
+        &self,
+        prompt: String,
+        background: Option<&str>,
+        on_text: &mut TextSink<'_>,
+    ) -> Result<String, String> {
+        let mut system = "You explain code to developers working on it. Say what the given symbol actually does, step by step where it isn't obvious, how it fits with the code it calls and is called from, and anything surprising: side effects, error cases, assumptions it makes. Cite the names of the functions and types involved. Reply in markdown.".to_string();
+        if let Some(background) = background {
+            system = format!("{}\n\n{}", system, background);
+        }
+
+        let messages = [Message {
+            role: "user".to_string(),
+            content: prompt.into(),
+        }];
+        self.stream_for(UsageFeature::Chat, Some(&system), &messages, 2048, Some(0.3), on_text)
+            .await?
+            .into_text()
+    }
+
+    /// A commit message or pull request description for the change in
+    /// `prompt`
+    async fn describe_change(
+        &self,
+        kind: ChangeDescriptionKind,
+        prompt: String,
+    ) -> Result<String, String> {
+        let text = self
+            .complete(
+                UsageFeature::ChangeDescription,
+                kind.system_prompt(),
+                prompt,
+                kind.max_tokens(),
+                Some(0.3),
+            )
+            .await?;
+        Ok(strip_code_fence(&text).to_string())
+    }
+
+    /// Test cases for the symbol described in `prompt`
+    async fn suggest_tests(
+        &self,
+        language: &str,
+        prompt: String,
+    ) -> Result<Vec<TestSuggestion>, String> {
+        let system_prompt = format!(
+            r#"You write tests for {} code. Suggest the test cases the given symbol is missing: its main behavior if untested, then edge cases and error paths. Don't repeat what its existing tests cover. Write each test in the style of the existing tests and the project's testing conventions, using the same test framework and helpers.
+
+Return ONLY a JSON array with no markdown formatting, each element an object with:
+- "name": the test's name
+- "description": the behavior or edge case it covers, in one sentence
+- "code": the complete test"#,
+            language
+        );
+        let text = self
+            .complete(UsageFeature::TestSuggestions, &system_prompt, prompt, 4096, Some(0.3))
+            .await?;
+        serde_json::from_str(strip_code_fence(&text))
+            .map_err(|e| format!("Failed to parse test suggestions: {}", e))
+    }
+
+    /// A hierarchical overview of a project from its outline
+    async fn summarize_codebase(&self, outline: &str) -> Result<String, String> {
+        let system_prompt = "You write overviews of codebases for developers and assistants new to them. From the outline given, describe what the project does, then its main parts and how they fit together, then each key module in a sentence or two. Reply in markdown, from the whole project down to its modules, in no more than about 800 words.";
+
+        let prompt = format!("Outline of the codebase:\n\n{}", outline);
+        self.complete(UsageFeature::CodebaseSummary, system_prompt, prompt, 2048, Some(0.3))
+            .await
+    }
+
+    /// One conventions profile from analyses of different parts of a project
+    async fn merge_patterns(&self, analyses: &[String]) -> Result<String, String> {
+        let system_prompt = "You merge analyses of different parts of one codebase into a single profile of its conventions. Keep what the parts agree on, say where conventions differ by language or directory, and drop details that only apply to one file. Reply in markdown with sections for naming, code organization, error handling, design patterns, testing and documentation.";
+
+        let prompt = analyses
+            .iter()
+            .enumerate()
+            .map(|(i, analysis)| format!("Analysis {}:\n{}", i + 1, analysis))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        self.complete(UsageFeature::Patterns, system_prompt, prompt, 4096, Some(0.3))
+            .await
+    }
+
+    /// Let the model answer with `tools` available. Each call it makes is
+    /// run with `run_tool` and the result sent back, until it answers
+    /// without one. The exchange is appended to `messages` and the final
+    /// answer returned.
+    async fn run_with_tools(
+        &self,
+        messages: &mut Vec<Message>,
+        system: Option<String>,
+        tools: &[ToolDefinition],
+        run_tool: &mut ToolRunner<'_>,
+    ) -> Result<String, String> {
+        for _ in 0..MAX_TOOL_ROUNDS {
+            let reply = self
+                .respond_for(
+                    UsageFeature::Chat,
+                    system.as_deref(),
+                    messages,
+                    tools,
+                    TOOL_USE_MAX_TOKENS,
+                    None,
+                )
+                .await?;
+
+            // Blocks this client can't read can't be sent back either
+            let content: Vec<ContentBlock> = reply
+                .content
+                .iter()
+                .filter(|block| !matches!(block, ContentBlock::Unsupported))
+                .cloned()
+                .collect();
+            messages.push(Message {
+                role: "assistant".to_string(),
+                content: content.into(),
+            });
+
+            if !reply.wants_tools {
+                return reply.into_text();
+            }
+
+            // A failed call is reported to the model, which may try another way
+            let results: Vec<ContentBlock> = reply
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::ToolUse { id, name, input } => {
+                        let (content, is_error) = match run_tool(name, input) {
+                            Ok(output) => (output, false),
+                            Err(e) => (e, true),
+                        };
+                        Some(ContentBlock::ToolResult {
+                            tool_use_id: id.clone(),
+                            content,
+                            is_error,
+                        })
+                    }
+                    _ => None,
+                })
+                .collect();
+            messages.push(Message {
+                role: "user".to_string(),
+                content: results.into(),
+            });
+        }
+
+        Err(format!("No answer after {} rounds of tool calls", MAX_TOOL_ROUNDS))
+    }
+}
+
+/// Intent as the model described it, before its fields are checked
+#[derive(Deserialize)]
+struct RawIntent {
+    action: String,
+    #[serde(default)]
+    keywords: Vec<String>,
+    scope: String,
+    #[serde(default)]
+    entities: Vec<String>,
+}
+
+/// Parse and check an intent reply, ignoring case and blank or repeated
+/// terms. The error says what was wrong, to be sent back to the model.
+fn parse_intent(text: &str) -> Result<PromptIntent, String> {
+    let raw: RawIntent = serde_json::from_str(strip_code_fence(text))
+        .map_err(|e| format!("invalid JSON ({})", e))?;
+
+    let action = raw.action.trim().to_lowercase();
+    let action = IntentAction::ALL
+        .into_iter()
+        .find(|candidate| candidate.as_str() == action)
+        .ok_or_else(|| {
+            let names: Vec<&str> = IntentAction::ALL.iter().map(|a| a.as_str()).collect();
+            format!("\"action\" must be one of {}", names.join("/"))
+        })?;
+    let scope = raw.scope.trim().to_lowercase();
+    let scope = IntentScope::ALL
+        .into_iter()
+        .find(|candidate| candidate.as_str() == scope)
+        .ok_or_else(|| {
+            let names: Vec<&str> = IntentScope::ALL.iter().map(|s| s.as_str()).collect();
+            format!("\"scope\" must be one of {}", names.join("/"))
+        })?;
+
+    Ok(PromptIntent {
+        action,
+        keywords: distinct_terms(raw.keywords),
+        scope,
+        entities: distinct_terms(raw.entities),
+    })
+}
+
+fn distinct_terms(terms: Vec<String>) -> Vec<String> {
+    let mut distinct: Vec<String> = Vec::new();
+    for term in terms {
+        let term = term.trim();
+        if !term.is_empty() && !distinct.iter().any(|t| t.eq_ignore_ascii_case(term)) {
+            distinct.push(term.to_string());
+        }
+    }
+    distinct
+}
+
+/// JSON from a response, tolerating a fenced block despite the instructions
+fn strip_code_fence(text: &str) -> &str {
+    text.trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_intent_strips_code_fence() {
+        let reply = "```json\n{\"action\": \"Fix\", \"keywords\": [\"login\"], \"scope\": \"file\", \"entities\": []}\n```";
+        let intent = parse_intent(reply).unwrap();
+        assert_eq!(intent.action, IntentAction::Fix);
+        assert_eq!(intent.scope, IntentScope::File);
+        assert_eq!(intent.keywords, vec!["login"]);
+
+        assert_eq!(strip_code_fence("```\n{}\n```"), "{}");
+        assert_eq!(strip_code_fence("  {} "), "{}");
+    }
+
+    #[test]
+    fn test_parse_intent_rejects_unknown_values() {
+        let bad_action = r#"{"action": "delete", "keywords": [], "scope": "file"}"#;
+        assert!(parse_intent(bad_action).unwrap_err().contains("\"action\""));
+
+        let bad_scope = r#"{"action": "fix", "keywords": [], "scope": "galaxy"}"#;
+        assert!(parse_intent(bad_scope).unwrap_err().contains("\"scope\""));
+
+        assert!(parse_intent("not json").unwrap_err().starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_parse_intent_dedups_terms() {
+        let reply = r#"{"action": "explain", "keywords": ["Auth", " auth ", "", "token"], "scope": "project", "entities": ["User", "user"]}"#;
+        let intent = parse_intent(reply).unwrap();
+        assert_eq!(intent.keywords, vec!["Auth", "token"]);
+        assert_eq!(intent.entities, vec!["User"]);
+    }
+}
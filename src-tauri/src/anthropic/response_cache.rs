@@ -0,0 +1,133 @@
+use crate::anthropic::models::{ContentBlock, Message, ToolDefinition, Usage};
+use crate::anthropic::provider::Reply;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long replies are reused when settings don't say otherwise
+pub const DEFAULT_RESPONSE_CACHE_HOURS: u64 = 24;
+
+#[derive(Serialize, Deserialize)]
+struct CachedReply {
+    created_at: u64,
+    content: Vec<ContentBlock>,
+    wants_tools: bool,
+}
+
+/// Replies kept on disk by request, so asking the same thing again doesn't
+/// wait on or pay for the model a second time
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self { dir, ttl }
+    }
+
+    /// Key of a request: a hash of everything that shapes the reply
+    pub fn key(
+        model: &str,
+        system: Option<&str>,
+        messages: &[Message],
+        tools: &[ToolDefinition],
+        max_tokens: u32,
+        temperature: Option<f32>,
+    ) -> String {
+        let request = json!({
+            "model": model,
+            "system": system,
+            "messages": messages,
+            "tools": tools,
+            "max_tokens": max_tokens,
+            "temperature": temperature,
+        });
+        hex::encode(Sha256::digest(request.to_string().as_bytes()))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The reply cached for `key`, unless it's expired. Cached replies
+    /// report no usage, as nothing was billed for them.
+    pub fn get(&self, key: &str) -> Option<Reply> {
+        let path = self.path(key);
+        let json = fs::read_to_string(&path).ok()?;
+        let cached: CachedReply = match serde_json::from_str(&json) {
+            Ok(cached) => cached,
+            Err(_) => {
+                let _ = fs::remove_file(&path);
+                return None;
+            }
+        };
+        if now_secs().saturating_sub(cached.created_at) > self.ttl.as_secs() {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        Some(Reply {
+            content: cached.content,
+            wants_tools: cached.wants_tools,
+            usage: Usage::default(),
+        })
+    }
+
+    /// Keep a reply for `key`. Failures are logged; caching never fails a
+    /// request.
+    pub fn put(&self, key: &str, reply: &Reply) {
+        let cached = CachedReply {
+            created_at: now_secs(),
+            content: reply.content.clone(),
+            wants_tools: reply.wants_tools,
+        };
+        let result = fs::create_dir_all(&self.dir)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string(&cached).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(self.path(key), json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("Failed to cache LLM response: {}", e);
+        }
+    }
+
+    /// Delete every cached reply, or only expired ones. Returns how many
+    /// were deleted.
+    pub fn clear(&self, expired_only: bool) -> Result<usize, String> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(format!("Failed to read response cache: {}", e)),
+        };
+
+        let mut removed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let expired = || {
+                fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|json| serde_json::from_str::<CachedReply>(&json).ok())
+                    .is_none_or(|cached| {
+                        now_secs().saturating_sub(cached.created_at) > self.ttl.as_secs()
+                    })
+            };
+            if (!expired_only || expired()) && fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
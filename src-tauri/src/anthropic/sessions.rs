@@ -0,0 +1,199 @@
+use crate::anthropic::attachments::media_tokens;
+use crate::anthropic::context_builder::TokenCounter;
+use crate::anthropic::models::{ContentBlock, Message, MessageContent};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Tokens of history sent with each turn; older turns are summarized
+pub const SESSION_HISTORY_TOKENS: usize = 40_000;
+
+/// A conversation about a project, saved as one JSON file per session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatSession {
+    pub id: u64,
+    pub project_path: String,
+    pub title: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+    /// Turns still sent to the model, oldest first
+    pub messages: Vec<Message>,
+    /// Summary of the turns dropped to fit the context
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+/// A session as listed, without its messages
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: u64,
+    pub title: String,
+    pub message_count: usize,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl ChatSession {
+    pub fn new(id: u64, project_path: String, title: String) -> Self {
+        let now = now_secs();
+        Self {
+            id,
+            project_path,
+            title,
+            created_at: now,
+            updated_at: now,
+            messages: Vec::new(),
+            summary: None,
+        }
+    }
+
+    pub fn info(&self) -> SessionInfo {
+        SessionInfo {
+            id: self.id,
+            title: self.title.clone(),
+            message_count: self.messages.len(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    fn path(dir: &Path, id: u64) -> PathBuf {
+        dir.join(format!("{}.json", id))
+    }
+
+    pub fn load(dir: &Path, id: u64) -> Result<Self, String> {
+        let path = Self::path(dir, id);
+        if !path.exists() {
+            return Err(format!("No chat session {}", id));
+        }
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read chat session: {}", e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse chat session: {}", e))
+    }
+
+    /// Every session in `dir`, most recently used first. Unreadable ones
+    /// are skipped.
+    pub fn load_all(dir: &Path) -> Vec<Self> {
+        let mut sessions: Vec<Self> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+                    .filter_map(|json| serde_json::from_str(&json).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+        sessions
+    }
+
+    /// An id no session in `dir` has
+    pub fn next_id(dir: &Path) -> u64 {
+        Self::load_all(dir)
+            .iter()
+            .map(|session| session.id + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<(), String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create sessions directory: {}", e))?;
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize chat session: {}", e))?;
+        fs::write(Self::path(dir, self.id), json)
+            .map_err(|e| format!("Failed to write chat session: {}", e))
+    }
+
+    pub fn delete(dir: &Path, id: u64) -> Result<(), String> {
+        let path = Self::path(dir, id);
+        if path.exists() {
+            fs::remove_file(path).map_err(|e| format!("Failed to delete chat session: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Remove the oldest turns until the rest fit in `budget` tokens,
+    /// returning them. Turns are only cut where the user starts one, so a
+    /// tool result never loses its call, and the latest turn always stays.
+    pub fn take_overflow(&mut self, budget: usize, counter: &TokenCounter) -> Vec<Message> {
+        let mut used = 0;
+        let mut keep_from = None;
+        for (i, message) in self.messages.iter().enumerate().rev() {
+            if starts_turn(message) && keep_from.is_none() {
+                keep_from = Some(i);
+            }
+            // Tool results included, since they're sent back with the turn
+            used += message_tokens(message, counter);
+            if used > budget {
+                break;
+            }
+            if starts_turn(message) {
+                keep_from = Some(i);
+            }
+        }
+        let keep_from = keep_from.unwrap_or(0);
+        self.messages.drain(..keep_from).collect()
+    }
+
+    pub fn touch(&mut self) {
+        self.updated_at = now_secs();
+    }
+}
+
+/// A user message with text and attachments, rather than tool results
+fn starts_turn(message: &Message) -> bool {
+    message.role == "user"
+        && match &message.content {
+            MessageContent::Text(_) => true,
+            MessageContent::Blocks(blocks) => !blocks
+                .iter()
+                .any(|block| matches!(block, ContentBlock::ToolResult { .. })),
+        }
+}
+
+/// Images and documents are counted by what they're expected to take rather
+/// than by their encoded data
+fn message_tokens(message: &Message, counter: &TokenCounter) -> usize {
+    match &message.content {
+        MessageContent::Text(text) => counter.count(text),
+        MessageContent::Blocks(blocks) => blocks
+            .iter()
+            .map(|block| {
+                media_tokens(block, counter).unwrap_or_else(|| {
+                    counter.count(&serde_json::to_string(block).unwrap_or_default())
+                })
+            })
+            .sum(),
+    }
+}
+
+/// A message as a line of transcript for summarizing. Tool calls show what
+/// was searched; their results are left out.
+pub fn transcript_line(message: &Message) -> String {
+    let text = match &message.content {
+        MessageContent::Text(text) => text.clone(),
+        MessageContent::Blocks(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text.clone()),
+                ContentBlock::ToolUse { name, input, .. } => Some(format!("[{} {}]", name, input)),
+                ContentBlock::Image { .. } => Some("[image]".to_string()),
+                ContentBlock::Document { title, .. } => Some(format!(
+                    "[document {}]",
+                    title.as_deref().unwrap_or("attached")
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    format!("{}: {}", message.role, text)
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
@@ -0,0 +1,156 @@
+use crate::anthropic::docstrings::symbol_source;
+use crate::models::code_index::{CodeSymbol, CodebaseIndex};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Existing tests of a symbol shown to the model
+const MAX_EXISTING_TESTS: usize = 5;
+/// Source of the symbol sent; longer bodies are cut off
+const MAX_SYMBOL_SOURCE_CHARS: usize = 12_000;
+/// Lines sent of each existing test
+const TEST_LINES: usize = 40;
+
+/// A test case proposed by the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestSuggestion {
+    pub name: String,
+    /// The behavior or edge case it covers
+    pub description: String,
+    /// The test, written to go alongside the existing tests
+    pub code: String,
+}
+
+/// Tests proposed for a symbol, with what they were written from
+#[derive(Debug, Clone, Serialize)]
+pub struct TestSuggestions {
+    pub symbol: CodeSymbol,
+    pub language: String,
+    /// Tests found calling the symbol
+    pub existing_tests: Vec<CodeSymbol>,
+    /// Whether the project's testing conventions were given to the model
+    pub conventions_used: bool,
+    pub suggestions: Vec<TestSuggestion>,
+}
+
+/// Whether a path is a test file or in a test directory
+pub fn is_test_path(path: &str) -> bool {
+    let path = Path::new(path);
+    let in_test_dir = path
+        .parent()
+        .into_iter()
+        .flat_map(|dir| dir.iter())
+        .filter_map(|c| c.to_str())
+        .any(|dir| matches!(dir, "test" | "tests" | "__tests__" | "spec"));
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    in_test_dir
+        || stem.starts_with("test_")
+        || stem.ends_with("_test")
+        || stem.ends_with(".test")
+        || stem.ends_with(".spec")
+}
+
+/// Whether a symbol is a test: named like one, in a test file, or marked
+/// with a test attribute or decorator in its file's `source`
+pub fn is_test_symbol(symbol: &CodeSymbol, source: &str) -> bool {
+    // A "Test" prefix alone also fits TestHarness or Testimonial, so
+    // classes count only through their path or attributes
+    if symbol.name.starts_with("test_") || is_test_path(&symbol.file_path) {
+        return true;
+    }
+    let lines: Vec<&str> = source.lines().collect();
+    let mut above = symbol.start_line.saturating_sub(1).min(lines.len());
+    while above > 0 {
+        let line = lines[above - 1].trim_start();
+        if line.starts_with("#[") || line.starts_with('@') {
+            if line.contains("test") {
+                return true;
+            }
+            above -= 1;
+        } else {
+            break;
+        }
+    }
+    false
+}
+
+/// Tests calling a symbol, with the source of each one's file. Callers in
+/// files that can't be read are left out.
+pub fn existing_tests(index: &CodebaseIndex, symbol: &CodeSymbol) -> Vec<(CodeSymbol, String)> {
+    let mut sources: HashMap<String, Option<String>> = HashMap::new();
+    let mut tests = Vec::new();
+    for caller in index.direct_callers(symbol) {
+        if tests.len() >= MAX_EXISTING_TESTS {
+            break;
+        }
+        let source = sources
+            .entry(caller.file_path.clone())
+            .or_insert_with(|| fs::read_to_string(&caller.file_path).ok());
+        if let Some(source) = source {
+            if is_test_symbol(&caller, source) {
+                let source = source.clone();
+                tests.push((caller, source));
+            }
+        }
+    }
+    tests
+}
+
+/// What the model is asked to write tests from: the symbol's source, the
+/// tests it already has, and the project's testing conventions when known
+pub fn test_prompt(
+    symbol: &CodeSymbol,
+    language: &str,
+    source: &str,
+    tests: &[(CodeSymbol, String)],
+    conventions: Option<&str>,
+) -> String {
+    let kind = format!("{:?}", symbol.kind).to_lowercase();
+    let mut prompt = format!(
+        "Suggest tests for {} `{}` in {} ({}):\n\n```{}\n{}\n```\n",
+        kind,
+        symbol.name,
+        symbol.file_path,
+        language,
+        language,
+        symbol_source(source, symbol, MAX_SYMBOL_SOURCE_CHARS)
+    );
+
+    if tests.is_empty() {
+        let _ = writeln!(prompt, "\nIt has no tests yet.");
+    } else {
+        let _ = writeln!(
+            prompt,
+            "\nIts existing tests, which the new ones shouldn't repeat:"
+        );
+        for (test, test_source) in tests {
+            let mut lines = symbol_source(test_source, test, usize::MAX)
+                .lines()
+                .take(TEST_LINES)
+                .collect::<Vec<_>>()
+                .join("\n");
+            if (test.end_line + 1).saturating_sub(test.start_line) > TEST_LINES {
+                lines.push_str("\n...");
+            }
+            let _ = writeln!(
+                prompt,
+                "\n{} in {}:\n```{}\n{}\n```",
+                test.name, test.file_path, language, lines
+            );
+        }
+    }
+
+    if let Some(conventions) = conventions {
+        let _ = writeln!(
+            prompt,
+            "\nThe project's testing conventions:\n{}",
+            conventions
+        );
+    }
+    prompt
+}
@@ -0,0 +1,42 @@
+use crate::anthropic::models::ToolDefinition;
+use serde::Deserialize;
+
+/// Searches the active project's index
+pub const SEARCH_CODE_TOOL: &str = "search_code";
+
+/// Results a search returns unless the model asks for fewer
+pub const DEFAULT_TOOL_RESULTS: usize = 8;
+pub const MAX_TOOL_RESULTS: usize = 20;
+
+/// System prompt for answering questions with the index as a tool
+pub const CODEBASE_ASSISTANT_PROMPT: &str = "You answer questions about a codebase. Use the search_code tool to find the relevant code before answering, searching again with other terms when results miss. Base your answer on the code found and cite file paths and line numbers.";
+
+#[derive(Debug, Deserialize)]
+pub struct SearchCodeInput {
+    pub query: String,
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+pub fn search_code_tool() -> ToolDefinition {
+    ToolDefinition {
+        name: SEARCH_CODE_TOOL.to_string(),
+        description: "Search the indexed codebase for symbols and code matching a query. \
+                      Returns the best matching chunks with their file paths and lines."
+            .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Keywords or identifiers to search for"
+                },
+                "max_results": {
+                    "type": "integer",
+                    "description": format!("Chunks to return, at most {}", MAX_TOOL_RESULTS)
+                }
+            },
+            "required": ["query"]
+        }),
+    }
+}
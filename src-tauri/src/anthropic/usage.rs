@@ -0,0 +1,207 @@
+use crate::anthropic::models::Usage;
+use crate::anthropic::sigv4::civil_from_days;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Serializes read-modify-write of the usage file across concurrent requests
+static USAGE_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// What a request to the LLM was made for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageFeature {
+    Intent,
+    Patterns,
+    QueryRewrite,
+    /// Answering questions and chat sessions, summaries of them included
+    Chat,
+    /// Docstrings and summaries generated by batch jobs
+    Batch,
+    /// Overviews of whole codebases
+    CodebaseSummary,
+    /// Doc comments proposed for single symbols
+    Docstring,
+    /// Commit messages and pull request descriptions
+    ChangeDescription,
+    /// Test cases proposed for single symbols
+    TestSuggestions,
+}
+
+/// Totals for one project, day, feature and model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    /// UTC, as `YYYY-MM-DD`
+    pub date: String,
+    /// Empty for requests made with no project open
+    pub project_path: String,
+    pub feature: UsageFeature,
+    pub model: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// USD at the catalog's prices when the requests were made; None for
+    /// models it doesn't price
+    pub cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost of the requests that could be priced
+    pub cost_usd: f64,
+    /// Requests to models without a known price, left out of the cost
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, entry: &UsageEntry) {
+        self.requests += entry.requests;
+        self.input_tokens += entry.input_tokens;
+        self.output_tokens += entry.output_tokens;
+        match entry.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += entry.requests,
+        }
+    }
+}
+
+/// Spending over a period, per day and in total
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    /// Newest day first
+    pub entries: Vec<UsageEntry>,
+    pub by_feature: BTreeMap<UsageFeature, UsageTotals>,
+    pub total: UsageTotals,
+}
+
+/// Where a client's usage is recorded: the usage file, with the project
+/// and model it's recorded against
+#[derive(Debug, Clone)]
+pub struct UsageRecorder {
+    path: PathBuf,
+    project_path: String,
+    /// USD per million input and output tokens, when known
+    prices: Option<(f64, f64)>,
+}
+
+impl UsageRecorder {
+    pub fn new(path: PathBuf, project_path: Option<String>, prices: Option<(f64, f64)>) -> Self {
+        Self {
+            path,
+            project_path: project_path.unwrap_or_default(),
+            prices,
+        }
+    }
+
+    /// Add a request's tokens to today's totals. Failures are logged; usage
+    /// tracking never fails a request.
+    pub fn record(&self, feature: UsageFeature, model: &str, usage: Usage) {
+        if let Err(e) = self.try_record(feature, model, usage) {
+            eprintln!("Failed to record LLM usage: {}", e);
+        }
+    }
+
+    fn try_record(&self, feature: UsageFeature, model: &str, usage: Usage) -> Result<(), String> {
+        let _guard = USAGE_FILE_LOCK
+            .lock()
+            .map_err(|e| format!("Failed to lock usage file: {}", e))?;
+
+        let mut entries = load_entries(&self.path)?;
+        let date = today();
+        let cost = self.prices.map(|(input, output)| {
+            (usage.input_tokens as f64 * input + usage.output_tokens as f64 * output) / 1_000_000.0
+        });
+
+        let existing = entries.iter_mut().find(|entry| {
+            entry.date == date
+                && entry.project_path == self.project_path
+                && entry.feature == feature
+                && entry.model == model
+        });
+        match existing {
+            Some(entry) => {
+                entry.requests += 1;
+                entry.input_tokens += u64::from(usage.input_tokens);
+                entry.output_tokens += u64::from(usage.output_tokens);
+                entry.cost_usd = entry.cost_usd.zip(cost).map(|(total, cost)| total + cost);
+            }
+            None => entries.push(UsageEntry {
+                date,
+                project_path: self.project_path.clone(),
+                feature,
+                model: model.to_string(),
+                requests: 1,
+                input_tokens: u64::from(usage.input_tokens),
+                output_tokens: u64::from(usage.output_tokens),
+                cost_usd: cost,
+            }),
+        }
+
+        let json = serde_json::to_string_pretty(&entries)
+            .map_err(|e| format!("Failed to serialize usage: {}", e))?;
+        fs::write(&self.path, json).map_err(|e| format!("Failed to write usage: {}", e))
+    }
+}
+
+/// Usage of one project, or of all when `project_path` is None, over the
+/// last `days` days including today, or all recorded
+pub fn usage_report(
+    path: &Path,
+    project_path: Option<&str>,
+    days: Option<u32>,
+) -> Result<UsageReport, String> {
+    let entries = {
+        let _guard = USAGE_FILE_LOCK
+            .lock()
+            .map_err(|e| format!("Failed to lock usage file: {}", e))?;
+        load_entries(path)?
+    };
+    let since = days.map(|days| date_days_ago(days.saturating_sub(1)));
+
+    // Dates are zero-padded, so they compare as strings
+    let mut entries: Vec<UsageEntry> = entries
+        .into_iter()
+        .filter(|entry| project_path.is_none_or(|path| entry.project_path == path))
+        .filter(|entry| since.as_ref().is_none_or(|since| entry.date >= *since))
+        .collect();
+    entries.sort_by(|a, b| b.date.cmp(&a.date).then(a.feature.cmp(&b.feature)));
+
+    let mut by_feature: BTreeMap<UsageFeature, UsageTotals> = BTreeMap::new();
+    let mut total = UsageTotals::default();
+    for entry in &entries {
+        by_feature.entry(entry.feature).or_default().add(entry);
+        total.add(entry);
+    }
+
+    Ok(UsageReport {
+        entries,
+        by_feature,
+        total,
+    })
+}
+
+fn load_entries(path: &Path) -> Result<Vec<UsageEntry>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read usage: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse usage: {}", e))
+}
+
+fn today() -> String {
+    date_days_ago(0)
+}
+
+fn date_days_ago(days: u32) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let (year, month, day) = civil_from_days((now / 86_400) as i64 - i64::from(days));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
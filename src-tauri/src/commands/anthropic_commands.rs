@@ -1,13 +1,541 @@
+use crate::anthropic::bedrock::BedrockClient;
+use crate::anthropic::catalog::{available_models, model_prices, ModelInfo};
+use crate::anthropic::change_description::{
+    change_prompt, changed_symbols, ChangeDescription, ChangeDescriptionKind,
+};
+use crate::anthropic::codebase_summary::{codebase_outline, CodebaseSummary};
+use crate::anthropic::context_builder::TokenCounter;
+use crate::anthropic::conventions::{
+    chunk_samples, sample_files, ConventionsProfile, DEFAULT_PATTERN_SAMPLE_TOKENS,
+    PATTERN_CHUNK_TOKENS,
+};
+use crate::anthropic::credentials::anthropic_api_key;
+use crate::anthropic::docstrings::{
+    docstring_edit, docstring_prompt, DocstringEdit, MAX_DOCSTRING_CALLERS,
+};
+use crate::anthropic::explain::SymbolContext;
+use crate::anthropic::models::PromptIntent;
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::ollama::{OllamaClient, OllamaConfig, OllamaModel, OllamaStatus};
+use crate::anthropic::openai::OpenAiClient;
+use crate::anthropic::provider::{LlmProvider, LlmProviderConfig};
+use crate::anthropic::response_cache::ResponseCache;
+use crate::anthropic::test_suggestions::{existing_tests, test_prompt, TestSuggestions};
+use crate::anthropic::usage::{usage_report, UsageRecorder, UsageReport};
 use crate::anthropic::AnthropicClient;
+use crate::commands::index_commands::{activate_project, current_settings, persistence, IndexerState};
+use crate::indexing::git_changes::diff as git_diff;
+use crate::indexing::persistence::ProjectSettings;
+use crate::models::code_index::{CodeSymbol, CodebaseIndex, SymbolKind};
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+/// A client for the configured provider and model, using the system
+/// prompts of `project` where it has its own and the app-wide ones
+/// otherwise. Keys are read from the OS keyring, never passed in from the
+/// UI. Token usage is recorded against `project`.
+pub fn llm_client(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    project: Option<&str>,
+) -> Result<Box<dyn LlmProvider>, String> {
+    let (project_prompts, usage_path, cache_dir) = {
+        let persistence = persistence(state, app_handle)?;
+
+        let prompts = match project {
+            Some(path) => {
+                let settings_path = persistence.get_project_settings_path(path);
+                ProjectSettings::load_or_default(&settings_path, path)?.system_prompts
+            }
+            None => SystemPrompts::default(),
+        };
+        (
+            prompts,
+            persistence.get_usage_path(),
+            persistence.get_response_cache_dir(),
+        )
+    };
+
+    let settings = current_settings(state)?;
+    let prompts = settings.system_prompts.overridden_by(&project_prompts);
+    let recorder = UsageRecorder::new(
+        usage_path,
+        project.map(String::from),
+        model_prices(&settings.llm_provider, &settings.llm_model),
+    );
+    let cache = (settings.response_cache_hours > 0).then(|| {
+        ResponseCache::new(
+            cache_dir,
+            Duration::from_secs(settings.response_cache_hours * 3600),
+        )
+    });
+    Ok(match settings.llm_provider {
+        LlmProviderConfig::Anthropic => Box::new(
+            AnthropicClient::new(anthropic_api_key()?)?
+                .with_model(settings.llm_model)
+                .with_system_prompts(prompts)
+                .with_usage_recorder(recorder)
+                .with_response_cache(cache),
+        ),
+        LlmProviderConfig::OpenAiCompatible(config) => Box::new(
+            OpenAiClient::new(config)?
+                .with_system_prompts(prompts)
+                .with_usage_recorder(recorder)
+                .with_response_cache(cache),
+        ),
+        LlmProviderConfig::Ollama(config) => Box::new(
+            OllamaClient::new(config)?
+                .with_system_prompts(prompts)
+                .with_usage_recorder(recorder)
+                .with_response_cache(cache),
+        ),
+        LlmProviderConfig::Bedrock(config) => Box::new(
+            BedrockClient::new(config)?
+                .with_system_prompts(prompts)
+                .with_usage_recorder(recorder)
+                .with_response_cache(cache),
+        ),
+        LlmProviderConfig::AzureOpenAi(config) => Box::new(
+            OpenAiClient::azure(config)?
+                .with_system_prompts(prompts)
+                .with_usage_recorder(recorder)
+                .with_response_cache(cache),
+        ),
+    })
+}
 
 #[tauri::command]
-pub async fn analyze_intent(api_key: String, prompt: String) -> Result<String, String> {
-    let client = AnthropicClient::new(api_key);
+pub async fn analyze_intent(
+    prompt: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<PromptIntent, String> {
+    let client = llm_client(&app_handle, &state, None)?;
     client.analyze_intent(&prompt).await
 }
 
 #[tauri::command]
-pub async fn extract_patterns(api_key: String, code_snippets: String) -> Result<String, String> {
-    let client = AnthropicClient::new(api_key);
+pub async fn extract_patterns(
+    code_snippets: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<String, String> {
+    let client = llm_client(&app_handle, &state, None)?;
     client.extract_patterns(&code_snippets).await
 }
+
+/// Extract the conventions of a whole project: sample files representing
+/// each of its languages and directories from the index, up to
+/// `max_sample_tokens` of code, analyze them a chunk at a time and merge
+/// the analyses into one profile. The profile is kept in the project's
+/// cache for test suggestions to follow.
+#[tauri::command]
+pub async fn extract_project_patterns(
+    path: String,
+    max_sample_tokens: Option<usize>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<ConventionsProfile, String> {
+    let client = llm_client(&app_handle, &state, Some(&path))?;
+
+    let samples = {
+        let active = activate_project(&state, &app_handle, &path)?;
+        sample_files(
+            active.index(),
+            max_sample_tokens.unwrap_or(DEFAULT_PATTERN_SAMPLE_TOKENS),
+            &TokenCounter::estimate(),
+        )
+    };
+    if samples.is_empty() {
+        return Err("No indexed files with symbols to analyze".to_string());
+    }
+
+    let chunks = chunk_samples(&samples, PATTERN_CHUNK_TOKENS);
+    let mut analyses = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        println!("Extracting patterns from chunk {} of {}", i + 1, chunks.len());
+        analyses.push(client.extract_patterns(chunk).await?);
+    }
+    let profile = if analyses.len() == 1 {
+        analyses.remove(0)
+    } else {
+        client.merge_patterns(&analyses).await?
+    };
+
+    let mut languages: Vec<String> = samples.iter().map(|s| s.language.clone()).collect();
+    languages.sort();
+    languages.dedup();
+    let profile = ConventionsProfile {
+        project_path: path,
+        profile,
+        languages,
+        files_sampled: samples.iter().map(|s| s.path.clone()).collect(),
+        chunks: chunks.len(),
+        sample_tokens: samples.iter().map(|s| s.tokens).sum(),
+    };
+    profile.save(&conventions_path(&app_handle, &state, &profile.project_path)?)?;
+    Ok(profile)
+}
+
+fn conventions_path(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    project_path: &str,
+) -> Result<PathBuf, String> {
+    Ok(persistence(state, app_handle)?.get_conventions_path(project_path))
+}
+
+fn codebase_summary_path(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    project_path: &str,
+) -> Result<PathBuf, String> {
+    Ok(persistence(state, app_handle)?.get_codebase_summary_path(project_path))
+}
+
+/// The project's overview, if one has been generated. Failures to read it
+/// are logged; it's only background for the model.
+pub fn cached_codebase_summary(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    project_path: &str,
+) -> Option<CodebaseSummary> {
+    codebase_summary_path(app_handle, state, project_path)
+        .and_then(|path| CodebaseSummary::load(&path))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            None
+        })
+}
+
+/// Have the model write an overview of the project from its directories,
+/// key modules, their most called symbols and what imports what. The
+/// overview is kept in the project's cache and reused, unless `refresh` is
+/// set, until the project is indexed again; chat sessions and codebase
+/// questions get it as background.
+#[tauri::command]
+pub async fn generate_codebase_summary(
+    refresh: Option<bool>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<CodebaseSummary, String> {
+    let summary_path = codebase_summary_path(&app_handle, &state, &project)?;
+
+    let (outline, indexed_at, files) = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        let index = active.index();
+        (codebase_outline(index), index.indexed_at, index.files.len())
+    };
+
+    if !refresh.unwrap_or(false) {
+        if let Some(cached) = CodebaseSummary::load(&summary_path)? {
+            if cached.indexed_at == indexed_at {
+                return Ok(cached);
+            }
+        }
+    }
+
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    let summary = CodebaseSummary {
+        project_path: project,
+        summary: client.summarize_codebase(&outline).await?,
+        model: client.model().to_string(),
+        generated_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        indexed_at,
+        files,
+    };
+    summary.save(&summary_path)?;
+    println!("Generated a summary of {} files", files);
+    Ok(summary)
+}
+
+/// A symbol of an indexed file, by name and optionally first line, with the
+/// file's language
+pub fn find_file_symbol(
+    index: &CodebaseIndex,
+    file_path: &str,
+    symbol_name: &str,
+    start_line: Option<usize>,
+) -> Result<(CodeSymbol, String), String> {
+    let file = index
+        .files
+        .get(file_path)
+        .ok_or_else(|| format!("File not indexed: {}", file_path))?;
+    let symbol = file
+        .symbols
+        .iter()
+        .filter(|s| !matches!(s.kind, SymbolKind::Import | SymbolKind::Export))
+        .find(|s| s.name == symbol_name && start_line.is_none_or(|line| s.start_line == line))
+        .ok_or_else(|| format!("Symbol not found: {} in {}", symbol_name, file_path))?;
+    Ok((symbol.clone(), file.language.clone()))
+}
+
+/// Propose a doc comment for a symbol, written by the model from the
+/// symbol's source and a few of its callers. Nothing is written to the
+/// file; the edit returned replaces the symbol's doc comment, or adds one,
+/// for the UI to show as a diff.
+#[tauri::command]
+pub async fn generate_docstring(
+    file_path: String,
+    symbol_name: String,
+    start_line: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<DocstringEdit, String> {
+    let (symbol, language, callers) = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        let index = active.index();
+        let (symbol, language) = find_file_symbol(index, &file_path, &symbol_name, start_line)?;
+        let callers: Vec<CodeSymbol> = index
+            .direct_callers(&symbol)
+            .into_iter()
+            .take(MAX_DOCSTRING_CALLERS)
+            .collect();
+        (symbol, language, callers)
+    };
+
+    let source = fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+    let callers: Vec<(CodeSymbol, String)> = callers
+        .into_iter()
+        .filter_map(|caller| {
+            let source = fs::read_to_string(&caller.file_path).ok()?;
+            Some((caller, source))
+        })
+        .collect();
+
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    let doc = client
+        .write_docstring(
+            &language,
+            docstring_prompt(&symbol, &language, &source, &callers),
+        )
+        .await?;
+    Ok(docstring_edit(&symbol, &language, &source, &doc))
+}
+
+/// Propose tests a symbol is missing, written by the model from the
+/// symbol's source, the tests already calling it and the testing section
+/// of the project's conventions profile, when `extract_project_patterns`
+/// has been run. Nothing is written to disk.
+#[tauri::command]
+pub async fn suggest_tests(
+    file_path: String,
+    symbol_name: String,
+    start_line: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<TestSuggestions, String> {
+    let (symbol, language, tests) = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        let index = active.index();
+        let (symbol, language) = find_file_symbol(index, &file_path, &symbol_name, start_line)?;
+        let tests = existing_tests(index, &symbol);
+        (symbol, language, tests)
+    };
+    let conventions = conventions_path(&app_handle, &state, &project)
+        .and_then(|path| ConventionsProfile::load(&path))
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            None
+        })
+        .and_then(|profile| profile.testing_section());
+
+    let source = fs::read_to_string(&file_path)
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+    let prompt = test_prompt(&symbol, &language, &source, &tests, conventions.as_deref());
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    let suggestions = client.suggest_tests(&language, prompt).await?;
+    Ok(TestSuggestions {
+        symbol,
+        language,
+        existing_tests: tests.into_iter().map(|(test, _)| test).collect(),
+        conventions_used: conventions.is_some(),
+        suggestions,
+    })
+}
+
+/// A piece of an explanation, as `explain_symbol` streams it
+#[derive(Debug, Clone, Serialize)]
+pub struct ExplanationEvent {
+    pub explanation_id: String,
+    pub text: String,
+}
+
+/// Explain what a symbol actually does, from its source, its parent, its
+/// file's imports, the code it calls and where it's called from, with the
+/// project's overview as background when one has been generated. The
+/// explanation is streamed as `symbol-explanation` events tagged with
+/// `explanation_id` as it's written, and returned whole at the end.
+#[tauri::command]
+pub async fn explain_symbol(
+    file_path: String,
+    symbol_name: String,
+    explanation_id: Option<String>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<String, String> {
+    let explanation_id = explanation_id.unwrap_or_else(|| format!("{}:{}", file_path, symbol_name));
+
+    let context = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        let index = active.index();
+        let (symbol, language) = find_file_symbol(index, &file_path, &symbol_name, None)?;
+        SymbolContext::gather(index, symbol, language)
+    };
+    let overview = cached_codebase_summary(&app_handle, &state, &project);
+
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    client
+        .explain_symbol(
+            context.prompt(),
+            overview.map(|overview| overview.preamble()).as_deref(),
+            &mut |text| {
+                let _ = app_handle.emit(
+                    "symbol-explanation",
+                    ExplanationEvent {
+                        explanation_id: explanation_id.clone(),
+                        text: text.to_string(),
+                    },
+                );
+            },
+        )
+        .await
+}
+
+/// Write a commit message for the staged changes, or every uncommitted
+/// change when nothing is staged, or a pull request description for the
+/// current branch since it left `base` (main or master by default). The
+/// diff is read with git and the indexed symbols it touches are described
+/// alongside it.
+#[tauri::command]
+pub async fn generate_change_description(
+    kind: ChangeDescriptionKind,
+    base: Option<String>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<ChangeDescription, String> {
+    let diff = git_diff(&project, kind.diff_target(), base.as_deref())?;
+    if diff.patch.trim().is_empty() {
+        return Err("No changes to describe".to_string());
+    }
+    let symbols = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        changed_symbols(active.index(), &diff)
+    };
+
+    let (prompt, diff_truncated) = change_prompt(kind, &diff, &symbols);
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    let text = client.describe_change(kind, prompt).await?;
+    Ok(ChangeDescription {
+        kind,
+        text,
+        files: diff.files.into_iter().map(|(path, _)| path).collect(),
+        symbols,
+        diff_truncated,
+    })
+}
+
+/// The built-in system prompts, for showing or restoring them in settings
+#[tauri::command]
+pub async fn get_default_system_prompts() -> Result<SystemPrompts, String> {
+    Ok(SystemPrompts::defaults())
+}
+
+/// Tokens `text` takes for `model`, the configured model by default, as
+/// counted by the provider
+#[tauri::command]
+pub async fn count_tokens(
+    text: String,
+    model: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<usize, String> {
+    let client = llm_client(&app_handle, &state, None)?;
+    let model = model.unwrap_or_else(|| client.model().to_string());
+    Ok(client.count_tokens(&text, &model).await?)
+}
+
+/// Models the configured provider offers, with context size, pricing and
+/// what each is recommended for, where known
+#[tauri::command]
+pub async fn list_models(state: State<'_, IndexerState>) -> Result<Vec<ModelInfo>, String> {
+    let settings = current_settings(&state)?;
+    available_models(&settings.llm_provider, &settings.llm_model).await
+}
+
+/// Tokens and estimated cost of LLM requests per day and feature, for one
+/// project or all of them, over the last `days` days or all recorded
+#[tauri::command]
+pub async fn get_usage_report(
+    project: Option<String>,
+    days: Option<u32>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<UsageReport, String> {
+    let usage_path = {
+        persistence(&state, &app_handle)?.get_usage_path()
+    };
+    usage_report(&usage_path, project.as_deref(), days)
+}
+
+/// Delete cached LLM replies, or only expired ones, returning how many were
+/// deleted
+#[tauri::command]
+pub async fn clear_response_cache(
+    expired_only: bool,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<usize, String> {
+    let cache_dir = {
+        persistence(&state, &app_handle)?.get_response_cache_dir()
+    };
+    let hours = current_settings(&state)?.response_cache_hours;
+    let cache = ResponseCache::new(cache_dir, Duration::from_secs(hours * 3600));
+    let removed = cache.clear(expired_only)?;
+    println!("Cleared {} cached LLM responses", removed);
+    Ok(removed)
+}
+
+/// The given Ollama config, or else the configured one, or else the defaults
+fn ollama_config(config: Option<OllamaConfig>, state: &IndexerState) -> Result<OllamaConfig, String> {
+    if let Some(config) = config {
+        return Ok(config);
+    }
+    Ok(match current_settings(state)?.llm_provider {
+        LlmProviderConfig::Ollama(config) => config,
+        _ => OllamaConfig::default(),
+    })
+}
+
+/// Models pulled into an Ollama server, for picking one in settings
+#[tauri::command]
+pub async fn list_ollama_models(
+    config: Option<OllamaConfig>,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<OllamaModel>, String> {
+    let config = ollama_config(config, &state)?;
+    Ok(OllamaClient::new(config)?.list_models().await?)
+}
+
+/// Whether an Ollama server is running and has the model pulled, checking
+/// the given config before it's saved, or the configured one
+#[tauri::command]
+pub async fn check_ollama(
+    config: Option<OllamaConfig>,
+    state: State<'_, IndexerState>,
+) -> Result<OllamaStatus, String> {
+    let config = ollama_config(config, &state)?;
+    Ok(OllamaClient::new(config)?.status().await)
+}
@@ -0,0 +1,231 @@
+use crate::anthropic::batches::{BatchJob, BatchJobKind, MAX_BATCH_SYMBOLS};
+use crate::anthropic::catalog::model_prices;
+use crate::anthropic::credentials::anthropic_api_key;
+use crate::anthropic::provider::{LlmProvider, LlmProviderConfig};
+use crate::anthropic::usage::{UsageFeature, UsageRecorder};
+use crate::anthropic::AnthropicClient;
+use crate::commands::index_commands::{activate_project, current_settings, persistence, IndexerState};
+use crate::indexing::tree_sitter_indexer::files_matching_patterns;
+use crate::models::code_index::{CodeSymbol, SymbolKind};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+/// Symbol kinds worth documenting; imports, exports and variables aren't
+const DOCUMENTED_KINDS: &[SymbolKind] = &[
+    SymbolKind::Function,
+    SymbolKind::Method,
+    SymbolKind::Class,
+    SymbolKind::Struct,
+    SymbolKind::Interface,
+    SymbolKind::Enum,
+];
+
+fn batch_jobs_path(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    project_path: &str,
+) -> Result<PathBuf, String> {
+    Ok(persistence(state, app_handle)?.get_batch_jobs_path(project_path))
+}
+
+/// Batches are Anthropic's, so jobs need it as the provider
+fn batch_client(state: &IndexerState) -> Result<AnthropicClient, String> {
+    let settings = current_settings(state)?;
+    if settings.llm_provider != LlmProviderConfig::Anthropic {
+        return Err("Batch jobs need Anthropic as the LLM provider".to_string());
+    }
+    Ok(AnthropicClient::new(anthropic_api_key()?)?.with_model(settings.llm_model))
+}
+
+/// Submit a batch job writing docstrings or summaries for the project's
+/// undocumented functions, methods and types, optionally only in files
+/// matching gitignore-style `patterns`. Jobs run in the background for up
+/// to a day at half the usual price; poll them with `poll_batch_job`.
+#[tauri::command]
+pub async fn submit_batch_job(
+    kind: BatchJobKind,
+    patterns: Option<Vec<String>>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<BatchJob, String> {
+    let jobs_path = batch_jobs_path(&app_handle, &state, &project)?;
+    let client = batch_client(&state)?;
+
+    let symbols: Vec<CodeSymbol> = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        let index = active.index();
+
+        let mut paths: Vec<String> = match patterns.filter(|p| !p.is_empty()) {
+            Some(patterns) => {
+                files_matching_patterns(&index.root_path, index.files.keys(), &patterns)?
+            }
+            None => index.files.keys().cloned().collect(),
+        };
+        paths.sort();
+        paths
+            .iter()
+            .filter_map(|path| index.files.get(path))
+            .flat_map(|file| &file.symbols)
+            .filter(|s| s.doc_comment.is_none() && DOCUMENTED_KINDS.contains(&s.kind))
+            .take(MAX_BATCH_SYMBOLS)
+            .cloned()
+            .collect()
+    };
+    if symbols.is_empty() {
+        return Err("No undocumented symbols to process".to_string());
+    }
+
+    let mut sources: HashMap<&str, String> = HashMap::new();
+    for symbol in &symbols {
+        if !sources.contains_key(symbol.file_path.as_str()) {
+            let source = fs::read_to_string(&symbol.file_path).unwrap_or_default();
+            sources.insert(&symbol.file_path, source);
+        }
+    }
+    let with_sources: Vec<(&CodeSymbol, &str)> = symbols
+        .iter()
+        .map(|symbol| (symbol, sources[symbol.file_path.as_str()].as_str()))
+        .collect();
+
+    let (requests, targets) = BatchJob::requests(kind, client.model(), &with_sources);
+    let batch = client.create_batch(&requests).await?;
+
+    let mut job = BatchJob {
+        id: batch.id.clone(),
+        project_path: project.clone(),
+        kind,
+        model: client.model().to_string(),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        status: String::new(),
+        counts: Default::default(),
+        symbols: targets,
+        ingested_at: None,
+        ingested: 0,
+    };
+    job.update(&batch);
+
+    let mut jobs = BatchJob::load_all(&jobs_path)?;
+    jobs.insert(0, job.clone());
+    BatchJob::save_all(&jobs_path, &jobs)?;
+    println!("Submitted batch {} for {} symbols", job.id, job.symbols.len());
+    Ok(job)
+}
+
+/// A project's batch jobs, newest first, as last polled
+#[tauri::command]
+pub async fn list_batch_jobs(
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<BatchJob>, String> {
+    BatchJob::load_all(&batch_jobs_path(&app_handle, &state, &project)?)
+}
+
+/// Check on a batch job. Once it has ended, its results are written into
+/// the index as doc comments of the symbols still without one, and saved
+/// with the project's cache.
+#[tauri::command]
+pub async fn poll_batch_job(
+    job_id: String,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<BatchJob, String> {
+    let jobs_path = batch_jobs_path(&app_handle, &state, &project)?;
+    let mut jobs = BatchJob::load_all(&jobs_path)?;
+    let mut job = jobs
+        .iter()
+        .find(|job| job.id == job_id)
+        .cloned()
+        .ok_or_else(|| format!("Batch job not found: {}", job_id))?;
+    if job.ingested_at.is_some() {
+        return Ok(job);
+    }
+
+    let client = batch_client(&state)?;
+    let batch = client.get_batch(&job.id).await?;
+    job.update(&batch);
+
+    if batch.processing_status == "ended" {
+        let replies = client.batch_results(&batch).await?;
+        let docs = job.docs(&replies);
+
+        // Written into the index under the same locks as the project lookup
+        let changed = {
+            let mut active = activate_project(&state, &app_handle, &project)?;
+
+            // Batched requests are billed at half price
+            let prices = model_prices(&LlmProviderConfig::Anthropic, &job.model)
+                .map(|(input, output)| (input / 2.0, output / 2.0));
+            let recorder = UsageRecorder::new(
+                active.persistence().get_usage_path(),
+                Some(project.clone()),
+                prices,
+            );
+            for reply in &replies {
+                recorder.record(UsageFeature::Batch, &job.model, reply.message.usage);
+            }
+
+            let (indexer, index) = active.parts_mut();
+            let changed = indexer.annotate_symbols(index, &docs)?;
+            let persistence = active.persistence();
+            if !changed.is_empty() && persistence.has_cached_index(&project) {
+                if let Err(e) = persistence.save_changed_files(active.index(), &changed) {
+                    eprintln!("Failed to save documented files: {}", e);
+                }
+            }
+            changed
+        };
+
+        job.ingested = docs.len();
+        job.ingested_at = Some(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        );
+        println!(
+            "Ingested {} results of batch {} into {} files",
+            docs.len(),
+            job.id,
+            changed.len()
+        );
+    }
+
+    if let Some(stored) = jobs.iter_mut().find(|stored| stored.id == job.id) {
+        *stored = job.clone();
+    }
+    BatchJob::save_all(&jobs_path, &jobs)?;
+    Ok(job)
+}
+
+/// Stop a batch job. Results of requests already processed are ingested
+/// when it's next polled.
+#[tauri::command]
+pub async fn cancel_batch_job(
+    job_id: String,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<BatchJob, String> {
+    let jobs_path = batch_jobs_path(&app_handle, &state, &project)?;
+    let mut jobs = BatchJob::load_all(&jobs_path)?;
+    let job = jobs
+        .iter_mut()
+        .find(|job| job.id == job_id)
+        .ok_or_else(|| format!("Batch job not found: {}", job_id))?;
+
+    let client = batch_client(&state)?;
+    let batch = client.cancel_batch(&job.id).await?;
+    job.update(&batch);
+    let job = job.clone();
+
+    BatchJob::save_all(&jobs_path, &jobs)?;
+    Ok(job)
+}
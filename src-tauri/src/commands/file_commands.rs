@@ -0,0 +1,291 @@
+use crate::commands::index_commands::{current_settings, IndexerState};
+use crate::commands::settings_commands::save_settings;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, State};
+
+/// Context lines added around a requested window when none are given
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// Lines of a file as currently on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct FileContent {
+    pub file_path: String,
+    pub content: String,
+    /// 1-based, inclusive range of the returned lines
+    pub start_line: usize,
+    pub end_line: usize,
+    pub total_lines: usize,
+}
+
+/// Resolve a path inside a project, refusing anything outside it
+pub fn project_file(file_path: &str, project: &str) -> Result<PathBuf, String> {
+    let root = Path::new(project)
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve project root: {}", e))?;
+    let path = Path::new(file_path)
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve {}: {}", file_path, e))?;
+
+    if !path.starts_with(&root) {
+        return Err(format!("File is outside the project: {}", file_path));
+    }
+    Ok(path)
+}
+
+/// Read a file, or the `start_line..=end_line` window of it (1-based)
+/// widened by `context_lines` on each side
+#[tauri::command]
+pub async fn get_file_content(
+    file_path: String,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    context_lines: Option<usize>,
+    project: String,
+) -> Result<FileContent, String> {
+    let path = project_file(&file_path, &project)?;
+    let source = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+
+    let lines: Vec<&str> = source.lines().collect();
+    let total_lines = lines.len();
+
+    let windowed = start_line.is_some() || end_line.is_some();
+    let context = if windowed { context_lines.unwrap_or(DEFAULT_CONTEXT_LINES) } else { 0 };
+    let first = start_line.unwrap_or(1).max(1).saturating_sub(context).max(1);
+    let last = end_line
+        .unwrap_or(total_lines)
+        .saturating_add(context)
+        .min(total_lines);
+
+    let content = if first <= last {
+        lines[first - 1..last].join("\n")
+    } else {
+        String::new()
+    };
+
+    Ok(FileContent {
+        file_path,
+        content,
+        start_line: first,
+        end_line: last,
+        total_lines,
+    })
+}
+
+/// Editors tried in order when no command is configured, with the
+/// template that opens a file at a line
+const KNOWN_EDITORS: &[(&str, &str)] = &[
+    ("code", "code -g {file}:{line}:{column}"),
+    ("cursor", "cursor -g {file}:{line}:{column}"),
+    ("zed", "zed {file}:{line}:{column}"),
+    ("subl", "subl {file}:{line}:{column}"),
+    ("idea", "idea --line {line} {file}"),
+    ("rustrover", "rustrover --line {line} {file}"),
+    ("webstorm", "webstorm --line {line} {file}"),
+    ("pycharm", "pycharm --line {line} {file}"),
+];
+
+/// Terminal editors from $VISUAL/$EDITOR that take `+line`
+const TERMINAL_EDITORS: &[&str] = &["vim", "nvim", "vi", "nano", "emacs", "hx", "micro"];
+
+/// How files are opened in an external editor; stored in the app settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditorConfig {
+    /// Command template; `{file}`, `{line}` and `{column}` are substituted
+    /// per argument. None detects an installed editor.
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+/// Find an executable on PATH, including Windows launcher extensions
+fn find_executable(name: &str) -> Option<PathBuf> {
+    let extensions: &[&str] = if cfg!(windows) { &["exe", "cmd", "bat"] } else { &[] };
+    let paths = std::env::var_os("PATH")?;
+
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(name);
+        std::iter::once(candidate.clone())
+            .chain(extensions.iter().map(|ext| candidate.with_extension(ext)))
+            .find(|path| path.is_file())
+    })
+}
+
+/// The command template for the first installed editor, falling back to
+/// a terminal editor from $VISUAL/$EDITOR
+fn detect_editor_command() -> Option<String> {
+    if let Some((_, template)) = KNOWN_EDITORS.iter().find(|(bin, _)| find_executable(bin).is_some()) {
+        return Some(template.to_string());
+    }
+
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).ok()?;
+    let name = Path::new(editor.split_whitespace().next()?).file_name()?.to_str()?.to_string();
+    if !TERMINAL_EDITORS.contains(&name.as_str()) {
+        return Some(format!("{} {{file}}", editor));
+    }
+
+    if cfg!(target_os = "macos") {
+        Some(format!("osascript -e \"tell application \\\"Terminal\\\" to do script \\\"{} +{{line}} '{{file}}'\\\"\"", editor))
+    } else if find_executable("x-terminal-emulator").is_some() {
+        Some(format!("x-terminal-emulator -e {} +{{line}} {{file}}", editor))
+    } else {
+        None
+    }
+}
+
+/// Split a command template into arguments, honoring double quotes, and
+/// fill in the placeholders
+fn expand_template(template: &str, file: &str, line: usize, column: usize) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    let mut chars = template.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg || !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if has_arg || !current.is_empty() {
+        args.push(current);
+    }
+
+    args.into_iter()
+        .map(|arg| {
+            arg.replace("{file}", file)
+                .replace("{line}", &line.to_string())
+                .replace("{column}", &column.to_string())
+        })
+        .collect()
+}
+
+/// Open a file at a line (and column) in the configured or detected editor
+#[tauri::command]
+pub async fn open_in_editor(
+    file_path: String,
+    line: Option<usize>,
+    column: Option<usize>,
+    project: String,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let path = project_file(&file_path, &project)?;
+    let template = current_settings(&state)?
+        .editor_command
+        .or_else(detect_editor_command)
+        .ok_or_else(|| "No editor found; set an editor command in settings".to_string())?;
+
+    let args = expand_template(
+        &template,
+        &path.to_string_lossy(),
+        line.unwrap_or(1).max(1),
+        column.unwrap_or(1).max(1),
+    );
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| "Editor command is empty".to_string())?;
+
+    // Windows editors are often .cmd launchers, which need the shell
+    let program_path = find_executable(program).unwrap_or_else(|| PathBuf::from(program));
+    let is_script = program_path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("cmd") || ext.eq_ignore_ascii_case("bat"));
+    let mut command = if is_script {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(&program_path);
+        command
+    } else {
+        Command::new(&program_path)
+    };
+
+    command
+        .args(args)
+        .spawn()
+        .map_err(|e| format!("Failed to launch editor '{}': {}", program, e))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_editor_config(state: State<'_, IndexerState>) -> Result<EditorConfig, String> {
+    Ok(EditorConfig {
+        command: current_settings(&state)?.editor_command,
+    })
+}
+
+/// Save the editor command template; None returns to detection
+#[tauri::command]
+pub async fn set_editor_config(
+    config: EditorConfig,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    if config.command.as_deref().is_some_and(|c| !c.contains("{file}")) {
+        return Err("Editor command must contain {file}".to_string());
+    }
+    save_settings(&app_handle, &state, |settings| settings.editor_command = config.command)
+}
+
+/// The command template that would be used without a configured one
+#[tauri::command]
+pub async fn detect_editor() -> Result<Option<String>, String> {
+    Ok(detect_editor_command())
+}
+
+/// Show a file selected in Finder, Explorer or the desktop's file manager.
+/// Linux falls back to opening the containing folder when the file manager
+/// cannot select items.
+#[tauri::command]
+pub async fn reveal_in_file_manager(
+    file_path: String,
+    project: String,
+) -> Result<(), String> {
+    let path = project_file(&file_path, &project)?;
+
+    let spawned = if cfg!(target_os = "macos") {
+        Command::new("open").arg("-R").arg(&path).spawn()
+    } else if cfg!(windows) {
+        // Explorer wants the flag and path as one argument
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(&path);
+        Command::new("explorer").arg(select).spawn()
+    } else {
+        let uri = format!("file://{}", path.to_string_lossy());
+        let shown = Command::new("dbus-send")
+            .args([
+                "--session",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{}", uri))
+            .arg("string:")
+            .status()
+            .is_ok_and(|status| status.success());
+
+        if shown {
+            return Ok(());
+        }
+        let folder = path.parent().unwrap_or(&path);
+        Command::new("xdg-open").arg(folder).spawn()
+    };
+
+    spawned
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open file manager: {}", e))
+}
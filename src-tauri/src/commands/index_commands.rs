@@ -1,14 +1,556 @@
-use crate::indexing::persistence::{CacheMetadata, PersistenceConfig};
-use crate::indexing::tree_sitter_indexer::TreeSitterIndexer;
+use crate::anthropic::attachments::{load_attachments, media_tokens, with_attachments};
+use crate::anthropic::context_builder::{
+    build_prompt, format_chunks, select_context, EnhancedPrompt, TokenCounter,
+    DEFAULT_CONTEXT_TOKENS,
+};
+use crate::anthropic::models::Message;
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::tools::{
+    search_code_tool, SearchCodeInput, CODEBASE_ASSISTANT_PROMPT, DEFAULT_TOOL_RESULTS,
+    MAX_TOOL_RESULTS, SEARCH_CODE_TOOL,
+};
+use crate::commands::anthropic_commands::{cached_codebase_summary, llm_client};
+use crate::commands::settings_commands::save_settings;
+use crate::indexing::cache_bundle::{read_bundle, read_manifest, write_bundle, BundleManifest};
+use crate::indexing::clustering::SemanticCluster;
+use crate::indexing::embedding_backend::EmbeddingBackendConfig;
+use crate::indexing::embedding_generator::{
+    download_model, is_model_available, DevicePreference, EmbeddingModel, ModelSource,
+};
+use crate::indexing::git_changes::changed_files;
+use crate::indexing::hybrid_search::HybridConfig;
+use crate::indexing::index_checkpoint::IndexCheckpoint;
+use crate::indexing::index_export::{rebase_index, rebuild_index, ExportFormat, IndexExport};
+use crate::indexing::persistence::{
+    CacheGcReport, CacheInfo, CacheMetadata, PersistenceConfig, PinnedChunk, ProjectSettings,
+    QueryHistoryEntry, QueryMode, CACHE_FORMAT_VERSION,
+};
+use crate::indexing::project_grep::{grep_file, GrepMatch, GrepOptions, DEFAULT_MAX_GREP_MATCHES};
+use crate::indexing::query_analyzer::QueryAnalyzer;
+use crate::indexing::tantivy_indexer::{FullTextConfig, TantivyIndexer, TantivySearchResult};
+use crate::indexing::text_normalizer::{NormalizerConfig, TextNormalizer};
+use crate::indexing::tree_sitter_indexer::{
+    files_matching_patterns, ProjectStores, TreeSitterIndexer,
+};
+use crate::indexing::vector_store::{DuplicateCluster, HnswConfig, VectorFilter, VectorStore};
 use crate::models::code_index::*;
-use std::sync::Mutex;
-use tauri::{AppHandle, State};
+use crate::settings::AppSettings;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 // Global state for the indexer
 pub struct IndexerState {
     pub indexer: Mutex<TreeSitterIndexer>,
     pub current_index: Mutex<Option<CodebaseIndex>>,
     pub persistence: Mutex<Option<PersistenceConfig>>,
+    // Read-only full-text indexes of cached projects, keyed by project path
+    pub project_searchers: Mutex<HashMap<String, TantivyIndexer>>,
+    // Loaded projects besides the active one, least recently used first
+    pub loaded_projects: Mutex<Vec<LoadedProject>>,
+    // App-wide settings; locked last and only long enough to read or swap them
+    pub settings: Mutex<AppSettings>,
+    // Outcome of the cache cleanup run at startup
+    pub cache_gc_report: Mutex<Option<CacheGcReport>>,
+}
+
+/// A copy of the app-wide settings
+pub fn current_settings(state: &IndexerState) -> Result<AppSettings, String> {
+    Ok(state
+        .settings
+        .lock()
+        .map_err(|e| format!("Failed to lock settings: {}", e))?
+        .clone())
+}
+
+/// Lock on the persistence config, set up on first use
+pub fn persistence<'a>(
+    state: &'a IndexerState,
+    app_handle: &AppHandle,
+) -> Result<PersistenceGuard<'a>, String> {
+    let mut persistence_lock = state
+        .persistence
+        .lock()
+        .map_err(|e| format!("Failed to lock persistence: {}", e))?;
+    if persistence_lock.is_none() {
+        *persistence_lock = Some(PersistenceConfig::new(app_handle)?);
+    }
+    Ok(PersistenceGuard(persistence_lock))
+}
+
+/// The locked persistence config `persistence` returns, never unset
+pub struct PersistenceGuard<'a>(MutexGuard<'a, Option<PersistenceConfig>>);
+
+impl Deref for PersistenceGuard<'_> {
+    type Target = PersistenceConfig;
+
+    fn deref(&self) -> &PersistenceConfig {
+        self.0
+            .as_ref()
+            .expect("persistence() sets the config before handing out the lock")
+    }
+}
+
+/// A loaded project set aside while another one is active
+pub struct LoadedProject {
+    pub index: CodebaseIndex,
+    pub stores: ProjectStores,
+}
+
+/// Make a loaded project the active one, parking the current one, and
+/// hold it locked. Query commands take the project's root path and run
+/// against the returned project, so a concurrent command can't swap in
+/// another project halfway through.
+pub fn activate_project<'a>(
+    state: &'a IndexerState,
+    app_handle: &AppHandle,
+    project: &str,
+) -> Result<ActiveProject<'a>, String> {
+    try_activate_project(state, app_handle, project)?
+        .ok_or_else(|| format!("Project not loaded: {}", project))
+}
+
+/// `activate_project`, or None when the project isn't loaded
+fn try_activate_project<'a>(
+    state: &'a IndexerState,
+    app_handle: &AppHandle,
+    project: &str,
+) -> Result<Option<ActiveProject<'a>>, String> {
+    let persistence = persistence(state, app_handle)?;
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+    let mut current_index = state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?;
+
+    if !current_index.as_ref().is_some_and(|index| index.root_path == project) {
+        let mut loaded = state
+            .loaded_projects
+            .lock()
+            .map_err(|e| format!("Failed to lock loaded projects: {}", e))?;
+        let Some(position) = loaded.iter().position(|p| p.index.root_path == project) else {
+            return Ok(None);
+        };
+        let next = loaded.remove(position);
+
+        if let Some(index) = current_index.take() {
+            loaded.push(LoadedProject {
+                index,
+                stores: indexer.park_project(),
+            });
+        }
+        indexer.resume_project(next.stores);
+        *current_index = Some(next.index);
+        load_project_settings(&persistence, &mut indexer, project)?;
+    }
+
+    Ok(Some(ActiveProject {
+        persistence,
+        indexer,
+        index: current_index,
+    }))
+}
+
+/// The project `activate_project` made active, locked until dropped. Holds
+/// std mutexes, so it must be dropped before a command awaits.
+pub struct ActiveProject<'a> {
+    persistence: PersistenceGuard<'a>,
+    indexer: MutexGuard<'a, TreeSitterIndexer>,
+    index: MutexGuard<'a, Option<CodebaseIndex>>,
+}
+
+impl ActiveProject<'_> {
+    pub fn persistence(&self) -> &PersistenceConfig {
+        &self.persistence
+    }
+
+    pub fn indexer(&self) -> &TreeSitterIndexer {
+        &self.indexer
+    }
+
+    pub fn index(&self) -> &CodebaseIndex {
+        self.index
+            .as_ref()
+            .expect("activate_project sets the index before handing out the lock")
+    }
+
+    /// The indexer and index together, for updating the index in place
+    pub fn parts_mut(&mut self) -> (&mut TreeSitterIndexer, &mut CodebaseIndex) {
+        let index = self
+            .index
+            .as_mut()
+            .expect("activate_project sets the index before handing out the lock");
+        (&mut self.indexer, index)
+    }
+
+    /// Add a search to the project's query history. Failures are only
+    /// logged so they never fail the search itself.
+    fn record_query(&self, entry: QueryHistoryEntry) {
+        let path = self.persistence.get_query_history_path(&self.index().root_path);
+        if let Err(e) = entry.append(&path) {
+            eprintln!("Failed to record query: {}", e);
+        }
+    }
+}
+
+/// Set the active project aside before `next` is opened in its place,
+/// keeping at most the configured number of projects loaded. A loaded copy of `next` is
+/// dropped since it is about to be reloaded.
+fn park_active_project(
+    state: &IndexerState,
+    indexer: &mut TreeSitterIndexer,
+    next: &str,
+) -> Result<(), String> {
+    let max_loaded_projects = current_settings(state)?.max_loaded_projects;
+    let mut current_index = state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?;
+    let mut loaded = state
+        .loaded_projects
+        .lock()
+        .map_err(|e| format!("Failed to lock loaded projects: {}", e))?;
+
+    loaded.retain(|p| p.index.root_path != next);
+    if current_index.as_ref().is_some_and(|index| index.root_path != next) {
+        if let Some(index) = current_index.take() {
+            loaded.push(LoadedProject {
+                index,
+                stores: indexer.park_project(),
+            });
+        }
+    }
+    while loaded.len() >= max_loaded_projects {
+        let evicted = loaded.remove(0);
+        println!("Unloaded project: {}", evicted.index.root_path);
+    }
+    Ok(())
+}
+
+/// Progress of a background re-embed after the embedding model changed
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbeddingMigrationProgress {
+    pub project_path: String,
+    pub files_done: usize,
+    pub total_files: usize,
+    pub done: bool,
+}
+
+/// A batch of `grep_project` matches, all from one file
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepMatchesEvent {
+    pub search_id: String,
+    pub matches: Vec<GrepMatch>,
+}
+
+/// Returned by `grep_project` once every file has been searched
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepSummary {
+    pub search_id: String,
+    pub files_searched: usize,
+    pub total_matches: usize,
+    /// The match limit was hit before all files were searched
+    pub truncated: bool,
+}
+
+/// Sent when a project opened by `switch_project` is ready, or failed to load
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectReady {
+    pub project_path: String,
+    pub result: Option<IndexResult>,
+    pub error: Option<String>,
+}
+
+/// Rebuild a project's vector stores with the current embedding model from
+/// its cached symbol index. Files are embedded one lock at a time so other
+/// commands keep working; the new stores replace the old ones at the end.
+fn migrate_embeddings(app_handle: &AppHandle, index: &CodebaseIndex) -> Result<(), String> {
+    let state = app_handle.state::<IndexerState>();
+    let start_time = std::time::Instant::now();
+
+    let (mut store, mut file_store) = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .empty_vector_stores()?;
+
+    let total_files = index.files.len();
+    let emit_progress = |files_done: usize, done: bool| {
+        let _ = app_handle.emit(
+            "embedding-migration-progress",
+            EmbeddingMigrationProgress {
+                project_path: index.root_path.clone(),
+                files_done,
+                total_files,
+                done,
+            },
+        );
+    };
+
+    for (i, file) in index.files.values().enumerate() {
+        // Another project was opened; its own load takes care of its vectors
+        let same_project = state
+            .current_index
+            .lock()
+            .map_err(|e| format!("Failed to lock index: {}", e))?
+            .as_ref()
+            .is_some_and(|current| current.root_path == index.root_path);
+        if !same_project {
+            return Err("Project changed during migration".to_string());
+        }
+
+        state
+            .indexer
+            .lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?
+            .reembed_file(file, &mut store, &mut file_store)?;
+
+        emit_progress(i + 1, false);
+    }
+
+    let persistence = persistence(&state, app_handle)?;
+
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+    indexer.install_vector_stores(store, file_store)?;
+    indexer.save_vector_store(
+        persistence.get_vector_index_path(&index.root_path),
+        persistence.get_vector_metadata_path(&index.root_path),
+    )?;
+    indexer.save_file_vector_store(
+        persistence.get_file_vector_index_path(&index.root_path),
+        persistence.get_file_vector_metadata_path(&index.root_path),
+    )?;
+    let status = indexer.semantic_status(None);
+    drop(indexer);
+    if let Err(e) = persistence.refresh_checksums(&index.root_path) {
+        eprintln!("Failed to record cache checksums: {}", e);
+    }
+    drop(persistence);
+
+    emit_progress(total_files, true);
+    let _ = app_handle.emit("semantic-status", status);
+
+    println!(
+        "Re-embedded {} files in {:?}",
+        total_files,
+        start_time.elapsed()
+    );
+    Ok(())
+}
+
+/// Apply a project's saved settings to the indexer
+fn load_project_settings(
+    persistence: &PersistenceConfig,
+    indexer: &mut TreeSitterIndexer,
+    path: &str,
+) -> Result<(), String> {
+    let settings_path = persistence.get_project_settings_path(path);
+    let settings = ProjectSettings::load_or_default(&settings_path, path)?;
+    indexer.set_default_hybrid_config(settings.hybrid_config);
+    indexer.set_normalizer_config(settings.normalizer.unwrap_or_default());
+    Ok(())
+}
+
+/// Sent when a project's cache is discarded for being in an older format
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUpgrade {
+    pub project_path: String,
+    pub from_version: u32,
+    pub to_version: u32,
+}
+
+/// Full-text hit attributed to the project it came from
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectSearchResult {
+    pub project_path: String,
+    #[serde(flatten)]
+    pub result: TantivySearchResult,
+    pub normalized_score: f32,
+}
+
+/// The project's cached index if one exists and no source file changed
+/// since it was saved. A cache written in another format is deleted, with a
+/// `cache-upgrade` event so the UI can explain the rebuild that follows.
+fn load_valid_cache(
+    app_handle: &AppHandle,
+    persistence: &PersistenceConfig,
+    path: &str,
+    settings: &AppSettings,
+) -> Result<Option<CodebaseIndex>, String> {
+    if !persistence.has_cached_index(path) {
+        return Ok(None);
+    }
+    println!("Checking cache validity for: {}", path);
+
+    let Ok(cached_metadata) = CacheMetadata::load(&persistence.get_cache_metadata_path(path)) else {
+        return Ok(None);
+    };
+    if cached_metadata.format_version != CACHE_FORMAT_VERSION {
+        println!(
+            "Cache format {} is outdated (current {}), rebuilding...",
+            cached_metadata.format_version, CACHE_FORMAT_VERSION
+        );
+        persistence.clear_project_cache(path)?;
+        let _ = app_handle.emit(
+            "cache-upgrade",
+            CacheUpgrade {
+                project_path: path.to_string(),
+                from_version: cached_metadata.format_version,
+                to_version: CACHE_FORMAT_VERSION,
+            },
+        );
+        return Ok(None);
+    }
+    let current_timestamps =
+        TreeSitterIndexer::collect_file_timestamps(path, &settings.ignore_globs)?;
+    if !cached_metadata.is_valid(&current_timestamps) {
+        println!("Cache is stale, re-indexing...");
+        return Ok(None);
+    }
+
+    // Damaged full-text or vector caches are rebuilt on open, but the
+    // symbol index is what everything else is built from
+    let corrupted = persistence.corrupted_cache_files(&cached_metadata);
+    if corrupted.contains(&persistence.get_main_index_path(path)) {
+        eprintln!("Cached index for {} failed its integrity check, re-indexing...", path);
+        persistence.clear_branch_cache(path)?;
+        return Ok(None);
+    }
+    for file in corrupted {
+        eprintln!("Cache file {} failed its integrity check, rebuilding it...", file.display());
+        let removed = if file.ends_with("meta.json") {
+            std::fs::remove_dir_all(persistence.get_tantivy_dir(path))
+        } else {
+            std::fs::remove_file(&file)
+        };
+        if let Err(e) = removed {
+            eprintln!("Failed to remove {}: {}", file.display(), e);
+        }
+    }
+
+    if cached_metadata.file_timestamps != current_timestamps {
+        // Contents match but a checkout touched the files; skip rehashing next time
+        let mut refreshed = cached_metadata;
+        refreshed.file_timestamps = current_timestamps;
+        if let Err(e) = persistence.save_cache_metadata(refreshed) {
+            eprintln!("Failed to refresh cache timestamps: {}", e);
+        }
+    }
+
+    println!("Cache is valid, loading from disk...");
+    let index = persistence.load_index(path, &settings.synonym_table())
+        .map_err(|e| println!("Cached index unreadable ({}), re-indexing...", e))
+        .ok();
+    if index.is_some() {
+        if let Err(e) = persistence.touch_cache(path) {
+            eprintln!("Failed to record cache access: {}", e);
+        }
+    }
+    Ok(index)
+}
+
+/// Make a project loaded from cache the active one, with its full-text
+/// index, settings, churn and vectors
+fn open_cached_index(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    persistence: &PersistenceConfig,
+    path: &str,
+    index: CodebaseIndex,
+    start_time: std::time::Instant,
+) -> Result<IndexResult, String> {
+    // Get indexer and set up Tantivy path
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    let tantivy_dir = persistence.get_tantivy_dir(path);
+    indexer.set_tantivy_path(tantivy_dir)?;
+    if indexer.full_text_is_empty() && index.total_files > 0 {
+        println!("Full-text index is empty, rebuilding from the cached index...");
+        indexer.rebuild_full_text(&index)?;
+        if let Err(e) = persistence.refresh_checksums(path) {
+            eprintln!("Failed to record cache checksums: {}", e);
+        }
+    }
+    load_project_settings(persistence, &mut indexer, path)?;
+    indexer.refresh_file_churn(path);
+
+    // Load vector store; a model or dimension mismatch forces a rebuild
+    let vector_index_path = persistence.get_vector_index_path(path);
+    let vector_metadata_path = persistence.get_vector_metadata_path(path);
+    let file_vector_index_path = persistence.get_file_vector_index_path(path);
+    let file_vector_metadata_path = persistence.get_file_vector_metadata_path(path);
+    let vectors_loaded = indexer
+        .load_vector_store(&vector_index_path, &vector_metadata_path)
+        .and_then(|_| {
+            indexer.load_file_vector_store(&file_vector_index_path, &file_vector_metadata_path)
+        });
+
+    // The symbol index is still good, so a stale vector cache (e.g. after
+    // switching models) is rebuilt in the background instead of re-parsing
+    let migration_index = match vectors_loaded {
+        Ok(()) => None,
+        Err(e) => {
+            println!("Vector cache unusable ({}), re-embedding in the background...", e);
+            indexer.clear_vector_stores();
+            Some(index.clone())
+        }
+    };
+
+    // Files re-indexed after the vectors were saved still have their old ones
+    let metadata_path = persistence.get_cache_metadata_path(path);
+    if let (None, Ok(mut metadata)) = (&migration_index, CacheMetadata::load(&metadata_path)) {
+        if !metadata.stale_vectors.is_empty() {
+            println!("Re-embedding {} files changed since the last save...", metadata.stale_vectors.len());
+            indexer.refresh_file_vectors(&index, &metadata.stale_vectors)?;
+            indexer.save_vector_store(&vector_index_path, &vector_metadata_path)?;
+            indexer.save_file_vector_store(&file_vector_index_path, &file_vector_metadata_path)?;
+            metadata.stale_vectors.clear();
+            persistence.save_cache_metadata(metadata)?;
+        }
+    }
+    drop(indexer);
+
+    // Calculate result
+    let total_symbols: usize = index.files.values().map(|f| f.symbols.len()).sum();
+
+    let result = IndexResult {
+        success: true,
+        total_files: index.total_files,
+        total_symbols,
+        languages: index.language_stats.keys().cloned().collect(),
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        errors: Vec::new(),
+    };
+
+    // Store index in state
+    *state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))? = Some(index);
+
+    if let Some(index) = migration_index {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            if let Err(e) = migrate_embeddings(&app_handle, &index) {
+                eprintln!("Embedding migration failed: {}", e);
+            }
+        });
+    }
+
+    println!("Loaded from cache in {:?}", start_time.elapsed());
+    Ok(result)
 }
 
 #[tauri::command]
@@ -20,79 +562,28 @@ pub async fn index_codebase(
 ) -> Result<IndexResult, String> {
     let start_time = std::time::Instant::now();
     let force_reindex = force_reindex.unwrap_or(false);
+    let settings = current_settings(&state)?;
 
     // Initialize persistence config if not already done
-    let mut persistence_lock = state
-        .persistence
-        .lock()
-        .map_err(|e| format!("Failed to lock persistence: {}", e))?;
+    let persistence_lock = persistence(&state, &app_handle)?;
+    persistence_lock.track_branch(&path);
 
-    if persistence_lock.is_none() {
-        *persistence_lock = Some(PersistenceConfig::new(&app_handle)?);
+    {
+        let mut indexer = state
+            .indexer
+            .lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        park_active_project(&state, &mut indexer, &path)?;
     }
 
-    let persistence = persistence_lock
-        .as_ref()
-        .ok_or_else(|| "Persistence not initialized".to_string())?;
-
     // Check if we have a valid cache
-    let use_cache = !force_reindex && persistence.has_cached_index(&path);
-
-    if use_cache {
-        // Try to load from cache
-        println!("Checking cache validity for: {}", path);
-
-        let cache_metadata_path = persistence.get_cache_metadata_path(&path);
-        if let Ok(cached_metadata) = CacheMetadata::load(&cache_metadata_path) {
-            // Collect current timestamps
-            let current_timestamps = TreeSitterIndexer::collect_file_timestamps(&path)?;
-
-            // Check if cache is still valid
-            if cached_metadata.is_valid(&current_timestamps) {
-                println!("Cache is valid, loading from disk...");
-
-                // Load main index
-                let main_index_path = persistence.get_main_index_path(&path);
-                let index = CodebaseIndex::load(&main_index_path)?;
-
-                // Get indexer and set up Tantivy path
-                let mut indexer = state
-                    .indexer
-                    .lock()
-                    .map_err(|e| format!("Failed to lock indexer: {}", e))?;
-
-                let tantivy_dir = persistence.get_tantivy_dir(&path);
-                indexer.set_tantivy_path(tantivy_dir)?;
-
-                // Load vector store
-                let vector_index_path = persistence.get_vector_index_path(&path);
-                let vector_metadata_path = persistence.get_vector_metadata_path(&path);
-                indexer.load_vector_store(&vector_index_path, &vector_metadata_path)?;
-
-                // Calculate result
-                let total_symbols: usize = index.files.values().map(|f| f.symbols.len()).sum();
-
-                let result = IndexResult {
-                    success: true,
-                    total_files: index.total_files,
-                    total_symbols,
-                    languages: index.language_stats.keys().cloned().collect(),
-                    duration_ms: start_time.elapsed().as_millis() as u64,
-                    errors: Vec::new(),
-                };
-
-                // Store index in state
-                *state
-                    .current_index
-                    .lock()
-                    .map_err(|e| format!("Failed to lock index: {}", e))? = Some(index);
-
-                println!("Loaded from cache in {:?}", start_time.elapsed());
-                return Ok(result);
-            } else {
-                println!("Cache is stale, re-indexing...");
-            }
-        }
+    let cached_index = if force_reindex {
+        None
+    } else {
+        load_valid_cache(&app_handle, &persistence_lock, &path, &settings)?
+    };
+    if let Some(index) = cached_index {
+        return open_cached_index(&app_handle, &state, &persistence_lock, &path, index, start_time);
     }
 
     drop(persistence_lock); // Release lock before indexing
@@ -101,13 +592,7 @@ pub async fn index_codebase(
     println!("Starting fresh indexing for: {}", path);
 
     // Get persistence config again (after dropping lock)
-    let persistence_lock = state
-        .persistence
-        .lock()
-        .map_err(|e| format!("Failed to lock persistence: {}", e))?;
-    let persistence = persistence_lock
-        .as_ref()
-        .ok_or_else(|| "Persistence not initialized".to_string())?;
+    let persistence = persistence(&state, &app_handle)?;
 
     // Create project directory
     let project_dir = persistence.get_project_dir(&path);
@@ -122,30 +607,46 @@ pub async fn index_codebase(
 
     let tantivy_dir = persistence.get_tantivy_dir(&path);
     indexer.set_tantivy_path(tantivy_dir)?;
+    load_project_settings(&persistence, &mut indexer, &path)?;
 
-    // Perform indexing
-    let index = indexer.index_codebase(&path)?;
+    // Perform indexing, resuming an interrupted run unless forced to start over
+    let checkpoint = IndexCheckpoint::new(persistence.get_checkpoint_dir(&path));
+    if force_reindex {
+        checkpoint.clear()?;
+    }
+    let index = indexer.index_codebase(&path, Some(&checkpoint))?;
 
     // Save everything to disk
     println!("Saving index to cache...");
 
     // Save main index
-    let main_index_path = persistence.get_main_index_path(&path);
-    index.save(&main_index_path)?;
+    persistence.save_index(&index)?;
 
     // Save vector store
     let vector_index_path = persistence.get_vector_index_path(&path);
     let vector_metadata_path = persistence.get_vector_metadata_path(&path);
     indexer.save_vector_store(&vector_index_path, &vector_metadata_path)?;
 
+    let file_vector_index_path = persistence.get_file_vector_index_path(&path);
+    let file_vector_metadata_path = persistence.get_file_vector_metadata_path(&path);
+    indexer.save_file_vector_store(&file_vector_index_path, &file_vector_metadata_path)?;
+
     // Collect and save cache metadata
-    let file_timestamps = TreeSitterIndexer::collect_file_timestamps(&path)?;
+    let file_timestamps = TreeSitterIndexer::collect_file_timestamps(&path, &settings.ignore_globs)?;
     let cache_metadata = CacheMetadata::new(path.clone(), index.total_files, file_timestamps);
-    let cache_metadata_path = persistence.get_cache_metadata_path(&path);
-    cache_metadata.save(&cache_metadata_path)?;
+    persistence.save_cache_metadata(cache_metadata)?;
+    if let Err(e) = checkpoint.clear() {
+        eprintln!("Failed to remove indexing checkpoint: {}", e);
+    }
 
     println!("Index saved to cache");
 
+    if let Some(max_bytes) = settings.max_cache_bytes {
+        for evicted in persistence.enforce_cache_limit(max_bytes, &path)? {
+            println!("Evicted cached index: {}", evicted);
+        }
+    }
+
     // Calculate result
     let total_symbols: usize = index.files.values().map(|f| f.symbols.len()).sum();
 
@@ -170,91 +671,1758 @@ pub async fn index_codebase(
 #[tauri::command]
 pub async fn query_index(
     query: IndexQuery,
+    project: String,
+    app_handle: AppHandle,
     state: State<'_, IndexerState>,
 ) -> Result<Vec<CodeChunk>, String> {
-    let indexer = state
+    let multi_query_enabled = state
         .indexer
         .lock()
-        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .multi_query_enabled();
 
-    let index_lock = state
-        .current_index
-        .lock()
-        .map_err(|e| format!("Failed to lock index: {}", e))?;
+    // Rewrite before taking the locks; a failed rewrite falls back to the
+    // plain query. Later pages reuse the first page's ranking instead.
+    let first_page = query.offset.unwrap_or(0) == 0;
+    let client = if multi_query_enabled && first_page {
+        llm_client(&app_handle, &state, Some(&project)).ok()
+    } else {
+        None
+    };
+    let rewrites = match client {
+        Some(client) => client
+            .rewrite_query(
+                &QueryAnalyzer::parse_query(&query.keywords.join(" ")).terms.join(" "),
+                MULTI_QUERY_REWRITES,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                eprintln!("Query rewriting failed: {}", e);
+                Vec::new()
+            }),
+        None => Vec::new(),
+    };
 
-    let index = index_lock
-        .as_ref()
-        .ok_or_else(|| "No codebase indexed".to_string())?;
+    let active = activate_project(&state, &app_handle, &project)?;
+    let results = if rewrites.is_empty() {
+        active.indexer().query_index(active.index(), &query)
+    } else {
+        active.indexer().query_index_multi(active.index(), &query, &rewrites)
+    };
+
+    // Later pages of the same search aren't new queries
+    if first_page {
+        let mut entry =
+            QueryHistoryEntry::new(query.keywords.join(" "), QueryMode::Keyword, results.len());
+        entry.symbol_kinds = query.symbol_kinds.clone();
+        entry.file_patterns = query.file_patterns.clone();
+        active.record_query(entry);
+    }
 
-    Ok(indexer.query_index(index, &query))
+    Ok(results)
 }
 
+/// Enrich a prompt with code from the project: analyze its intent, search
+/// for the keywords and entities found, and append the most relevant results
+/// that fit in `max_context_tokens`. Files in `attachments`, such as a
+/// screenshot of a bug, are loaded to go along with the prompt and take
+/// their share of the budget.
 #[tauri::command]
-pub async fn get_index_stats(state: State<'_, IndexerState>) -> Result<serde_json::Value, String> {
-    let index_lock = state
-        .current_index
-        .lock()
-        .map_err(|e| format!("Failed to lock index: {}", e))?;
+pub async fn enhance_prompt(
+    prompt: String,
+    attachments: Option<Vec<String>>,
+    max_context_tokens: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<EnhancedPrompt, String> {
+    let attachments = load_attachments(&attachments.unwrap_or_default())?;
 
-    let index = index_lock
-        .as_ref()
-        .ok_or_else(|| "No codebase indexed".to_string())?;
+    // Analyze before taking the locks
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    let intent = client.analyze_intent(&prompt).await?;
 
-    Ok(serde_json::json!({
-        "total_files": index.total_files,
-        "languages": index.language_stats,
-        "root_path": index.root_path,
-        "indexed_at": index.indexed_at,
-    }))
+    let keywords: Vec<String> = intent
+        .keywords
+        .iter()
+        .chain(&intent.entities)
+        .filter(|term| term.trim().len() > 2)
+        .cloned()
+        .collect();
+
+    let results = if keywords.is_empty() {
+        Vec::new()
+    } else {
+        let query = IndexQuery {
+            keywords,
+            max_results: Some(ENHANCE_SEARCH_RESULTS),
+            ..Default::default()
+        };
+
+        let active = activate_project(&state, &app_handle, &project)?;
+        active.indexer().query_index(active.index(), &query)
+    };
+
+    let counter = TokenCounter::for_chunks(client.as_ref(), &results).await;
+    let attachment_tokens: usize = attachments
+        .iter()
+        .filter_map(|block| media_tokens(block, &counter))
+        .sum();
+    let (chunks, context_tokens) = select_context(
+        results,
+        max_context_tokens
+            .unwrap_or(DEFAULT_CONTEXT_TOKENS)
+            .saturating_sub(attachment_tokens),
+        &counter,
+    );
+
+    Ok(EnhancedPrompt {
+        prompt: build_prompt(&prompt, &intent, &chunks),
+        original: prompt,
+        intent,
+        chunks,
+        context_tokens,
+        tokens_estimated: counter.is_estimate(),
+        attachments,
+        attachment_tokens,
+    })
 }
 
+/// Answer a question about the project, letting the model search the index
+/// as many times as it needs while working out the answer. `attachments`
+/// are files sent along with the question, such as screenshots.
 #[tauri::command]
-pub async fn get_file_symbols(
-    file_path: String,
+pub async fn ask_codebase(
+    question: String,
+    attachments: Option<Vec<String>>,
+    project: String,
+    app_handle: AppHandle,
     state: State<'_, IndexerState>,
-) -> Result<Vec<CodeSymbol>, String> {
-    let index_lock = state
-        .current_index
-        .lock()
-        .map_err(|e| format!("Failed to lock index: {}", e))?;
+) -> Result<String, String> {
+    // Fails early for a project that isn't loaded
+    drop(activate_project(&state, &app_handle, &project)?);
 
-    let index = index_lock
-        .as_ref()
-        .ok_or_else(|| "No codebase indexed".to_string())?;
+    let attachments = load_attachments(&attachments.unwrap_or_default())?;
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+    let mut messages = vec![Message {
+        role: "user".to_string(),
+        content: with_attachments(question, attachments),
+    }];
+    let overview = cached_codebase_summary(&app_handle, &state, &project);
+    let system = match overview {
+        Some(overview) => format!("{}\n\n{}", CODEBASE_ASSISTANT_PROMPT, overview.preamble()),
+        None => CODEBASE_ASSISTANT_PROMPT.to_string(),
+    };
+    client
+        .run_with_tools(
+            &mut messages,
+            Some(system),
+            &[search_code_tool()],
+            &mut |name, input| run_index_tool(&state, &app_handle, &project, name, input),
+        )
+        .await
+}
 
-    index
-        .files
-        .get(&file_path)
-        .map(|f| f.symbols.clone())
-        .ok_or_else(|| format!("File not found: {}", file_path))
+/// Run a tool call the model made against a project's index
+pub fn run_index_tool(
+    state: &IndexerState,
+    app_handle: &AppHandle,
+    project: &str,
+    name: &str,
+    input: &serde_json::Value,
+) -> Result<String, String> {
+    if name != SEARCH_CODE_TOOL {
+        return Err(format!("Unknown tool: {}", name));
+    }
+    let input: SearchCodeInput = serde_json::from_value(input.clone())
+        .map_err(|e| format!("Invalid search input: {}", e))?;
+
+    let query = IndexQuery {
+        keywords: input.query.split_whitespace().map(String::from).collect(),
+        max_results: Some(
+            input
+                .max_results
+                .unwrap_or(DEFAULT_TOOL_RESULTS)
+                .clamp(1, MAX_TOOL_RESULTS),
+        ),
+        ..Default::default()
+    };
+
+    let active = activate_project(state, app_handle, project)?;
+    let results = active.indexer().query_index(active.index(), &query);
+    if results.is_empty() {
+        return Ok("No matching code found.".to_string());
+    }
+    Ok(format_chunks(&results))
 }
 
+/// Keyword query scoped to the code being worked on: files changed in the
+/// working tree or on the branch since it left `base` (main or master by
+/// default). Results in those files are boosted, or with `restrict` the
+/// only ones returned.
 #[tauri::command]
-pub async fn search_files(
-    query: String,
+pub async fn query_changed_code(
+    keywords: Vec<String>,
+    base: Option<String>,
+    restrict: Option<bool>,
     max_results: Option<usize>,
+    offset: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
     state: State<'_, IndexerState>,
-) -> Result<Vec<String>, String> {
-    let indexer = state.indexer.lock()
-        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+) -> Result<Vec<CodeChunk>, String> {
+    // git runs before the locks are taken
+    let files = changed_files(&project, base.as_deref())?;
 
-    let index_lock = state.current_index.lock()
-        .map_err(|e| format!("Failed to lock index: {}", e))?;
+    let query = IndexQuery {
+        keywords,
+        max_results,
+        offset,
+        changed_scope: Some(ChangedScope {
+            files,
+            restrict: restrict.unwrap_or(false),
+        }),
+        ..Default::default()
+    };
 
-    let index = index_lock.as_ref()
-        .ok_or_else(|| "No codebase indexed".to_string())?;
+    let active = activate_project(&state, &app_handle, &project)?;
+    let results = active.indexer().query_index(active.index(), &query);
+
+    if query.offset.unwrap_or(0) == 0 {
+        active.record_query(QueryHistoryEntry::new(
+            query.keywords.join(" "),
+            QueryMode::Keyword,
+            results.len(),
+        ));
+    }
 
-    Ok(indexer.query_file_paths(index, &query, max_results.unwrap_or(50)))
+    Ok(results)
 }
 
+/// Re-parse a single file of a project, e.g. after it was saved,
+/// and patch it into every search index. A deleted file is removed.
+/// Returns the file's new entry. When the project has a cached index, the
+/// file's entry is saved to it as well; its vectors are re-embedded the
+/// next time the cache is opened.
 #[tauri::command]
-pub async fn search_semantic(
-    query: String,
-    max_results: Option<usize>,
+pub async fn reindex_file(
+    path: String,
+    project: String,
+    app_handle: AppHandle,
     state: State<'_, IndexerState>,
-) -> Result<Vec<CodeChunk>, String> {
+) -> Result<Option<IndexedFile>, String> {
+    if !Path::new(&path).starts_with(&project) {
+        return Err(format!("File is outside the project: {}", path));
+    }
+
+    let mut active = activate_project(&state, &app_handle, &project)?;
+    let (indexer, index) = active.parts_mut();
+    let file = indexer.reindex_file(index, &path)?;
+
+    // Save just this file, so the cache stays valid without a full save
+    let persistence = active.persistence();
+    if persistence.has_cached_index(&project) {
+        let saved = persistence.save_changed_files(active.index(), std::slice::from_ref(&path));
+        if let Err(e) = saved {
+            eprintln!("Failed to save re-indexed file: {}", e);
+        }
+    }
+    println!(
+        "Re-indexed {} ({} symbols)",
+        path,
+        file.as_ref().map_or(0, |f| f.symbols.len())
+    );
+    Ok(file)
+}
+
+/// Past searches of a project, newest first
+#[tauri::command]
+pub async fn get_query_history(
+    limit: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<QueryHistoryEntry>, String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let mut history = QueryHistoryEntry::load_all(&persistence.get_query_history_path(&project))?;
+    if let Some(limit) = limit {
+        history.truncate(limit);
+    }
+    Ok(history)
+}
+
+/// Forget a project's past searches
+#[tauri::command]
+pub async fn clear_query_history(
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let path = persistence(&state, &app_handle)?.get_query_history_path(&project);
+
+    if path.exists() {
+        std::fs::remove_file(&path)
+            .map_err(|e| format!("Failed to remove query history: {}", e))?;
+    }
+    println!("Cleared query history for: {}", project);
+    Ok(())
+}
+
+/// Pin a search result so it can be added to prompts regardless of ranking.
+/// A chunk covering a single symbol follows that symbol when its lines
+/// move. Returns the project's pins.
+#[tauri::command]
+pub async fn pin_chunk(
+    chunk: CodeChunk,
+    note: Option<String>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<PinnedChunk>, String> {
+    if chunk.end_line < chunk.start_line {
+        return Err(format!("Invalid line range: {}-{}", chunk.start_line, chunk.end_line));
+    }
+    let symbol_name = match chunk.symbols.as_slice() {
+        [name] => Some(name.clone()),
+        _ => None,
+    };
+
+    // Held until the pins are saved, so concurrent edits don't lose each other
+    let persistence = persistence(&state, &app_handle)?;
+    let pins_path = persistence.get_pins_path(&project);
+
+    let mut pins = PinnedChunk::load_all(&pins_path)?;
+    let already_pinned = pins.iter().any(|pin| {
+        pin.file_path == chunk.file_path
+            && pin.start_line == chunk.start_line
+            && pin.end_line == chunk.end_line
+            && pin.symbol_name == symbol_name
+    });
+    if !already_pinned {
+        pins.push(PinnedChunk {
+            id: pins.iter().map(|pin| pin.id + 1).max().unwrap_or(0),
+            file_path: chunk.file_path,
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            symbol_name,
+            note,
+            pinned_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        });
+        PinnedChunk::save_all(&pins, &pins_path)?;
+    }
+    Ok(pins)
+}
+
+/// Remove a pin by id. Returns the project's remaining pins.
+#[tauri::command]
+pub async fn unpin_chunk(
+    id: u64,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<PinnedChunk>, String> {
+    // Held until the pins are saved, so concurrent edits don't lose each other
+    let persistence = persistence(&state, &app_handle)?;
+    let pins_path = persistence.get_pins_path(&project);
+
+    let mut pins = PinnedChunk::load_all(&pins_path)?;
+    let count = pins.len();
+    pins.retain(|pin| pin.id != id);
+    if pins.len() == count {
+        return Err(format!("No pin with id {}", id));
+    }
+    PinnedChunk::save_all(&pins, &pins_path)?;
+    Ok(pins)
+}
+
+/// A project's pins, oldest first
+#[tauri::command]
+pub async fn list_pins(
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<PinnedChunk>, String> {
+    PinnedChunk::load_all(&persistence(&state, &app_handle)?.get_pins_path(&project))
+}
+
+/// Current contents of a project's pinned chunks, ready to add to a prompt
+#[tauri::command]
+pub async fn get_pinned_chunks(
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<CodeChunk>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    let pins = PinnedChunk::load_all(&active.persistence().get_pins_path(&project))?;
+    Ok(active.indexer().pinned_chunks(active.index(), &pins))
+}
+
+/// Make a project the active one without blocking on its cache. A loaded
+/// project is swapped in at once and true is returned. Otherwise the
+/// current project stays active while the new one loads in the background
+/// (indexing it if its cache is stale), and a `project-ready` event
+/// follows.
+#[tauri::command]
+pub async fn switch_project(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<bool, String> {
+    // Looked up and swapped in under the same locks, so it can't be
+    // unloaded in between
+    if try_activate_project(&state, &app_handle, &path)?.is_some() {
+        return Ok(true);
+    }
+
+    let persistence = persistence(&state, &app_handle)?.clone();
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let start_time = std::time::Instant::now();
+        let state = app_handle.state::<IndexerState>();
+
+        // Deserialize without holding any lock, so the current project stays usable
+        persistence.track_branch(&path);
+        let outcome = current_settings(&state)
+            .and_then(|settings| {
+                load_valid_cache(&app_handle, &persistence, &path, &settings)
+            })
+            .and_then(|cached_index| match cached_index {
+                Some(index) => {
+                    let mut indexer = state
+                        .indexer
+                        .lock()
+                        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+                    park_active_project(&state, &mut indexer, &path)?;
+                    drop(indexer);
+                    open_cached_index(&app_handle, &state, &persistence, &path, index, start_time)
+                }
+                None => tauri::async_runtime::block_on(index_codebase(
+                    path.clone(),
+                    app_handle.clone(),
+                    app_handle.state::<IndexerState>(),
+                    None,
+                )),
+            });
+
+        if let Err(ref e) = outcome {
+            eprintln!("Failed to open {}: {}", path, e);
+        }
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(e) => (None, Some(e)),
+        };
+        let _ = app_handle.emit(
+            "project-ready",
+            ProjectReady {
+                project_path: path,
+                result,
+                error,
+            },
+        );
+    });
+
+    Ok(false)
+}
+
+/// Alternate phrasings requested per query when multi-query is enabled
+const MULTI_QUERY_REWRITES: usize = 4;
+
+/// Search results an enhanced prompt's context is picked from
+const ENHANCE_SEARCH_RESULTS: usize = 20;
+
+/// Forget a project held in memory, whether active or set aside
+fn drop_loaded_project(
+    state: &IndexerState,
+    indexer: &mut TreeSitterIndexer,
+    path: &str,
+) -> Result<(), String> {
+    let mut current_index = state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?;
+    if current_index.as_ref().is_some_and(|index| index.root_path == path) {
+        *current_index = None;
+        indexer.close_project();
+    }
+
+    state
+        .loaded_projects
+        .lock()
+        .map_err(|e| format!("Failed to lock loaded projects: {}", e))?
+        .retain(|p| p.index.root_path != path);
+    Ok(())
+}
+
+/// Root paths of the loaded projects, the active one first
+#[tauri::command]
+pub async fn get_loaded_projects(state: State<'_, IndexerState>) -> Result<Vec<String>, String> {
+    let active = state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?
+        .as_ref()
+        .map(|index| index.root_path.clone());
+    let loaded = state
+        .loaded_projects
+        .lock()
+        .map_err(|e| format!("Failed to lock loaded projects: {}", e))?;
+
+    Ok(active
+        .into_iter()
+        .chain(loaded.iter().rev().map(|p| p.index.root_path.clone()))
+        .collect())
+}
+
+/// Release a loaded project's memory; its cache on disk is kept
+#[tauri::command]
+pub async fn unload_project(path: String, state: State<'_, IndexerState>) -> Result<(), String> {
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+    drop_loaded_project(&state, &mut indexer, &path)
+}
+
+/// Delete a project's cached index. If it is the open project, its
+/// in-memory index and stores are dropped too, so the next `index_codebase`
+/// starts from a clean slate.
+#[tauri::command]
+pub async fn clear_project_cache(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+    drop_loaded_project(&state, &mut indexer, &path)?;
+
+    // Read-only searchers hold the Tantivy directory open
+    state
+        .project_searchers
+        .lock()
+        .map_err(|e| format!("Failed to lock project searchers: {}", e))?
+        .remove(&path);
+
+    persistence.clear_project_cache(&path)?;
+    println!("Cleared cache for: {}", path);
+    Ok(())
+}
+
+/// Disk used by the cached indexes against the configured budget
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheUsage {
+    /// Most recently used first
+    pub projects: Vec<CacheInfo>,
+    pub total_bytes: u64,
+    pub max_bytes: Option<u64>,
+}
+
+#[tauri::command]
+pub async fn get_cached_projects(
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<CacheUsage, String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let mut projects = persistence.get_cached_projects()?;
+    drop(persistence);
+    projects.sort_by_key(|p| std::cmp::Reverse(p.last_accessed));
+
+    Ok(CacheUsage {
+        total_bytes: projects.iter().map(|p| p.size_bytes).sum(),
+        projects,
+        max_bytes: current_settings(&state)?.max_cache_bytes,
+    })
+}
+
+/// Remove the caches of projects that no longer exist, keeping the report
+/// for `get_cache_gc_report`. Run once at startup.
+pub fn collect_cache_garbage(app_handle: &AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<IndexerState>();
+
+    // Held throughout so a project being indexed isn't mistaken for garbage
+    let persistence = persistence(&state, app_handle)?;
+    let report = persistence.collect_garbage()?;
+    drop(persistence);
+
+    if !report.removed.is_empty() {
+        println!(
+            "Removed {} orphaned caches, freeing {} bytes",
+            report.removed.len(),
+            report.freed_bytes
+        );
+    }
+    for error in &report.errors {
+        eprintln!("{}", error);
+    }
+
+    *state
+        .cache_gc_report
+        .lock()
+        .map_err(|e| format!("Failed to lock cache report: {}", e))? = Some(report);
+    Ok(())
+}
+
+/// What the startup cache cleanup removed; None while it is still running
+#[tauri::command]
+pub async fn get_cache_gc_report(
+    state: State<'_, IndexerState>,
+) -> Result<Option<CacheGcReport>, String> {
+    Ok(state
+        .cache_gc_report
+        .lock()
+        .map_err(|e| format!("Failed to lock cache report: {}", e))?
+        .clone())
+}
+
+/// Delete the cached indexes of all projects and drop the open project
+#[tauri::command]
+pub async fn clear_all_caches(
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .close_project();
+    *state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))? = None;
+    state
+        .loaded_projects
+        .lock()
+        .map_err(|e| format!("Failed to lock loaded projects: {}", e))?
+        .clear();
+    state
+        .project_searchers
+        .lock()
+        .map_err(|e| format!("Failed to lock project searchers: {}", e))?
+        .clear();
+
+    persistence.clear_all_caches()?;
+    println!("Cleared all index caches");
+    Ok(())
+}
+
+/// Files listed in the index stats' largest files
+const LARGEST_FILES: usize = 10;
+
+/// Files and symbols directly inside one directory
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DirectoryStats {
+    pub files: usize,
+    pub symbols: usize,
+}
+
+#[tauri::command]
+pub async fn get_index_stats(
+    app_handle: AppHandle,
+    project: String,
+    state: State<'_, IndexerState>,
+) -> Result<serde_json::Value, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+    let (symbol_vectors, file_vectors) = active.indexer().vector_counts();
+    let index = active.index();
+
+    let mut symbol_kinds: HashMap<String, usize> = HashMap::new();
+    let mut directories: BTreeMap<String, DirectoryStats> = BTreeMap::new();
+    for file in index.files.values() {
+        for symbol in &file.symbols {
+            *symbol_kinds.entry(format!("{:?}", symbol.kind)).or_insert(0) += 1;
+        }
+
+        // Counted against the directory holding the file, relative to the root
+        let dir = Path::new(&file.path)
+            .parent()
+            .and_then(|dir| dir.strip_prefix(&index.root_path).ok())
+            .map(|dir| dir.to_string_lossy().to_string())
+            .filter(|dir| !dir.is_empty())
+            .unwrap_or_else(|| ".".to_string());
+        let entry = directories.entry(dir).or_default();
+        entry.files += 1;
+        entry.symbols += file.symbols.len();
+    }
+
+    let mut largest_files: Vec<&IndexedFile> = index.files.values().collect();
+    largest_files.sort_by(|a, b| b.line_count.cmp(&a.line_count).then_with(|| a.path.cmp(&b.path)));
+    let largest_files: Vec<serde_json::Value> = largest_files
+        .into_iter()
+        .take(LARGEST_FILES)
+        .map(|file| {
+            serde_json::json!({
+                "path": file.path,
+                "lines": file.line_count,
+                "symbols": file.symbols.len(),
+            })
+        })
+        .collect();
+
+    Ok(serde_json::json!({
+        "total_files": index.total_files,
+        "languages": index.language_stats,
+        "root_path": index.root_path,
+        "indexed_at": index.indexed_at,
+        "total_symbols": index.corpus_stats.total_symbols,
+        "total_lines": index.files.values().map(|f| f.line_count).sum::<usize>(),
+        "symbol_kinds": symbol_kinds,
+        "directories": directories,
+        "largest_files": largest_files,
+        "symbol_vectors": symbol_vectors,
+        "file_vectors": file_vectors,
+        "cache_size_bytes": active.persistence().project_cache_size(&index.root_path),
+    }))
+}
+
+/// Write a project's index to a file for other tools. Embedding
+/// vector metadata is included when `include_vectors` is set.
+#[tauri::command]
+pub async fn export_index(
+    path: String,
+    format: ExportFormat,
+    include_vectors: Option<bool>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let (index, vectors) = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        let vectors = if include_vectors.unwrap_or(false) {
+            active.indexer().vector_metadata()
+        } else {
+            Vec::new()
+        };
+        (active.index().clone(), vectors)
+    };
+
+    IndexExport::new(index, vectors).write(Path::new(&path), format)?;
+    println!("Exported index to {}", path);
+    Ok(())
+}
+
+/// Open an index exported by `export_index` (here or on another machine)
+/// without parsing the code. `project_path` moves an index built under a
+/// different root onto the local checkout. Embeddings are recomputed in
+/// the background when semantic search is available.
+#[tauri::command]
+pub async fn import_index(
+    path: String,
+    project_path: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<IndexResult, String> {
+    let start_time = std::time::Instant::now();
+
+    let persistence = persistence(&state, &app_handle)?;
+
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    let mut index = IndexExport::read(Path::new(&path), indexer.normalizer())?.index;
+    if let Some(root) = project_path.filter(|root| *root != index.root_path) {
+        index = rebase_index(index, &root, indexer.normalizer());
+    }
+    let root = index.root_path.clone();
+
+    park_active_project(&state, &mut indexer, &root)?;
+    indexer.close_project();
+    std::fs::create_dir_all(persistence.get_project_dir(&root))
+        .map_err(|e| format!("Failed to create project directory: {}", e))?;
+    indexer.set_tantivy_path(persistence.get_tantivy_dir(&root))?;
+    load_project_settings(&persistence, &mut indexer, &root)?;
+    indexer.rebuild_full_text(&index)?;
+    indexer.refresh_file_churn(&root);
+    let semantic_available = indexer.embedding_device().is_some();
+    drop(indexer);
+
+    // Cache it like a local index when the checkout is here to validate against
+    persistence.save_index(&index)?;
+    match TreeSitterIndexer::collect_file_timestamps(&root, &current_settings(&state)?.ignore_globs) {
+        Ok(timestamps) => persistence
+            .save_cache_metadata(CacheMetadata::new(root.clone(), index.total_files, timestamps))?,
+        Err(e) => println!("Imported index not cached ({})", e),
+    }
+
+    let result = IndexResult {
+        success: true,
+        total_files: index.total_files,
+        total_symbols: index.corpus_stats.total_symbols,
+        languages: index.language_stats.keys().cloned().collect(),
+        duration_ms: start_time.elapsed().as_millis() as u64,
+        errors: Vec::new(),
+    };
+
+    let migration_index = semantic_available.then(|| index.clone());
+    *state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))? = Some(index);
+    drop(persistence);
+
+    if let Some(index) = migration_index {
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            if let Err(e) = migrate_embeddings(&app_handle, &index) {
+                eprintln!("Embedding migration failed: {}", e);
+            }
+        });
+    }
+
+    println!("Imported index for {} in {:?}", root, start_time.elapsed());
+    Ok(result)
+}
+
+/// Pack a project's whole cache (symbol index, full-text index, vectors and
+/// metadata) into one file that `import_cache_bundle` can open elsewhere,
+/// so huge repositories only have to be indexed once per team
+#[tauri::command]
+pub async fn export_cache_bundle(
+    path: String,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<BundleManifest, String> {
+    let persistence = persistence(&state, &app_handle)?.clone();
+
+    if !persistence.has_cached_index(&project) {
+        return Err(format!("No cached index for {}", project));
+    }
+
+    let manifest = write_bundle(
+        &persistence.get_project_dir(&project),
+        &project,
+        CACHE_FORMAT_VERSION,
+        Path::new(&path),
+    )?;
+    println!("Exported cache bundle of {} ({} files) to {}", project, manifest.file_count, path);
+    Ok(manifest)
+}
+
+/// Open a bundle written by `export_cache_bundle` as the cache of
+/// `project_path`, by default the root it was built under. Paths are
+/// rewritten when the roots differ and the full-text index is rebuilt.
+/// The bundle is trusted to match the local checkout.
+#[tauri::command]
+pub async fn import_cache_bundle(
+    path: String,
+    project_path: Option<String>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<IndexResult, String> {
+    let start_time = std::time::Instant::now();
+    let bundle = Path::new(&path);
+
+    let manifest = read_manifest(bundle)?;
+    if manifest.cache_format_version != CACHE_FORMAT_VERSION {
+        return Err(format!(
+            "Cache bundle holds cache format {} but this version of Prompto uses {}",
+            manifest.cache_format_version, CACHE_FORMAT_VERSION
+        ));
+    }
+    let root = project_path.unwrap_or_else(|| manifest.root_path.clone());
+
+    let persistence = persistence(&state, &app_handle)?;
+    persistence.track_branch(&root);
+
+    let mut indexer = state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+    park_active_project(&state, &mut indexer, &root)?;
+    indexer.close_project();
+
+    // Replaces only the cache of the checked-out branch
+    let project_dir = persistence.get_project_dir(&root);
+    persistence.clear_branch_cache(&root)?;
+    std::fs::create_dir_all(&project_dir)
+        .map_err(|e| format!("Failed to create project directory: {}", e))?;
+    read_bundle(bundle, &project_dir)?;
+
+    let mut index = persistence.load_index(&root, indexer.query_synonyms())?;
+    if manifest.root_path != root {
+        println!("Moving cache bundle from {} to {}", manifest.root_path, root);
+        load_project_settings(&persistence, &mut indexer, &root)?;
+        index = rebase_index(index, &root, indexer.normalizer());
+        persistence.save_index(&index)?;
+
+        for metadata_path in [
+            persistence.get_vector_metadata_path(&root),
+            persistence.get_file_vector_metadata_path(&root),
+        ] {
+            if metadata_path.exists() {
+                let rebased =
+                    VectorStore::rebase_saved_metadata(&metadata_path, &manifest.root_path, &root);
+                if let Err(e) = rebased {
+                    // Without metadata the vectors are re-embedded on open
+                    println!("Dropping bundled vectors ({})", e);
+                    let _ = std::fs::remove_file(&metadata_path);
+                }
+            }
+        }
+
+        // Stored paths are baked into the full-text index; it is rebuilt on open
+        let _ = std::fs::remove_dir_all(persistence.get_tantivy_dir(&root));
+    }
+    drop(indexer);
+
+    // Timestamps from the machine that built the cache would never match
+    let timestamps =
+        TreeSitterIndexer::collect_file_timestamps(&root, &current_settings(&state)?.ignore_globs)?;
+    persistence.save_cache_metadata(CacheMetadata::new(root.clone(), index.total_files, timestamps))?;
+
+    let result = open_cached_index(&app_handle, &state, &persistence, &root, index, start_time)?;
+    println!("Imported cache bundle for {} in {:?}", root, start_time.elapsed());
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn get_file_symbols(
+    file_path: String,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<CodeSymbol>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+    let index = active.index();
+
+    index
+        .files
+        .get(&file_path)
+        .map(|f| f.symbols.clone())
+        .ok_or_else(|| format!("File not found: {}", file_path))
+}
+
+/// Definition locations for a symbol name, best first; the file the
+/// lookup starts from and a kind narrow it down
+#[tauri::command]
+pub async fn goto_definition(
+    symbol_name: String,
+    file_path: Option<String>,
+    kind: Option<SymbolKind>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<CodeSymbol>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+    let index = active.index();
+
+    Ok(index.find_definitions(&symbol_name, file_path.as_deref(), kind.as_ref()))
+}
+
+/// Symbols enclosing and inside a line range of a file, e.g. an editor
+/// selection, plus their callers, to seed "explain this selection" prompts
+#[tauri::command]
+pub async fn get_symbols_in_range(
+    file_path: String,
+    start_line: usize,
+    end_line: usize,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<SelectionSymbols, String> {
+    if end_line < start_line {
+        return Err(format!("Invalid line range: {}-{}", start_line, end_line));
+    }
+
+    let active = activate_project(&state, &app_handle, &project)?;
+    let index = active.index();
+
+    index
+        .symbols_in_range(&file_path, start_line, end_line)
+        .ok_or_else(|| format!("File not indexed: {}", file_path))
+}
+
+/// Callers (incoming) or callees (outgoing) of a symbol, `depth` levels
+/// deep (default 3)
+#[tauri::command]
+pub async fn get_call_hierarchy(
+    symbol_name: String,
+    file_path: Option<String>,
+    direction: CallDirection,
+    depth: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<CallHierarchyNode, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+    let index = active.index();
+
+    let root = index
+        .find_definitions(&symbol_name, file_path.as_deref(), None)
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("Symbol not found: {}", symbol_name))?;
+
+    Ok(index.call_hierarchy(&root, direction, depth.unwrap_or(3)))
+}
+
+#[tauri::command]
+pub async fn search_files(
+    query: String,
+    max_results: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<String>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    let results =
+        active.indexer().query_file_paths(active.index(), &query, max_results.unwrap_or(50));
+
+    active.record_query(QueryHistoryEntry::new(query, QueryMode::Files, results.len()));
+    Ok(results)
+}
+
+/// Plain-text search over the indexed files, for text the symbol index can't
+/// answer. Matches are streamed as `grep-matches` events tagged with
+/// `search_id`, one event per file; the summary is returned at the end.
+#[tauri::command]
+pub async fn grep_project(
+    pattern: String,
+    options: Option<GrepOptions>,
+    search_id: Option<String>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<GrepSummary, String> {
+    let options = options.unwrap_or_default();
+    let regex = options.build_regex(&pattern)?;
+    let search_id = search_id.unwrap_or_else(|| pattern.clone());
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_GREP_MATCHES);
+
+    // Only indexed files are searched, so ignore rules already apply
+    let files = {
+        let active = activate_project(&state, &app_handle, &project)?;
+        grep_candidates(active.index(), &pattern, &options)?
+    };
+
+    // Reading every file would tie up an async worker for the whole scan
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut summary = GrepSummary {
+            search_id: search_id.clone(),
+            files_searched: 0,
+            total_matches: 0,
+            truncated: false,
+        };
+        for path in &files {
+            if summary.total_matches >= max_results {
+                summary.truncated = true;
+                break;
+            }
+
+            let matches = grep_file(path, &regex, max_results - summary.total_matches);
+            summary.files_searched += 1;
+            if matches.is_empty() {
+                continue;
+            }
+
+            summary.total_matches += matches.len();
+            let _ = app_handle.emit(
+                "grep-matches",
+                GrepMatchesEvent {
+                    search_id: search_id.clone(),
+                    matches,
+                },
+            );
+        }
+        summary
+    })
+    .await
+    .map_err(|e| format!("Grep failed: {}", e))
+}
+
+/// Indexed files to grep, in search order. For literal patterns the files
+/// whose symbols, calls or imports mention the pattern go first, so the
+/// likeliest matches stream in before the rest.
+fn grep_candidates(
+    index: &CodebaseIndex,
+    pattern: &str,
+    options: &GrepOptions,
+) -> Result<Vec<String>, String> {
+    let mut files = match &options.file_patterns {
+        Some(patterns) if !patterns.is_empty() => {
+            files_matching_patterns(&index.root_path, index.files.keys(), patterns)?
+        }
+        _ => index.files.keys().cloned().collect(),
+    };
+    files.sort();
+
+    if !options.regex {
+        let needle = pattern.to_lowercase();
+        let mentions = |path: &String| {
+            index.files.get(path).is_some_and(|file| {
+                file.imports.iter().any(|import| import.to_lowercase().contains(&needle))
+                    || file.symbols.iter().any(|symbol| {
+                        symbol.name.to_lowercase().contains(&needle)
+                            || symbol.calls.iter().any(|call| call.to_lowercase().contains(&needle))
+                    })
+            })
+        };
+        files.sort_by_key(|path| !mentions(path));
+    }
+
+    Ok(files)
+}
+
+#[tauri::command]
+pub async fn search_semantic(
+    query: String,
+    max_results: Option<usize>,
+    filter: Option<VectorFilter>,
+    min_similarity: Option<f32>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<CodeChunk>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    let min_similarity = min_similarity.unwrap_or(f32::MIN);
+    let results = active.indexer().search_semantic_filtered(
+        &query,
+        max_results.unwrap_or(20),
+        filter.as_ref().unwrap_or(&VectorFilter::default()),
+    )?;
+
+    let results: Vec<CodeChunk> = results.into_iter()
+        .filter(|chunk| chunk.relevance_score >= min_similarity)
+        .collect();
+
+    let mut entry = QueryHistoryEntry::new(query, QueryMode::Semantic, results.len());
+    entry.filter = filter;
+    active.record_query(entry);
+    Ok(results)
+}
+
+/// Semantic search that first picks candidate files, then symbols within them
+#[tauri::command]
+pub async fn search_coarse_to_fine(
+    query: String,
+    max_results: Option<usize>,
+    candidate_files: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<CodeChunk>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    let results = active.indexer().search_coarse_to_fine(
+        &query,
+        max_results.unwrap_or(20),
+        candidate_files.unwrap_or(10),
+    )?;
+
+    active.record_query(QueryHistoryEntry::new(query, QueryMode::CoarseToFine, results.len()));
+    Ok(results)
+}
+
+/// "More like this": implementations related to a given symbol
+#[tauri::command]
+pub async fn find_similar_symbols(
+    file_path: String,
+    symbol_name: String,
+    k: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<CodeChunk>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    active.indexer().find_similar_symbols(&file_path, &symbol_name, k.unwrap_or(10))
+}
+
+/// Report clusters of highly similar symbols across the codebase
+#[tauri::command]
+pub async fn find_duplicates(
+    threshold: Option<f32>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<DuplicateCluster>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    active.indexer().find_duplicates(threshold.unwrap_or(0.95))
+}
+
+/// Cluster symbol embeddings into labeled groups for a semantic map
+#[tauri::command]
+pub async fn get_semantic_map(
+    clusters: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<SemanticCluster>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    active.indexer().cluster_symbols(clusters)
+}
+
+/// Symbol name completions for the prompt editor's symbol picker
+#[tauri::command]
+pub async fn suggest_symbols(
+    prefix: String,
+    limit: Option<usize>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<SymbolSuggestion>, String> {
+    let active = activate_project(&state, &app_handle, &project)?;
+
+    Ok(active.indexer().suggest_symbols(active.index(), &prefix, limit.unwrap_or(10)))
+}
+
+#[tauri::command]
+pub async fn search_all_projects(
+    query: String,
+    max_results: Option<usize>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<ProjectSearchResult>, String> {
+    let max_results = max_results.unwrap_or(50);
+
+    let persistence = persistence(&state, &app_handle)?;
+
+    let mut searchers = state
+        .project_searchers
+        .lock()
+        .map_err(|e| format!("Failed to lock project searchers: {}", e))?;
+
+    // A project cached on several branches is searched on the most recently used one
+    let mut projects = persistence.get_cached_projects()?;
+    projects.sort_by_key(|p| std::cmp::Reverse(p.last_accessed));
+    let mut seen = HashSet::new();
+
+    let mut merged = Vec::new();
+    for project in projects {
+        if !seen.insert(project.project_path.clone()) {
+            continue;
+        }
+        if !searchers.contains_key(&project.project_path) {
+            let tantivy_dir = project.cache_dir.join("tantivy");
+            match TantivyIndexer::open_read_only(tantivy_dir) {
+                Ok(searcher) => {
+                    searchers.insert(project.project_path.clone(), searcher);
+                }
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", project.project_path, e);
+                    continue;
+                }
+            }
+        }
+
+        let searcher = match searchers.get(&project.project_path) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let results = match searcher.search(&query, max_results) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Search failed for {}: {}", project.project_path, e);
+                continue;
+            }
+        };
+
+        // BM25 scores are not comparable across indexes, so scale per project
+        let top_score = results
+            .first()
+            .map(|r| r.raw_score)
+            .unwrap_or(1.0)
+            .max(f32::EPSILON);
+
+        merged.extend(results.into_iter().map(|r| ProjectSearchResult {
+            project_path: project.project_path.clone(),
+            normalized_score: r.raw_score / top_score,
+            result: r,
+        }));
+    }
+
+    merged.sort_by(|a, b| {
+        b.normalized_score
+            .partial_cmp(&a.normalized_score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    merged.truncate(max_results);
+
+    Ok(merged)
+}
+
+#[tauri::command]
+pub async fn get_full_text_config(state: State<'_, IndexerState>) -> Result<FullTextConfig, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.full_text_config().clone())
+}
+
+#[tauri::command]
+pub async fn set_full_text_config(
+    config: FullTextConfig,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    config.validate()?;
+    state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_full_text_config(config.clone());
+
+    save_settings(&app_handle, &state, |settings| settings.full_text_config = config)
+}
+
+#[tauri::command]
+pub async fn get_embedding_device(state: State<'_, IndexerState>) -> Result<Option<String>, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.embedding_device().map(String::from))
+}
+
+#[tauri::command]
+pub async fn set_embedding_device(
+    device: DevicePreference,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<String, String> {
+    let active = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_embedding_device(device)?;
+
+    save_settings(&app_handle, &state, |settings| settings.embedding_device = device)?;
+    Ok(active)
+}
+
+#[tauri::command]
+pub async fn get_embedding_model(state: State<'_, IndexerState>) -> Result<EmbeddingModel, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.embedding_model())
+}
+
+/// Switch embedding models. The semantic index is emptied, so the frontend
+/// should re-index the current project afterwards.
+#[tauri::command]
+pub async fn set_embedding_model(
+    model: EmbeddingModel,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_embedding_model(model)?;
+
+    save_settings(&app_handle, &state, |settings| settings.embedding_model = model)
+}
+
+#[tauri::command]
+pub async fn get_semantic_status(state: State<'_, IndexerState>) -> Result<SemanticStatus, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.semantic_status(None))
+}
+
+/// Load the embedding model from a local directory and/or disable downloads.
+/// A source without a cache directory keeps the current one.
+#[tauri::command]
+pub async fn set_model_source(
+    source: ModelSource,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<SemanticStatus, String> {
+    if source.local_path.as_ref().is_some_and(|path| !path.is_absolute())
+        || source.cache_dir.as_ref().is_some_and(|dir| !dir.is_absolute())
+    {
+        return Err("Model directories must be absolute paths".to_string());
+    }
+    let local_path = source.local_path.as_ref().map(|path| path.display().to_string());
+    let cache_dir = source.cache_dir.as_ref().map(|dir| dir.display().to_string());
+    let offline = source.offline;
+
+    let status = {
+        let mut indexer = state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        let source = ModelSource {
+            cache_dir: source.cache_dir.or_else(|| indexer.model_source().cache_dir.clone()),
+            ..source
+        };
+        indexer.set_model_source(source)
+    };
+
+    save_settings(&app_handle, &state, |settings| {
+        settings.model_local_path = local_path;
+        if cache_dir.is_some() {
+            settings.model_cache_dir = cache_dir;
+        }
+        settings.model_offline = offline;
+    })?;
+    Ok(status)
+}
+
+/// Make sure the selected embedding model is on disk, downloading it with
+/// `model-download-progress` events if needed, then load it.
+pub async fn ensure_embedding_model(app_handle: &AppHandle) -> Result<SemanticStatus, String> {
+    let state = app_handle.state::<IndexerState>();
+
+    let (model, source, local) = {
+        let indexer = state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        (
+            indexer.embedding_model(),
+            indexer.model_source().clone(),
+            matches!(indexer.embedding_backend(), EmbeddingBackendConfig::Local),
+        )
+    };
+
+    // Remote backends have nothing to download
+    if !local || is_model_available(model, &source) {
+        let mut indexer = state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        return Ok(indexer.reload_embedding_generator());
+    }
+
+    if source.offline {
+        return Err(format!("Offline mode: {} is not available locally", model.model_id()));
+    }
+
+    let cache_dir = match source.cache_dir {
+        Some(dir) => dir,
+        None => app_handle
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data dir: {}", e))?
+            .join("models"),
+    };
+
+    println!("Downloading {} to {}", model.model_id(), cache_dir.display());
+    download_model(model, &cache_dir, |progress| {
+        let _ = app_handle.emit("model-download-progress", progress);
+    })
+    .await?;
+
+    let mut indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+    indexer.set_model_cache_dir(cache_dir);
+    let status = indexer.reload_embedding_generator();
+    let _ = app_handle.emit("semantic-status", status.clone());
+
+    Ok(status)
+}
+
+#[tauri::command]
+pub async fn download_embedding_model(app_handle: AppHandle) -> Result<SemanticStatus, String> {
+    ensure_embedding_model(&app_handle).await
+}
+
+/// Choose where downloaded models are stored
+#[tauri::command]
+pub async fn set_model_cache_dir(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let dir = std::path::PathBuf::from(&path);
+    if !dir.is_absolute() {
+        return Err("Model cache directory must be an absolute path".to_string());
+    }
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create model cache directory: {}", e))?;
+
+    state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_model_cache_dir(dir);
+
+    save_settings(&app_handle, &state, |settings| settings.model_cache_dir = Some(path))
+}
+
+/// Turn cross-encoder re-ranking of search results on or off
+#[tauri::command]
+pub async fn set_reranking_enabled(
+    enabled: bool,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<bool, String> {
+    let enabled = {
+        let mut indexer = state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        indexer.set_reranking_enabled(enabled)?;
+        indexer.reranking_enabled()
+    };
+
+    save_settings(&app_handle, &state, |settings| settings.reranking = enabled)?;
+    Ok(enabled)
+}
+
+#[tauri::command]
+pub async fn get_reranking_enabled(state: State<'_, IndexerState>) -> Result<bool, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.reranking_enabled())
+}
+
+/// Choose between local embeddings and a hosted embedding API
+#[tauri::command]
+pub async fn set_embedding_backend(
+    config: EmbeddingBackendConfig,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<SemanticStatus, String> {
+    let status = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_embedding_backend(config.clone());
+
+    save_settings(&app_handle, &state, |settings| settings.embedding_backend = config)?;
+    Ok(status)
+}
+
+/// The configured embedding backend, with any API key blanked out
+#[tauri::command]
+pub async fn get_embedding_backend(
+    state: State<'_, IndexerState>,
+) -> Result<EmbeddingBackendConfig, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    let mut config = indexer.embedding_backend().clone();
+    if let EmbeddingBackendConfig::Remote(ref mut remote) = config {
+        remote.api_key.clear();
+    }
+
+    Ok(config)
+}
+
+#[tauri::command]
+pub async fn get_hnsw_config(state: State<'_, IndexerState>) -> Result<HnswConfig, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.hnsw_config().clone())
+}
+
+/// Tune HNSW recall versus speed. Returns true if the codebase must be
+/// re-indexed for the new build parameters to apply.
+#[tauri::command]
+pub async fn set_hnsw_config(
+    config: HnswConfig,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<bool, String> {
+    let needs_reindex = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_hnsw_config(config.clone())?;
+
+    save_settings(&app_handle, &state, |settings| settings.hnsw = config)?;
+    Ok(needs_reindex)
+}
+
+/// Saved default search config for a project, if any
+#[tauri::command]
+pub async fn get_default_search_config(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Option<HybridConfig>, String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let settings_path = persistence.get_project_settings_path(&path);
+    Ok(ProjectSettings::load_or_default(&settings_path, &path)?.hybrid_config)
+}
+
+/// Save the default search config for a project; None restores the
+/// query-type presets. Takes effect immediately for the current project.
+#[tauri::command]
+pub async fn set_default_search_config(
+    path: String,
+    config: Option<HybridConfig>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    if let Some(ref config) = config {
+        config.validate()?;
+    }
+
+    let persistence = persistence(&state, &app_handle)?;
+
+    let settings_path = persistence.get_project_settings_path(&path);
+    let mut settings = ProjectSettings::load_or_default(&settings_path, &path)?;
+    settings.hybrid_config = config.clone();
+    settings.save(&settings_path)?;
+
+    let is_current = state.current_index.lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?
+        .as_ref()
+        .is_some_and(|index| index.root_path == path);
+    if is_current {
+        state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?
+            .set_default_hybrid_config(config);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_normalizer_config(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<NormalizerConfig, String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let settings_path = persistence.get_project_settings_path(&path);
+    Ok(ProjectSettings::load_or_default(&settings_path, &path)?
+        .normalizer
+        .unwrap_or_default())
+}
+
+/// Save a project's stop words, code mode and stemming language; None
+/// restores the defaults. A new stemming language is fully reflected in
+/// ranking after the next re-index.
+#[tauri::command]
+pub async fn set_normalizer_config(
+    path: String,
+    config: Option<NormalizerConfig>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let settings_path = persistence.get_project_settings_path(&path);
+    let mut settings = ProjectSettings::load_or_default(&settings_path, &path)?;
+    settings.normalizer = config.clone();
+    settings.save(&settings_path)?;
+
+    let is_current = state.current_index.lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?
+        .as_ref()
+        .is_some_and(|index| index.root_path == path);
+    if is_current {
+        state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?
+            .set_normalizer_config(config.unwrap_or_default());
+    }
+
+    Ok(())
+}
+
+/// System prompts saved for a project, replacing the app-wide ones
+#[tauri::command]
+pub async fn get_project_system_prompts(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<SystemPrompts, String> {
+    let persistence = persistence(&state, &app_handle)?;
+
+    let settings_path = persistence.get_project_settings_path(&path);
+    Ok(ProjectSettings::load_or_default(&settings_path, &path)?.system_prompts)
+}
+
+/// Save a project's system prompts; a prompt left None uses the app-wide
+/// one. Takes effect from the next request.
+#[tauri::command]
+pub async fn set_project_system_prompts(
+    path: String,
+    prompts: SystemPrompts,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    prompts.validate()?;
+
+    let persistence = persistence(&state, &app_handle)?;
+
+    let settings_path = persistence.get_project_settings_path(&path);
+    let mut settings = ProjectSettings::load_or_default(&settings_path, &path)?;
+    settings.system_prompts = prompts;
+    settings.save(&settings_path)
+}
+
+#[tauri::command]
+pub async fn get_query_synonyms(
+    state: State<'_, IndexerState>,
+) -> Result<HashMap<String, Vec<String>>, String> {
+    let indexer = state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+
+    Ok(indexer.query_synonyms().clone())
+}
+
+/// Swap in a synonym table and rebuild the active project's symbol lookup
+/// tables with it, so symbols indexed under the old table lose its
+/// expansions. Other loaded projects keep them until opened from cache or
+/// re-indexed.
+pub fn apply_query_synonyms(
+    state: &IndexerState,
+    indexer: &mut TreeSitterIndexer,
+    synonyms: HashMap<String, Vec<String>>,
+) -> Result<(), String> {
+    indexer.set_query_synonyms(synonyms);
+
+    let mut current_index = state.current_index.lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))?;
+    if let Some(index) = current_index.take() {
+        *current_index = Some(rebuild_index(
+            index.root_path,
+            index.files.into_values(),
+            index.indexed_at,
+            indexer.normalizer(),
+        ));
+    }
+    Ok(())
+}
+
+/// Replace the synonym/abbreviation table used for query expansion and
+/// symbol name normalization; None restores the built-in table
+#[tauri::command]
+pub async fn set_query_synonyms(
+    synonyms: Option<HashMap<String, Vec<String>>>,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    {
+        let mut indexer = state.indexer.lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        let table = synonyms.clone().unwrap_or_else(TextNormalizer::default_synonyms);
+        apply_query_synonyms(&state, &mut indexer, table)?;
+    }
+
+    save_settings(&app_handle, &state, |settings| settings.query_synonyms = synonyms)
+}
+
+/// Enable or disable LLM rewriting of queries into alternate phrasings.
+/// Costs one API call per search; requires the provider's API key to be set.
+#[tauri::command]
+pub async fn set_multi_query_enabled(
+    enabled: bool,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    state.indexer.lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_multi_query_enabled(enabled);
+
+    save_settings(&app_handle, &state, |settings| settings.multi_query = enabled)
+}
+
+#[tauri::command]
+pub async fn get_multi_query_enabled(state: State<'_, IndexerState>) -> Result<bool, String> {
     let indexer = state.indexer.lock()
         .map_err(|e| format!("Failed to lock indexer: {}", e))?;
 
-    indexer.search_semantic(&query, max_results.unwrap_or(20))
+    Ok(indexer.multi_query_enabled())
 }
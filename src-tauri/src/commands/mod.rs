@@ -1,2 +1,6 @@
 pub mod index_commands;
 pub mod anthropic_commands;
+pub mod file_commands;
+pub mod settings_commands;
+pub mod session_commands;
+pub mod batch_commands;
@@ -0,0 +1,140 @@
+use crate::anthropic::attachments::{load_attachments, with_attachments};
+use crate::anthropic::context_builder::TokenCounter;
+use crate::anthropic::models::Message;
+use crate::anthropic::sessions::{transcript_line, ChatSession, SessionInfo, SESSION_HISTORY_TOKENS};
+use crate::anthropic::tools::{search_code_tool, CODEBASE_ASSISTANT_PROMPT};
+use crate::commands::anthropic_commands::{cached_codebase_summary, llm_client};
+use crate::commands::index_commands::{
+    activate_project, persistence, run_index_tool, IndexerState,
+};
+use std::path::PathBuf;
+use tauri::{AppHandle, State};
+
+/// Characters of the first message used as a session's title
+const SESSION_TITLE_CHARS: usize = 60;
+
+fn sessions_dir(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    project_path: &str,
+) -> Result<PathBuf, String> {
+    Ok(persistence(state, app_handle)?.get_sessions_dir(project_path))
+}
+
+/// Start a conversation about a project. Without a title, the first message
+/// names it.
+#[tauri::command]
+pub async fn create_session(
+    title: Option<String>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<ChatSession, String> {
+    let dir = sessions_dir(&app_handle, &state, &project)?;
+
+    let session = ChatSession::new(ChatSession::next_id(&dir), project, title.unwrap_or_default());
+    session.save(&dir)?;
+    Ok(session)
+}
+
+/// A project's sessions, most recently used first
+#[tauri::command]
+pub async fn list_sessions(
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<Vec<SessionInfo>, String> {
+    let dir = sessions_dir(&app_handle, &state, &project)?;
+    Ok(ChatSession::load_all(&dir)
+        .iter()
+        .map(ChatSession::info)
+        .collect())
+}
+
+#[tauri::command]
+pub async fn get_session(
+    session_id: u64,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<ChatSession, String> {
+    ChatSession::load(&sessions_dir(&app_handle, &state, &project)?, session_id)
+}
+
+#[tauri::command]
+pub async fn delete_session(
+    session_id: u64,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<(), String> {
+    ChatSession::delete(&sessions_dir(&app_handle, &state, &project)?, session_id)
+}
+
+/// Continue a session with a message and return the reply. The model may
+/// search the project's index while answering. Turns past the history
+/// budget are folded into the session's summary; if summarizing fails they
+/// are dropped. `attachments` are files sent along with the message, such
+/// as screenshots.
+#[tauri::command]
+pub async fn chat(
+    session_id: u64,
+    message: String,
+    attachments: Option<Vec<String>>,
+    project: String,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<String, String> {
+    let dir = sessions_dir(&app_handle, &state, &project)?;
+    let mut session = ChatSession::load(&dir, session_id)?;
+    // Fails early for a project that isn't loaded
+    drop(activate_project(&state, &app_handle, &project)?);
+    let attachments = load_attachments(&attachments.unwrap_or_default())?;
+
+    let client = llm_client(&app_handle, &state, Some(&project))?;
+
+    if session.title.is_empty() {
+        session.title = message.chars().take(SESSION_TITLE_CHARS).collect();
+    }
+    session.messages.push(Message {
+        role: "user".to_string(),
+        content: with_attachments(message, attachments),
+    });
+
+    let overflow = session.take_overflow(SESSION_HISTORY_TOKENS, &TokenCounter::estimate());
+    if !overflow.is_empty() {
+        let transcript: Vec<String> = overflow.iter().map(transcript_line).collect();
+        match client
+            .summarize_conversation(session.summary.as_deref(), &transcript.join("\n\n"))
+            .await
+        {
+            Ok(summary) => session.summary = Some(summary),
+            Err(e) => eprintln!("Failed to summarize earlier turns: {}", e),
+        }
+    }
+
+    let mut system = CODEBASE_ASSISTANT_PROMPT.to_string();
+    if let Some(overview) = cached_codebase_summary(&app_handle, &state, &project) {
+        system = format!("{}\n\n{}", system, overview.preamble());
+    }
+    if let Some(summary) = &session.summary {
+        system = format!(
+            "{}\n\nSummary of the earlier conversation:\n{}",
+            system, summary
+        );
+    }
+
+    // A failed turn isn't saved, so the message can be sent again
+    let reply = client
+        .run_with_tools(
+            &mut session.messages,
+            Some(system),
+            &[search_code_tool()],
+            &mut |name, input| run_index_tool(&state, &app_handle, &project, name, input),
+        )
+        .await?;
+
+    session.touch();
+    session.save(&dir)?;
+    Ok(reply)
+}
@@ -0,0 +1,282 @@
+use crate::anthropic::credentials::{delete_api_key, load_api_key, store_api_key};
+use crate::anthropic::http::{configure_http, configure_network};
+use crate::commands::index_commands::{
+    apply_query_synonyms, current_settings, persistence, IndexerState,
+};
+use crate::indexing::embedding_backend::EmbeddingBackendConfig;
+use crate::indexing::embedding_generator::ModelSource;
+use crate::indexing::persistence::PersistenceConfig;
+use crate::settings::AppSettings;
+use std::mem::discriminant;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data dir: {}", e))
+}
+
+/// Load the saved settings at startup and hand them to the indexer. The
+/// embedding model is only selected here; the startup model check loads it.
+pub fn init_settings(app_handle: &AppHandle) -> Result<(), String> {
+    let data_dir = app_data_dir(app_handle)?;
+    let settings = AppSettings::load(&data_dir)?;
+    let state = app_handle.state::<IndexerState>();
+    configure_network(settings.network.clone());
+    configure_http(settings.llm_http.clone());
+
+    {
+        let mut indexer = state
+            .indexer
+            .lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        indexer.set_ignore_globs(settings.ignore_globs.clone());
+        indexer.set_app_hybrid_config(settings.hybrid_config.clone());
+        indexer.set_full_text_config(settings.full_text_config.clone());
+        indexer.set_hnsw_config(settings.hnsw.clone())?;
+        indexer.set_query_synonyms(settings.synonym_table());
+        indexer.set_multi_query_enabled(settings.multi_query);
+        indexer.select_embeddings(settings.embedding_model, settings.embedding_backend.clone());
+        indexer.select_embedding_device(settings.embedding_device);
+        indexer.select_model_source(model_source(&data_dir, &settings));
+    }
+
+    // An unavailable cache directory (say, an unmounted disk) falls back to
+    // the default one for this session
+    if settings.cache_dir.is_some() {
+        match PersistenceConfig::with_cache_dir(app_handle, settings.cache_dir.as_deref()) {
+            Ok(persistence) => {
+                *state
+                    .persistence
+                    .lock()
+                    .map_err(|e| format!("Failed to lock persistence: {}", e))? = Some(persistence);
+            }
+            Err(e) => eprintln!("Using the default cache directory: {}", e),
+        }
+    }
+
+    *state
+        .settings
+        .lock()
+        .map_err(|e| format!("Failed to lock settings: {}", e))? = settings;
+    Ok(())
+}
+
+/// Load the re-ranking model if the saved settings turn re-ranking on. Kept
+/// out of `init_settings`, since the model may have to be downloaded first.
+pub fn init_reranking(app_handle: &AppHandle) -> Result<(), String> {
+    let state = app_handle.state::<IndexerState>();
+    if !current_settings(&state)?.reranking {
+        return Ok(());
+    }
+    state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .set_reranking_enabled(true)
+}
+
+/// Where embedding models are loaded from. Downloads go to the
+/// `model_cache_dir` setting, or the models directory in the app data dir.
+fn model_source(app_data_dir: &Path, settings: &AppSettings) -> ModelSource {
+    ModelSource {
+        local_path: settings.model_local_path.as_ref().map(PathBuf::from),
+        bundled_dir: None,
+        cache_dir: Some(
+            settings
+                .model_cache_dir
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| app_data_dir.join("models")),
+        ),
+        offline: settings.model_offline,
+    }
+}
+
+/// Persist a change made by one of the single-setting commands
+pub fn save_settings(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    update: impl FnOnce(&mut AppSettings),
+) -> Result<(), String> {
+    let mut settings = state
+        .settings
+        .lock()
+        .map_err(|e| format!("Failed to lock settings: {}", e))?;
+    update(&mut settings);
+    settings.save(&app_data_dir(app_handle)?)
+}
+
+/// The app-wide settings, with any API key blanked out
+#[tauri::command]
+pub async fn get_settings(state: State<'_, IndexerState>) -> Result<AppSettings, String> {
+    Ok(current_settings(&state)?.redacted())
+}
+
+/// Move the caches to the directory a `cache_dir` setting names. Open
+/// projects are closed first, since their indexes are open in the old one.
+fn move_caches(
+    app_handle: &AppHandle,
+    state: &IndexerState,
+    cache_dir: Option<&str>,
+) -> Result<(), String> {
+    let mut persistence_lock = state
+        .persistence
+        .lock()
+        .map_err(|e| format!("Failed to lock persistence: {}", e))?;
+    if persistence_lock.is_none() {
+        *persistence_lock = Some(PersistenceConfig::new(app_handle)?);
+    }
+
+    state
+        .indexer
+        .lock()
+        .map_err(|e| format!("Failed to lock indexer: {}", e))?
+        .close_project();
+    *state
+        .current_index
+        .lock()
+        .map_err(|e| format!("Failed to lock index: {}", e))? = None;
+    state
+        .loaded_projects
+        .lock()
+        .map_err(|e| format!("Failed to lock loaded projects: {}", e))?
+        .clear();
+    state
+        .project_searchers
+        .lock()
+        .map_err(|e| format!("Failed to lock project searchers: {}", e))?
+        .clear();
+
+    let target = PersistenceConfig::cache_dir_for(app_handle, cache_dir)?;
+    let relocated = persistence_lock
+        .as_ref()
+        .ok_or_else(|| "Persistence not initialized".to_string())?
+        .relocate(target)?;
+    println!("Moved caches to {}", relocated.cache_dir.display());
+    *persistence_lock = Some(relocated);
+    Ok(())
+}
+
+/// Replace the app-wide settings, save them and apply them. A blank remote
+/// embedding or LLM provider API key or secret keeps the saved one. A new
+/// embedding model, backend, device or model source is loaded right away; a
+/// new model or backend needs a re-index, like `set_embedding_model`.
+/// Changed ignore globs and HNSW build parameters apply from the next
+/// index, and a new model cache directory from the next download. A new cache directory gets the existing
+/// caches moved into it, closing any open projects.
+#[tauri::command]
+pub async fn update_settings(
+    mut settings: AppSettings,
+    app_handle: AppHandle,
+    state: State<'_, IndexerState>,
+) -> Result<AppSettings, String> {
+    settings.validate()?;
+
+    let previous = current_settings(&state)?;
+    if let (EmbeddingBackendConfig::Remote(new), EmbeddingBackendConfig::Remote(old)) =
+        (&mut settings.embedding_backend, &previous.embedding_backend)
+    {
+        if new.api_key.is_empty() {
+            new.api_key = old.api_key.clone();
+        }
+    }
+    // Only a key for the same kind of provider is kept
+    if discriminant(&settings.llm_provider) == discriminant(&previous.llm_provider) {
+        let mut previous_provider = previous.llm_provider.clone();
+        if let (Some(new), Some(old)) = (
+            settings.llm_provider.secret_mut(),
+            previous_provider.secret_mut(),
+        ) {
+            if new.is_empty() {
+                *new = old.clone();
+            }
+        }
+    }
+    // Moved before saving, so a failed move leaves the old setting in place
+    if settings.cache_dir != previous.cache_dir {
+        move_caches(&app_handle, &state, settings.cache_dir.as_deref())?;
+    }
+    if settings.reranking != previous.reranking {
+        state
+            .indexer
+            .lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?
+            .set_reranking_enabled(settings.reranking)?;
+    }
+    settings.save(&app_data_dir(&app_handle)?)?;
+    configure_network(settings.network.clone());
+    configure_http(settings.llm_http.clone());
+
+    if let Some(max_bytes) = settings.max_cache_bytes {
+        let active = state
+            .current_index
+            .lock()
+            .map_err(|e| format!("Failed to lock index: {}", e))?
+            .as_ref()
+            .map(|index| index.root_path.clone())
+            .unwrap_or_default();
+
+        for evicted in
+            persistence(&state, &app_handle)?.enforce_cache_limit(max_bytes, &active)?
+        {
+            println!("Evicted cached index: {}", evicted);
+        }
+    }
+
+    {
+        let mut indexer = state
+            .indexer
+            .lock()
+            .map_err(|e| format!("Failed to lock indexer: {}", e))?;
+        indexer.set_ignore_globs(settings.ignore_globs.clone());
+        indexer.set_app_hybrid_config(settings.hybrid_config.clone());
+        indexer.set_full_text_config(settings.full_text_config.clone());
+        indexer.set_hnsw_config(settings.hnsw.clone())?;
+        if settings.query_synonyms != previous.query_synonyms {
+            apply_query_synonyms(&state, &mut indexer, settings.synonym_table())?;
+        }
+        indexer.set_multi_query_enabled(settings.multi_query);
+        indexer.select_model_source(model_source(&app_data_dir(&app_handle)?, &settings));
+        if settings.embedding_model != previous.embedding_model
+            || settings.embedding_backend != previous.embedding_backend
+            || settings.model_local_path != previous.model_local_path
+            || settings.model_offline != previous.model_offline
+            || settings.embedding_device != previous.embedding_device
+        {
+            indexer.select_embeddings(settings.embedding_model, settings.embedding_backend.clone());
+            indexer.select_embedding_device(settings.embedding_device);
+            indexer.reload_embedding_generator();
+        }
+    }
+
+    *state
+        .settings
+        .lock()
+        .map_err(|e| format!("Failed to lock settings: {}", e))? = settings.clone();
+    Ok(settings.redacted())
+}
+
+/// Save the Anthropic API key in the OS keyring; an empty key removes it.
+/// Commands read it from there, so it's only ever sent from the UI here.
+#[tauri::command]
+pub async fn set_api_key(api_key: String) -> Result<(), String> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        delete_api_key()?;
+        println!("Removed Anthropic API key");
+    } else {
+        store_api_key(api_key)?;
+        println!("Stored Anthropic API key");
+    }
+    Ok(())
+}
+
+/// Whether an Anthropic API key is saved; the key itself is never returned
+#[tauri::command]
+pub async fn get_api_status() -> Result<serde_json::Value, String> {
+    let configured = load_api_key()?.is_some();
+    Ok(serde_json::json!({ "configured": configured }))
+}
@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufReader, BufWriter};
+use std::path::{Component, Path, PathBuf};
+
+/// Bumped when the bundle layout changes incompatibly
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// zstd level for bundles; they are written once and usually copied over
+/// a network, so a little extra save time is worth it
+const BUNDLE_COMPRESSION_LEVEL: i32 = 9;
+
+/// Leads every bundle: where the cache came from and in which formats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    /// `CACHE_FORMAT_VERSION` of the bundled cache
+    pub cache_format_version: u32,
+    /// Project root the cached paths are under
+    pub root_path: String,
+    pub file_count: usize,
+}
+
+/// One cache file, with its path relative to the project's cache directory
+#[derive(Serialize, Deserialize)]
+struct BundleEntry {
+    path: String,
+    data: Vec<u8>,
+}
+
+/// Pack every file of a project's cache directory into one zstd-compressed
+/// bundle. Lock files are left out. Returns the manifest written.
+pub fn write_bundle(
+    cache_dir: &Path,
+    root_path: &str,
+    cache_format_version: u32,
+    out: &Path,
+) -> Result<BundleManifest, String> {
+    let mut files = Vec::new();
+    collect_files(cache_dir, cache_dir, &mut files)
+        .map_err(|e| format!("Failed to read cache directory: {}", e))?;
+    files.sort();
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        cache_format_version,
+        root_path: root_path.to_string(),
+        file_count: files.len(),
+    };
+
+    let file = fs::File::create(out)
+        .map_err(|e| format!("Failed to create {}: {}", out.display(), e))?;
+    let mut encoder = zstd::Encoder::new(BufWriter::new(file), BUNDLE_COMPRESSION_LEVEL)
+        .map_err(|e| format!("Failed to start compression: {}", e))?;
+    let write_err = |e: bincode::Error| format!("Failed to write bundle: {}", e);
+
+    bincode::serialize_into(&mut encoder, &manifest).map_err(write_err)?;
+    for relative in &files {
+        let data = fs::read(cache_dir.join(relative))
+            .map_err(|e| format!("Failed to read {}: {}", relative.display(), e))?;
+        let entry = BundleEntry {
+            path: relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            data,
+        };
+        bincode::serialize_into(&mut encoder, &Some(entry)).map_err(write_err)?;
+    }
+    bincode::serialize_into(&mut encoder, &None::<BundleEntry>).map_err(write_err)?;
+
+    encoder
+        .finish()
+        .map_err(|e| format!("Failed to finish bundle: {}", e))?;
+    Ok(manifest)
+}
+
+/// Read just a bundle's manifest
+pub fn read_manifest(bundle: &Path) -> Result<BundleManifest, String> {
+    let (manifest, _) = open_bundle(bundle)?;
+    Ok(manifest)
+}
+
+/// Unpack a bundle into `dest`, which should be empty. Fails on bundles
+/// from a newer version or with paths escaping `dest`.
+pub fn read_bundle(bundle: &Path, dest: &Path) -> Result<BundleManifest, String> {
+    let (manifest, mut decoder) = open_bundle(bundle)?;
+    let read_err = |e: bincode::Error| format!("Failed to read bundle: {}", e);
+
+    while let Some(entry) =
+        bincode::deserialize_from::<_, Option<BundleEntry>>(&mut decoder).map_err(read_err)?
+    {
+        let relative = safe_relative_path(&entry.path)
+            .ok_or_else(|| format!("Bundle contains an invalid path: {}", entry.path))?;
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::write(&target, entry.data)
+            .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    }
+
+    Ok(manifest)
+}
+
+type Decoder = zstd::Decoder<'static, BufReader<BufReader<fs::File>>>;
+
+fn open_bundle(bundle: &Path) -> Result<(BundleManifest, Decoder), String> {
+    let file = fs::File::open(bundle)
+        .map_err(|e| format!("Failed to open {}: {}", bundle.display(), e))?;
+    let mut decoder = zstd::Decoder::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to start decompression: {}", e))?;
+
+    let manifest: BundleManifest = bincode::deserialize_from(&mut decoder)
+        .map_err(|e| format!("Not a cache bundle: {}", e))?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(format!(
+            "Cache bundle format {} is newer than this version of Prompto supports ({})",
+            manifest.format_version, BUNDLE_FORMAT_VERSION
+        ));
+    }
+    Ok((manifest, decoder))
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if path.extension().is_none_or(|ext| ext != "lock") {
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `path` as a relative path that stays inside the directory it is joined to
+fn safe_relative_path(path: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(path);
+    let is_safe = relative.components().count() > 0
+        && relative.components().all(|c| matches!(c, Component::Normal(_)));
+    is_safe.then_some(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let cache = tempfile::tempdir().unwrap();
+        fs::write(cache.path().join("metadata.json"), "{}").unwrap();
+        fs::create_dir(cache.path().join("tantivy")).unwrap();
+        fs::write(cache.path().join("tantivy").join("meta.json"), "[1, 2]").unwrap();
+        fs::write(cache.path().join("tantivy").join(".tantivy-writer.lock"), "").unwrap();
+
+        let out = tempfile::tempdir().unwrap();
+        let bundle = out.path().join("cache.bundle");
+        let manifest = write_bundle(cache.path(), "/repo", 3, &bundle).unwrap();
+        assert_eq!(manifest.file_count, 2);
+        assert_eq!(read_manifest(&bundle).unwrap().root_path, "/repo");
+
+        let dest = tempfile::tempdir().unwrap();
+        let manifest = read_bundle(&bundle, dest.path()).unwrap();
+        assert_eq!(manifest.cache_format_version, 3);
+        assert_eq!(fs::read_to_string(dest.path().join("tantivy").join("meta.json")).unwrap(), "[1, 2]");
+        assert!(!dest.path().join("tantivy").join(".tantivy-writer.lock").exists());
+    }
+
+    #[test]
+    fn test_rejects_escaping_paths() {
+        assert!(safe_relative_path("tantivy/meta.json").is_some());
+        assert!(safe_relative_path("../outside").is_none());
+        assert!(safe_relative_path("/etc/passwd").is_none());
+        assert!(safe_relative_path("").is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::indexing::vector_store::{VectorMetadata, VectorStore};
+
+/// Upper bound on k-means iterations
+const MAX_ITERATIONS: usize = 25;
+
+/// Symbols reported per cluster, closest to the centroid first
+const REPRESENTATIVES: usize = 5;
+
+/// Words too generic to describe a cluster
+const LABEL_STOP_WORDS: &[&str] = &[
+    "get", "set", "new", "from", "into", "with", "for", "the", "test", "impl", "default",
+];
+
+/// A group of semantically related symbols
+#[derive(Debug, Clone, Serialize)]
+pub struct SemanticCluster {
+    pub id: usize,
+    /// Most frequent words in member symbol names
+    pub label: String,
+    pub size: usize,
+    pub representatives: Vec<VectorMetadata>,
+    /// Files contributing the most members
+    pub top_files: Vec<String>,
+}
+
+/// Cluster the whole-symbol vectors of a store into `k` groups.
+/// When `k` is not given it grows with the square root of the symbol count.
+pub fn cluster_symbols(store: &VectorStore, k: Option<usize>) -> Result<Vec<SemanticCluster>, String> {
+    let mut entries: Vec<(&VectorMetadata, Vec<f32>)> = store
+        .all_metadata()
+        .filter(|(_, m)| m.is_whole_symbol())
+        .filter_map(|(id, m)| store.get_vector(id).map(|v| (m, v)))
+        .collect();
+
+    if entries.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Stable input order keeps the clustering deterministic
+    entries.sort_by(|a, b| {
+        (&a.0.file_path, a.0.start_line).cmp(&(&b.0.file_path, b.0.start_line))
+    });
+
+    let k = k
+        .unwrap_or_else(|| ((entries.len() as f32 / 2.0).sqrt() as usize).clamp(2, 30))
+        .clamp(1, entries.len());
+
+    let vectors: Vec<Vec<f32>> = entries.iter().map(|(_, v)| normalized(v)).collect();
+    let (assignments, centroids) = kmeans(&vectors, k);
+
+    let mut members: Vec<Vec<usize>> = vec![Vec::new(); k];
+    for (i, &cluster) in assignments.iter().enumerate() {
+        members[cluster].push(i);
+    }
+
+    let mut clusters: Vec<SemanticCluster> = members
+        .into_iter()
+        .enumerate()
+        .filter(|(_, m)| !m.is_empty())
+        .map(|(cluster, mut indices)| {
+            indices.sort_by(|&a, &b| {
+                dot(&vectors[b], &centroids[cluster])
+                    .partial_cmp(&dot(&vectors[a], &centroids[cluster]))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+            let metadata: Vec<&VectorMetadata> = indices.iter().map(|&i| entries[i].0).collect();
+
+            SemanticCluster {
+                id: cluster,
+                label: label_for(&metadata),
+                size: metadata.len(),
+                representatives: metadata
+                    .iter()
+                    .take(REPRESENTATIVES)
+                    .map(|m| (*m).clone())
+                    .collect(),
+                top_files: top_files(&metadata, 3),
+            }
+        })
+        .collect();
+
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.size));
+    Ok(clusters)
+}
+
+/// Spherical k-means over unit vectors with farthest-first initialization.
+/// Returns each vector's cluster and the final centroids, both empty when
+/// there are no vectors.
+pub fn kmeans(vectors: &[Vec<f32>], k: usize) -> (Vec<usize>, Vec<Vec<f32>>) {
+    if vectors.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+    let k = k.min(vectors.len()).max(1);
+
+    // Seed with the first vector, then repeatedly the vector least similar
+    // to every centroid chosen so far
+    let mut centroids = vec![vectors[0].clone()];
+    let mut closest: Vec<f32> = vectors.iter().map(|v| dot(v, &vectors[0])).collect();
+    while centroids.len() < k {
+        let (next, _) = closest
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.partial_cmp(b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .unwrap();
+        centroids.push(vectors[next].clone());
+        for (i, v) in vectors.iter().enumerate() {
+            closest[i] = closest[i].max(dot(v, &vectors[next]));
+        }
+    }
+
+    let mut assignments = vec![usize::MAX; vectors.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (i, v) in vectors.iter().enumerate() {
+            let best = centroids
+                .iter()
+                .enumerate()
+                .max_by(|a, b| {
+                    dot(v, a.1)
+                        .partial_cmp(&dot(v, b.1))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .map(|(c, _)| c)
+                .unwrap_or(0);
+            if assignments[i] != best {
+                assignments[i] = best;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+
+        let dims = vectors[0].len();
+        let mut sums = vec![vec![0.0f32; dims]; k];
+        for (v, &cluster) in vectors.iter().zip(&assignments) {
+            for (s, x) in sums[cluster].iter_mut().zip(v) {
+                *s += x;
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // An emptied cluster keeps its previous centroid
+            if sum.iter().any(|&x| x != 0.0) {
+                *centroid = normalized(&sum);
+            }
+        }
+    }
+
+    (assignments, centroids)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(v: &[f32]) -> Vec<f32> {
+    let norm = dot(v, v).sqrt();
+    if norm == 0.0 {
+        return v.to_vec();
+    }
+    v.iter().map(|x| x / norm).collect()
+}
+
+/// Split snake_case and camelCase names into lowercase words
+fn name_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for ch in name.chars() {
+        if ch == '_' || !ch.is_alphanumeric() || (ch.is_uppercase() && !current.is_empty()) {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            if ch == '_' || !ch.is_alphanumeric() {
+                continue;
+            }
+        }
+        current.extend(ch.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+        .into_iter()
+        .filter(|w| w.len() > 2 && !LABEL_STOP_WORDS.contains(&w.as_str()))
+        .collect()
+}
+
+fn label_for(members: &[&VectorMetadata]) -> String {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for m in members {
+        for word in name_words(&m.symbol_name) {
+            *counts.entry(word).or_default() += 1;
+        }
+    }
+
+    let mut words: Vec<(String, usize)> = counts.into_iter().collect();
+    words.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let label: Vec<String> = words.into_iter().take(3).map(|(w, _)| w).collect();
+    if label.is_empty() {
+        members
+            .first()
+            .map(|m| m.symbol_name.clone())
+            .unwrap_or_default()
+    } else {
+        label.join(" ")
+    }
+}
+
+fn top_files(members: &[&VectorMetadata], limit: usize) -> Vec<String> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for m in members {
+        *counts.entry(m.file_path.as_str()).or_default() += 1;
+    }
+
+    let mut files: Vec<(&str, usize)> = counts.into_iter().collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    files.into_iter().take(limit).map(|(f, _)| f.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmeans_separates_groups() {
+        let vectors = vec![
+            normalized(&[1.0, 0.1, 0.0]),
+            normalized(&[0.9, 0.0, 0.1]),
+            normalized(&[0.0, 1.0, 0.1]),
+            normalized(&[0.1, 0.9, 0.0]),
+        ];
+
+        let (assignments, _) = kmeans(&vectors, 2);
+        assert_eq!(assignments[0], assignments[1]);
+        assert_eq!(assignments[2], assignments[3]);
+        assert_ne!(assignments[0], assignments[2]);
+    }
+
+    #[test]
+    fn test_kmeans_empty_input() {
+        let (assignments, centroids) = kmeans(&[], 3);
+        assert!(assignments.is_empty());
+        assert!(centroids.is_empty());
+    }
+
+    #[test]
+    fn test_name_words() {
+        assert_eq!(name_words("parseConfigFile"), vec!["parse", "config", "file"]);
+        assert_eq!(name_words("get_user_id"), vec!["user"]);
+    }
+}
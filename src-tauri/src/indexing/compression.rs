@@ -0,0 +1,35 @@
+/// Leading bytes of every zstd frame
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// zstd level for cache files; higher levels barely shrink them further
+/// but slow down every save
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Compress a cache file's contents
+pub fn compress(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    zstd::encode_all(bytes, COMPRESSION_LEVEL).map_err(|e| format!("Failed to compress: {}", e))
+}
+
+/// Undo `compress`. Caches written before compression was added are
+/// returned unchanged.
+pub fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, String> {
+    if !bytes.starts_with(ZSTD_MAGIC) {
+        return Ok(bytes);
+    }
+    zstd::decode_all(bytes.as_slice()).map_err(|e| format!("Failed to decompress: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_uncompressed_passthrough() {
+        let data = b"fn load_config() {}\n".repeat(100);
+        let compressed = compress(&data).unwrap();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(compressed).unwrap(), data);
+
+        assert_eq!(decompress(data.clone()).unwrap(), data);
+    }
+}
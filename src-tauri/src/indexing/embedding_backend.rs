@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+
+use crate::anthropic::http::blocking_client_builder;
+use crate::indexing::embedding_generator::EmbeddingGenerator;
+#[cfg(feature = "onnx")]
+use crate::indexing::embedding_generator::EmbeddingModel;
+
+/// Anything that can turn text into embedding vectors for the vector store
+pub trait EmbeddingBackend: Send {
+    /// Identifier recorded alongside stored vectors
+    fn model_id(&self) -> &str;
+
+    fn embedding_dim(&self) -> usize;
+
+    /// Where inference runs ("cpu", "cuda", "metal" or "remote")
+    fn device_name(&self) -> &'static str;
+
+    /// Embed a document (symbol, chunk or file summary)
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String>;
+
+    /// Embed several documents, in order
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        texts.iter().map(|text| self.embed(text)).collect()
+    }
+
+    /// Embed a search query. Some providers encode queries differently.
+    fn embed_query(&self, text: &str) -> Result<Vec<f32>, String> {
+        self.embed(text)
+    }
+}
+
+impl EmbeddingBackend for EmbeddingGenerator {
+    fn model_id(&self) -> &str {
+        self.model().model_id()
+    }
+
+    fn embedding_dim(&self) -> usize {
+        EmbeddingGenerator::embedding_dim(self)
+    }
+
+    fn device_name(&self) -> &'static str {
+        EmbeddingGenerator::device_name(self)
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        EmbeddingGenerator::embed(self, text)
+    }
+}
+
+/// Which embedding implementation semantic search uses
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EmbeddingBackendConfig {
+    /// Candle inference on this machine
+    #[default]
+    Local,
+    /// ONNX Runtime inference on this machine via fastembed; faster than
+    /// candle on CPU. Requires building with the `onnx` feature.
+    Onnx,
+    /// Hosted embedding API; code is sent to the provider
+    Remote(RemoteEmbeddingConfig),
+}
+
+/// Local embeddings computed with ONNX Runtime.
+///
+/// Runs the same weights as the candle backend, so vectors are recorded
+/// under the same model id and stay valid when switching between the two.
+#[cfg(feature = "onnx")]
+pub struct OnnxEmbedder {
+    model: fastembed::TextEmbedding,
+    model_kind: EmbeddingModel,
+    embedding_dim: usize,
+}
+
+#[cfg(feature = "onnx")]
+impl OnnxEmbedder {
+    /// Load a model, downloading its ONNX export into `cache_dir` if needed
+    pub fn new(model_kind: EmbeddingModel, cache_dir: Option<std::path::PathBuf>) -> Result<Self, String> {
+        println!("Initializing ONNX embeddings ({})...", model_kind.model_id());
+
+        let model_name = match model_kind {
+            EmbeddingModel::AllMiniLmL6V2 => fastembed::EmbeddingModel::AllMiniLML6V2,
+            EmbeddingModel::BgeSmallEn => fastembed::EmbeddingModel::BGESmallENV15,
+            EmbeddingModel::JinaV2BaseCode => fastembed::EmbeddingModel::JinaEmbeddingsV2BaseCode,
+        };
+
+        let mut options = fastembed::InitOptions::new(model_name).with_show_download_progress(false);
+        if let Some(dir) = cache_dir {
+            options = options.with_cache_dir(dir.join("fastembed"));
+        }
+
+        let model = fastembed::TextEmbedding::try_new(options)
+            .map_err(|e| format!("Failed to load ONNX model: {}", e))?;
+
+        let embedding_dim = model
+            .embed(vec!["dimension probe"], None)
+            .map_err(|e| format!("ONNX inference failed: {}", e))?
+            .first()
+            .map(|v| v.len())
+            .ok_or_else(|| "ONNX model produced no embedding".to_string())?;
+
+        println!("ONNX embeddings ready (dim: {})", embedding_dim);
+
+        Ok(Self {
+            model,
+            model_kind,
+            embedding_dim,
+        })
+    }
+}
+
+#[cfg(feature = "onnx")]
+impl EmbeddingBackend for OnnxEmbedder {
+    fn model_id(&self) -> &str {
+        self.model_kind.model_id()
+    }
+
+    fn embedding_dim(&self) -> usize {
+        self.embedding_dim
+    }
+
+    fn device_name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        self.embed_batch(&[text.to_string()])?
+            .pop()
+            .ok_or_else(|| "ONNX model produced no embedding".to_string())
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if texts.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.model
+            .embed(texts.to_vec(), None)
+            .map_err(|e| format!("ONNX inference failed: {}", e))
+    }
+}
+
+/// Hosted embedding providers
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteProvider {
+    Voyage,
+    OpenAi,
+}
+
+impl RemoteProvider {
+    fn default_model(&self) -> &'static str {
+        match self {
+            RemoteProvider::Voyage => "voyage-code-2",
+            RemoteProvider::OpenAi => "text-embedding-3-small",
+        }
+    }
+
+    fn default_url(&self) -> &'static str {
+        match self {
+            RemoteProvider::Voyage => "https://api.voyageai.com/v1/embeddings",
+            RemoteProvider::OpenAi => "https://api.openai.com/v1/embeddings",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            RemoteProvider::Voyage => "voyage",
+            RemoteProvider::OpenAi => "openai",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteEmbeddingConfig {
+    pub provider: RemoteProvider,
+    pub api_key: String,
+    /// Provider model name; the provider's code-friendly default when unset
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Override the endpoint, e.g. for an OpenAI-compatible proxy
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// Inputs per request; both providers accept at least this many
+const REMOTE_BATCH_SIZE: usize = 128;
+/// A hung provider fails the request instead of stalling indexing, which
+/// holds the indexer lock
+const REMOTE_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REMOTE_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Embeddings computed by a hosted API
+pub struct RemoteEmbedder {
+    client: reqwest::blocking::Client,
+    config: RemoteEmbeddingConfig,
+    model: String,
+    model_id: String,
+    embedding_dim: usize,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+impl RemoteEmbedder {
+    /// Create the embedder, probing the API once to validate the key and
+    /// learn the embedding dimension
+    pub fn new(config: RemoteEmbeddingConfig) -> Result<Self, String> {
+        let model = config
+            .model
+            .clone()
+            .unwrap_or_else(|| config.provider.default_model().to_string());
+
+        // Through the configured proxy, like the model downloads
+        let client = blocking_client_builder()
+            .connect_timeout(REMOTE_CONNECT_TIMEOUT)
+            .timeout(REMOTE_REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        let mut embedder = Self {
+            client,
+            model_id: format!("{}/{}", config.provider.prefix(), model),
+            model,
+            config,
+            embedding_dim: 0,
+        };
+
+        let probe = embedder.request(&["dimension probe".to_string()], false)?;
+        embedder.embedding_dim = probe
+            .first()
+            .map(|v| v.len())
+            .ok_or_else(|| "Embedding API returned no vectors".to_string())?;
+
+        println!(
+            "Remote embeddings ready ({}, dim: {})",
+            embedder.model_id, embedder.embedding_dim
+        );
+
+        Ok(embedder)
+    }
+
+    fn request(&self, texts: &[String], is_query: bool) -> Result<Vec<Vec<f32>>, String> {
+        let url = self
+            .config
+            .base_url
+            .as_deref()
+            .unwrap_or_else(|| self.config.provider.default_url());
+
+        let mut body = json!({
+            "model": self.model,
+            "input": texts,
+        });
+        if self.config.provider == RemoteProvider::Voyage {
+            body["input_type"] = json!(if is_query { "query" } else { "document" });
+        }
+
+        let mut parsed = off_async_worker(|| -> Result<EmbeddingResponse, String> {
+            let response = self
+                .client
+                .post(url)
+                .bearer_auth(&self.config.api_key)
+                .json(&body)
+                .send()
+                .map_err(|e| format!("Failed to send embedding request: {}", e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response
+                    .text()
+                    .unwrap_or_else(|_| "Unknown error".to_string());
+                return Err(format!(
+                    "Embedding request failed with status {}: {}",
+                    status.as_u16(),
+                    error_text
+                ));
+            }
+
+            response
+                .json()
+                .map_err(|e| format!("Failed to parse embedding response: {}", e))
+        })?;
+
+        if parsed.data.len() != texts.len() {
+            return Err(format!(
+                "Embedding API returned {} vectors for {} inputs",
+                parsed.data.len(),
+                texts.len()
+            ));
+        }
+
+        parsed.data.sort_by_key(|d| d.index);
+        Ok(parsed.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+/// Run blocking HTTP work so it's allowed on an async worker thread, where
+/// indexing commands run and reqwest's blocking client refuses to wait
+fn off_async_worker<T>(work: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(_) => tokio::task::block_in_place(work),
+        Err(_) => work(),
+    }
+}
+
+impl EmbeddingBackend for RemoteEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn embedding_dim(&self) -> usize {
+        self.embedding_dim
+    }
+
+    fn device_name(&self) -> &'static str {
+        "remote"
+    }
+
+    fn embed(&self, text: &str) -> Result<Vec<f32>, String> {
+        self.request(&[text.to_string()], false)?
+            .pop()
+            .ok_or_else(|| "Embedding API returned no vectors".to_string())
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(REMOTE_BATCH_SIZE) {
+            embeddings.extend(self.request(batch, false)?);
+        }
+        Ok(embeddings)
+    }
+
+    fn embed_query(&self, text: &str) -> Result<Vec<f32>, String> {
+        self.request(&[text.to_string()], true)?
+            .pop()
+            .ok_or_else(|| "Embedding API returned no vectors".to_string())
+    }
+}
@@ -1,82 +1,417 @@
 use candle_core::{Device, Tensor};
 use candle_nn::VarBuilder;
+use candle_core::Module;
 use candle_transformers::models::bert::{BertModel, Config as BertConfig};
-use hf_hub::{api::sync::Api, Repo, RepoType};
+use candle_transformers::models::jina_bert::{BertModel as JinaBertModel, Config as JinaBertConfig};
+use crate::anthropic::http::client_builder;
+use hf_hub::{api::sync::ApiBuilder, Cache, Repo, RepoType};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use tokenizers::Tokenizer;
 
-use crate::models::code_index::CodeSymbol;
+use crate::models::code_index::{CodeSymbol, IndexedFile};
+
+/// Which compute device the embedding model should run on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DevicePreference {
+    /// Use CUDA or Metal when compiled in and present, otherwise CPU
+    #[default]
+    Auto,
+    Cpu,
+    Cuda,
+    Metal,
+}
 
-/// Generates semantic embeddings for code using BERT model
-pub struct EmbeddingGenerator {
-    model: BertModel,
-    tokenizer: Tokenizer,
-    device: Device,
-    embedding_dim: usize,
+/// Resolve a device preference, falling back to CPU when the GPU is unusable
+pub(crate) fn select_device(preference: DevicePreference) -> Device {
+    let gpu = match preference {
+        DevicePreference::Cpu => return Device::Cpu,
+        DevicePreference::Cuda => Device::new_cuda(0),
+        DevicePreference::Metal => Device::new_metal(0),
+        DevicePreference::Auto => {
+            if candle_core::utils::cuda_is_available() {
+                Device::new_cuda(0)
+            } else if candle_core::utils::metal_is_available() {
+                Device::new_metal(0)
+            } else {
+                return Device::Cpu;
+            }
+        }
+    };
+
+    match gpu {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("GPU device unavailable ({}), falling back to CPU", e);
+            Device::Cpu
+        }
+    }
 }
 
-impl EmbeddingGenerator {
-    /// Creates a new EmbeddingGenerator with all-MiniLM-L6-v2 model
-    pub fn new() -> Result<Self, String> {
-        println!("Initializing embedding generator...");
+/// Embedding models that can be selected in settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingModel {
+    /// sentence-transformers/all-MiniLM-L6-v2 (384 dims, fast)
+    #[default]
+    AllMiniLmL6V2,
+    /// BAAI/bge-small-en-v1.5 (384 dims)
+    BgeSmallEn,
+    /// jinaai/jina-embeddings-v2-base-code (768 dims, trained on code)
+    JinaV2BaseCode,
+}
+
+impl EmbeddingModel {
+    /// HuggingFace repository id, also recorded alongside stored vectors
+    pub fn model_id(&self) -> &'static str {
+        match self {
+            EmbeddingModel::AllMiniLmL6V2 => "sentence-transformers/all-MiniLM-L6-v2",
+            EmbeddingModel::BgeSmallEn => "BAAI/bge-small-en-v1.5",
+            EmbeddingModel::JinaV2BaseCode => "jinaai/jina-embeddings-v2-base-code",
+        }
+    }
+
+    /// Directory name used for bundled copies of the model
+    pub fn dir_name(&self) -> &'static str {
+        let id = self.model_id();
+        id.rsplit('/').next().unwrap_or(id)
+    }
+
+    /// BGE models are trained to use the [CLS] token as the sentence embedding
+    fn uses_cls_pooling(&self) -> bool {
+        matches!(self, EmbeddingModel::BgeSmallEn)
+    }
+}
+
+/// Where model files are loaded from, in order of preference
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModelSource {
+    /// User-specified directory containing config.json, tokenizer.json
+    /// and model.safetensors; takes precedence over everything else
+    #[serde(default)]
+    pub local_path: Option<PathBuf>,
+    /// Models shipped with the app, one subdirectory per model
+    #[serde(skip)]
+    pub bundled_dir: Option<PathBuf>,
+    /// Where downloaded models are stored, one subdirectory per model.
+    /// Defaults to the HuggingFace cache when unset.
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// Never contact HuggingFace; only local, bundled or already cached files are used
+    #[serde(default)]
+    pub offline: bool,
+}
+
+/// Files that make up a model on disk
+const MODEL_FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Progress of a model file download, emitted to the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelDownloadProgress {
+    pub model_id: String,
+    pub file: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub done: bool,
+}
+
+/// Resolved paths of the files needed to load a model
+pub(crate) struct ModelFiles {
+    pub config: PathBuf,
+    pub tokenizer: PathBuf,
+    pub weights: PathBuf,
+}
+
+impl ModelFiles {
+    fn from_dir(dir: &Path) -> Option<Self> {
+        let files = Self {
+            config: dir.join("config.json"),
+            tokenizer: dir.join("tokenizer.json"),
+            weights: dir.join("model.safetensors"),
+        };
+
+        if files.config.is_file() && files.tokenizer.is_file() && files.weights.is_file() {
+            Some(files)
+        } else {
+            None
+        }
+    }
+
+    /// Locate model files without downloading anything if possible
+    fn resolve(model: EmbeddingModel, source: &ModelSource) -> Result<Self, String> {
+        Self::resolve_repo(model.model_id(), source)
+    }
 
-        // Use CPU device (GPU support can be added later)
-        let device = Device::Cpu;
+    /// Locate the files of any HuggingFace model repository, using the last
+    /// path segment of the repo id as its directory name
+    pub(crate) fn resolve_repo(model_id: &str, source: &ModelSource) -> Result<Self, String> {
+        let dir_name = model_id.rsplit('/').next().unwrap_or(model_id);
+
+        if let Some(ref dir) = source.local_path {
+            return Self::from_dir(dir)
+                .ok_or_else(|| format!("Model files not found in {}", dir.display()));
+        }
+
+        if let Some(ref dir) = source.bundled_dir {
+            if let Some(files) = Self::from_dir(&dir.join(dir_name)) {
+                println!("Using bundled model from {}", dir.display());
+                return Ok(files);
+            }
+        }
+
+        if let Some(ref dir) = source.cache_dir {
+            if let Some(files) = Self::from_dir(&dir.join(dir_name)) {
+                return Ok(files);
+            }
+        }
+
+        if source.offline {
+            let cache = Cache::default().model(model_id.to_string());
+            return match (
+                cache.get("config.json"),
+                cache.get("tokenizer.json"),
+                cache.get("model.safetensors"),
+            ) {
+                (Some(config), Some(tokenizer), Some(weights)) => Ok(Self {
+                    config,
+                    tokenizer,
+                    weights,
+                }),
+                _ => Err(format!(
+                    "Offline mode: {} is not bundled, cached, or available at a local path",
+                    model_id
+                )),
+            };
+        }
 
         // Download model from HuggingFace
-        let api = Api::new().map_err(|e| format!("Failed to create HF API: {}", e))?;
+        let mut builder = ApiBuilder::new().with_progress(false);
+        if let Some(ref dir) = source.cache_dir {
+            builder = builder.with_cache_dir(dir.clone());
+        }
+        let api = builder
+            .build()
+            .map_err(|e| format!("Failed to create HF API: {}", e))?;
         let repo = api.repo(Repo::new(
-            "sentence-transformers/all-MiniLM-L6-v2".to_string(),
+            model_id.to_string(),
             RepoType::Model,
         ));
 
         println!("Downloading model files from HuggingFace...");
 
         // Download required files
-        let config_path = repo
+        let config = repo
             .get("config.json")
             .map_err(|e| format!("Failed to download config: {}", e))?;
-        let tokenizer_path = repo
+        let tokenizer = repo
             .get("tokenizer.json")
             .map_err(|e| format!("Failed to download tokenizer: {}", e))?;
-        let weights_path = repo
+        let weights = repo
             .get("model.safetensors")
             .map_err(|e| format!("Failed to download weights: {}", e))?;
 
+        Ok(Self {
+            config,
+            tokenizer,
+            weights,
+        })
+    }
+}
+
+/// Check whether a model can be loaded without touching the network
+pub fn is_model_available(model: EmbeddingModel, source: &ModelSource) -> bool {
+    let offline = ModelSource {
+        offline: true,
+        ..source.clone()
+    };
+    ModelFiles::resolve(model, &offline).is_ok()
+}
+
+/// Download a model into `<cache_dir>/<model dir>` while reporting progress.
+///
+/// Files are streamed to a `.part` file and renamed once complete, so an
+/// interrupted download is never mistaken for a usable model.
+pub async fn download_model<F>(
+    model: EmbeddingModel,
+    cache_dir: &Path,
+    on_progress: F,
+) -> Result<PathBuf, String>
+where
+    F: Fn(ModelDownloadProgress),
+{
+    let model_dir = cache_dir.join(model.dir_name());
+    std::fs::create_dir_all(&model_dir)
+        .map_err(|e| format!("Failed to create model directory: {}", e))?;
+
+    // Through the configured proxy, without the LLM requests' timeout
+    let client = client_builder()
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+    for file in MODEL_FILES {
+        let dest = model_dir.join(file);
+        if dest.is_file() {
+            continue;
+        }
+
+        let url = format!(
+            "https://huggingface.co/{}/resolve/main/{}",
+            model.model_id(),
+            file
+        );
+        let mut response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", file, e))?;
+
+        if !response.status().is_success() {
+            return Err(format!(
+                "Failed to download {}: HTTP {}",
+                file,
+                response.status()
+            ));
+        }
+
+        let total_bytes = response.content_length();
+        let partial = model_dir.join(format!("{}.part", file));
+        let mut out = std::fs::File::create(&partial)
+            .map_err(|e| format!("Failed to create {}: {}", partial.display(), e))?;
+
+        let mut downloaded_bytes = 0u64;
+        let mut last_reported = 0u64;
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(|e| format!("Failed to download {}: {}", file, e))?
+        {
+            std::io::Write::write_all(&mut out, &chunk)
+                .map_err(|e| format!("Failed to write {}: {}", partial.display(), e))?;
+            downloaded_bytes += chunk.len() as u64;
+
+            // Throttle events to roughly one per megabyte
+            if downloaded_bytes - last_reported >= 1_000_000 {
+                last_reported = downloaded_bytes;
+                on_progress(ModelDownloadProgress {
+                    model_id: model.model_id().to_string(),
+                    file: file.to_string(),
+                    downloaded_bytes,
+                    total_bytes,
+                    done: false,
+                });
+            }
+        }
+        drop(out);
+
+        std::fs::rename(&partial, &dest)
+            .map_err(|e| format!("Failed to finalize {}: {}", dest.display(), e))?;
+
+        on_progress(ModelDownloadProgress {
+            model_id: model.model_id().to_string(),
+            file: file.to_string(),
+            downloaded_bytes,
+            total_bytes,
+            done: true,
+        });
+    }
+
+    Ok(model_dir)
+}
+
+/// Model architectures supported by the generator
+enum Encoder {
+    Bert(BertModel),
+    JinaBert(JinaBertModel),
+}
+
+/// Generates semantic embeddings for code using BERT model
+pub struct EmbeddingGenerator {
+    model: Encoder,
+    model_kind: EmbeddingModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    embedding_dim: usize,
+}
+
+impl EmbeddingGenerator {
+    /// Creates a new EmbeddingGenerator with all-MiniLM-L6-v2 model
+    pub fn new() -> Result<Self, String> {
+        Self::load(
+            EmbeddingModel::default(),
+            DevicePreference::default(),
+            &ModelSource::default(),
+        )
+    }
+
+    /// Creates a new EmbeddingGenerator for a model on the preferred compute device
+    pub fn load(
+        model_kind: EmbeddingModel,
+        preference: DevicePreference,
+        source: &ModelSource,
+    ) -> Result<Self, String> {
+        println!("Initializing embedding generator ({})...", model_kind.model_id());
+
+        let device = select_device(preference);
+        println!("Using {} device for embeddings", device_name(&device));
+
+        let files = ModelFiles::resolve(model_kind, source)?;
+
         println!("Loading model configuration...");
 
-        // Load config
-        let config_content = std::fs::read_to_string(&config_path)
+        let config_content = std::fs::read_to_string(&files.config)
             .map_err(|e| format!("Failed to read config: {}", e))?;
-        let config: BertConfig = serde_json::from_str(&config_content)
-            .map_err(|e| format!("Failed to parse config: {}", e))?;
-
-        let embedding_dim = config.hidden_size;
 
         // Load tokenizer
-        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+        let tokenizer = Tokenizer::from_file(&files.tokenizer)
             .map_err(|e| format!("Failed to load tokenizer: {}", e))?;
 
         println!("Loading model weights...");
 
         // Load model weights
         let vb = unsafe {
-            VarBuilder::from_mmaped_safetensors(&[weights_path], candle_core::DType::F32, &device)
+            VarBuilder::from_mmaped_safetensors(&[files.weights], candle_core::DType::F32, &device)
                 .map_err(|e| format!("Failed to load weights: {}", e))?
         };
 
-        let model = BertModel::load(vb, &config)
-            .map_err(|e| format!("Failed to create model: {}", e))?;
+        let (model, embedding_dim) = match model_kind {
+            EmbeddingModel::JinaV2BaseCode => {
+                let config: JinaBertConfig = serde_json::from_str(&config_content)
+                    .map_err(|e| format!("Failed to parse config: {}", e))?;
+                let model = JinaBertModel::new(vb, &config)
+                    .map_err(|e| format!("Failed to create model: {}", e))?;
+                (Encoder::JinaBert(model), config.hidden_size)
+            }
+            _ => {
+                let config: BertConfig = serde_json::from_str(&config_content)
+                    .map_err(|e| format!("Failed to parse config: {}", e))?;
+                let model = BertModel::load(vb, &config)
+                    .map_err(|e| format!("Failed to create model: {}", e))?;
+                (Encoder::Bert(model), config.hidden_size)
+            }
+        };
 
         println!("Embedding generator ready (dim: {})", embedding_dim);
 
         Ok(Self {
             model,
+            model_kind,
             tokenizer,
             device,
             embedding_dim,
         })
     }
 
+    /// The model this generator was loaded with
+    pub fn model(&self) -> EmbeddingModel {
+        self.model_kind
+    }
+
+    /// Name of the device the model is running on ("cpu", "cuda" or "metal")
+    pub fn device_name(&self) -> &'static str {
+        device_name(&self.device)
+    }
+
     /// Returns the dimensionality of embeddings produced by this generator
     pub fn embedding_dim(&self) -> usize {
         self.embedding_dim
@@ -113,13 +448,26 @@ impl EmbeddingGenerator {
         let attention_mask = self.vec2d_to_tensor(&attention_mask_vec)?;
 
         // Run model
-        let output = self
-            .model
-            .forward(&input_ids, &attention_mask, None)
-            .map_err(|e| format!("Model forward failed: {}", e))?;
-
-        // Mean pooling
-        let embeddings = self.mean_pooling(&output, &attention_mask)?;
+        let output = match self.model {
+            Encoder::Bert(ref model) => {
+                let token_type_ids = input_ids
+                    .zeros_like()
+                    .map_err(|e| format!("Failed to create token type ids: {}", e))?;
+                model.forward(&input_ids, &token_type_ids, Some(&attention_mask))
+            }
+            Encoder::JinaBert(ref model) => model.forward(&input_ids),
+        }
+        .map_err(|e| format!("Model forward failed: {}", e))?;
+
+        // Pool token embeddings into one vector per text
+        let embeddings = if self.model_kind.uses_cls_pooling() {
+            output
+                .narrow(1, 0, 1)
+                .and_then(|cls| cls.squeeze(1))
+                .map_err(|e| format!("Failed to extract CLS token: {}", e))?
+        } else {
+            self.mean_pooling(&output, &attention_mask)?
+        };
 
         // Normalize embeddings
         let normalized = self.normalize_embedding(&embeddings)?;
@@ -205,6 +553,14 @@ impl EmbeddingGenerator {
     }
 }
 
+fn device_name(device: &Device) -> &'static str {
+    match device {
+        Device::Cpu => "cpu",
+        Device::Cuda(_) => "cuda",
+        Device::Metal(_) => "metal",
+    }
+}
+
 /// Convert a CodeSymbol to text for embedding
 pub fn symbol_to_text(symbol: &CodeSymbol) -> String {
     let mut parts = Vec::new();
@@ -228,6 +584,134 @@ pub fn symbol_to_text(symbol: &CodeSymbol) -> String {
     parts.join(" ")
 }
 
+/// Name-only text for a symbol's identifier vector
+pub fn symbol_name_text(symbol: &CodeSymbol) -> String {
+    match symbol.signature {
+        Some(ref sig) => format!("{} {:?} {}", symbol.name, symbol.kind, sig),
+        None => format!("{} {:?}", symbol.name, symbol.kind),
+    }
+}
+
+/// Doc comment and source of a short symbol for its body vector. Long
+/// symbols are covered by `symbol_body_chunks` instead.
+pub fn symbol_body_text(symbol: &CodeSymbol, source_code: &str) -> Option<String> {
+    let span = symbol.end_line.saturating_sub(symbol.start_line) + 1;
+    if span > BODY_CHUNK_LINES || symbol.start_line == 0 {
+        return None;
+    }
+
+    let body = source_code
+        .lines()
+        .skip(symbol.start_line - 1)
+        .take(span)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if body.trim().is_empty() {
+        return None;
+    }
+
+    Some(match symbol.doc_comment {
+        Some(ref doc) => format!("{}\n{}", doc, body),
+        None => body,
+    })
+}
+
+/// Upper bound on symbol names listed in a file summary
+const FILE_SUMMARY_MAX_SYMBOLS: usize = 50;
+
+/// Leading comment lines considered the file's header
+const FILE_HEADER_MAX_LINES: usize = 10;
+
+/// Summarize a file for its file-level embedding: its location, the
+/// leading comment block and the symbols it defines
+pub fn file_to_text(file: &IndexedFile, source_code: &str) -> String {
+    let path = file.path.replace('\\', "/");
+    let mut components = path.rsplit('/');
+    let name = components.next().unwrap_or(&path);
+    let mut parts = vec![match components.next() {
+        Some(dir) => format!("{}/{} {}", dir, name, file.language),
+        None => format!("{} {}", name, file.language),
+    }];
+
+    let header: Vec<&str> = source_code
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| {
+            ["//", "#", "/*", "*", "\"\"\""]
+                .iter()
+                .any(|marker| line.starts_with(marker))
+        })
+        .take(FILE_HEADER_MAX_LINES)
+        .map(|line| line.trim_start_matches(['/', '#', '*', '!', '"', ' ']))
+        .filter(|line| !line.is_empty())
+        .collect();
+    if !header.is_empty() {
+        parts.push(header.join(" "));
+    }
+
+    for symbol in file.symbols.iter().take(FILE_SUMMARY_MAX_SYMBOLS) {
+        parts.push(format!("{:?} {}", symbol.kind, symbol.name));
+        if let Some(ref doc) = symbol.doc_comment {
+            parts.push(doc.clone());
+        }
+    }
+
+    parts.join("\n")
+}
+
+/// Symbols longer than this many lines also get per-chunk body embeddings
+pub const BODY_CHUNK_LINES: usize = 40;
+
+/// Lines shared between consecutive body chunks so logic spanning a
+/// boundary is still embedded together
+pub const BODY_CHUNK_OVERLAP: usize = 10;
+
+/// A slice of a long symbol body, embedded separately from the symbol itself
+#[derive(Debug, Clone)]
+pub struct BodyChunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// Split a long symbol body into overlapping line windows.
+///
+/// Short symbols are fully covered by `symbol_to_text` and yield nothing.
+/// Each chunk is prefixed with the symbol name so it keeps its context.
+pub fn symbol_body_chunks(symbol: &CodeSymbol, source_code: &str) -> Vec<BodyChunk> {
+    let span = symbol.end_line.saturating_sub(symbol.start_line) + 1;
+    if span <= BODY_CHUNK_LINES || symbol.start_line == 0 {
+        return Vec::new();
+    }
+
+    let lines: Vec<&str> = source_code
+        .lines()
+        .skip(symbol.start_line - 1)
+        .take(span)
+        .collect();
+
+    let step = BODY_CHUNK_LINES - BODY_CHUNK_OVERLAP;
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+
+    while offset < lines.len() {
+        let end = (offset + BODY_CHUNK_LINES).min(lines.len());
+        chunks.push(BodyChunk {
+            start_line: symbol.start_line + offset,
+            end_line: symbol.start_line + end - 1,
+            text: format!("{} {:?}\n{}", symbol.name, symbol.kind, lines[offset..end].join("\n")),
+        });
+
+        if end == lines.len() {
+            break;
+        }
+        offset += step;
+    }
+
+    chunks
+}
+
 /// Calculate cosine similarity between two embeddings
 pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
     if a.len() != b.len() {
@@ -260,6 +744,94 @@ mod tests {
         assert!((cosine_similarity(&c, &d) - 0.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_cpu_preference_selects_cpu() {
+        let device = select_device(DevicePreference::Cpu);
+        assert_eq!(device_name(&device), "cpu");
+    }
+
+    #[test]
+    fn test_local_path_requires_all_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("config.json"), "{}").unwrap();
+
+        let source = ModelSource {
+            local_path: Some(dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        assert!(ModelFiles::resolve(EmbeddingModel::default(), &source).is_err());
+
+        std::fs::write(dir.path().join("tokenizer.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("model.safetensors"), "").unwrap();
+        assert!(ModelFiles::resolve(EmbeddingModel::default(), &source).is_ok());
+    }
+
+    #[test]
+    fn test_symbol_body_chunks_overlap() {
+        use crate::models::code_index::SymbolKind;
+
+        let source: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+        let mut symbol = CodeSymbol {
+            name: "long_function".to_string(),
+            kind: SymbolKind::Function,
+            file_path: "lib.rs".to_string(),
+            start_line: 11,
+            end_line: 90,
+            signature: None,
+            doc_comment: None,
+            parent: None,
+            calls: Vec::new(),
+        };
+
+        let chunks = symbol_body_chunks(&symbol, &source);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (11, 50));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (41, 80));
+        assert_eq!((chunks[2].start_line, chunks[2].end_line), (71, 90));
+        assert!(chunks[0].text.starts_with("long_function"));
+        assert!(chunks[2].text.ends_with("line 90"));
+
+        // Long symbols get chunks instead of a single body text
+        assert!(symbol_body_text(&symbol, &source).is_none());
+
+        symbol.end_line = 30;
+        assert!(symbol_body_chunks(&symbol, &source).is_empty());
+        let body = symbol_body_text(&symbol, &source).unwrap();
+        assert!(body.starts_with("line 11") && body.ends_with("line 30"));
+    }
+
+    #[test]
+    fn test_file_to_text_includes_header_and_symbols() {
+        use crate::models::code_index::SymbolKind;
+
+        let file = IndexedFile {
+            path: "/repo/src/auth/session.rs".to_string(),
+            language: "rust".to_string(),
+            symbols: vec![CodeSymbol {
+                name: "refresh_token".to_string(),
+                kind: SymbolKind::Function,
+                file_path: "/repo/src/auth/session.rs".to_string(),
+                start_line: 4,
+                end_line: 8,
+                signature: None,
+                doc_comment: None,
+                parent: None,
+                calls: Vec::new(),
+            }],
+            imports: Vec::new(),
+            exports: Vec::new(),
+            last_modified: 0,
+            line_count: 0,
+        };
+        let source = "//! Session handling and token refresh\n\nuse std::time::Duration;\n";
+
+        let text = file_to_text(&file, source);
+        assert!(text.starts_with("auth/session.rs rust"));
+        assert!(text.contains("Session handling and token refresh"));
+        assert!(!text.contains("Duration"));
+        assert!(text.contains("Function refresh_token"));
+    }
+
     #[test]
     fn test_normalization() {
         let vec = vec![3.0, 4.0];
@@ -280,6 +852,7 @@ mod tests {
             signature: Some("fn authenticate_user(username: &str, password: &str) -> bool".to_string()),
             doc_comment: Some("Authenticates a user with username and password".to_string()),
             parent: None,
+            calls: Vec::new(),
         };
 
         let text = symbol_to_text(&symbol);
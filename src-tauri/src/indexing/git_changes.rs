@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// Branches tried, in order, when no base is given
+const DEFAULT_BASES: &[&str] = &["main", "master", "origin/main", "origin/master"];
+
+/// Which changes a diff covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffTarget {
+    /// What the next commit would hold: the staged changes, or every
+    /// uncommitted change when nothing is staged
+    Commit,
+    /// Everything on the branch since it left the base, uncommitted
+    /// changes included, as a pull request would show it
+    Branch,
+}
+
+/// A diff with the lines it touches in each file
+#[derive(Debug, Clone)]
+pub struct GitDiff {
+    /// Unified diff text
+    pub patch: String,
+    /// Files joined onto the root path, with the line ranges changed in
+    /// their new version, inclusive
+    pub files: Vec<(String, Vec<(usize, usize)>)>,
+}
+
+/// Files changed in the working tree and on the current branch: everything
+/// differing from the merge base with `base` (the first of main/master that
+/// exists when None), staged or not, plus untracked files. Paths are joined
+/// onto `root_path` like the indexer's. Outside any of those branches only
+/// the uncommitted changes count.
+pub fn changed_files(root_path: &str, base: Option<&str>) -> Result<Vec<String>, String> {
+    let merge_base = merge_base(root_path, base)?;
+    let diff = git(root_path, &["diff", "--name-only", "--relative", &merge_base])?;
+    let untracked = git(root_path, &["ls-files", "--others", "--exclude-standard"])?;
+
+    let mut files = join_paths(root_path, &diff);
+    files.extend(join_paths(root_path, &untracked));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// The changes a commit or pull request would hold. Branch diffs are taken
+/// from the merge base with `base`, found as for `changed_files`.
+pub fn diff(root_path: &str, target: DiffTarget, base: Option<&str>) -> Result<GitDiff, String> {
+    const DIFF_ARGS: [&str; 4] = ["diff", "--relative", "--no-color", "--no-ext-diff"];
+    let patch = match target {
+        DiffTarget::Commit => {
+            let staged = git(root_path, &[&DIFF_ARGS[..], &["--cached"]].concat())?;
+            if staged.trim().is_empty() {
+                git(root_path, &[&DIFF_ARGS[..], &["HEAD"]].concat())?
+            } else {
+                staged
+            }
+        }
+        DiffTarget::Branch => {
+            let merge_base = merge_base(root_path, base)?;
+            git(
+                root_path,
+                &[&DIFF_ARGS[..], &[merge_base.as_str()]].concat(),
+            )?
+        }
+    };
+
+    let files = changed_ranges(&patch)
+        .into_iter()
+        .map(|(path, ranges)| {
+            let path = Path::new(root_path)
+                .join(path)
+                .to_string_lossy()
+                .to_string();
+            (path, ranges)
+        })
+        .collect();
+    Ok(GitDiff { patch, files })
+}
+
+/// The commit `base` and HEAD share, or HEAD itself when there is no base
+/// branch to compare against
+fn merge_base(root_path: &str, base: Option<&str>) -> Result<String, String> {
+    let merge_base = match base {
+        Some(base) => git(root_path, &["merge-base", "HEAD", base])?,
+        None => DEFAULT_BASES
+            .iter()
+            .find_map(|base| git(root_path, &["merge-base", "HEAD", base]).ok())
+            .unwrap_or_else(|| "HEAD".to_string()),
+    };
+    Ok(merge_base.trim().to_string())
+}
+
+/// Files of a unified diff with the line ranges of their new version its
+/// hunks cover. Deleted files are left out; a hunk only removing lines
+/// counts the line it removed them before.
+fn changed_ranges(patch: &str) -> Vec<(String, Vec<(usize, usize)>)> {
+    let mut files: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
+    let mut current: Option<usize> = None;
+    for line in patch.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            current = path.strip_prefix("b/").map(|path| {
+                files.push((path.to_string(), Vec::new()));
+                files.len() - 1
+            });
+        } else if let Some(hunk) = line.strip_prefix("@@ ") {
+            let Some(file) = current else {
+                continue;
+            };
+            // "@@ -12,5 +14,7 @@": the new side starts at 14 and spans 7 lines
+            let Some(new_side) = hunk
+                .split_whitespace()
+                .find_map(|part| part.strip_prefix('+'))
+            else {
+                continue;
+            };
+            let mut parts = new_side.splitn(2, ',');
+            let start: usize = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+            let len: usize = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+            let start = start.max(1);
+            files[file].1.push((start, start + len.max(1) - 1));
+        }
+    }
+    files
+}
+
+/// The branch checked out in `root_path`; None outside git or on a
+/// detached HEAD
+pub fn current_branch(root_path: &str) -> Option<String> {
+    let branch = git(root_path, &["symbolic-ref", "--short", "-q", "HEAD"]).ok()?;
+    let branch = branch.trim();
+    (!branch.is_empty()).then(|| branch.to_string())
+}
+
+fn git(root_path: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root_path)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn join_paths(root_path: &str, output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|line| Path::new(root_path).join(line).to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_ranges() {
+        let patch = "diff --git a/src/a.rs b/src/a.rs\n\
+--- a/src/a.rs\n\
++++ b/src/a.rs\n\
+@@ -3,4 +3,6 @@ fn main() {\n\
++    added();\n\
+@@ -20 +22,0 @@\n\
+-    removed();\n\
+diff --git a/src/old.rs b/src/old.rs\n\
+--- a/src/old.rs\n\
++++ /dev/null\n\
+@@ -1,2 +0,0 @@\n\
+-gone\n";
+        assert_eq!(
+            changed_ranges(patch),
+            vec![("src/a.rs".to_string(), vec![(3, 8), (22, 22)])]
+        );
+    }
+
+    #[test]
+    fn test_join_paths() {
+        let files = join_paths("/repo", "src/a.rs\n\nsrc/b.rs\n");
+        assert_eq!(
+            files,
+            vec![
+                Path::new("/repo").join("src/a.rs").to_string_lossy().to_string(),
+                Path::new("/repo").join("src/b.rs").to_string_lossy().to_string(),
+            ]
+        );
+    }
+}
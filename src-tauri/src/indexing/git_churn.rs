@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+
+/// How far back commits are counted
+pub const CHURN_WINDOW_DAYS: u32 = 90;
+
+/// Count the commits touching each file in the last `since_days` days.
+/// Keys are the repository-relative paths joined onto `root_path`, matching
+/// the paths the indexer records. Fails if git is missing or `root_path`
+/// is not inside a repository.
+pub fn file_churn(root_path: &str, since_days: u32) -> Result<HashMap<String, u32>, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root_path)
+        .args(["log", "--no-merges", "--format=", "--name-only", "--relative"])
+        .arg(format!("--since={}.days", since_days))
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(count_paths(root_path, &String::from_utf8_lossy(&output.stdout)))
+}
+
+fn count_paths(root_path: &str, log: &str) -> HashMap<String, u32> {
+    let mut churn = HashMap::new();
+    for line in log.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let path = Path::new(root_path).join(line).to_string_lossy().to_string();
+        *churn.entry(path).or_insert(0) += 1;
+    }
+    churn
+}
+
+/// Scale commit counts into [0, 1] relative to the busiest file, on a log
+/// scale so a handful of hot files don't flatten everything else
+pub fn normalized_churn(churn: &HashMap<String, u32>) -> HashMap<String, f32> {
+    let max = churn.values().copied().max().unwrap_or(0);
+    if max == 0 {
+        return HashMap::new();
+    }
+
+    let scale = (1.0 + max as f32).ln();
+    churn
+        .iter()
+        .map(|(path, &count)| (path.clone(), (1.0 + count as f32).ln() / scale))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_and_normalize() {
+        let log = "src/a.rs\nsrc/b.rs\n\nsrc/a.rs\n\nsrc/a.rs\n";
+        let churn = count_paths("/repo", log);
+
+        let a = Path::new("/repo").join("src/a.rs").to_string_lossy().to_string();
+        let b = Path::new("/repo").join("src/b.rs").to_string_lossy().to_string();
+        assert_eq!(churn[&a], 3);
+        assert_eq!(churn[&b], 1);
+
+        let normalized = normalized_churn(&churn);
+        assert_eq!(normalized[&a], 1.0);
+        assert!((normalized[&b] - 2f32.ln() / 4f32.ln()).abs() < 1e-6);
+    }
+}
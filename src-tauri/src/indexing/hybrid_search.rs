@@ -1,39 +1,179 @@
-use crate::models::code_index::CodeChunk;
+use crate::indexing::reranker::Reranker;
+use crate::models::code_index::{CodeChunk, EngineScore, ScoreBreakdown};
 use std::collections::HashMap;
 
 pub struct HybridSearcher;
 
+/// Adjustments applied on top of the fused ranking
+#[derive(Default)]
+pub struct RankingSignals<'a> {
+    /// Per-file boosts keyed by path; a result's score is scaled by 1 + boost
+    pub file_boosts: HashMap<String, f32>,
+    /// Re-scores the top results when set
+    pub reranker: Option<&'a dyn Reranker>,
+}
+
 impl HybridSearcher {
     pub fn search(
         &self,
+        query: &str,
         traditional_results: Vec<CodeChunk>,
         full_text_results: Vec<CodeChunk>,
         semantic_results: Vec<CodeChunk>,
         config: &HybridConfig,
+        signals: &RankingSignals,
     ) -> Vec<CodeChunk> {
-        let fused_results = self.reciprocal_rank_fusion(
+        let mut fused_results = self.fuse(
             &[
-                (traditional_results, config.traditional_weight),
-                (full_text_results, config.full_text_weight),
-                (semantic_results, config.semantic_weight),
+                (SearchEngine::Traditional, traditional_results, config.traditional_weight),
+                (SearchEngine::FullText, full_text_results, config.full_text_weight),
+                (SearchEngine::Semantic, semantic_results, config.semantic_weight),
             ],
-            config.rrf_k,
+            config,
         );
 
+        if !signals.file_boosts.is_empty() {
+            self.apply_file_boosts(&mut fused_results, &signals.file_boosts);
+        }
+
+        // Drop weak matches before reranking replaces the fused scores
+        fused_results.retain(|chunk| chunk.relevance_score >= config.min_fused_score);
+
+        if let Some(reranker) = signals.reranker {
+            self.rerank(query, &mut fused_results, config.rerank_top_n, reranker);
+        }
+
         fused_results.into_iter()
             .take(config.max_results)
             .collect()
     }
 
-    fn reciprocal_rank_fusion(
+    /// Scale each fused score by 1 + its file's boost and re-sort
+    fn apply_file_boosts(&self, results: &mut [CodeChunk], file_boosts: &HashMap<String, f32>) {
+        for chunk in results.iter_mut() {
+            if let Some(&boost) = file_boosts.get(&chunk.file_path) {
+                chunk.relevance_score *= 1.0 + boost;
+                if let Some(ref mut breakdown) = chunk.score_breakdown {
+                    breakdown.file_boost = Some(boost);
+                }
+            }
+        }
+
+        results.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    /// Fuse the results of several phrasings of one query with plain RRF.
+    /// The first list, the original query, weighs as much as all rewrites
+    /// together, so rewrites add recall without drowning out exact matches.
+    pub fn fuse_query_variants(
         &self,
-        result_lists: &[(Vec<CodeChunk>, f32)],
-        k: f32,
+        result_lists: Vec<Vec<CodeChunk>>,
+        config: &HybridConfig,
     ) -> Vec<CodeChunk> {
+        let rewrites = result_lists.len().saturating_sub(1).max(1) as f32;
         let mut scores: HashMap<String, (f32, CodeChunk)> = HashMap::new();
 
-        for (results, weight) in result_lists {
-            for (rank, chunk) in results.iter().enumerate() {
+        for (i, results) in result_lists.into_iter().enumerate() {
+            let weight = if i == 0 { 1.0 } else { 1.0 / rewrites };
+            for (rank, chunk) in results.into_iter().enumerate() {
+                let key = format!("{}:{}:{}", chunk.file_path, chunk.start_line, chunk.end_line);
+                let rrf_score = weight / (config.rrf_k + (rank as f32 + 1.0));
+                scores
+                    .entry(key)
+                    .and_modify(|(score, _)| *score += rrf_score)
+                    .or_insert((rrf_score, chunk));
+            }
+        }
+
+        let mut results: Vec<CodeChunk> = scores
+            .into_values()
+            .map(|(score, mut chunk)| {
+                chunk.relevance_score = score;
+                chunk
+            })
+            .collect();
+
+        results.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        results.truncate(config.max_results);
+        results
+    }
+
+    /// Re-order the top `top_n` fused results by reranker score. Their
+    /// relevance becomes the sigmoid of the reranker logit; the untouched
+    /// tail keeps its fused scores and stays after them.
+    /// On failure the fused order is kept.
+    fn rerank(
+        &self,
+        query: &str,
+        results: &mut [CodeChunk],
+        top_n: usize,
+        reranker: &dyn Reranker,
+    ) {
+        let top_n = top_n.min(results.len());
+        if top_n == 0 {
+            return;
+        }
+
+        let head = &mut results[..top_n];
+        let passages: Vec<String> = head.iter()
+            .map(|chunk| format!(
+                "{}\n{}\n{}",
+                chunk.file_path,
+                chunk.symbols.join(" "),
+                chunk.content
+            ))
+            .collect();
+
+        let scores = match reranker.score(query, &passages) {
+            Ok(scores) if scores.len() == head.len() => scores,
+            Ok(_) => {
+                eprintln!("Reranker returned wrong number of scores, keeping fused order");
+                return;
+            }
+            Err(e) => {
+                eprintln!("Reranking failed, keeping fused order: {}", e);
+                return;
+            }
+        };
+
+        for (chunk, score) in head.iter_mut().zip(scores) {
+            chunk.relevance_score = 1.0 / (1.0 + (-score).exp());
+            if let Some(ref mut breakdown) = chunk.score_breakdown {
+                breakdown.reranker = Some(chunk.relevance_score);
+            }
+        }
+
+        head.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    /// Merge the engines' result lists into one ranking using the
+    /// configured fusion strategy
+    fn fuse(
+        &self,
+        result_lists: &[(SearchEngine, Vec<CodeChunk>, f32)],
+        config: &HybridConfig,
+    ) -> Vec<CodeChunk> {
+        let mut scores: HashMap<String, (f32, CodeChunk)> = HashMap::new();
+
+        for (engine, results, weight) in result_lists {
+            let contributions = match config.fusion_strategy {
+                FusionStrategy::Rrf => rrf_contributions(results.len(), *weight, config.rrf_k),
+                FusionStrategy::WeightedScore => normalized_contributions(results, *weight),
+            };
+
+            for ((rank, chunk), contribution) in results.iter().enumerate().zip(contributions) {
                 let key = format!(
                     "{}:{}:{}",
                     chunk.file_path,
@@ -41,11 +181,29 @@ impl HybridSearcher {
                     chunk.end_line
                 );
 
-                let rrf_score = weight / (k + (rank as f32 + 1.0));
+                let engine_score = EngineScore {
+                    rank: rank + 1,
+                    score: chunk.relevance_score,
+                    contribution,
+                };
 
-                scores.entry(key)
-                    .and_modify(|(score, _)| *score += rrf_score)
-                    .or_insert((rrf_score, chunk.clone()));
+                let (score, fused) = scores.entry(key).or_insert_with(|| {
+                    let mut fused = chunk.clone();
+                    fused.score_breakdown = Some(ScoreBreakdown::default());
+                    (0.0, fused)
+                });
+                *score += contribution;
+
+                // An engine may list the same chunk twice; only its best rank is reported
+                if let Some(ref mut breakdown) = fused.score_breakdown {
+                    let slot = engine.slot(breakdown);
+                    if slot.is_none() {
+                        *slot = Some(engine_score);
+                    }
+                    if breakdown.match_type.is_none() {
+                        breakdown.match_type = chunk.score_breakdown.as_ref().and_then(|b| b.match_type);
+                    }
+                }
             }
         }
 
@@ -66,6 +224,60 @@ impl HybridSearcher {
     }
 }
 
+/// Reciprocal rank fusion: weight / (k + rank), ignoring the scores
+fn rrf_contributions(len: usize, weight: f32, k: f32) -> Vec<f32> {
+    (0..len).map(|rank| weight / (k + (rank as f32 + 1.0))).collect()
+}
+
+/// Min-max normalize a list's scores into [0, 1] and apply the weight. A
+/// list whose scores are all equal gives every result the full weight.
+fn normalized_contributions(results: &[CodeChunk], weight: f32) -> Vec<f32> {
+    let min = results.iter().map(|c| c.relevance_score).fold(f32::INFINITY, f32::min);
+    let max = results.iter().map(|c| c.relevance_score).fold(f32::NEG_INFINITY, f32::max);
+    let range = max - min;
+
+    results
+        .iter()
+        .map(|c| {
+            if range > f32::EPSILON {
+                weight * (c.relevance_score - min) / range
+            } else {
+                weight
+            }
+        })
+        .collect()
+}
+
+/// How the engines' result lists are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FusionStrategy {
+    /// Reciprocal rank fusion; robust to differently scaled scores
+    #[default]
+    Rrf,
+    /// Weighted sum of min-max normalized scores; keeps the margin of a
+    /// clear exact match that rank-only fusion discards
+    WeightedScore,
+}
+
+/// The engines whose results are fused
+#[derive(Debug, Clone, Copy)]
+enum SearchEngine {
+    Traditional,
+    FullText,
+    Semantic,
+}
+
+impl SearchEngine {
+    fn slot<'a>(&self, breakdown: &'a mut ScoreBreakdown) -> &'a mut Option<EngineScore> {
+        match self {
+            SearchEngine::Traditional => &mut breakdown.traditional,
+            SearchEngine::FullText => &mut breakdown.full_text,
+            SearchEngine::Semantic => &mut breakdown.semantic,
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct HybridConfig {
     pub traditional_weight: f32,
@@ -73,6 +285,56 @@ pub struct HybridConfig {
     pub semantic_weight: f32,
     pub rrf_k: f32,
     pub max_results: usize,
+    /// How many fused results are re-scored when a reranker is enabled
+    #[serde(default = "default_rerank_top_n")]
+    pub rerank_top_n: usize,
+    /// Semantic results below this cosine similarity are not fused at all
+    #[serde(default = "default_min_similarity")]
+    pub min_similarity: f32,
+    /// Fused results below this score are dropped. Under RRF a result ranked
+    /// first in every list scores 1/(rrf_k + 1), about 0.016 with the
+    /// defaults; weighted-score fusion scores range from 0 to the weight sum.
+    #[serde(default)]
+    pub min_fused_score: f32,
+    #[serde(default)]
+    pub fusion_strategy: FusionStrategy,
+    /// Extra weight for recently modified files: a file edited just now has
+    /// its fused score multiplied by 1 + recency_weight. 0 disables.
+    #[serde(default = "default_recency_weight")]
+    pub recency_weight: f32,
+    /// Days after which a file's recency boost has halved
+    #[serde(default = "default_recency_half_life_days")]
+    pub recency_half_life_days: f32,
+    /// Extra weight for files with many recent commits; the busiest file's
+    /// score is multiplied by 1 + churn_weight. 0 disables.
+    #[serde(default)]
+    pub churn_weight: f32,
+}
+
+/// Recency boost for a file modified at `last_modified` (Unix seconds)
+pub fn recency_boost(last_modified: u64, now: u64, config: &HybridConfig) -> f32 {
+    if config.recency_weight <= 0.0 || last_modified == 0 {
+        return 0.0;
+    }
+
+    let age_days = now.saturating_sub(last_modified) as f32 / 86_400.0;
+    config.recency_weight * 0.5f32.powf(age_days / config.recency_half_life_days)
+}
+
+fn default_rerank_top_n() -> usize {
+    30
+}
+
+fn default_min_similarity() -> f32 {
+    0.2
+}
+
+fn default_recency_weight() -> f32 {
+    0.1
+}
+
+fn default_recency_half_life_days() -> f32 {
+    7.0
 }
 
 impl Default for HybridConfig {
@@ -83,11 +345,70 @@ impl Default for HybridConfig {
             semantic_weight: 0.4,
             rrf_k: 60.0,
             max_results: 50,
+            rerank_top_n: default_rerank_top_n(),
+            min_similarity: default_min_similarity(),
+            min_fused_score: 0.0,
+            fusion_strategy: FusionStrategy::default(),
+            recency_weight: default_recency_weight(),
+            recency_half_life_days: default_recency_half_life_days(),
+            churn_weight: 0.0,
         }
     }
 }
 
 impl HybridConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if [self.traditional_weight, self.full_text_weight, self.semantic_weight]
+            .iter()
+            .any(|w| !w.is_finite() || *w < 0.0)
+        {
+            return Err("Engine weights must be non-negative".to_string());
+        }
+        if !self.rrf_k.is_finite() || self.rrf_k <= 0.0 {
+            return Err(format!("rrf_k must be positive, got {}", self.rrf_k));
+        }
+        if self.max_results == 0 {
+            return Err("max_results must be positive".to_string());
+        }
+        if self.rerank_top_n == 0 {
+            return Err("rerank_top_n must be positive".to_string());
+        }
+        // NaN compares false both ways, so an unchecked threshold drops every result
+        if !self.min_similarity.is_finite() || !(-1.0..=1.0).contains(&self.min_similarity) {
+            return Err(format!(
+                "min_similarity must be between -1 and 1, got {}",
+                self.min_similarity
+            ));
+        }
+        if !self.min_fused_score.is_finite() || self.min_fused_score < 0.0 {
+            return Err("min_fused_score must be non-negative".to_string());
+        }
+        if !self.recency_weight.is_finite() || self.recency_weight < 0.0 {
+            return Err("recency_weight must be non-negative".to_string());
+        }
+        if !self.recency_half_life_days.is_finite() || self.recency_half_life_days <= 0.0 {
+            return Err("recency_half_life_days must be positive".to_string());
+        }
+        if !self.churn_weight.is_finite() || self.churn_weight < 0.0 {
+            return Err("churn_weight must be non-negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// Mix engine weights and similarity cutoff with another config, taking
+    /// `share` (0 to 1) of this one; everything else is kept from `self`
+    pub fn blend(&self, other: &HybridConfig, share: f32) -> HybridConfig {
+        let share = share.clamp(0.0, 1.0);
+        let mix = |a: f32, b: f32| a * share + b * (1.0 - share);
+        HybridConfig {
+            traditional_weight: mix(self.traditional_weight, other.traditional_weight),
+            full_text_weight: mix(self.full_text_weight, other.full_text_weight),
+            semantic_weight: mix(self.semantic_weight, other.semantic_weight),
+            min_similarity: mix(self.min_similarity, other.min_similarity),
+            ..self.clone()
+        }
+    }
+
     pub fn exact_match() -> Self {
         Self {
             traditional_weight: 0.7,
@@ -127,6 +448,204 @@ mod tests {
         // chunk appears in multiple result sets
     }
 
+    struct KeywordReranker(&'static str);
+
+    impl Reranker for KeywordReranker {
+        fn score(&self, _query: &str, passages: &[String]) -> Result<Vec<f32>, String> {
+            Ok(passages.iter()
+                .map(|p| if p.contains(self.0) { 5.0 } else { -5.0 })
+                .collect())
+        }
+    }
+
+    fn chunk(file_path: &str, symbol: &str) -> CodeChunk {
+        CodeChunk {
+            file_path: file_path.to_string(),
+            start_line: 1,
+            end_line: 10,
+            content: String::new(),
+            language: "rust".to_string(),
+            symbols: vec![symbol.to_string()],
+            relevance_score: 0.0,
+            score_breakdown: None,
+        }
+    }
+
+    #[test]
+    fn test_rerank_reorders_top_n_only() {
+        let searcher = HybridSearcher;
+        let config = HybridConfig {
+            rerank_top_n: 2,
+            ..Default::default()
+        };
+        let full_text = vec![
+            chunk("a.rs", "parse_config"),
+            chunk("b.rs", "load_settings"),
+            chunk("c.rs", "load_settings_cache"),
+        ];
+
+        let results = searcher.search(
+            "load settings",
+            Vec::new(),
+            full_text,
+            Vec::new(),
+            &config,
+            &RankingSignals {
+                reranker: Some(&KeywordReranker("load_settings")),
+                ..Default::default()
+            },
+        );
+
+        let order: Vec<&str> = results.iter().map(|c| c.file_path.as_str()).collect();
+        assert_eq!(order, vec!["b.rs", "a.rs", "c.rs"]);
+        assert!(results[1].relevance_score > results[2].relevance_score);
+    }
+
+    #[test]
+    fn test_min_fused_score_drops_weak_matches() {
+        let searcher = HybridSearcher;
+        let config = HybridConfig {
+            min_fused_score: 0.005,
+            ..Default::default()
+        };
+
+        // Ranked first: 0.4 / 61 ≈ 0.0066, ranked second: 0.4 / 62 ≈ 0.0065,
+        // but only in the semantic list at rank 40: 0.4 / 100 = 0.004
+        let full_text = vec![chunk("a.rs", "parse_config"), chunk("b.rs", "load_settings")];
+        let mut semantic: Vec<CodeChunk> = (0..39)
+            .map(|i| chunk(&format!("filler{}.rs", i), "filler"))
+            .collect();
+        semantic.push(chunk("weak.rs", "unrelated"));
+
+        let results = searcher.search("config", Vec::new(), full_text, semantic, &config, &RankingSignals::default());
+
+        assert!(results.iter().any(|c| c.file_path == "a.rs"));
+        assert!(results.iter().all(|c| c.file_path != "weak.rs"));
+    }
+
+    #[test]
+    fn test_score_breakdown_per_engine() {
+        let searcher = HybridSearcher;
+        let config = HybridConfig::default();
+
+        let full_text = vec![chunk("a.rs", "parse_config"), chunk("b.rs", "load_settings")];
+        let mut semantic_hit = chunk("b.rs", "load_settings");
+        semantic_hit.relevance_score = 0.8;
+
+        let results = searcher.search(
+            "settings",
+            Vec::new(),
+            full_text,
+            vec![semantic_hit],
+            &config,
+            &RankingSignals::default(),
+        );
+
+        let b = results.iter().find(|c| c.file_path == "b.rs").unwrap();
+        let breakdown = b.score_breakdown.as_ref().unwrap();
+        assert!(breakdown.traditional.is_none());
+        assert_eq!(breakdown.full_text.as_ref().unwrap().rank, 2);
+        let semantic = breakdown.semantic.as_ref().unwrap();
+        assert_eq!(semantic.rank, 1);
+        assert_eq!(semantic.score, 0.8);
+
+        let total = breakdown.full_text.as_ref().unwrap().contribution + semantic.contribution;
+        assert!((total - b.relevance_score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_weighted_score_fusion_keeps_margin() {
+        let searcher = HybridSearcher;
+        let config = HybridConfig {
+            fusion_strategy: FusionStrategy::WeightedScore,
+            ..Default::default()
+        };
+
+        let scored = |file: &str, score: f32| {
+            let mut c = chunk(file, "symbol");
+            c.relevance_score = score;
+            c
+        };
+
+        // A dominant exact full-text match that is a near tie semantically.
+        // RRF scores exact.rs and weak.rs identically (ranks 1+2 vs 2+1).
+        let full_text = vec![scored("exact.rs", 10.0), scored("weak.rs", 1.0), scored("other.rs", 0.5)];
+        let semantic = vec![scored("weak.rs", 0.61), scored("exact.rs", 0.60), scored("other.rs", 0.30)];
+
+        let results = searcher.search("q", Vec::new(), full_text, semantic, &config, &RankingSignals::default());
+
+        assert_eq!(results[0].file_path, "exact.rs");
+        assert_eq!(results[1].file_path, "weak.rs");
+        // Full text normalizes to 1.0, semantic to 0.30 / 0.31
+        let expected = 0.4 + 0.4 * (0.30 / 0.31);
+        assert!((results[0].relevance_score - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_fuse_query_variants_favors_original() {
+        let searcher = HybridSearcher;
+        let config = HybridConfig::default();
+
+        let original = vec![chunk("a.rs", "login")];
+        let rewrites = vec![
+            vec![chunk("b.rs", "authenticate")],
+            vec![chunk("c.rs", "session"), chunk("b.rs", "authenticate")],
+        ];
+
+        let mut lists = vec![original];
+        lists.extend(rewrites);
+        let results = searcher.fuse_query_variants(lists, &config);
+
+        let order: Vec<&str> = results.iter().map(|c| c.file_path.as_str()).collect();
+        // b.rs is found by both rewrites, but they only weigh as much as the original
+        assert_eq!(order, vec!["a.rs", "b.rs", "c.rs"]);
+    }
+
+    #[test]
+    fn test_recency_boost_reorders_ties() {
+        let searcher = HybridSearcher;
+        let config = HybridConfig::default();
+        let now = 1_700_000_000;
+
+        assert!((recency_boost(now, now, &config) - 0.1).abs() < 1e-6);
+        assert!((recency_boost(now - 7 * 86_400, now, &config) - 0.05).abs() < 1e-6);
+        assert_eq!(recency_boost(0, now, &config), 0.0);
+
+        // Equal RRF scores; the recently edited file wins
+        let traditional = vec![chunk("old.rs", "handler")];
+        let full_text = vec![chunk("new.rs", "handler")];
+        let signals = RankingSignals {
+            file_boosts: HashMap::from([
+                ("old.rs".to_string(), recency_boost(now - 90 * 86_400, now, &config)),
+                ("new.rs".to_string(), recency_boost(now - 3_600, now, &config)),
+            ]),
+            ..Default::default()
+        };
+        let config = HybridConfig {
+            traditional_weight: 0.5,
+            full_text_weight: 0.5,
+            ..config
+        };
+
+        let results = searcher.search("handler", traditional, full_text, Vec::new(), &config, &signals);
+        assert_eq!(results[0].file_path, "new.rs");
+        assert!(results[0].score_breakdown.as_ref().unwrap().file_boost.unwrap() > 0.09);
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(HybridConfig::default().validate().is_ok());
+        assert!(HybridConfig { semantic_weight: -0.1, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { rrf_k: 0.0, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { max_results: 0, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { rerank_top_n: 0, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { min_similarity: f32::NAN, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { min_similarity: 1.5, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { min_similarity: -1.0, ..Default::default() }.validate().is_ok());
+        assert!(HybridConfig { min_fused_score: -0.1, ..Default::default() }.validate().is_err());
+        assert!(HybridConfig { min_fused_score: f32::INFINITY, ..Default::default() }.validate().is_err());
+    }
+
     #[test]
     fn test_config_weights_sum() {
         let config = HybridConfig::default();
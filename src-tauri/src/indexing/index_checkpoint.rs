@@ -0,0 +1,151 @@
+use crate::indexing::compression::{compress, decompress};
+use crate::indexing::vector_store::VectorMetadata;
+use crate::models::code_index::IndexedFile;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// A vector with what it covers, as stored in a vector store
+pub type StoredVector = (Vec<f32>, VectorMetadata);
+
+/// A file indexed by an interrupted run, with the vectors embedded for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointedFile {
+    pub file: IndexedFile,
+    pub symbol_vectors: Vec<StoredVector>,
+    pub file_vectors: Vec<StoredVector>,
+}
+
+/// The files of the directories finished since the previous shard
+#[derive(Serialize, Deserialize)]
+struct Shard {
+    /// Model the vectors were embedded with; None when indexed without
+    embedding_model: Option<String>,
+    files: Vec<CheckpointedFile>,
+}
+
+/// Partial results of a long index run, saved as numbered shards so a run
+/// that crashed or was killed resumes instead of starting over
+pub struct IndexCheckpoint {
+    dir: PathBuf,
+}
+
+impl IndexCheckpoint {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Save files as the next shard. It is written under a temporary name
+    /// first, so a crash mid-write never leaves a partial shard behind.
+    pub fn save_shard(
+        &self,
+        embedding_model: Option<&str>,
+        files: Vec<CheckpointedFile>,
+    ) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create checkpoint directory: {}", e))?;
+
+        let shard = Shard {
+            embedding_model: embedding_model.map(str::to_string),
+            files,
+        };
+        let bytes = bincode::serialize(&shard)
+            .map_err(|e| format!("Failed to serialize checkpoint: {}", e))?;
+
+        let path = self.dir.join(format!("{:06}.bin", self.next_sequence()));
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, compress(&bytes)?)
+            .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+        fs::rename(&temp_path, &path).map_err(|e| format!("Failed to write checkpoint: {}", e))
+    }
+
+    /// Files saved by earlier shards, keyed by path; later shards win.
+    /// Shards embedded with another model or that can't be read are skipped.
+    pub fn load(&self, embedding_model: Option<&str>) -> HashMap<String, CheckpointedFile> {
+        let mut files = HashMap::new();
+        for path in self.shard_paths() {
+            let shard = fs::read(&path)
+                .ok()
+                .and_then(|bytes| decompress(bytes).ok())
+                .and_then(|bytes| bincode::deserialize::<Shard>(&bytes).ok());
+            match shard {
+                Some(shard) if shard.embedding_model.as_deref() == embedding_model => {
+                    files.extend(shard.files.into_iter().map(|f| (f.file.path.clone(), f)));
+                }
+                _ => eprintln!("Skipping unusable checkpoint {}", path.display()),
+            }
+        }
+        files
+    }
+
+    /// Delete every shard, once the run they belong to is saved
+    pub fn clear(&self) -> Result<(), String> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to remove checkpoint: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// Shard files in the order they were written
+    fn shard_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        paths
+    }
+
+    fn next_sequence(&self) -> usize {
+        self.shard_paths()
+            .last()
+            .and_then(|path| path.file_stem()?.to_str()?.parse::<usize>().ok())
+            .map_or(0, |last| last + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpointed(path: &str) -> CheckpointedFile {
+        CheckpointedFile {
+            file: IndexedFile {
+                path: path.to_string(),
+                language: "rust".to_string(),
+                symbols: Vec::new(),
+                imports: Vec::new(),
+                exports: Vec::new(),
+                last_modified: 0,
+                line_count: 1,
+            },
+            symbol_vectors: Vec::new(),
+            file_vectors: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_shards_resume_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let checkpoint = IndexCheckpoint::new(dir.path().join("checkpoint"));
+        checkpoint.save_shard(Some("bge"), vec![checkpointed("/repo/a.rs")]).unwrap();
+        checkpoint.save_shard(Some("bge"), vec![checkpointed("/repo/src/b.rs")]).unwrap();
+
+        let files = checkpoint.load(Some("bge"));
+        assert_eq!(files.len(), 2);
+        assert!(files.contains_key("/repo/src/b.rs"));
+
+        // Vectors from another model can't be mixed into the new stores
+        assert!(checkpoint.load(None).is_empty());
+
+        checkpoint.clear().unwrap();
+        assert!(checkpoint.load(Some("bge")).is_empty());
+    }
+}
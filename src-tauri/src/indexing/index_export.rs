@@ -0,0 +1,285 @@
+use crate::indexing::text_normalizer::TextNormalizer;
+use crate::indexing::vector_store::VectorMetadata;
+use crate::models::code_index::{CodebaseIndex, IndexedFile};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::Path;
+
+/// Bumped when the exported layout changes incompatibly
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Leading bytes of every SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// File format written by `export_index`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// The whole index as one JSON document
+    Json,
+    /// Files, symbols, imports, calls and vectors as SQLite tables.
+    /// Requires building with the `sqlite` feature.
+    Sqlite,
+}
+
+/// An index as exported for other tools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexExport {
+    pub format_version: u32,
+    pub index: CodebaseIndex,
+    /// What each stored embedding vector covers; empty unless requested
+    #[serde(default)]
+    pub vectors: Vec<VectorMetadata>,
+}
+
+impl IndexExport {
+    pub fn new(index: CodebaseIndex, vectors: Vec<VectorMetadata>) -> Self {
+        Self {
+            format_version: EXPORT_FORMAT_VERSION,
+            index,
+            vectors,
+        }
+    }
+
+    /// Write the export in the given format, replacing any existing file
+    pub fn write(&self, path: &Path, format: ExportFormat) -> Result<(), String> {
+        match format {
+            ExportFormat::Json => self.write_json(path),
+            #[cfg(feature = "sqlite")]
+            ExportFormat::Sqlite => self.write_sqlite(path),
+            #[cfg(not(feature = "sqlite"))]
+            ExportFormat::Sqlite => {
+                Err("This build does not include SQLite export (enable the `sqlite` feature)".to_string())
+            }
+        }
+    }
+
+    /// Read an export in either format. SQLite exports only hold files and
+    /// symbols, so their lookup tables are rebuilt with `normalizer` and
+    /// they carry no vector metadata.
+    pub fn read(path: &Path, normalizer: &TextNormalizer) -> Result<Self, String> {
+        let mut file = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let mut header = [0u8; SQLITE_HEADER.len()];
+        let is_sqlite = file.read_exact(&mut header).is_ok() && header == SQLITE_HEADER;
+
+        let export = if is_sqlite {
+            Self::read_sqlite(path, normalizer)?
+        } else {
+            let file = std::fs::File::open(path)
+                .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+            serde_json::from_reader(std::io::BufReader::new(file))
+                .map_err(|e| format!("Failed to parse index export: {}", e))?
+        };
+
+        if export.format_version > EXPORT_FORMAT_VERSION {
+            return Err(format!(
+                "Index export format {} is newer than this version of Prompto supports ({})",
+                export.format_version, EXPORT_FORMAT_VERSION
+            ));
+        }
+        Ok(export)
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn read_sqlite(path: &Path, normalizer: &TextNormalizer) -> Result<Self, String> {
+        use crate::indexing::index_store::read_files;
+        use rusqlite::{Connection, OpenFlags};
+
+        let sql_err = |e: rusqlite::Error| format!("Failed to read SQLite export: {}", e);
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(sql_err)?;
+
+        let meta = |key: &str| -> Result<String, String> {
+            conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+                .map_err(sql_err)
+        };
+        let format_version: u32 = meta("format_version")?
+            .parse()
+            .map_err(|e| format!("Invalid export format version: {}", e))?;
+        let root_path = meta("root_path")?;
+        let indexed_at: u64 = meta("indexed_at")?
+            .parse()
+            .map_err(|e| format!("Invalid export timestamp: {}", e))?;
+        let files = read_files(&conn, None).map_err(sql_err)?;
+
+        Ok(Self {
+            format_version,
+            index: rebuild_index(root_path, files.into_values(), indexed_at, normalizer),
+            vectors: Vec::new(),
+        })
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    fn read_sqlite(_path: &Path, _normalizer: &TextNormalizer) -> Result<Self, String> {
+        Err("This build does not include SQLite import (enable the `sqlite` feature)".to_string())
+    }
+
+    fn write_json(&self, path: &Path) -> Result<(), String> {
+        let file = std::fs::File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)
+            .map_err(|e| format!("Failed to write index export: {}", e))
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn write_sqlite(&self, path: &Path) -> Result<(), String> {
+        use crate::indexing::index_store::{insert_file, INDEX_SCHEMA};
+        use rusqlite::{params, Connection};
+
+        if path.exists() {
+            std::fs::remove_file(path)
+                .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))?;
+        }
+
+        let sql_err = |e: rusqlite::Error| format!("Failed to write SQLite export: {}", e);
+        let mut conn = Connection::open(path).map_err(sql_err)?;
+        conn.execute_batch(INDEX_SCHEMA).map_err(sql_err)?;
+        conn.execute_batch(
+            "CREATE TABLE vectors (
+                 symbol_name TEXT NOT NULL,
+                 file_path TEXT NOT NULL,
+                 start_line INTEGER NOT NULL,
+                 end_line INTEGER NOT NULL,
+                 facet TEXT NOT NULL,
+                 chunk_start INTEGER,
+                 chunk_end INTEGER
+             );",
+        )
+        .map_err(sql_err)?;
+
+        let tx = conn.transaction().map_err(sql_err)?;
+        {
+            let mut meta = tx
+                .prepare("INSERT INTO meta (key, value) VALUES (?1, ?2)")
+                .map_err(sql_err)?;
+            for (key, value) in [
+                ("format_version", self.format_version.to_string()),
+                ("root_path", self.index.root_path.clone()),
+                ("indexed_at", self.index.indexed_at.to_string()),
+            ] {
+                meta.execute(params![key, value]).map_err(sql_err)?;
+            }
+
+            for file in self.index.files.values() {
+                insert_file(&tx, file).map_err(sql_err)?;
+            }
+
+            let mut vectors = tx
+                .prepare(
+                    "INSERT INTO vectors (symbol_name, file_path, start_line, end_line, facet, chunk_start, chunk_end)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                )
+                .map_err(sql_err)?;
+            for vector in &self.vectors {
+                vectors
+                    .execute(params![
+                        vector.symbol_name,
+                        vector.file_path,
+                        vector.start_line,
+                        vector.end_line,
+                        format!("{:?}", vector.facet).to_lowercase(),
+                        vector.chunk_lines.map(|(start, _)| start),
+                        vector.chunk_lines.map(|(_, end)| end),
+                    ])
+                    .map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)
+    }
+}
+
+/// Build an index from its files, recreating the lookup tables
+pub fn rebuild_index(
+    root_path: String,
+    files: impl IntoIterator<Item = IndexedFile>,
+    indexed_at: u64,
+    normalizer: &TextNormalizer,
+) -> CodebaseIndex {
+    let mut index = CodebaseIndex::new(root_path);
+    for file in files {
+        index.add_file(file, normalizer);
+    }
+    index.indexed_at = indexed_at;
+    index
+}
+
+/// Move an index built under another root (say, on a CI machine) to
+/// `root`, rewriting every file path beneath the old root
+pub fn rebase_index(index: CodebaseIndex, root: &str, normalizer: &TextNormalizer) -> CodebaseIndex {
+    let from = index.root_path.clone();
+    let files = index.files.into_values().map(|mut file| {
+        file.path = rebase_path(&file.path, &from, root);
+        for symbol in &mut file.symbols {
+            symbol.file_path = rebase_path(&symbol.file_path, &from, root);
+        }
+        file
+    });
+    rebuild_index(root.to_string(), files, index.indexed_at, normalizer)
+}
+
+/// `path` moved from under `from` to under `to`; paths outside `from`
+/// are kept
+pub fn rebase_path(path: &str, from: &str, to: &str) -> String {
+    match Path::new(path).strip_prefix(from) {
+        Ok(relative) => Path::new(to).join(relative).to_string_lossy().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::text_normalizer::TextNormalizer;
+    use crate::models::code_index::{CodeSymbol, IndexedFile, SymbolKind};
+
+    fn sample_index() -> CodebaseIndex {
+        let mut index = CodebaseIndex::new("/repo".to_string());
+        index.add_file(
+            IndexedFile {
+                path: "/repo/src/lib.rs".to_string(),
+                language: "rust".to_string(),
+                symbols: vec![CodeSymbol {
+                    name: "load_config".to_string(),
+                    kind: SymbolKind::Function,
+                    file_path: "/repo/src/lib.rs".to_string(),
+                    start_line: 1,
+                    end_line: 5,
+                    signature: None,
+                    doc_comment: None,
+                    parent: None,
+                    calls: vec!["read_file".to_string()],
+                }],
+                imports: Vec::new(),
+                exports: Vec::new(),
+                last_modified: 0,
+                line_count: 5,
+            },
+            &TextNormalizer::new(),
+        );
+        index
+    }
+
+    #[test]
+    fn test_json_export_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.json");
+        IndexExport::new(sample_index(), Vec::new())
+            .write(&path, ExportFormat::Json)
+            .unwrap();
+
+        let export = IndexExport::read(&path, &TextNormalizer::new()).unwrap();
+        assert_eq!(export.format_version, EXPORT_FORMAT_VERSION);
+        assert!(export.index.symbol_map.contains_key("load_config"));
+        assert!(export.index.callers["read_file"].contains("load_config"));
+    }
+
+    #[test]
+    fn test_rebase_index_moves_paths() {
+        let index = rebase_index(sample_index(), "/home/dev/repo", &TextNormalizer::new());
+        let expected = Path::new("/home/dev/repo").join("src/lib.rs").to_string_lossy().to_string();
+
+        assert_eq!(index.root_path, "/home/dev/repo");
+        assert!(index.files.contains_key(&expected));
+        assert_eq!(index.symbol_map["load_config"][0].file_path, expected);
+    }
+}
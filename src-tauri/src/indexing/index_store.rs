@@ -0,0 +1,311 @@
+use crate::indexing::index_export::rebuild_index;
+use crate::indexing::persistence::CACHE_FORMAT_VERSION;
+use crate::indexing::text_normalizer::TextNormalizer;
+use crate::models::code_index::{CodeSymbol, CodebaseIndex, IndexedFile, SymbolKind};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Tables holding a symbol index, shared by the cache store and SQLite exports
+pub const INDEX_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS files (
+        path TEXT PRIMARY KEY,
+        language TEXT NOT NULL,
+        last_modified INTEGER NOT NULL,
+        line_count INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS symbols (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        file_path TEXT NOT NULL REFERENCES files(path),
+        start_line INTEGER NOT NULL,
+        end_line INTEGER NOT NULL,
+        signature TEXT,
+        doc_comment TEXT,
+        parent TEXT
+    );
+    CREATE TABLE IF NOT EXISTS imports (file_path TEXT NOT NULL REFERENCES files(path), statement TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS calls (symbol_id INTEGER NOT NULL REFERENCES symbols(id), callee TEXT NOT NULL);
+    CREATE INDEX IF NOT EXISTS symbols_name ON symbols(name);
+    CREATE INDEX IF NOT EXISTS symbols_file ON symbols(file_path);
+    CREATE INDEX IF NOT EXISTS imports_file ON imports(file_path);
+    CREATE INDEX IF NOT EXISTS calls_symbol ON calls(symbol_id);
+    CREATE INDEX IF NOT EXISTS calls_callee ON calls(callee);";
+
+/// A project's symbol index cached as SQLite tables, so single files can be
+/// read or replaced without loading the whole index
+pub struct IndexStore {
+    conn: Connection,
+}
+
+impl IndexStore {
+    /// Open the store at `path`, creating it if needed
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open index store {}: {}", path.display(), e))?;
+        conn.execute_batch(INDEX_SCHEMA).map_err(store_err)?;
+        Ok(Self { conn })
+    }
+
+    /// Replace everything stored with `index`
+    pub fn save_index(&mut self, index: &CodebaseIndex) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(store_err)?;
+        tx.execute_batch(
+            "DELETE FROM calls; DELETE FROM imports; DELETE FROM symbols; DELETE FROM files; DELETE FROM meta;",
+        )
+        .map_err(store_err)?;
+        for (key, value) in [
+            ("format_version", CACHE_FORMAT_VERSION.to_string()),
+            ("root_path", index.root_path.clone()),
+            ("indexed_at", index.indexed_at.to_string()),
+        ] {
+            tx.execute("INSERT INTO meta (key, value) VALUES (?1, ?2)", params![key, value])
+                .map_err(store_err)?;
+        }
+        for file in index.files.values() {
+            insert_file(&tx, file).map_err(store_err)?;
+        }
+        tx.commit().map_err(store_err)?;
+
+        println!("CodebaseIndex saved ({} files)", index.total_files);
+        Ok(())
+    }
+
+    /// Load the whole index, rebuilding its lookup tables with `normalizer`.
+    /// Fails for a store written in another cache format.
+    pub fn load_index(&self, normalizer: &TextNormalizer) -> Result<CodebaseIndex, String> {
+        let format_version = self.meta("format_version")?;
+        if format_version != CACHE_FORMAT_VERSION.to_string() {
+            return Err(format!(
+                "Index store format {} is not the current format {}",
+                format_version, CACHE_FORMAT_VERSION
+            ));
+        }
+        let root_path = self.meta("root_path")?;
+        let indexed_at: u64 = self
+            .meta("indexed_at")?
+            .parse()
+            .map_err(|e| format!("Invalid index timestamp: {}", e))?;
+        let files = read_files(&self.conn, None).map_err(store_err)?;
+
+        let index = rebuild_index(root_path, files.into_values(), indexed_at, normalizer);
+        println!("CodebaseIndex loaded ({} files)", index.total_files);
+        Ok(index)
+    }
+
+    /// One file's entry, without loading the rest of the index
+    pub fn load_file(&self, path: &str) -> Result<Option<IndexedFile>, String> {
+        Ok(read_files(&self.conn, Some(path))
+            .map_err(store_err)?
+            .remove(path))
+    }
+
+    /// Store a file's new entry in place of its old one; None removes it
+    pub fn update_file(&mut self, path: &str, file: Option<&IndexedFile>) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(store_err)?;
+        tx.execute(
+            "DELETE FROM calls WHERE symbol_id IN (SELECT id FROM symbols WHERE file_path = ?1)",
+            [path],
+        )
+        .map_err(store_err)?;
+        for table in ["symbols", "imports"] {
+            tx.execute(&format!("DELETE FROM {} WHERE file_path = ?1", table), [path])
+                .map_err(store_err)?;
+        }
+        tx.execute("DELETE FROM files WHERE path = ?1", [path])
+            .map_err(store_err)?;
+        if let Some(file) = file {
+            insert_file(&tx, file).map_err(store_err)?;
+        }
+        tx.commit().map_err(store_err)
+    }
+
+    fn meta(&self, key: &str) -> Result<String, String> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+            .map_err(|e| format!("Index store has no {}: {}", key, e))
+    }
+}
+
+fn store_err(e: rusqlite::Error) -> String {
+    format!("Index store error: {}", e)
+}
+
+/// Insert a file with its imports, symbols and their calls
+pub fn insert_file(conn: &Connection, file: &IndexedFile) -> rusqlite::Result<()> {
+    conn.prepare_cached("INSERT INTO files (path, language, last_modified, line_count) VALUES (?1, ?2, ?3, ?4)")?
+        .execute(params![file.path, file.language, file.last_modified, file.line_count])?;
+
+    let mut imports = conn.prepare_cached("INSERT INTO imports (file_path, statement) VALUES (?1, ?2)")?;
+    for statement in &file.imports {
+        imports.execute(params![file.path, statement])?;
+    }
+
+    let mut symbols = conn.prepare_cached(
+        "INSERT INTO symbols (name, kind, file_path, start_line, end_line, signature, doc_comment, parent)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+    )?;
+    let mut calls = conn.prepare_cached("INSERT INTO calls (symbol_id, callee) VALUES (?1, ?2)")?;
+    for symbol in &file.symbols {
+        let symbol_id = symbols.insert(params![
+            symbol.name,
+            format!("{:?}", symbol.kind),
+            symbol.file_path,
+            symbol.start_line,
+            symbol.end_line,
+            symbol.signature,
+            symbol.doc_comment,
+            symbol.parent,
+        ])?;
+        for callee in &symbol.calls {
+            calls.execute(params![symbol_id, callee])?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the stored files, or only `path`, keyed and ordered by path
+pub fn read_files(
+    conn: &Connection,
+    path: Option<&str>,
+) -> rusqlite::Result<BTreeMap<String, IndexedFile>> {
+    let mut files: BTreeMap<String, IndexedFile> = BTreeMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT path, language, last_modified, line_count FROM files WHERE ?1 IS NULL OR path = ?1",
+    )?;
+    let rows = stmt.query_map([path], |row| {
+        Ok(IndexedFile {
+            path: row.get(0)?,
+            language: row.get(1)?,
+            symbols: Vec::new(),
+            imports: Vec::new(),
+            exports: Vec::new(),
+            last_modified: row.get(2)?,
+            line_count: row.get(3)?,
+        })
+    })?;
+    for file in rows {
+        let file = file?;
+        files.insert(file.path.clone(), file);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT file_path, statement FROM imports WHERE ?1 IS NULL OR file_path = ?1 ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map([path], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (file_path, statement) = row?;
+        if let Some(file) = files.get_mut(&file_path) {
+            file.imports.push(statement);
+        }
+    }
+
+    let mut calls: HashMap<i64, Vec<String>> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT calls.symbol_id, calls.callee FROM calls JOIN symbols ON symbols.id = calls.symbol_id
+         WHERE ?1 IS NULL OR symbols.file_path = ?1 ORDER BY calls.rowid",
+    )?;
+    let rows = stmt.query_map([path], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+    for row in rows {
+        let (symbol_id, callee) = row?;
+        calls.entry(symbol_id).or_default().push(callee);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT id, name, kind, file_path, start_line, end_line, signature, doc_comment, parent
+         FROM symbols WHERE ?1 IS NULL OR file_path = ?1 ORDER BY id",
+    )?;
+    let rows = stmt.query_map([path], |row| {
+        let id: i64 = row.get(0)?;
+        let kind: String = row.get(2)?;
+        let kind = SymbolKind::parse(&kind).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                2,
+                Type::Text,
+                format!("Unknown symbol kind: {}", kind).into(),
+            )
+        })?;
+        Ok((
+            id,
+            CodeSymbol {
+                name: row.get(1)?,
+                kind,
+                file_path: row.get(3)?,
+                start_line: row.get(4)?,
+                end_line: row.get(5)?,
+                signature: row.get(6)?,
+                doc_comment: row.get(7)?,
+                parent: row.get(8)?,
+                calls: Vec::new(),
+            },
+        ))
+    })?;
+    for row in rows {
+        let (id, mut symbol) = row?;
+        symbol.calls = calls.remove(&id).unwrap_or_default();
+        if let Some(file) = files.get_mut(&symbol.file_path) {
+            file.symbols.push(symbol);
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, symbol: &str) -> IndexedFile {
+        IndexedFile {
+            path: path.to_string(),
+            language: "rust".to_string(),
+            symbols: vec![CodeSymbol {
+                name: symbol.to_string(),
+                kind: SymbolKind::Function,
+                file_path: path.to_string(),
+                start_line: 1,
+                end_line: 3,
+                signature: Some(format!("fn {}()", symbol)),
+                doc_comment: None,
+                parent: None,
+                calls: vec!["helper".to_string()],
+            }],
+            imports: vec!["use std::fs;".to_string()],
+            exports: Vec::new(),
+            last_modified: 42,
+            line_count: 3,
+        }
+    }
+
+    #[test]
+    fn test_store_round_trips_and_updates_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let normalizer = TextNormalizer::new();
+        let mut index = CodebaseIndex::new("/repo".to_string());
+        index.add_file(file("/repo/a.rs", "load_config"), &normalizer);
+        index.add_file(file("/repo/b.rs", "save_config"), &normalizer);
+
+        let mut store = IndexStore::open(&dir.path().join("index.db")).unwrap();
+        store.save_index(&index).unwrap();
+
+        let loaded = store.load_index(&normalizer).unwrap();
+        assert_eq!(loaded.total_files, 2);
+        assert!(loaded.callers["helper"].contains("load_config"));
+        assert_eq!(
+            store.load_file("/repo/a.rs").unwrap().unwrap().imports,
+            vec!["use std::fs;".to_string()]
+        );
+
+        store.update_file("/repo/a.rs", Some(&file("/repo/a.rs", "read_config"))).unwrap();
+        store.update_file("/repo/b.rs", None).unwrap();
+        assert_eq!(store.load_file("/repo/a.rs").unwrap().unwrap().symbols[0].name, "read_config");
+        assert!(store.load_file("/repo/b.rs").unwrap().is_none());
+
+        let loaded = store.load_index(&normalizer).unwrap();
+        assert_eq!(loaded.total_files, 1);
+        assert_eq!(loaded.files["/repo/a.rs"].symbols[0].calls, vec!["helper".to_string()]);
+    }
+}
@@ -3,7 +3,19 @@ pub mod text_normalizer;
 pub mod relevance_scorer;
 pub mod tantivy_indexer;
 pub mod embedding_generator;
+pub mod embedding_backend;
 pub mod vector_store;
+pub mod clustering;
 pub mod hybrid_search;
+pub mod git_churn;
+pub mod git_changes;
+pub mod reranker;
 pub mod query_analyzer;
 pub mod persistence;
+pub mod compression;
+pub mod cache_bundle;
+pub mod index_export;
+pub mod index_checkpoint;
+#[cfg(feature = "sqlite")]
+pub mod index_store;
+pub mod project_grep;
@@ -1,34 +1,179 @@
+use crate::anthropic::prompts::SystemPrompts;
+use crate::indexing::git_changes::current_branch;
+use crate::indexing::hybrid_search::HybridConfig;
+#[cfg(not(feature = "sqlite"))]
+use crate::indexing::index_export::rebuild_index;
+#[cfg(feature = "sqlite")]
+use crate::indexing::index_store::IndexStore;
+use crate::indexing::text_normalizer::{NormalizerConfig, TextNormalizer};
+use crate::indexing::tree_sitter_indexer::modified_secs;
+use crate::indexing::vector_store::VectorFilter;
+use crate::models::code_index::{CodebaseIndex, SymbolKind};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
 
+/// Directory within a project's cache holding index run checkpoints
+const CHECKPOINT_DIR: &str = "checkpoint";
+
 /// Configuration for where to store index files
+#[derive(Clone)]
 pub struct PersistenceConfig {
     pub cache_dir: PathBuf,
+    /// Per-project settings; kept apart so clearing a cache keeps them
+    pub settings_dir: PathBuf,
+    /// Git branch each project was last seen on, None outside git. Each
+    /// branch gets its own cache, so hopping between branches doesn't
+    /// rebuild the index every time.
+    branches: Arc<Mutex<HashMap<String, Option<String>>>>,
 }
 
 impl PersistenceConfig {
     /// Create persistence config using Tauri's app data directory
     pub fn new(app_handle: &AppHandle) -> Result<Self, String> {
-        let cache_dir = app_handle
+        Self::with_cache_dir(app_handle, None)
+    }
+
+    /// Keep caches in `cache_dir` instead of the app data dir. Project
+    /// settings stay in the app data dir either way.
+    pub fn with_cache_dir(
+        app_handle: &AppHandle,
+        cache_dir: Option<&str>,
+    ) -> Result<Self, String> {
+        let cache_dir = Self::cache_dir_for(app_handle, cache_dir)?;
+        let settings_dir = Self::app_data_dir(app_handle)?.join("project_settings");
+
+        // Create cache directory if it doesn't exist
+        fs::create_dir_all(&cache_dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        fs::create_dir_all(&settings_dir)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+
+        Ok(Self {
+            cache_dir,
+            settings_dir,
+            branches: Arc::default(),
+        })
+    }
+
+    /// Where caches go for a `cache_dir` setting
+    pub fn cache_dir_for(
+        app_handle: &AppHandle,
+        cache_dir: Option<&str>,
+    ) -> Result<PathBuf, String> {
+        match cache_dir {
+            Some(dir) => Ok(PathBuf::from(dir)),
+            None => Ok(Self::app_data_dir(app_handle)?.join("indexes")),
+        }
+    }
+
+    fn app_data_dir(app_handle: &AppHandle) -> Result<PathBuf, String> {
+        app_handle
             .path()
             .app_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?
-            .join("indexes");
+            .map_err(|e| format!("Failed to get app data dir: {}", e))
+    }
 
-        // Create cache directory if it doesn't exist
+    /// Move every cache to `cache_dir` and return the config using it.
+    /// Caches are renamed where possible and copied across file systems.
+    /// If any cache fails to move, those already moved are put back and the
+    /// caches stay where they were. A cache already at the destination wins
+    /// over the one being moved.
+    pub fn relocate(&self, cache_dir: PathBuf) -> Result<Self, String> {
+        if cache_dir != self.cache_dir && cache_dir.starts_with(&self.cache_dir) {
+            return Err("The cache directory can't be moved inside itself".to_string());
+        }
         fs::create_dir_all(&cache_dir)
             .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        let relocated = Self {
+            cache_dir,
+            settings_dir: self.settings_dir.clone(),
+            branches: self.branches.clone(),
+        };
+        if !self.cache_dir.exists() || same_dir(&self.cache_dir, &relocated.cache_dir) {
+            return Ok(relocated);
+        }
+
+        let entries = fs::read_dir(&self.cache_dir)
+            .map_err(|e| format!("Failed to read cache directory: {}", e))?;
+        let mut moved: Vec<(PathBuf, PathBuf, bool)> = Vec::new();
+        let mut duplicates = Vec::new();
+        for entry in entries {
+            let source = entry
+                .map_err(|e| format!("Failed to read entry: {}", e))?
+                .path();
+            let Some(name) = source.file_name() else { continue };
+            let target = relocated.cache_dir.join(name);
+            if target.exists() {
+                duplicates.push(source);
+                continue;
+            }
 
-        Ok(Self { cache_dir })
+            let outcome = match fs::rename(&source, &target) {
+                Ok(()) => Ok(true),
+                Err(_) => copy_recursive(&source, &target).map(|_| false),
+            };
+            match outcome {
+                Ok(renamed) => moved.push((source, target, renamed)),
+                Err(e) => {
+                    let _ = remove_path(&target);
+                    for (source, target, renamed) in moved {
+                        if renamed {
+                            let _ = fs::rename(&target, &source);
+                        } else {
+                            let _ = remove_path(&target);
+                        }
+                    }
+                    return Err(format!("Failed to move cache {}: {}", source.display(), e));
+                }
+            }
+        }
+
+        // Only now that everything arrived are the copied originals removed
+        let leftovers = moved
+            .into_iter()
+            .filter(|(_, _, renamed)| !renamed)
+            .map(|(source, _, _)| source)
+            .chain(duplicates);
+        for source in leftovers {
+            if let Err(e) = remove_path(&source) {
+                eprintln!("Failed to remove old cache {}: {}", source.display(), e);
+            }
+        }
+        Ok(relocated)
     }
 
-    /// Get the directory for a specific project's index
+    /// Get the directory for a specific project's index on its current branch
     pub fn get_project_dir(&self, project_path: &str) -> PathBuf {
-        let hash = Self::hash_path(project_path);
-        self.cache_dir.join(hash)
+        let key = match self.branch_of(project_path) {
+            Some(branch) => format!("{}@{}", project_path, branch),
+            None => project_path.to_string(),
+        };
+        self.cache_dir.join(Self::hash_path(&key))
+    }
+
+    /// Look up which branch a project has checked out, for the cache paths
+    /// that follow. Called before opening a project, since the branch may
+    /// have changed since it was last looked up.
+    pub fn track_branch(&self, project_path: &str) -> Option<String> {
+        let branch = current_branch(project_path);
+        if let Ok(mut branches) = self.branches.lock() {
+            branches.insert(project_path.to_string(), branch.clone());
+        }
+        branch
+    }
+
+    /// The project's branch as last tracked, looking it up the first time
+    pub fn branch_of(&self, project_path: &str) -> Option<String> {
+        let known = match self.branches.lock() {
+            Ok(branches) => branches.get(project_path).cloned(),
+            Err(_) => return None,
+        };
+        known.unwrap_or_else(|| self.track_branch(project_path))
     }
 
     /// Create a simple hash of the project path for directory naming
@@ -41,9 +186,94 @@ impl PersistenceConfig {
         format!("{:x}", hasher.finish())
     }
 
-    /// Get path for the main index file
+    /// Get path for the main index file: a SQLite store, or a bincode
+    /// blob in builds without the `sqlite` feature
     pub fn get_main_index_path(&self, project_path: &str) -> PathBuf {
-        self.get_project_dir(project_path).join("index.bin")
+        #[cfg(feature = "sqlite")]
+        let file_name = "index.db";
+        #[cfg(not(feature = "sqlite"))]
+        let file_name = "index.bin";
+        self.get_project_dir(project_path).join(file_name)
+    }
+
+    /// Cache a project's symbol index
+    pub fn save_index(&self, index: &CodebaseIndex) -> Result<(), String> {
+        let path = self.get_main_index_path(&index.root_path);
+
+        #[cfg(feature = "sqlite")]
+        {
+            IndexStore::open(&path)?.save_index(index)?;
+
+            // Caches from before the SQLite store kept a bincode blob
+            let legacy = self.get_project_dir(&index.root_path).join("index.bin");
+            if legacy.exists() {
+                let _ = fs::remove_file(legacy);
+            }
+            Ok(())
+        }
+        #[cfg(not(feature = "sqlite"))]
+        index.save(path)
+    }
+
+    /// Save just the files re-indexed since the index was saved, which may
+    /// also have been added or removed, and update the cache metadata to
+    /// match. Their vectors are only in memory, so they are marked to be
+    /// re-embedded when the cache is next opened. Builds without the
+    /// `sqlite` feature rewrite the whole index.
+    pub fn save_changed_files(&self, index: &CodebaseIndex, changed: &[String]) -> Result<(), String> {
+        let root = &index.root_path;
+        let path = self.get_main_index_path(root);
+
+        #[cfg(feature = "sqlite")]
+        {
+            let mut store = IndexStore::open(&path)?;
+            for file_path in changed {
+                store.update_file(file_path, index.files.get(file_path))?;
+            }
+        }
+        #[cfg(not(feature = "sqlite"))]
+        index.save(&path)?;
+
+        let metadata_path = self.get_cache_metadata_path(root);
+        let mut metadata = CacheMetadata::load(&metadata_path)?;
+        for file_path in changed {
+            metadata.record_file(file_path, index.files.contains_key(file_path));
+            metadata.stale_vectors.insert(file_path.clone());
+        }
+        metadata.file_count = index.total_files;
+        metadata.checksums = self.cache_checksums(root);
+        metadata.save(&metadata_path)
+    }
+
+    /// Load a project's cached symbol index. Lookup tables are rebuilt with
+    /// the project's normalizer and the given synonym table, so they match
+    /// the current table rather than the one the cache was saved under.
+    pub fn load_index(
+        &self,
+        project_path: &str,
+        synonyms: &HashMap<String, Vec<String>>,
+    ) -> Result<CodebaseIndex, String> {
+        let path = self.get_main_index_path(project_path);
+        let settings =
+            ProjectSettings::load_or_default(&self.get_project_settings_path(project_path), project_path)?;
+        let mut normalizer = TextNormalizer::new();
+        normalizer.set_config(settings.normalizer.unwrap_or_default());
+        normalizer.set_synonyms(synonyms.clone());
+
+        #[cfg(feature = "sqlite")]
+        {
+            IndexStore::open(&path)?.load_index(&normalizer)
+        }
+        #[cfg(not(feature = "sqlite"))]
+        {
+            let index = CodebaseIndex::load(path)?;
+            Ok(rebuild_index(
+                index.root_path,
+                index.files.into_values(),
+                index.indexed_at,
+                &normalizer,
+            ))
+        }
     }
 
     /// Get path for the vector index file
@@ -57,16 +287,82 @@ impl PersistenceConfig {
             .join("vectors_metadata.bin")
     }
 
+    /// Get path for the file-level vector index
+    pub fn get_file_vector_index_path(&self, project_path: &str) -> PathBuf {
+        self.get_project_dir(project_path).join("file_vectors.usearch")
+    }
+
+    /// Get path for the file-level vector metadata
+    pub fn get_file_vector_metadata_path(&self, project_path: &str) -> PathBuf {
+        self.get_project_dir(project_path)
+            .join("file_vectors_metadata.bin")
+    }
+
     /// Get path for the Tantivy index directory
     pub fn get_tantivy_dir(&self, project_path: &str) -> PathBuf {
         self.get_project_dir(project_path).join("tantivy")
     }
 
+    /// Get path for the checkpoints of an unfinished index run
+    pub fn get_checkpoint_dir(&self, project_path: &str) -> PathBuf {
+        self.get_project_dir(project_path).join(CHECKPOINT_DIR)
+    }
+
+    /// Get path for the LLM-written overview of the project
+    pub fn get_codebase_summary_path(&self, project_path: &str) -> PathBuf {
+        self.get_project_dir(project_path).join("summary.json")
+    }
+
+    /// Get path for the latest conventions profile extracted from the project
+    pub fn get_conventions_path(&self, project_path: &str) -> PathBuf {
+        self.get_project_dir(project_path).join("conventions.json")
+    }
+
     /// Get path for the cache metadata file
     pub fn get_cache_metadata_path(&self, project_path: &str) -> PathBuf {
         self.get_project_dir(project_path).join("metadata.json")
     }
 
+    /// Get path for a project's query history; shared by all its branches
+    pub fn get_query_history_path(&self, project_path: &str) -> PathBuf {
+        self.settings_dir
+            .join(format!("{}.history.jsonl", Self::hash_path(project_path)))
+    }
+
+    /// Get path for a project's settings file
+    pub fn get_project_settings_path(&self, project_path: &str) -> PathBuf {
+        self.settings_dir
+            .join(format!("{}.json", Self::hash_path(project_path)))
+    }
+
+    /// Get path for a project's pinned chunks; kept with the settings
+    pub fn get_pins_path(&self, project_path: &str) -> PathBuf {
+        self.settings_dir
+            .join(format!("{}.pins.json", Self::hash_path(project_path)))
+    }
+
+    /// Get the directory of a project's chat sessions
+    pub fn get_sessions_dir(&self, project_path: &str) -> PathBuf {
+        self.settings_dir
+            .join(format!("{}.sessions", Self::hash_path(project_path)))
+    }
+
+    /// Get path for a project's LLM batch jobs
+    pub fn get_batch_jobs_path(&self, project_path: &str) -> PathBuf {
+        self.settings_dir
+            .join(format!("{}.batches.json", Self::hash_path(project_path)))
+    }
+
+    /// Get path for LLM token usage, of all projects
+    pub fn get_usage_path(&self) -> PathBuf {
+        self.settings_dir.join("usage.json")
+    }
+
+    /// Get the directory of cached LLM replies, beside the project settings
+    pub fn get_response_cache_dir(&self) -> PathBuf {
+        self.settings_dir.with_file_name("llm_responses")
+    }
+
     /// Check if a cached index exists for a project
     pub fn has_cached_index(&self, project_path: &str) -> bool {
         let main_index = self.get_main_index_path(project_path);
@@ -74,8 +370,8 @@ impl PersistenceConfig {
         main_index.exists() && metadata.exists()
     }
 
-    /// Delete cached index for a project
-    pub fn clear_project_cache(&self, project_path: &str) -> Result<(), String> {
+    /// Delete the cache of a project's checked-out branch only
+    pub fn clear_branch_cache(&self, project_path: &str) -> Result<(), String> {
         let project_dir = self.get_project_dir(project_path);
         if project_dir.exists() {
             fs::remove_dir_all(&project_dir)
@@ -84,6 +380,146 @@ impl PersistenceConfig {
         Ok(())
     }
 
+    /// Cache files whose checksums are recorded in the cache metadata
+    fn checksummed_files(&self, project_path: &str) -> Vec<PathBuf> {
+        vec![
+            self.get_main_index_path(project_path),
+            self.get_vector_index_path(project_path),
+            self.get_file_vector_index_path(project_path),
+            self.get_tantivy_dir(project_path).join("meta.json"),
+        ]
+    }
+
+    /// Checksums of the project's cache files as they are on disk, keyed by
+    /// path within the project's cache directory
+    fn cache_checksums(&self, project_path: &str) -> BTreeMap<String, u32> {
+        let project_dir = self.get_project_dir(project_path);
+        self.checksummed_files(project_path)
+            .into_iter()
+            .filter_map(|path| {
+                let relative = path.strip_prefix(&project_dir).ok()?;
+                let name = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                Some((name, file_checksum(&path)?))
+            })
+            .collect()
+    }
+
+    /// Cache files that no longer match the checksums recorded when the
+    /// cache was saved; a missing file counts as corrupted. Caches saved
+    /// before checksums were recorded always pass.
+    pub fn corrupted_cache_files(&self, metadata: &CacheMetadata) -> Vec<PathBuf> {
+        let project_dir = self.get_project_dir(&metadata.project_path);
+        metadata
+            .checksums
+            .iter()
+            .map(|(name, &expected)| (project_dir.join(name), expected))
+            .filter(|(path, expected)| file_checksum(path) != Some(*expected))
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// Record the checksums of cache files rewritten since the metadata was
+    /// saved, e.g. a full-text index rebuilt on open
+    pub fn refresh_checksums(&self, project_path: &str) -> Result<(), String> {
+        let path = self.get_cache_metadata_path(project_path);
+        let mut metadata = CacheMetadata::load(&path)?;
+        metadata.checksums = self.cache_checksums(project_path);
+        metadata.save(&path)
+    }
+
+    /// Delete cached index for a project, on every branch
+    pub fn clear_project_cache(&self, project_path: &str) -> Result<(), String> {
+        let mut dirs: Vec<PathBuf> = self
+            .get_cached_projects()?
+            .into_iter()
+            .filter(|p| p.project_path == project_path)
+            .map(|p| p.cache_dir)
+            .collect();
+        dirs.push(self.get_project_dir(project_path));
+
+        for dir in dirs {
+            if dir.exists() {
+                fs::remove_dir_all(&dir)
+                    .map_err(|e| format!("Failed to remove cache directory: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete every project's cached index; project settings are kept
+    pub fn clear_all_caches(&self) -> Result<(), String> {
+        if !self.cache_dir.exists() {
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(&self.cache_dir)
+            .map_err(|e| format!("Failed to read cache directory: {}", e))?;
+
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read entry: {}", e))?
+                .path();
+            if path.is_dir() {
+                fs::remove_dir_all(&path)
+                    .map_err(|e| format!("Failed to remove cache directory: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bytes on disk used by a project's cached index
+    pub fn project_cache_size(&self, project_path: &str) -> u64 {
+        Self::calculate_dir_size(&self.get_project_dir(project_path)).unwrap_or(0)
+    }
+
+    /// Write a project's cache metadata, tagged with its current branch and
+    /// the checksums of the cache files. Call after the cache files are saved.
+    pub fn save_cache_metadata(&self, mut metadata: CacheMetadata) -> Result<(), String> {
+        metadata.branch = self.branch_of(&metadata.project_path);
+        metadata.checksums = self.cache_checksums(&metadata.project_path);
+        metadata.save(&self.get_cache_metadata_path(&metadata.project_path))
+    }
+
+    /// Record that a project's cache was just loaded, for LRU eviction
+    pub fn touch_cache(&self, project_path: &str) -> Result<(), String> {
+        let path = self.get_cache_metadata_path(project_path);
+        let mut metadata = CacheMetadata::load(&path)?;
+        metadata.last_accessed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        metadata.save(&path)
+    }
+
+    /// Delete the least recently used caches until all caches fit in
+    /// `max_bytes`. The cache of `keep` on its current branch is never
+    /// deleted. Returns the evicted project paths.
+    pub fn enforce_cache_limit(&self, max_bytes: u64, keep: &str) -> Result<Vec<String>, String> {
+        let mut projects = self.get_cached_projects()?;
+        let mut total: u64 = projects.iter().map(|p| p.size_bytes).sum();
+        projects.sort_by_key(|p| p.last_accessed);
+        let keep_dir = self.get_project_dir(keep);
+
+        let mut evicted = Vec::new();
+        for project in projects {
+            if total <= max_bytes {
+                break;
+            }
+            if project.cache_dir == keep_dir {
+                continue;
+            }
+            fs::remove_dir_all(&project.cache_dir)
+                .map_err(|e| format!("Failed to remove cache directory: {}", e))?;
+            total = total.saturating_sub(project.size_bytes);
+            evicted.push(project.project_path);
+        }
+        Ok(evicted)
+    }
+
     /// Get all cached project paths
     pub fn get_cached_projects(&self) -> Result<Vec<CacheInfo>, String> {
         let mut projects = Vec::new();
@@ -105,10 +541,13 @@ impl PersistenceConfig {
                     if let Ok(metadata) = CacheMetadata::load(&metadata_path) {
                         let size = Self::calculate_dir_size(&path).unwrap_or(0);
                         projects.push(CacheInfo {
+                            last_accessed: metadata.last_used(),
                             project_path: metadata.project_path,
+                            branch: metadata.branch,
                             cached_at: metadata.cached_at,
                             file_count: metadata.file_count,
                             size_bytes: size,
+                            cache_dir: path.clone(),
                         });
                     }
                 }
@@ -118,6 +557,59 @@ impl PersistenceConfig {
         Ok(projects)
     }
 
+    /// Delete cache directories whose project no longer exists or whose
+    /// metadata can't be read. A directory holding the checkpoints of an
+    /// interrupted index run has no metadata yet and is kept so the run can
+    /// resume.
+    pub fn collect_garbage(&self) -> Result<CacheGcReport, String> {
+        let mut report = CacheGcReport {
+            ran_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            ..Default::default()
+        };
+        if !self.cache_dir.exists() {
+            return Ok(report);
+        }
+
+        let entries = fs::read_dir(&self.cache_dir)
+            .map_err(|e| format!("Failed to read cache directory: {}", e))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| format!("Failed to read entry: {}", e))?
+                .path();
+            if !path.is_dir() {
+                continue;
+            }
+            report.scanned += 1;
+
+            let (project_path, reason) = match CacheMetadata::load(&path.join("metadata.json")) {
+                Ok(metadata) if Path::new(&metadata.project_path).exists() => continue,
+                Ok(metadata) => (Some(metadata.project_path), "Project no longer exists"),
+                Err(_) if path.join(CHECKPOINT_DIR).exists() => continue,
+                Err(_) => (None, "Cache metadata is missing or unreadable"),
+            };
+
+            let size_bytes = Self::calculate_dir_size(&path).unwrap_or(0);
+            match fs::remove_dir_all(&path) {
+                Ok(()) => {
+                    report.freed_bytes += size_bytes;
+                    report.removed.push(RemovedCache {
+                        project_path,
+                        reason: reason.to_string(),
+                        size_bytes,
+                    });
+                }
+                Err(e) => report
+                    .errors
+                    .push(format!("Failed to remove {}: {}", path.display(), e)),
+            }
+        }
+
+        Ok(report)
+    }
+
     /// Calculate total size of a directory
     fn calculate_dir_size(path: &Path) -> Result<u64, std::io::Error> {
         let mut total = 0;
@@ -136,28 +628,148 @@ impl PersistenceConfig {
     }
 }
 
+/// User preferences stored per project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    pub project_path: String,
+    /// Replaces the query-type presets when a query has no config of its own
+    #[serde(default)]
+    pub hybrid_config: Option<HybridConfig>,
+    /// Stop words and stemming; None uses the defaults
+    #[serde(default)]
+    pub normalizer: Option<NormalizerConfig>,
+    /// System prompts for this project, replacing the app-wide ones
+    #[serde(default)]
+    pub system_prompts: SystemPrompts,
+}
+
+impl ProjectSettings {
+    /// Load a project's settings, or defaults when none were saved
+    pub fn load_or_default(path: &Path, project_path: &str) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self {
+                project_path: project_path.to_string(),
+                ..Default::default()
+            });
+        }
+
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read project settings: {}", e))?;
+
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse project settings: {}", e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize project settings: {}", e))?;
+
+        fs::write(path, json).map_err(|e| format!("Failed to write project settings: {}", e))
+    }
+}
+
+/// A chunk the user wants in every prompt's context, whatever the ranking
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedChunk {
+    pub id: u64,
+    pub file_path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Symbol the chunk covers; its current lines are used when it moves
+    #[serde(default)]
+    pub symbol_name: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub pinned_at: u64,
+}
+
+impl PinnedChunk {
+    /// Load a project's pins, or none when nothing was pinned
+    pub fn load_all(path: &Path) -> Result<Vec<Self>, String> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let json = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read pinned chunks: {}", e))?;
+
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse pinned chunks: {}", e))
+    }
+
+    pub fn save_all(pins: &[Self], path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(pins)
+            .map_err(|e| format!("Failed to serialize pinned chunks: {}", e))?;
+
+        fs::write(path, json).map_err(|e| format!("Failed to write pinned chunks: {}", e))
+    }
+}
+
+/// Bumped whenever a change to the cached index layout makes older caches
+/// unreadable; those are rebuilt instead of failing to deserialize
+pub const CACHE_FORMAT_VERSION: u32 = 1;
+
 /// Metadata about a cached index
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheMetadata {
     pub project_path: String,
+    /// `CACHE_FORMAT_VERSION` of the writer; 0 for caches from before
+    /// versioning
+    #[serde(default)]
+    pub format_version: u32,
     pub cached_at: u64,
+    /// When the cache was last loaded; 0 in caches written before this was
+    /// tracked
+    #[serde(default)]
+    pub last_accessed: u64,
+    /// Git branch the cache was built on
+    #[serde(default)]
+    pub branch: Option<String>,
     pub file_count: usize,
     pub file_timestamps: HashMap<String, u64>,
+    /// Content hashes of the same files. A checkout rewrites the files that
+    /// differ between branches, so on returning to a branch their
+    /// timestamps are newer but the contents match the cache.
+    #[serde(default)]
+    pub file_hashes: HashMap<String, u64>,
+    /// CRC32 of the symbol index, vector indexes and full-text index meta,
+    /// checked on load to catch caches damaged on disk
+    #[serde(default)]
+    pub checksums: BTreeMap<String, u32>,
+    /// Files re-indexed since the vector stores were saved, whose saved
+    /// vectors are outdated
+    #[serde(default)]
+    pub stale_vectors: BTreeSet<String>,
 }
 
 impl CacheMetadata {
+    /// Metadata for a cache of the given files, hashing their contents
     pub fn new(project_path: String, file_count: usize, file_timestamps: HashMap<String, u64>) -> Self {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let file_hashes = file_timestamps
+            .keys()
+            .filter_map(|path| Some((path.clone(), content_hash(path)?)))
+            .collect();
         Self {
             project_path,
-            cached_at: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            format_version: CACHE_FORMAT_VERSION,
+            cached_at: now,
+            last_accessed: now,
+            branch: None,
             file_count,
             file_timestamps,
+            file_hashes,
+            checksums: BTreeMap::new(),
+            stale_vectors: BTreeSet::new(),
         }
     }
 
+    /// When the cache was last written or loaded
+    pub fn last_used(&self) -> u64 {
+        self.last_accessed.max(self.cached_at)
+    }
+
     pub fn save(&self, path: &Path) -> Result<(), String> {
         let json = serde_json::to_string_pretty(self)
             .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
@@ -174,7 +786,24 @@ impl CacheMetadata {
         serde_json::from_str(&json).map_err(|e| format!("Failed to parse metadata: {}", e))
     }
 
-    /// Check if the cache is still valid by comparing file timestamps
+    /// Track a re-indexed file as it is now, or stop tracking one that is no
+    /// longer indexed
+    fn record_file(&mut self, path: &str, indexed: bool) {
+        if !indexed {
+            self.file_timestamps.remove(path);
+            self.file_hashes.remove(path);
+            return;
+        }
+        self.file_timestamps
+            .insert(path.to_string(), modified_secs(Path::new(path)));
+        match content_hash(path) {
+            Some(hash) => self.file_hashes.insert(path.to_string(), hash),
+            None => self.file_hashes.remove(path),
+        };
+    }
+
+    /// Check if the cache is still valid by comparing file timestamps, and
+    /// the contents of files whose timestamp changed
     pub fn is_valid(&self, current_timestamps: &HashMap<String, u64>) -> bool {
         // Check if file count matches
         if self.file_timestamps.len() != current_timestamps.len() {
@@ -185,6 +814,9 @@ impl CacheMetadata {
         for (path, &cached_time) in &self.file_timestamps {
             match current_timestamps.get(path) {
                 Some(&current_time) if current_time == cached_time => continue,
+                Some(_) if self.file_hashes.get(path).is_some_and(|&hash| content_hash(path) == Some(hash)) => {
+                    continue
+                }
                 _ => return false, // File was modified or removed
             }
         }
@@ -200,11 +832,183 @@ impl CacheMetadata {
     }
 }
 
+/// Queries kept per project; older ones are dropped as new ones arrive
+pub const MAX_QUERY_HISTORY: usize = 1000;
+
+/// Which search a recorded query went through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMode {
+    Keyword,
+    Semantic,
+    CoarseToFine,
+    Files,
+}
+
+/// A past search, with enough of its filters to run it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryHistoryEntry {
+    pub query: String,
+    pub mode: QueryMode,
+    #[serde(default)]
+    pub symbol_kinds: Option<Vec<SymbolKind>>,
+    #[serde(default)]
+    pub file_patterns: Option<Vec<String>>,
+    /// Metadata filter of a semantic search
+    #[serde(default)]
+    pub filter: Option<VectorFilter>,
+    pub result_count: usize,
+    pub timestamp: u64,
+}
+
+impl QueryHistoryEntry {
+    pub fn new(query: String, mode: QueryMode, result_count: usize) -> Self {
+        Self {
+            query,
+            mode,
+            symbol_kinds: None,
+            file_patterns: None,
+            filter: None,
+            result_count,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+        }
+    }
+
+    /// Append to a history file, one JSON object per line. The file is
+    /// rewritten without its oldest entries once it passes MAX_QUERY_HISTORY.
+    pub fn append(&self, path: &Path) -> Result<(), String> {
+        let line = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize query history: {}", e))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        }
+
+        let existing = fs::read_to_string(path).unwrap_or_default();
+        let line_count = existing.lines().count();
+        if line_count >= MAX_QUERY_HISTORY {
+            let mut kept: Vec<&str> = existing
+                .lines()
+                .skip(line_count + 1 - MAX_QUERY_HISTORY)
+                .collect();
+            kept.push(&line);
+            return fs::write(path, kept.join("\n") + "\n")
+                .map_err(|e| format!("Failed to write query history: {}", e));
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Failed to open query history: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to write query history: {}", e))
+    }
+
+    /// Load a history file, newest first. Lines that fail to parse are skipped.
+    pub fn load_all(path: &Path) -> Result<Vec<Self>, String> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read query history: {}", e))?;
+
+        Ok(content
+            .lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
+
+/// Hash of a file's contents, None if it can't be read
+fn content_hash(path: &str) -> Option<u64> {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let bytes = fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Whether two paths name the same existing directory
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        fs::create_dir_all(target)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        fs::copy(source, target).map(|_| ())
+    }
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else if path.exists() {
+        fs::remove_file(path)
+    } else {
+        Ok(())
+    }
+}
+
+/// CRC32 of a file, None if it can't be read
+fn file_checksum(path: &Path) -> Option<u32> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        match file.read(&mut buffer).ok()? {
+            0 => return Some(hasher.finalize()),
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+}
+
+/// A cache directory deleted by `collect_garbage`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemovedCache {
+    /// Project the cache belonged to, if its metadata could be read
+    pub project_path: Option<String>,
+    pub reason: String,
+    pub size_bytes: u64,
+}
+
+/// What a cache garbage collection found and removed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheGcReport {
+    pub ran_at: u64,
+    /// Cache directories looked at
+    pub scanned: usize,
+    pub removed: Vec<RemovedCache>,
+    pub freed_bytes: u64,
+    /// Directories that should have been removed but couldn't be
+    pub errors: Vec<String>,
+}
+
 /// Information about a cached project
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheInfo {
     pub project_path: String,
+    pub branch: Option<String>,
     pub cached_at: u64,
+    pub last_accessed: u64,
     pub file_count: usize,
     pub size_bytes: u64,
+    #[serde(skip)]
+    pub cache_dir: PathBuf,
 }
@@ -0,0 +1,144 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Matches returned by a search unless the options ask for another limit
+pub const DEFAULT_MAX_GREP_MATCHES: usize = 1000;
+
+/// Longer lines (e.g. minified code) are cut to this many bytes
+const MAX_LINE_LEN: usize = 1000;
+
+/// How a grep pattern is interpreted
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GrepOptions {
+    /// Treat the pattern as a regular expression instead of literal text
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Only match where the pattern isn't part of a longer word
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Gitignore-style globs selecting the files searched; `!` excludes
+    #[serde(default)]
+    pub file_patterns: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_results: Option<usize>,
+}
+
+impl GrepOptions {
+    pub fn build_regex(&self, pattern: &str) -> Result<Regex, String> {
+        if pattern.is_empty() {
+            return Err("Search pattern is empty".to_string());
+        }
+
+        let mut source = if self.regex {
+            pattern.to_string()
+        } else {
+            regex::escape(pattern)
+        };
+        if self.whole_word {
+            source = format!(r"\b(?:{})\b", source);
+        }
+
+        RegexBuilder::new(&source)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid pattern: {}", e))
+    }
+}
+
+/// A line containing at least one match
+#[derive(Debug, Clone, Serialize)]
+pub struct GrepMatch {
+    pub file_path: String,
+    /// 1-based
+    pub line_number: usize,
+    pub line: String,
+    /// Byte ranges of the matches within `line`
+    pub ranges: Vec<(usize, usize)>,
+}
+
+/// Matching lines of one file, at most `limit`. Unreadable and non-UTF-8
+/// files have no matches.
+pub fn grep_file(path: &str, regex: &Regex, limit: usize) -> Vec<GrepMatch> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let line = truncate_line(line);
+            let ranges: Vec<(usize, usize)> = regex
+                .find_iter(line)
+                .map(|m| (m.start(), m.end()))
+                .collect();
+            (!ranges.is_empty()).then(|| GrepMatch {
+                file_path: path.to_string(),
+                line_number: i + 1,
+                line: line.to_string(),
+                ranges,
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+fn truncate_line(line: &str) -> &str {
+    if line.len() <= MAX_LINE_LEN {
+        return line;
+    }
+    let mut end = MAX_LINE_LEN;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    &line[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_regex() {
+        let literal = GrepOptions::default().build_regex("a.b(").unwrap();
+        assert!(literal.is_match("call A.B(x)"));
+        assert!(!literal.is_match("axb("));
+
+        let word = GrepOptions {
+            whole_word: true,
+            case_sensitive: true,
+            ..Default::default()
+        }
+        .build_regex("index")
+        .unwrap();
+        assert!(word.is_match("let index = 0;"));
+        assert!(!word.is_match("reindex()"));
+        assert!(!word.is_match("Index"));
+
+        let regex = GrepOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert!(regex.build_regex(r"fn \w+_file").unwrap().is_match("pub fn grep_file("));
+        assert!(regex.build_regex("(").is_err());
+    }
+
+    #[test]
+    fn test_grep_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        fs::write(&path, "fn parse() {}\nfn run() { parse(); parse(); }\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let regex = GrepOptions::default().build_regex("parse").unwrap();
+        let matches = grep_file(&path, &regex, 10);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].line_number, 2);
+        assert_eq!(matches[1].ranges, vec![(11, 16), (20, 25)]);
+
+        assert_eq!(grep_file(&path, &regex, 1).len(), 1);
+    }
+}
@@ -1,27 +1,96 @@
 use crate::indexing::hybrid_search::HybridConfig;
+use crate::indexing::vector_store::VectorFilter;
+use crate::models::code_index::SymbolKind;
+use regex::{Regex, RegexBuilder};
 
 pub struct QueryAnalyzer;
 
-#[derive(Debug, PartialEq)]
+/// Nouns that mark the word before them as a language hint ("the rust code")
+const LANGUAGE_HINT_NOUNS: &[&str] = &[
+    "code", "files", "file", "source", "side", "frontend", "backend", "components",
+];
+
+/// Nouns that mark the word before them as a directory ("in the auth module")
+const PATH_HINT_NOUNS: &[&str] = &["folder", "directory", "dir", "module", "package"];
+
+/// A query split into field filters and free-text terms
+#[derive(Debug, Default)]
+pub struct ParsedQuery {
+    pub terms: Vec<String>,
+    pub filter: VectorFilter,
+    /// Lowercased `-term` exclusions; results mentioning them are dropped
+    pub exclude_terms: Vec<String>,
+    /// The `field:value` and `-term` tokens the filter and exclusions were built from
+    pub filter_tokens: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum QueryType {
     ExactSymbol,
     FilePath,
     SemanticIntent,
     CodeContent,
+    /// A regex or glob, matched against names instead of tokenized
+    Pattern,
     Mixed,
 }
 
+/// How sure `classify_query` is of its guess, from 0 to 1
+#[derive(Debug, Clone)]
+pub struct QueryClassification {
+    pub query_type: QueryType,
+    pub confidence: f32,
+    /// The next most likely type when the guess is uncertain
+    pub alternative: Option<QueryType>,
+}
+
+/// Classifications at least this confident use their preset unblended
+const CONFIDENT: f32 = 0.75;
+
+/// Whether a word is written like an identifier: snake_case, camelCase,
+/// a path like `std::fs` or containing digits
+fn is_identifier_like(word: &str) -> bool {
+    word.contains('_')
+        || word.contains("::")
+        || word.chars().any(|c| c.is_ascii_digit())
+        || word.chars().skip(1).any(|c| c.is_uppercase())
+}
+
+/// Sequences that only appear in regular expressions
+const REGEX_MARKERS: &[&str] = &[".*", ".+", ".?", "\\w", "\\d", "\\s", "\\b", "\\.", "|", "[", "("];
+
 impl QueryAnalyzer {
     pub fn analyze_query(query: &str) -> QueryType {
+        Self::classify_query(query).query_type
+    }
+
+    /// Classify a query and say how sure the guess is. Uncertain guesses
+    /// name the runner-up type so configs can be blended.
+    pub fn classify_query(query: &str) -> QueryClassification {
         let lower = query.to_lowercase();
         let words: Vec<&str> = query.split_whitespace().collect();
+        let classified = |query_type, confidence, alternative| QueryClassification {
+            query_type,
+            confidence,
+            alternative,
+        };
+
+        // Regexes and globs, before their slashes and dots read as paths
+        if let Some(is_regex) = Self::pattern_kind(query) {
+            return classified(QueryType::Pattern, if is_regex { 0.9 } else { 0.8 }, None);
+        }
 
         // File path patterns
-        if query.contains('/') || query.contains('\\')
-            || query.ends_with(".rs") || query.ends_with(".ts") || query.ends_with(".js")
-            || query.ends_with(".py") || query.ends_with(".java") || query.ends_with(".go")
-        {
-            return QueryType::FilePath;
+        let has_separator = query.contains('/') || query.contains('\\');
+        let has_extension = query.ends_with(".rs") || query.ends_with(".ts") || query.ends_with(".js")
+            || query.ends_with(".py") || query.ends_with(".java") || query.ends_with(".go");
+        if has_separator || has_extension {
+            return match (has_separator, has_extension) {
+                (true, true) => classified(QueryType::FilePath, 0.95, None),
+                _ if words.len() == 1 => classified(QueryType::FilePath, 0.8, None),
+                // "read/write locking" is prose with a slash in it
+                _ => classified(QueryType::FilePath, 0.55, Some(QueryType::Mixed)),
+            };
         }
 
         // Semantic patterns
@@ -29,24 +98,254 @@ impl QueryAnalyzer {
             || lower.starts_with("why") || lower.contains("how to")
             || lower.starts_with("where") || lower.starts_with("when")
         {
-            return QueryType::SemanticIntent;
+            return if words.len() >= 3 {
+                classified(QueryType::SemanticIntent, 0.85, None)
+            } else {
+                // "whence", "however" or a terse "what parser"
+                classified(QueryType::SemanticIntent, 0.6, Some(QueryType::Mixed))
+            };
         }
 
         // Code patterns
-        if query.contains("fn ") || query.contains("async ")
-            || query.contains("class ") || query.contains("impl ")
-            || query.contains("struct ") || query.contains("trait ")
-            || query.contains("interface ") || query.contains("function ")
-        {
-            return QueryType::CodeContent;
+        let code_keywords = ["fn ", "async ", "class ", "impl ", "struct ", "trait ", "interface ", "function "];
+        if code_keywords.iter().any(|k| query.contains(k)) {
+            return if code_keywords.iter().any(|k| query.starts_with(k)) {
+                classified(QueryType::CodeContent, 0.85, None)
+            } else {
+                classified(QueryType::CodeContent, 0.6, Some(QueryType::Mixed))
+            };
         }
 
         // Single word likely symbol
         if words.len() == 1 {
-            return QueryType::ExactSymbol;
+            return if is_identifier_like(query) {
+                classified(QueryType::ExactSymbol, 0.9, None)
+            } else {
+                // A plain word like "authentication" may be a concept
+                classified(QueryType::ExactSymbol, 0.6, Some(QueryType::SemanticIntent))
+            };
+        }
+
+        if words.iter().any(|w| is_identifier_like(w)) {
+            classified(QueryType::Mixed, 0.6, Some(QueryType::ExactSymbol))
+        } else if words.len() >= 4 {
+            classified(QueryType::Mixed, 0.6, Some(QueryType::SemanticIntent))
+        } else {
+            classified(QueryType::Mixed, 0.8, None)
+        }
+    }
+
+    /// The search config for a classification. Below `CONFIDENT` the
+    /// preset is blended with the runner-up's in proportion to confidence.
+    pub fn config_for_classification(classification: &QueryClassification) -> HybridConfig {
+        let config = Self::get_config_for_query(&classification.query_type);
+        match classification.alternative {
+            Some(ref alternative) if classification.confidence < CONFIDENT => {
+                config.blend(&Self::get_config_for_query(alternative), classification.confidence)
+            }
+            _ => config,
+        }
+    }
+
+    /// Whether a single-token query is a regex (Some(true)) or a glob
+    /// (Some(false)). A trailing `?` is punctuation, not a wildcard.
+    fn pattern_kind(query: &str) -> Option<bool> {
+        let query = query.trim();
+        if query.is_empty() || query.contains(char::is_whitespace) {
+            return None;
+        }
+
+        let anchored = query.len() > 1 && (query.starts_with('^') || query.ends_with('$'));
+        if anchored || REGEX_MARKERS.iter().any(|m| query.contains(m)) {
+            return Some(true);
         }
 
-        QueryType::Mixed
+        let body = query.strip_suffix('?').unwrap_or(query);
+        if body.contains('*') || body.contains('?') {
+            return Some(false);
+        }
+
+        None
+    }
+
+    /// Compile a `Pattern` query. Globs match whole names, case-insensitively;
+    /// regexes are used as written. None for other queries or invalid regexes.
+    pub fn pattern_regex(query: &str) -> Option<Regex> {
+        let query = query.trim();
+        match Self::pattern_kind(query)? {
+            true => Regex::new(query).ok(),
+            false => {
+                let translated: String = query
+                    .chars()
+                    .map(|c| match c {
+                        '*' => ".*".to_string(),
+                        '?' => ".".to_string(),
+                        c => regex::escape(&c.to_string()),
+                    })
+                    .collect();
+                RegexBuilder::new(&format!("^{}$", translated))
+                    .case_insensitive(true)
+                    .build()
+                    .ok()
+            }
+        }
+    }
+
+    /// Split `kind:function lang:rust path:src/indexing auth` into field
+    /// filters and free text. Fields take comma-separated values and may be
+    /// repeated; tokens with unknown fields or values stay free text.
+    /// A leading `-` turns a field into an exclusion (`-path:tests/`) and a
+    /// plain term into a negative term (`-mock`).
+    pub fn parse_query(query: &str) -> ParsedQuery {
+        let mut parsed = ParsedQuery::default();
+
+        for token in query.split_whitespace() {
+            let (negated, body) = match token.strip_prefix('-') {
+                Some(rest) if !rest.is_empty() => (true, rest),
+                _ => (false, token),
+            };
+
+            let applied = match body.split_once(':') {
+                Some((field, value)) => Self::apply_field(&mut parsed.filter, field, value, negated),
+                None if negated => {
+                    parsed.exclude_terms.push(body.to_lowercase());
+                    true
+                }
+                None => false,
+            };
+
+            if applied {
+                parsed.filter_tokens.push(token.to_string());
+            } else {
+                parsed.terms.push(token.to_string());
+            }
+        }
+
+        Self::apply_hints(&mut parsed);
+        parsed
+    }
+
+    /// Turn phrases like "in the python code", "the react frontend" or
+    /// "in the indexing module" into language and path filters. Explicit
+    /// fields take precedence, and a query is never reduced to nothing.
+    fn apply_hints(parsed: &mut ParsedQuery) {
+        let words: Vec<String> = parsed.terms.iter().map(|t| t.to_lowercase()).collect();
+        // Per word, whether it belongs to a language (true) or path (false) hint
+        let mut hinted: Vec<Option<bool>> = vec![None; words.len()];
+        let mut languages: Vec<String> = Vec::new();
+        let mut path: Option<String> = None;
+
+        let mut i = 0;
+        while i < words.len() {
+            // An optional "in" and "the" may lead the hint
+            let led_by_in = words[i] == "in";
+            let mut word = if led_by_in { i + 1 } else { i };
+            if words.get(word).is_some_and(|w| w == "the") {
+                word += 1;
+            }
+            let Some(current) = words.get(word) else { break };
+            let noun = words.get(word + 1).map(String::as_str);
+
+            let (end, is_language) = match Self::hint_languages(current) {
+                Some(langs) if led_by_in || noun.is_some_and(|n| LANGUAGE_HINT_NOUNS.contains(&n)) => {
+                    languages.extend(langs.iter().map(|l| l.to_string()));
+                    let end = if noun.is_some_and(|n| LANGUAGE_HINT_NOUNS.contains(&n)) { word + 1 } else { word };
+                    (end, true)
+                }
+                None if led_by_in && path.is_none() && noun.is_some_and(|n| PATH_HINT_NOUNS.contains(&n)) => {
+                    path = Some(parsed.terms[word].clone());
+                    (word + 1, false)
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            hinted[i..=end].iter_mut().for_each(|h| *h = Some(is_language));
+            i = end + 1;
+        }
+
+        if hinted.iter().all(Option::is_some) {
+            return;
+        }
+
+        let use_languages = !languages.is_empty() && parsed.filter.languages.is_none();
+        let use_path = path.is_some() && parsed.filter.path_prefix.is_none();
+
+        if use_languages {
+            languages.dedup();
+            parsed.filter_tokens.push(format!("lang:{}", languages.join(",")));
+            parsed.filter.languages = Some(languages);
+        }
+        if let Some(path) = path.filter(|_| use_path) {
+            parsed.filter_tokens.push(format!("path:{}", path));
+            parsed.filter.path_prefix = Some(path);
+        }
+
+        let mut hinted = hinted.into_iter();
+        parsed.terms.retain(|_| match hinted.next().flatten() {
+            Some(true) => !use_languages,
+            Some(false) => !use_path,
+            None => true,
+        });
+    }
+
+    /// Languages a word in a hint phrase refers to
+    fn hint_languages(word: &str) -> Option<&'static [&'static str]> {
+        match word {
+            "rust" => Some(&["rust"]),
+            "python" => Some(&["python"]),
+            "typescript" => Some(&["typescript"]),
+            "javascript" => Some(&["javascript"]),
+            "react" | "jsx" | "tsx" => Some(&["typescript", "javascript"]),
+            _ => None,
+        }
+    }
+
+    /// Add one `field:value` token to the filter; false if not understood
+    fn apply_field(filter: &mut VectorFilter, field: &str, value: &str, negated: bool) -> bool {
+        let values: Vec<&str> = value.split(',').filter(|v| !v.is_empty()).collect();
+        if values.is_empty() {
+            return false;
+        }
+
+        match field.to_lowercase().as_str() {
+            "kind" => {
+                let Some(kinds) = values.iter().map(|v| SymbolKind::parse(v)).collect::<Option<Vec<_>>>() else {
+                    return false;
+                };
+                let target = if negated { &mut filter.exclude_symbol_kinds } else { &mut filter.symbol_kinds };
+                target.get_or_insert_with(Vec::new).extend(kinds);
+            }
+            "lang" | "language" => {
+                let target = if negated { &mut filter.exclude_languages } else { &mut filter.languages };
+                target
+                    .get_or_insert_with(Vec::new)
+                    .extend(values.iter().map(|v| Self::language_name(v).to_string()));
+            }
+            "path" if negated => {
+                filter
+                    .exclude_paths
+                    .get_or_insert_with(Vec::new)
+                    .extend(values.iter().map(|v| v.to_string()));
+            }
+            "path" => filter.path_prefix = Some(value.to_string()),
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Map language aliases to the names the indexer records
+    fn language_name(alias: &str) -> &str {
+        match alias.to_lowercase().as_str() {
+            "rs" => "rust",
+            "ts" | "tsx" => "typescript",
+            "js" | "jsx" => "javascript",
+            "py" => "python",
+            _ => alias,
+        }
     }
 
     pub fn get_config_for_query(query_type: &QueryType) -> HybridConfig {
@@ -60,6 +359,12 @@ impl QueryAnalyzer {
             },
             QueryType::SemanticIntent => HybridConfig::semantic_focused(),
             QueryType::CodeContent => HybridConfig::content_focused(),
+            QueryType::Pattern => HybridConfig {
+                traditional_weight: 1.0,
+                full_text_weight: 0.0,
+                semantic_weight: 0.0,
+                ..Default::default()
+            },
             QueryType::Mixed => HybridConfig::default(),
         }
     }
@@ -102,6 +407,113 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_query_fields() {
+        let parsed = QueryAnalyzer::parse_query("kind:function,method lang:rs path:src/indexing auth token");
+
+        assert_eq!(parsed.terms, vec!["auth", "token"]);
+        assert_eq!(
+            parsed.filter.symbol_kinds,
+            Some(vec![SymbolKind::Function, SymbolKind::Method])
+        );
+        assert_eq!(parsed.filter.languages, Some(vec!["rust".to_string()]));
+        assert_eq!(parsed.filter.path_prefix.as_deref(), Some("src/indexing"));
+        assert_eq!(parsed.filter_tokens.len(), 3);
+
+        // Unknown fields and values are ordinary search text
+        let parsed = QueryAnalyzer::parse_query("std::fs kind:widget");
+        assert_eq!(parsed.terms, vec!["std::fs", "kind:widget"]);
+        assert!(parsed.filter.is_empty());
+    }
+
+    #[test]
+    fn test_parse_query_exclusions() {
+        let parsed = QueryAnalyzer::parse_query("handler -path:tests/,generated/ -kind:import -Mock");
+
+        assert_eq!(parsed.terms, vec!["handler"]);
+        assert_eq!(parsed.exclude_terms, vec!["mock"]);
+        assert_eq!(
+            parsed.filter.exclude_paths,
+            Some(vec!["tests/".to_string(), "generated/".to_string()])
+        );
+        assert_eq!(parsed.filter.exclude_symbol_kinds, Some(vec![SymbolKind::Import]));
+        assert!(!parsed.filter.matches_fields("rust", "/repo/tests/util.rs", Some(&SymbolKind::Function)));
+        assert!(parsed.filter.matches_fields("rust", "/repo/src/handler.rs", Some(&SymbolKind::Function)));
+
+        // A lone dash is just text
+        assert_eq!(QueryAnalyzer::parse_query("a - b").terms, vec!["a", "-", "b"]);
+    }
+
+    #[test]
+    fn test_classification_confidence() {
+        let confident = QueryAnalyzer::classify_query("parse_config");
+        assert_eq!(confident.query_type, QueryType::ExactSymbol);
+        assert!(confident.confidence >= CONFIDENT);
+
+        let unsure = QueryAnalyzer::classify_query("authentication");
+        assert_eq!(unsure.query_type, QueryType::ExactSymbol);
+        assert_eq!(unsure.alternative, Some(QueryType::SemanticIntent));
+
+        // Blended weights fall between the two presets
+        let exact = QueryAnalyzer::get_config_for_query(&QueryType::ExactSymbol);
+        let semantic = QueryAnalyzer::get_config_for_query(&QueryType::SemanticIntent);
+        let blended = QueryAnalyzer::config_for_classification(&unsure);
+        assert!(blended.semantic_weight > exact.semantic_weight);
+        assert!(blended.semantic_weight < semantic.semantic_weight);
+        assert!(blended.traditional_weight > semantic.traditional_weight);
+
+        let config = QueryAnalyzer::config_for_classification(&confident);
+        assert_eq!(config.traditional_weight, exact.traditional_weight);
+    }
+
+    #[test]
+    fn test_pattern_detection() {
+        for query in ["^handle_.*$", "parse_(config|args)", "get\\w+", "*Service", "src/**/*.rs", "step_?_setup"] {
+            assert_eq!(QueryAnalyzer::analyze_query(query), QueryType::Pattern, "Failed for query: {}", query);
+        }
+        for query in ["what does this do?", "search results ranking", "index_codebase"] {
+            assert_ne!(QueryAnalyzer::analyze_query(query), QueryType::Pattern, "Failed for query: {}", query);
+        }
+
+        let glob = QueryAnalyzer::pattern_regex("*service").unwrap();
+        assert!(glob.is_match("AuthService"));
+        assert!(!glob.is_match("ServiceLocator"));
+
+        let regex = QueryAnalyzer::pattern_regex("^parse_(config|args)$").unwrap();
+        assert!(regex.is_match("parse_args"));
+        assert!(!regex.is_match("parse_query"));
+
+        assert!(QueryAnalyzer::pattern_regex("index_codebase").is_none());
+    }
+
+    #[test]
+    fn test_parse_query_hints() {
+        let parsed = QueryAnalyzer::parse_query("token refresh in the python code");
+        assert_eq!(parsed.terms, vec!["token", "refresh"]);
+        assert_eq!(parsed.filter.languages, Some(vec!["python".to_string()]));
+        assert_eq!(parsed.filter_tokens, vec!["lang:python"]);
+
+        let parsed = QueryAnalyzer::parse_query("routing in the react frontend");
+        assert_eq!(parsed.terms, vec!["routing"]);
+        assert_eq!(
+            parsed.filter.languages,
+            Some(vec!["typescript".to_string(), "javascript".to_string()])
+        );
+
+        let parsed = QueryAnalyzer::parse_query("cache eviction in the indexing module");
+        assert_eq!(parsed.terms, vec!["cache", "eviction"]);
+        assert_eq!(parsed.filter.path_prefix.as_deref(), Some("indexing"));
+
+        // Explicit fields win, and a bare hint stays the query
+        let parsed = QueryAnalyzer::parse_query("lang:rust parser in python");
+        assert_eq!(parsed.filter.languages, Some(vec!["rust".to_string()]));
+        assert_eq!(parsed.terms, vec!["parser", "in", "python"]);
+        assert_eq!(QueryAnalyzer::parse_query("the rust code").terms, vec!["the", "rust", "code"]);
+
+        // Language names elsewhere are ordinary terms
+        assert_eq!(QueryAnalyzer::parse_query("python bindings").terms, vec!["python", "bindings"]);
+    }
+
     #[test]
     fn test_semantic_patterns() {
         let semantic_queries = vec![
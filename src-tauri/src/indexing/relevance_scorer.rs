@@ -1,4 +1,5 @@
 use crate::models::code_index::SymbolKind;
+use serde::{Deserialize, Serialize};
 
 pub struct RelevanceScorer;
 
@@ -16,12 +17,13 @@ impl RelevanceScorer {
             MatchType::Prefix => 0.7,
             MatchType::Contains => 0.5,
             MatchType::Normalized => 0.6,
+            MatchType::Acronym => 0.75,
         };
 
         let length_ratio = query_term.len() as f32 / symbol_name.len() as f32;
         let length_bonus = length_ratio * 0.2;
 
-        let idf = (total_symbols as f32 / term_frequency as f32).ln();
+        let idf = (total_symbols.max(1) as f32 / term_frequency.max(1) as f32).ln().max(0.0);
         let idf_bonus = (idf / 10.0).min(0.3);
 
         (base_score + length_bonus + idf_bonus).min(1.0)
@@ -47,15 +49,42 @@ impl RelevanceScorer {
         let doc_bonus = if has_doc_comment { 0.05 } else { 0.0 };
         (symbol_score * 0.7 + kind_score * 0.3 + doc_bonus).min(1.0)
     }
+
+    /// Score `query` as an in-order, case-insensitive subsequence of `name`
+    /// by how tightly its characters cluster: 1.0 when contiguous, None
+    /// when some character is missing
+    pub fn fuzzy_match_score(query: &str, name: &str) -> Option<f32> {
+        let mut query_chars = query.chars().filter(|c| c.is_alphanumeric()).peekable();
+        query_chars.peek()?;
+
+        let mut first = None;
+        let mut matched = 0;
+        for (i, ch) in name.chars().enumerate() {
+            let Some(wanted) = query_chars.peek() else { break };
+            if ch.to_lowercase().eq(wanted.to_lowercase()) {
+                first.get_or_insert(i);
+                matched += 1;
+                query_chars.next();
+                if query_chars.peek().is_none() {
+                    let span = i + 1 - first.unwrap_or(0);
+                    return Some(matched as f32 / span as f32);
+                }
+            }
+        }
+        None
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// How a traditional-search result's name matched the query term
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MatchType {
     Exact,
     NormalizedExact,
     Prefix,
     Contains,
     Normalized,
+    /// The query spells the initials of the name's words
+    Acronym,
 }
 
 #[cfg(test)]
@@ -81,4 +110,26 @@ mod tests {
         let import_score = RelevanceScorer::score_symbol_kind(&SymbolKind::Import);
         assert!(func_score > import_score);
     }
+
+    #[test]
+    fn test_fuzzy_match_score() {
+        let tight = RelevanceScorer::fuzzy_match_score("hybsrch", "HybridSearcher").unwrap();
+        let contiguous = RelevanceScorer::fuzzy_match_score("search", "HybridSearcher").unwrap();
+        assert!((contiguous - 1.0).abs() < 1e-6);
+        assert!(tight < contiguous);
+
+        assert!(RelevanceScorer::fuzzy_match_score("hsx", "HybridSearcher").is_none());
+        assert!(RelevanceScorer::fuzzy_match_score("", "HybridSearcher").is_none());
+    }
+
+    #[test]
+    fn test_rare_terms_score_higher() {
+        let rare = RelevanceScorer::score_symbol_match("parse_config", "parse", MatchType::Prefix, 1000, 2);
+        let common = RelevanceScorer::score_symbol_match("parse_config", "parse", MatchType::Prefix, 1000, 900);
+        assert!(rare > common);
+
+        // An empty corpus must not produce NaN
+        let score = RelevanceScorer::score_symbol_match("a", "a", MatchType::Contains, 0, 0);
+        assert!(score.is_finite());
+    }
 }
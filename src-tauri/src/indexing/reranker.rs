@@ -0,0 +1,131 @@
+use candle_core::{Device, Module, Tensor};
+use candle_nn::{Linear, VarBuilder};
+use candle_transformers::models::bert::{BertModel, Config as BertConfig};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use crate::indexing::embedding_generator::{select_device, DevicePreference, ModelFiles, ModelSource};
+
+/// Cross-encoder trained on MS MARCO passage ranking
+pub const RERANKER_MODEL_ID: &str = "cross-encoder/ms-marco-MiniLM-L-6-v2";
+
+/// Maximum tokens per (query, passage) pair
+const MAX_PAIR_TOKENS: usize = 512;
+
+/// Scores how relevant each passage is to a query. Higher is better.
+pub trait Reranker {
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>, String>;
+}
+
+/// Re-ranks search results by running query and passage through a BERT
+/// cross-encoder together, which is slower but far more precise than
+/// comparing independently computed embeddings
+pub struct CrossEncoderReranker {
+    model: BertModel,
+    pooler: Linear,
+    classifier: Linear,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl CrossEncoderReranker {
+    /// Load the cross-encoder. `local_path` in the source only applies to the
+    /// embedding model, so it is ignored here.
+    pub fn load(preference: DevicePreference, source: &ModelSource) -> Result<Self, String> {
+        println!("Initializing reranker ({})...", RERANKER_MODEL_ID);
+
+        let device = select_device(preference);
+        let source = ModelSource {
+            local_path: None,
+            ..source.clone()
+        };
+        let files = ModelFiles::resolve_repo(RERANKER_MODEL_ID, &source)?;
+
+        let config_content = std::fs::read_to_string(&files.config)
+            .map_err(|e| format!("Failed to read reranker config: {}", e))?;
+        let config: BertConfig = serde_json::from_str(&config_content)
+            .map_err(|e| format!("Failed to parse reranker config: {}", e))?;
+
+        let mut tokenizer = Tokenizer::from_file(&files.tokenizer)
+            .map_err(|e| format!("Failed to load reranker tokenizer: {}", e))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_PAIR_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| format!("Failed to configure truncation: {}", e))?;
+
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[files.weights], candle_core::DType::F32, &device)
+                .map_err(|e| format!("Failed to load reranker weights: {}", e))?
+        };
+
+        let model = BertModel::load(vb.clone(), &config)
+            .map_err(|e| format!("Failed to create reranker: {}", e))?;
+        let pooler = candle_nn::linear(
+            config.hidden_size,
+            config.hidden_size,
+            vb.pp("bert.pooler.dense"),
+        )
+        .map_err(|e| format!("Failed to load reranker pooler: {}", e))?;
+        let classifier = candle_nn::linear(config.hidden_size, 1, vb.pp("classifier"))
+            .map_err(|e| format!("Failed to load reranker classifier: {}", e))?;
+
+        println!("Reranker ready");
+
+        Ok(Self {
+            model,
+            pooler,
+            classifier,
+            tokenizer,
+            device,
+        })
+    }
+
+    /// Relevance logit for a single (query, passage) pair
+    fn score_pair(&self, query: &str, passage: &str) -> Result<f32, String> {
+        let encoding = self
+            .tokenizer
+            .encode((query, passage), true)
+            .map_err(|e| format!("Tokenization failed: {}", e))?;
+
+        let input_ids = self.to_tensor(encoding.get_ids())?;
+        let token_type_ids = self.to_tensor(encoding.get_type_ids())?;
+        let attention_mask = self.to_tensor(encoding.get_attention_mask())?;
+
+        let output = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))
+            .map_err(|e| format!("Reranker forward failed: {}", e))?;
+
+        // Classification head: tanh(pooler([CLS])) -> linear -> single logit
+        let logits = output
+            .narrow(1, 0, 1)
+            .and_then(|cls| cls.squeeze(1))
+            .and_then(|cls| self.pooler.forward(&cls))
+            .and_then(|pooled| pooled.tanh())
+            .and_then(|pooled| self.classifier.forward(&pooled))
+            .and_then(|logits| logits.flatten_all())
+            .and_then(|logits| logits.to_vec1::<f32>())
+            .map_err(|e| format!("Reranker head failed: {}", e))?;
+
+        logits
+            .first()
+            .copied()
+            .ok_or_else(|| "Reranker produced no score".to_string())
+    }
+
+    fn to_tensor(&self, data: &[u32]) -> Result<Tensor, String> {
+        Tensor::new(data, &self.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(|e| format!("Failed to create tensor: {}", e))
+    }
+}
+
+impl Reranker for CrossEncoderReranker {
+    fn score(&self, query: &str, passages: &[String]) -> Result<Vec<f32>, String> {
+        passages
+            .iter()
+            .map(|passage| self.score_pair(query, passage))
+            .collect()
+    }
+}
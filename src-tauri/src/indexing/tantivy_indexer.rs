@@ -1,10 +1,17 @@
-use crate::models::code_index::{CodeSymbol, SymbolKind};
+use crate::models::code_index::{CodeSymbol, SymbolKind, SymbolSuggestion};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::PathBuf;
 use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::query::{QueryParser, TermQuery};
 use tantivy::schema::*;
-use tantivy::{doc, Index, IndexWriter, ReloadPolicy};
+use tantivy::tokenizer::{LowerCaser, NgramTokenizer, TextAnalyzer};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy};
+
+/// Tokenizer used for the symbol autocomplete field
+const EDGE_NGRAM_TOKENIZER: &str = "edge_ngram";
+/// Longest prefix indexed for autocomplete
+pub const MAX_PREFIX_LEN: usize = 20;
 
 /// Result from a Tantivy full-text search
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,17 +24,91 @@ pub struct TantivySearchResult {
     pub doc_comment: Option<String>,
     pub start_line: usize,
     pub end_line: usize,
-    pub score: f32,
+    pub score: f32,     // Normalized according to FullTextConfig
+    pub raw_score: f32, // BM25 score as returned by Tantivy
+}
+
+/// How raw BM25 scores are mapped before being handed to fusion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreNormalization {
+    /// Keep raw BM25 scores (unbounded, query dependent)
+    None,
+    /// Divide by the best score of the query, so the top hit is 1.0
+    MaxScore,
+    /// Map into [0, 1) with score / (score + saturation), stable across queries
+    Saturating,
+}
+
+/// Tunable ranking parameters for full-text search.
+///
+/// Tantivy 0.22 fixes BM25 at k1 = 1.2 and b = 0.75, so per-field boosts
+/// are the knobs available for shaping the ranking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FullTextConfig {
+    pub symbol_name_boost: f32,
+    pub file_path_boost: f32,
+    pub signature_boost: f32,
+    pub doc_comment_boost: f32,
+    pub normalization: ScoreNormalization,
+    pub saturation: f32,
+}
+
+impl Default for FullTextConfig {
+    fn default() -> Self {
+        Self {
+            symbol_name_boost: 2.0,
+            file_path_boost: 1.0,
+            signature_boost: 1.0,
+            doc_comment_boost: 0.8,
+            normalization: ScoreNormalization::MaxScore,
+            saturation: 5.0,
+        }
+    }
+}
+
+impl FullTextConfig {
+    /// Normalize a raw score given the best raw score of the same query
+    pub fn normalize(&self, raw_score: f32, top_score: f32) -> f32 {
+        match self.normalization {
+            ScoreNormalization::None => raw_score,
+            ScoreNormalization::MaxScore => raw_score / top_score.max(f32::EPSILON),
+            ScoreNormalization::Saturating => {
+                raw_score / (raw_score + self.saturation.max(f32::EPSILON))
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if [
+            self.symbol_name_boost,
+            self.file_path_boost,
+            self.signature_boost,
+            self.doc_comment_boost,
+        ]
+        .iter()
+        .any(|boost| !boost.is_finite() || *boost < 0.0)
+        {
+            return Err("Field boosts must be non-negative".to_string());
+        }
+        if !self.saturation.is_finite() || self.saturation < 0.0 {
+            return Err("saturation must be non-negative".to_string());
+        }
+        Ok(())
+    }
 }
 
 /// Tantivy-based full-text search indexer
 pub struct TantivyIndexer {
     index: Index,
-    writer: IndexWriter,
+    writer: Option<IndexWriter>, // None when opened read-only
+    reader: IndexReader, // Shared across searches, reloaded on commit
     schema: Schema,
     // Field handles for fast access
     symbol_name: Field,
+    symbol_prefix: Field,
     file_path: Field,
+    file_key: Field, // Untokenized path, for deleting a file's documents
     language: Field,
     symbol_kind: Field,
     signature: Field,
@@ -35,6 +116,7 @@ pub struct TantivyIndexer {
     start_line: Field,
     end_line: Field,
     index_dir: PathBuf, // Keep track of index directory
+    config: FullTextConfig,
 }
 
 impl TantivyIndexer {
@@ -42,11 +124,19 @@ impl TantivyIndexer {
     pub fn new<P: Into<PathBuf>>(index_dir: P) -> Result<Self, String> {
         let index_dir = index_dir.into();
 
-        // Build schema with 8 fields
+        // Build schema with 10 fields
         let mut schema_builder = Schema::builder();
 
         let symbol_name = schema_builder.add_text_field("symbol_name", TEXT | STORED);
+        let prefix_indexing = TextFieldIndexing::default()
+            .set_tokenizer(EDGE_NGRAM_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqs);
+        let symbol_prefix = schema_builder.add_text_field(
+            "symbol_prefix",
+            TextOptions::default().set_indexing_options(prefix_indexing),
+        );
         let file_path = schema_builder.add_text_field("file_path", TEXT | STORED);
+        let file_key = schema_builder.add_text_field("file_key", STRING);
         let language = schema_builder.add_text_field("language", STRING | STORED);
         let symbol_kind = schema_builder.add_text_field("symbol_kind", STRING | STORED);
         let signature = schema_builder.add_text_field("signature", TEXT | STORED);
@@ -60,8 +150,23 @@ impl TantivyIndexer {
         std::fs::create_dir_all(&index_dir)
             .map_err(|e| format!("Failed to create index directory: {}", e))?;
 
+        // Indexes written with a different set of fields must be rebuilt
+        let meta_path = index_dir.join("meta.json");
+        if meta_path.exists() {
+            let outdated = Index::open_in_dir(&index_dir)
+                .map(|existing| existing.schema() != schema)
+                .unwrap_or(true);
+            if outdated {
+                println!("Tantivy schema changed, recreating index...");
+                std::fs::remove_dir_all(&index_dir)
+                    .map_err(|e| format!("Failed to remove outdated index: {}", e))?;
+                std::fs::create_dir_all(&index_dir)
+                    .map_err(|e| format!("Failed to create index directory: {}", e))?;
+            }
+        }
+
         // Create or open index in persistent directory
-        let index = if index_dir.join("meta.json").exists() {
+        let index = if meta_path.exists() {
             // Index exists, open it
             Index::open_in_dir(&index_dir)
                 .map_err(|e| format!("Failed to open index: {}", e))?
@@ -70,18 +175,24 @@ impl TantivyIndexer {
             Index::create_in_dir(&index_dir, schema.clone())
                 .map_err(|e| format!("Failed to create index: {}", e))?
         };
+        Self::register_tokenizers(&index)?;
 
         // Create index writer with 50MB buffer
         let writer = index
             .writer(50_000_000)
             .map_err(|e| format!("Failed to create writer: {}", e))?;
 
+        let reader = Self::build_reader(&index)?;
+
         Ok(Self {
             index,
-            writer,
+            writer: Some(writer),
+            reader,
             schema,
             symbol_name,
+            symbol_prefix,
             file_path,
+            file_key,
             language,
             symbol_kind,
             signature,
@@ -89,33 +200,93 @@ impl TantivyIndexer {
             start_line,
             end_line,
             index_dir,
+            config: FullTextConfig::default(),
         })
     }
 
-    /// Load an existing index from disk
-    pub fn load<P: Into<PathBuf>>(index_dir: P) -> Result<Self, String> {
-        Self::new(index_dir)
+    /// Register custom tokenizers (they are not persisted with the index)
+    fn register_tokenizers(index: &Index) -> Result<(), String> {
+        let edge_ngram = NgramTokenizer::prefix_only(1, MAX_PREFIX_LEN)
+            .map_err(|e| format!("Failed to create ngram tokenizer: {}", e))?;
+        let analyzer = TextAnalyzer::builder(edge_ngram).filter(LowerCaser).build();
+        index.tokenizers().register(EDGE_NGRAM_TOKENIZER, analyzer);
+        Ok(())
     }
 
-    /// Clear the index directory (for re-indexing)
-    pub fn clear(&mut self) -> Result<(), String> {
-        // Delete and recreate the index
-        let _ = std::fs::remove_dir_all(&self.index_dir);
-        std::fs::create_dir_all(&self.index_dir)
-            .map_err(|e| format!("Failed to recreate index directory: {}", e))?;
+    /// Build a reader that is reloaded explicitly after each commit
+    fn build_reader(index: &Index) -> Result<IndexReader, String> {
+        index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()
+            .map_err(|e| format!("Failed to create reader: {}", e))
+    }
 
-        // Recreate the index
-        let index = Index::create_in_dir(&self.index_dir, self.schema.clone())
-            .map_err(|e| format!("Failed to create index: {}", e))?;
+    /// Open an existing index for searching only, without taking the writer lock
+    pub fn open_read_only<P: Into<PathBuf>>(index_dir: P) -> Result<Self, String> {
+        let index_dir = index_dir.into();
 
-        let writer = index
-            .writer(50_000_000)
-            .map_err(|e| format!("Failed to create writer: {}", e))?;
+        let index = Index::open_in_dir(&index_dir)
+            .map_err(|e| format!("Failed to open index: {}", e))?;
+        Self::register_tokenizers(&index)?;
 
-        self.index = index;
-        self.writer = writer;
+        let schema = index.schema();
+        let field = |name: &str| {
+            schema
+                .get_field(name)
+                .map_err(|e| format!("Index is missing field {}: {}", name, e))
+        };
 
-        Ok(())
+        // Another process (or the active project) may still be writing to it
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::OnCommitWithDelay)
+            .try_into()
+            .map_err(|e| format!("Failed to create reader: {}", e))?;
+
+        Ok(Self {
+            symbol_name: field("symbol_name")?,
+            symbol_prefix: field("symbol_prefix")?,
+            file_path: field("file_path")?,
+            file_key: field("file_key")?,
+            language: field("language")?,
+            symbol_kind: field("symbol_kind")?,
+            signature: field("signature")?,
+            doc_comment: field("doc_comment")?,
+            start_line: field("start_line")?,
+            end_line: field("end_line")?,
+            index,
+            writer: None,
+            reader,
+            schema,
+            index_dir,
+            config: FullTextConfig::default(),
+        })
+    }
+
+    /// Replace the ranking configuration used by subsequent searches
+    pub fn set_config(&mut self, config: FullTextConfig) {
+        self.config = config;
+    }
+
+    fn writer_mut(&mut self) -> Result<&mut IndexWriter, String> {
+        self.writer
+            .as_mut()
+            .ok_or_else(|| "Index was opened read-only".to_string())
+    }
+
+    /// Load an existing index from disk
+    pub fn load<P: Into<PathBuf>>(index_dir: P) -> Result<Self, String> {
+        Self::new(index_dir)
+    }
+
+    /// Remove all documents (for re-indexing)
+    pub fn clear(&mut self) -> Result<(), String> {
+        // Deleting through the writer keeps the directory lock and schema intact
+        self.writer_mut()?
+            .delete_all_documents()
+            .map_err(|e| format!("Failed to clear index: {}", e))?;
+        self.commit()
     }
 
     /// Add a symbol to the full-text index
@@ -135,7 +306,9 @@ impl TantivyIndexer {
 
         let mut doc = doc!(
             self.symbol_name => symbol.name.clone(),
+            self.symbol_prefix => symbol.name.clone(),
             self.file_path => symbol.file_path.clone(),
+            self.file_key => symbol.file_path.clone(),
             self.language => language.to_string(),
             self.symbol_kind => kind_str.to_string(),
             self.start_line => symbol.start_line as u64,
@@ -151,18 +324,35 @@ impl TantivyIndexer {
             doc.add_text(self.doc_comment, comment);
         }
 
-        self.writer
+        self.writer_mut()?
             .add_document(doc)
             .map_err(|e| format!("Failed to add document: {}", e))?;
 
         Ok(())
     }
 
+    /// Delete every symbol of a file; takes effect at the next commit
+    pub fn remove_file(&mut self, file_path: &str) -> Result<(), String> {
+        let term = Term::from_field_text(self.file_key, file_path);
+        self.writer_mut()?.delete_term(term);
+        Ok(())
+    }
+
+    /// Number of indexed symbols as of the last commit
+    pub fn num_docs(&self) -> u64 {
+        self.reader.searcher().num_docs()
+    }
+
     /// Commit all pending writes
     pub fn commit(&mut self) -> Result<(), String> {
-        self.writer
+        self.writer_mut()?
             .commit()
             .map_err(|e| format!("Failed to commit: {}", e))?;
+
+        // Make the new segments visible to subsequent searches
+        self.reader
+            .reload()
+            .map_err(|e| format!("Failed to reload reader: {}", e))?;
         Ok(())
     }
 
@@ -172,18 +362,21 @@ impl TantivyIndexer {
         query_str: &str,
         limit: usize,
     ) -> Result<Vec<TantivySearchResult>, String> {
-        // Get a reader
-        let reader = self
-            .index
-            .reader_builder()
-            .reload_policy(ReloadPolicy::OnCommitWithDelay)
-            .try_into()
-            .map_err(|e| format!("Failed to create reader: {}", e))?;
+        self.search_page(query_str, 0, limit)
+    }
 
-        let searcher = reader.searcher();
+    /// Search the index, skipping the first `offset` hits
+    pub fn search_page(
+        &self,
+        query_str: &str,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<TantivySearchResult>, String> {
+        // Searchers are cheap snapshots of the cached reader
+        let searcher = self.reader.searcher();
 
         // Build query parser for multiple fields
-        let query_parser = QueryParser::for_index(
+        let mut query_parser = QueryParser::for_index(
             &self.index,
             vec![
                 self.symbol_name,
@@ -192,20 +385,30 @@ impl TantivyIndexer {
                 self.doc_comment,
             ],
         );
+        query_parser.set_field_boost(self.symbol_name, self.config.symbol_name_boost);
+        query_parser.set_field_boost(self.file_path, self.config.file_path_boost);
+        query_parser.set_field_boost(self.signature, self.config.signature_boost);
+        query_parser.set_field_boost(self.doc_comment, self.config.doc_comment_boost);
 
         // Parse query
         let query = query_parser
             .parse_query(query_str)
             .map_err(|e| format!("Failed to parse query: {}", e))?;
 
-        // Search
-        let top_docs = searcher
-            .search(&query, &TopDocs::with_limit(limit))
+        // Search; the best hit is collected alongside the page so scores
+        // stay normalized against it on every page
+        let (best, top_docs) = searcher
+            .search(
+                &query,
+                &(TopDocs::with_limit(1), TopDocs::with_limit(limit).and_offset(offset)),
+            )
             .map_err(|e| format!("Search failed: {}", e))?;
 
+        let top_score = best.first().map(|(score, _)| *score).unwrap_or(1.0);
+
         // Convert results
         let mut results = Vec::new();
-        for (score, doc_address) in top_docs {
+        for (raw_score, doc_address) in top_docs {
             let retrieved_doc: TantivyDocument = searcher
                 .doc(doc_address)
                 .map_err(|e| format!("Failed to retrieve doc: {}", e))?;
@@ -263,10 +466,197 @@ impl TantivyIndexer {
                 doc_comment,
                 start_line,
                 end_line,
-                score,
+                score: self.config.normalize(raw_score, top_score),
+                raw_score,
             });
         }
 
         Ok(results)
     }
+
+    /// Symbol names starting with `prefix`, case-insensitively, looked up
+    /// in the edge-ngram field. Only the first `MAX_PREFIX_LEN` characters
+    /// are indexed, so longer prefixes can return names that diverge after
+    /// that. Ranked by BM25, which favours short names.
+    pub fn suggest_symbols(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<SymbolSuggestion>, String> {
+        let prefix: String = prefix
+            .trim()
+            .to_lowercase()
+            .chars()
+            .take(MAX_PREFIX_LEN)
+            .collect();
+        if prefix.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let searcher = self.reader.searcher();
+        let term = Term::from_field_text(self.symbol_prefix, &prefix);
+        let query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+
+        // Over-fetch since the same name is often defined in several files
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(limit * 4))
+            .map_err(|e| format!("Search failed: {}", e))?;
+
+        let mut seen = HashSet::new();
+        let mut suggestions = Vec::new();
+        for (score, doc_address) in top_docs {
+            let retrieved_doc: TantivyDocument = searcher
+                .doc(doc_address)
+                .map_err(|e| format!("Failed to retrieve doc: {}", e))?;
+
+            let name = retrieved_doc
+                .get_first(self.symbol_name)
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+            let kind = retrieved_doc
+                .get_first(self.symbol_kind)
+                .and_then(|v| v.as_str())
+                .and_then(SymbolKind::parse);
+            let Some(kind) = kind else {
+                continue;
+            };
+            if name.is_empty() || !seen.insert(name.clone()) {
+                continue;
+            }
+
+            suggestions.push(SymbolSuggestion {
+                kind,
+                file_path: retrieved_doc
+                    .get_first(self.file_path)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string(),
+                start_line: retrieved_doc
+                    .get_first(self.start_line)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0) as usize,
+                name,
+                score,
+            });
+            if suggestions.len() == limit {
+                break;
+            }
+        }
+
+        Ok(suggestions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_normalization() {
+        let mut config = FullTextConfig {
+            normalization: ScoreNormalization::MaxScore,
+            ..Default::default()
+        };
+        assert!((config.normalize(4.0, 8.0) - 0.5).abs() < 1e-6);
+
+        config.normalization = ScoreNormalization::Saturating;
+        config.saturation = 2.0;
+        assert!((config.normalize(2.0, 8.0) - 0.5).abs() < 1e-6);
+
+        config.normalization = ScoreNormalization::None;
+        assert_eq!(config.normalize(4.0, 8.0), 4.0);
+    }
+
+    #[test]
+    fn test_full_text_config_validate() {
+        assert!(FullTextConfig::default().validate().is_ok());
+
+        let negative_boost = FullTextConfig {
+            signature_boost: -1.0,
+            ..Default::default()
+        };
+        assert!(negative_boost.validate().is_err());
+
+        let nan_saturation = FullTextConfig {
+            saturation: f32::NAN,
+            ..Default::default()
+        };
+        assert!(nan_saturation.validate().is_err());
+    }
+
+    fn symbol(name: &str, kind: SymbolKind) -> CodeSymbol {
+        CodeSymbol {
+            name: name.to_string(),
+            kind,
+            file_path: "src/search.rs".to_string(),
+            start_line: 1,
+            end_line: 10,
+            signature: None,
+            doc_comment: None,
+            parent: None,
+            calls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_suggest_symbols_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut indexer = TantivyIndexer::new(dir.path()).unwrap();
+
+        indexer.add_symbol(&symbol("HybridSearcher", SymbolKind::Struct), "rust").unwrap();
+        indexer.add_symbol(&symbol("HybridConfig", SymbolKind::Struct), "rust").unwrap();
+        indexer.add_symbol(&symbol("query_index", SymbolKind::Function), "rust").unwrap();
+        indexer.commit().unwrap();
+
+        let suggestions = indexer.suggest_symbols("hyb", 10).unwrap();
+        let names: Vec<_> = suggestions.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(suggestions.len(), 2);
+        assert!(names.contains(&"HybridSearcher"));
+        assert!(names.contains(&"HybridConfig"));
+        assert_eq!(suggestions[0].kind, SymbolKind::Struct);
+
+        assert!(indexer.suggest_symbols("", 10).unwrap().is_empty());
+
+        indexer.clear().unwrap();
+        assert!(indexer.suggest_symbols("hyb", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut indexer = TantivyIndexer::new(dir.path()).unwrap();
+
+        for name in ["parse_config", "parse_args", "parse_query"] {
+            indexer.add_symbol(&symbol(name, SymbolKind::Function), "rust").unwrap();
+        }
+        indexer.commit().unwrap();
+
+        let all = indexer.search("parse", 10).unwrap();
+        let page = indexer.search_page("parse", 1, 10).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].symbol_name, all[1].symbol_name);
+        assert_eq!(page[0].score, all[1].score);
+    }
+
+    #[test]
+    fn test_remove_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut indexer = TantivyIndexer::new(dir.path()).unwrap();
+
+        let mut other = symbol("parse_args", SymbolKind::Function);
+        other.file_path = "src/search.rs.orig".to_string();
+        indexer.add_symbol(&symbol("parse_config", SymbolKind::Function), "rust").unwrap();
+        indexer.add_symbol(&other, "rust").unwrap();
+        indexer.commit().unwrap();
+
+        indexer.remove_file("src/search.rs").unwrap();
+        indexer.commit().unwrap();
+
+        let results = indexer.search("parse", 10).unwrap();
+        assert_eq!(indexer.num_docs(), 1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].symbol_name, "parse_args");
+    }
 }
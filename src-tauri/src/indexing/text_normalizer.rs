@@ -1,29 +1,227 @@
 use rust_stemmers::{Algorithm, Stemmer};
+use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+/// English filler words dropped from normalized text
+const PROSE_STOP_WORDS: &[&str] = &[
+    "the", "a", "an", "and", "or", "but", "in", "on", "at",
+    "to", "for", "of", "with", "by", "from", "as", "is", "was",
+];
+
+/// Words too common in identifiers to be informative in prose, but
+/// meaningful when matching symbol names
+const CODE_STOP_WORDS: &[&str] = &[
+    "get", "set", "new", "old", "tmp", "temp", "var", "fn", "func",
+];
+
+/// Shorthand commonly used in identifiers, mapped to what it abbreviates
+const DEFAULT_SYNONYMS: &[(&str, &[&str])] = &[
+    ("auth", &["authentication", "authorization"]),
+    ("db", &["database"]),
+    ("cfg", &["config", "configuration"]),
+    ("config", &["configuration", "settings"]),
+    ("repo", &["repository"]),
+    ("msg", &["message"]),
+    ("err", &["error"]),
+    ("req", &["request"]),
+    ("res", &["response", "result"]),
+    ("ctx", &["context"]),
+    ("env", &["environment"]),
+    ("impl", &["implementation"]),
+    ("util", &["utility", "helper"]),
+    ("btn", &["button"]),
+    ("idx", &["index"]),
+    ("arg", &["argument"]),
+    ("param", &["parameter"]),
+    ("init", &["initialize"]),
+    ("conn", &["connection"]),
+    ("mgr", &["manager"]),
+    ("svc", &["service"]),
+    ("dir", &["directory"]),
+    ("buf", &["buffer"]),
+];
 
 pub struct TextNormalizer {
-    stemmer: Stemmer,
+    stemmer: Option<Stemmer>,
     stop_words: HashSet<String>,
+    synonyms: HashMap<String, Vec<String>>,
 }
 
-impl TextNormalizer {
-    pub fn new() -> Self {
-        Self {
-            stemmer: Stemmer::create(Algorithm::English),
-            stop_words: Self::create_stop_words(),
+/// Language whose stemming rules reduce words to a common form
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum StemmingLanguage {
+    #[default]
+    English,
+    French,
+    German,
+    Spanish,
+    Portuguese,
+    Italian,
+    Dutch,
+    Swedish,
+    Russian,
+    /// Keep words as written
+    None,
+}
+
+impl StemmingLanguage {
+    fn algorithm(self) -> Option<Algorithm> {
+        match self {
+            StemmingLanguage::English => Some(Algorithm::English),
+            StemmingLanguage::French => Some(Algorithm::French),
+            StemmingLanguage::German => Some(Algorithm::German),
+            StemmingLanguage::Spanish => Some(Algorithm::Spanish),
+            StemmingLanguage::Portuguese => Some(Algorithm::Portuguese),
+            StemmingLanguage::Italian => Some(Algorithm::Italian),
+            StemmingLanguage::Dutch => Some(Algorithm::Dutch),
+            StemmingLanguage::Swedish => Some(Algorithm::Swedish),
+            StemmingLanguage::Russian => Some(Algorithm::Russian),
+            StemmingLanguage::None => None,
         }
     }
+}
 
-    fn create_stop_words() -> HashSet<String> {
-        [
-            "the", "a", "an", "and", "or", "but", "in", "on", "at",
-            "to", "for", "of", "with", "by", "from", "as", "is", "was",
-            "get", "set", "new", "old", "tmp", "temp", "var", "fn", "func",
-        ]
+/// Per-project stop words and stemming
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NormalizerConfig {
+    /// Words dropped from normalized text
+    #[serde(default = "default_stop_words")]
+    pub stop_words: Vec<String>,
+    /// Keep identifier words like get/set/new even when listed as stop words
+    #[serde(default = "default_code_mode")]
+    pub code_mode: bool,
+    #[serde(default)]
+    pub stemming: StemmingLanguage,
+}
+
+fn default_stop_words() -> Vec<String> {
+    PROSE_STOP_WORDS
         .iter()
+        .chain(CODE_STOP_WORDS)
         .map(|s| s.to_string())
         .collect()
+}
+
+fn default_code_mode() -> bool {
+    true
+}
+
+impl Default for NormalizerConfig {
+    fn default() -> Self {
+        Self {
+            stop_words: default_stop_words(),
+            code_mode: default_code_mode(),
+            stemming: StemmingLanguage::default(),
+        }
+    }
+}
+
+/// A query term produced by expansion
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedTerm {
+    pub term: String,
+    /// True for a keyword as the user typed it
+    pub original: bool,
+}
+
+impl TextNormalizer {
+    pub fn new() -> Self {
+        let mut normalizer = Self {
+            stemmer: None,
+            stop_words: HashSet::new(),
+            synonyms: Self::default_synonyms(),
+        };
+        normalizer.set_config(NormalizerConfig::default());
+        normalizer
+    }
+
+    /// Apply new stop words and stemming; the synonym table is kept
+    pub fn set_config(&mut self, config: NormalizerConfig) {
+        self.stemmer = config.stemming.algorithm().map(Stemmer::create);
+        self.stop_words = config
+            .stop_words
+            .iter()
+            .map(|w| w.to_lowercase())
+            .filter(|w| !(config.code_mode && CODE_STOP_WORDS.contains(&w.as_str())))
+            .collect();
+    }
+
+    fn stem(&self, word: &str) -> String {
+        match self.stemmer {
+            Some(ref stemmer) => stemmer.stem(word).to_string(),
+            None => word.to_string(),
+        }
+    }
+
+    pub fn default_synonyms() -> HashMap<String, Vec<String>> {
+        DEFAULT_SYNONYMS
+            .iter()
+            .map(|(term, expansions)| {
+                (term.to_string(), expansions.iter().map(|e| e.to_string()).collect())
+            })
+            .collect()
+    }
+
+    pub fn synonyms(&self) -> &HashMap<String, Vec<String>> {
+        &self.synonyms
+    }
+
+    /// Replace the synonym table. Keys are matched case-insensitively.
+    /// Lookup tables built with the old table must be rebuilt to match.
+    pub fn set_synonyms(&mut self, synonyms: HashMap<String, Vec<String>>) {
+        self.synonyms = synonyms
+            .into_iter()
+            .map(|(term, expansions)| {
+                (
+                    term.to_lowercase(),
+                    expansions.into_iter().map(|e| e.to_lowercase()).collect(),
+                )
+            })
+            .collect();
+    }
+
+    /// Expand query keywords with synonyms (in both directions) and stems,
+    /// so shorthand like "auth" also finds `authenticate_user`. Originals
+    /// come first; each term appears once.
+    pub fn expand_query(&self, keywords: &[String]) -> Vec<ExpandedTerm> {
+        let mut seen = HashSet::new();
+        let mut terms = Vec::new();
+
+        for keyword in keywords {
+            if seen.insert(keyword.to_lowercase()) {
+                terms.push(ExpandedTerm {
+                    term: keyword.clone(),
+                    original: true,
+                });
+            }
+        }
+
+        for keyword in keywords {
+            let lower = keyword.to_lowercase();
+            let mut related: Vec<&str> = Vec::new();
+
+            if let Some(expansions) = self.synonyms.get(&lower) {
+                related.extend(expansions.iter().map(String::as_str));
+            }
+            for (term, expansions) in &self.synonyms {
+                if expansions.contains(&lower) {
+                    related.push(term);
+                }
+            }
+
+            let stem = self.stem(&lower);
+            for term in related.into_iter().map(str::to_string).chain([stem]) {
+                if term.len() > 1 && seen.insert(term.clone()) {
+                    terms.push(ExpandedTerm {
+                        term,
+                        original: false,
+                    });
+                }
+            }
+        }
+
+        terms
     }
 
     /// Normalize text for searching (stem + stop word removal)
@@ -32,22 +230,32 @@ impl TextNormalizer {
             .map(|w| w.to_lowercase())
             .filter(|w| !self.stop_words.contains(w))
             .filter(|w| w.len() > 2)
-            .map(|w| self.stemmer.stem(&w).to_string())
+            .map(|w| self.stem(&w))
             .collect()
     }
 
-    /// Normalize symbol name (handle camelCase/snake_case)
+    /// Normalize symbol name (handle camelCase/snake_case). Abbreviated
+    /// words also contribute their expansions, so `load_cfg` and
+    /// "load configuration" share terms.
     pub fn normalize_symbol(&self, name: &str) -> Vec<String> {
-        let mut tokens = Vec::new();
-
-        for part in name.split('_') {
-            tokens.extend(self.split_camel_case(part));
+        let mut terms = Vec::new();
+        for token in self.symbol_tokens(name).into_iter().filter(|t| t.len() > 1) {
+            let expansions = self.synonyms.get(&token).map(Vec::as_slice).unwrap_or(&[]);
+            for word in std::iter::once(&token).chain(expansions) {
+                let term = self.stem(word);
+                if !terms.contains(&term) {
+                    terms.push(term);
+                }
+            }
         }
+        terms
+    }
 
-        tokens.into_iter()
+    /// The lowercase words of a camelCase or snake_case name, unstemmed
+    pub fn symbol_tokens(&self, name: &str) -> Vec<String> {
+        name.split('_')
+            .flat_map(|part| self.split_camel_case(part))
             .map(|t| t.to_lowercase())
-            .filter(|t| t.len() > 1)
-            .map(|t| self.stemmer.stem(&t).to_string())
             .collect()
     }
 
@@ -99,6 +307,53 @@ mod tests {
         assert!(result.contains(&"handler".to_string()));
     }
 
+    #[test]
+    fn test_normalize_symbol_expands_abbreviations() {
+        let normalizer = TextNormalizer::new();
+        let abbreviated = normalizer.normalize_symbol("loadCfg");
+        let spelled_out = normalizer.normalize("load configuration");
+
+        assert!(abbreviated.contains(&"cfg".to_string()));
+        assert!(spelled_out.iter().all(|term| abbreviated.contains(term)));
+    }
+
+    #[test]
+    fn test_expand_query_synonyms_and_stems() {
+        let normalizer = TextNormalizer::new();
+        let terms: Vec<String> = normalizer
+            .expand_query(&["auth".to_string(), "indexing".to_string()])
+            .into_iter()
+            .map(|t| t.term)
+            .collect();
+
+        assert_eq!(&terms[..2], &["auth".to_string(), "indexing".to_string()]);
+        assert!(terms.contains(&"authentication".to_string()));
+        assert!(terms.contains(&"index".to_string()));
+
+        // Expansions also map back to their shorthand
+        let reverse = normalizer.expand_query(&["Database".to_string()]);
+        assert!(reverse.iter().any(|t| t.term == "db" && !t.original));
+    }
+
+    #[test]
+    fn test_code_mode_keeps_identifier_words() {
+        let mut normalizer = TextNormalizer::new();
+        assert_eq!(normalizer.normalize("get the user"), vec!["get", "user"]);
+
+        normalizer.set_config(NormalizerConfig {
+            code_mode: false,
+            ..Default::default()
+        });
+        assert_eq!(normalizer.normalize("get the user"), vec!["user"]);
+
+        normalizer.set_config(NormalizerConfig {
+            stop_words: vec!["user".to_string()],
+            stemming: StemmingLanguage::None,
+            ..Default::default()
+        });
+        assert_eq!(normalizer.normalize("the indexing user"), vec!["the", "indexing"]);
+    }
+
     #[test]
     fn test_indexing_stems_to_index() {
         let normalizer = TextNormalizer::new();
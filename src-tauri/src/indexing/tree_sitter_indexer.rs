@@ -1,14 +1,31 @@
 use crate::models::code_index::*;
-use crate::indexing::text_normalizer::TextNormalizer;
-use crate::indexing::tantivy_indexer::TantivyIndexer;
-use crate::indexing::embedding_generator::{EmbeddingGenerator, symbol_to_text};
-use crate::indexing::vector_store::{VectorStore, VectorMetadata};
-use crate::indexing::hybrid_search::HybridSearcher;
-use crate::indexing::query_analyzer::QueryAnalyzer;
+use crate::indexing::text_normalizer::{NormalizerConfig, TextNormalizer};
+use crate::indexing::tantivy_indexer::{FullTextConfig, TantivyIndexer, MAX_PREFIX_LEN};
+use crate::indexing::embedding_generator::{
+    file_to_text, symbol_body_chunks, symbol_body_text, symbol_name_text, symbol_to_text,
+    DevicePreference, EmbeddingGenerator, EmbeddingModel, ModelSource,
+};
+#[cfg(feature = "onnx")]
+use crate::indexing::embedding_backend::OnnxEmbedder;
+use crate::indexing::embedding_backend::{EmbeddingBackend, EmbeddingBackendConfig, RemoteEmbedder};
+use crate::indexing::vector_store::{
+    DuplicateCluster, HnswConfig, SearchResult, VectorFacet, VectorFilter, VectorMetadata,
+    VectorStore,
+};
+use crate::indexing::clustering::{cluster_symbols, SemanticCluster};
+use crate::indexing::index_checkpoint::{CheckpointedFile, IndexCheckpoint, StoredVector};
+use crate::indexing::persistence::PinnedChunk;
+use crate::indexing::git_churn::{file_churn, normalized_churn, CHURN_WINDOW_DAYS};
+use crate::indexing::hybrid_search::{recency_boost, HybridConfig, HybridSearcher, RankingSignals};
+use crate::indexing::reranker::{CrossEncoderReranker, Reranker};
+use crate::indexing::query_analyzer::{QueryAnalyzer, QueryType};
+use crate::indexing::relevance_scorer::{MatchType, RelevanceScorer};
+use ignore::overrides::OverrideBuilder;
 use ignore::WalkBuilder;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::Path;
+use std::sync::Mutex;
 use tree_sitter::{Language, Node, Parser};
 
 pub struct TreeSitterIndexer {
@@ -16,17 +33,60 @@ pub struct TreeSitterIndexer {
     queries: HashMap<String, String>,
     normalizer: TextNormalizer,
     tantivy_indexer: Option<TantivyIndexer>,
-    embedding_generator: Option<EmbeddingGenerator>,
+    embedding_generator: Option<Box<dyn EmbeddingBackend>>,
     vector_store: Option<VectorStore>,
+    file_vector_store: Option<VectorStore>, // One vector per file for coarse-to-fine retrieval
     tantivy_path: Option<std::path::PathBuf>,
+    full_text_config: FullTextConfig,
+    embedding_model: EmbeddingModel,
+    device_preference: DevicePreference,
+    model_source: ModelSource,
+    hnsw_config: HnswConfig,
+    backend_config: EmbeddingBackendConfig,
+    reranker: Option<CrossEncoderReranker>,
+    default_hybrid_config: Option<HybridConfig>, // Project's saved search defaults
+    app_hybrid_config: Option<HybridConfig>, // App-wide defaults, below the project's
+    ignore_globs: Vec<String>, // Skipped when indexing, on top of .gitignore
+    multi_query_enabled: bool,
+    file_churn: HashMap<String, f32>, // Normalized recent commit counts per file
+    ranked_results: Mutex<Option<RankedResults>>, // Last ranking, for paging
+}
+
+/// A loaded project's full-text index, vectors and churn while another
+/// project is open
+pub struct ProjectStores {
+    tantivy_indexer: Option<TantivyIndexer>,
+    tantivy_path: Option<std::path::PathBuf>,
+    vector_store: Option<VectorStore>,
+    file_vector_store: Option<VectorStore>,
+    file_churn: HashMap<String, f32>,
+}
+
+/// A query's ranked results kept so later pages skip the searches
+struct RankedResults {
+    key: String,
+    results: Vec<CodeChunk>,
+    /// The ranking returned fewer results than asked for, so no deeper pages exist
+    exhausted: bool,
 }
 
 impl TreeSitterIndexer {
     pub fn new() -> Result<Self, String> {
-        // Initialize embedding generator and vector store
-        let embedding_generator = EmbeddingGenerator::new().ok();
+        // Initialize embedding generator and vector store from files already on
+        // disk; missing models are downloaded in the background with progress
+        let startup_source = ModelSource {
+            offline: true,
+            ..Default::default()
+        };
+        let embedding_generator = EmbeddingGenerator::load(
+            EmbeddingModel::default(),
+            DevicePreference::default(),
+            &startup_source,
+        )
+        .ok()
+        .map(|gen| Box::new(gen) as Box<dyn EmbeddingBackend>);
         let vector_store = if let Some(ref gen) = embedding_generator {
-            VectorStore::new(gen.embedding_dim()).ok()
+            VectorStore::for_model(gen.model_id(), gen.embedding_dim()).ok()
         } else {
             None
         };
@@ -38,7 +98,21 @@ impl TreeSitterIndexer {
             tantivy_indexer: None, // Will be initialized when needed
             embedding_generator,
             vector_store,
+            file_vector_store: None, // Built during indexing
             tantivy_path: None,
+            full_text_config: FullTextConfig::default(),
+            embedding_model: EmbeddingModel::default(),
+            device_preference: DevicePreference::default(),
+            model_source: ModelSource::default(),
+            hnsw_config: HnswConfig::default(),
+            backend_config: EmbeddingBackendConfig::default(),
+            reranker: None,
+            default_hybrid_config: None,
+            app_hybrid_config: None,
+            ignore_globs: Vec::new(),
+            multi_query_enabled: false,
+            file_churn: HashMap::new(),
+            ranked_results: Mutex::new(None),
         };
 
         // Initialize parsers for each language
@@ -57,10 +131,331 @@ impl TreeSitterIndexer {
     pub fn set_tantivy_path<P: Into<std::path::PathBuf>>(&mut self, path: P) -> Result<(), String> {
         let path = path.into();
         self.tantivy_path = Some(path.clone());
-        self.tantivy_indexer = Some(TantivyIndexer::new(path)?);
+        let mut tantivy = TantivyIndexer::new(path)?;
+        tantivy.set_config(self.full_text_config.clone());
+        self.tantivy_indexer = Some(tantivy);
+        Ok(())
+    }
+
+    /// Ranking configuration applied to full-text searches
+    pub fn full_text_config(&self) -> &FullTextConfig {
+        &self.full_text_config
+    }
+
+    /// Update the full-text ranking configuration, including the open index
+    pub fn set_full_text_config(&mut self, config: FullTextConfig) {
+        if let Some(ref mut tantivy) = self.tantivy_indexer {
+            tantivy.set_config(config.clone());
+        }
+        self.full_text_config = config;
+    }
+
+    /// Reload the embedding model on a different compute device.
+    /// Returns the name of the device actually in use after fallback.
+    pub fn set_embedding_device(&mut self, preference: DevicePreference) -> Result<String, String> {
+        let generator = self.load_backend(self.embedding_model, preference)?;
+        let device = generator.device_name().to_string();
+
+        if self.vector_store.is_none() {
+            self.vector_store =
+                VectorStore::with_config(generator.model_id(), generator.embedding_dim(), &self.hnsw_config)
+                    .ok();
+        }
+        self.embedding_generator = Some(generator);
+        self.device_preference = preference;
+
+        Ok(device)
+    }
+
+    /// Pick the compute device without loading the model; the next
+    /// `reload_embedding_generator` uses it
+    pub fn select_embedding_device(&mut self, preference: DevicePreference) {
+        self.device_preference = preference;
+    }
+
+    pub fn query_synonyms(&self) -> &HashMap<String, Vec<String>> {
+        self.normalizer.synonyms()
+    }
+
+    /// Replace the synonym table used to expand keyword and full-text queries
+    pub fn set_query_synonyms(&mut self, synonyms: HashMap<String, Vec<String>>) {
+        self.normalizer.set_synonyms(synonyms);
+    }
+
+    /// Apply a project's stop words and stemming. Term statistics keep the
+    /// old stemming until the project is re-indexed.
+    pub fn set_normalizer_config(&mut self, config: NormalizerConfig) {
+        self.normalizer.set_config(config);
+    }
+
+    /// Enable or disable LLM query rewriting in `query_index`. Each search
+    /// then costs an API call, so it is off by default.
+    pub fn set_multi_query_enabled(&mut self, enabled: bool) {
+        self.multi_query_enabled = enabled;
+    }
+
+    pub fn multi_query_enabled(&self) -> bool {
+        self.multi_query_enabled
+    }
+
+    /// Recount recent commits per file for the churn ranking signal.
+    /// Projects outside git simply get no churn boost.
+    pub fn refresh_file_churn(&mut self, root_path: &str) {
+        self.file_churn = match file_churn(root_path, CHURN_WINDOW_DAYS) {
+            Ok(churn) => normalized_churn(&churn),
+            Err(e) => {
+                println!("No git churn for {}: {}", root_path, e);
+                HashMap::new()
+            }
+        };
+    }
+
+    /// Search config used for queries without their own, in place of the
+    /// query-type presets. None restores the presets.
+    pub fn set_default_hybrid_config(&mut self, config: Option<HybridConfig>) {
+        self.default_hybrid_config = config;
+    }
+
+    /// App-wide search config, used when neither the query nor the project
+    /// has one
+    pub fn set_app_hybrid_config(&mut self, config: Option<HybridConfig>) {
+        self.app_hybrid_config = config;
+    }
+
+    /// Gitignore-style globs of files to leave out of the index
+    pub fn set_ignore_globs(&mut self, globs: Vec<String>) {
+        self.ignore_globs = globs;
+    }
+
+    /// Enable or disable cross-encoder re-ranking of the top hybrid results
+    pub fn set_reranking_enabled(&mut self, enabled: bool) -> Result<(), String> {
+        if !enabled {
+            self.reranker = None;
+        } else if self.reranker.is_none() {
+            self.reranker = Some(CrossEncoderReranker::load(
+                self.device_preference,
+                &self.model_source,
+            )?);
+        }
+        Ok(())
+    }
+
+    pub fn reranking_enabled(&self) -> bool {
+        self.reranker.is_some()
+    }
+
+    /// Change where model files come from and reload the embedding model.
+    /// If no model can be loaded, semantic search is disabled and queries
+    /// fall back to keyword and full-text search.
+    pub fn set_model_source(&mut self, source: ModelSource) -> SemanticStatus {
+        self.select_model_source(source);
+        self.reload_embedding_generator()
+    }
+
+    /// Change where model files come from without reloading; the bundled
+    /// model directory is kept
+    pub fn select_model_source(&mut self, source: ModelSource) {
+        let bundled_dir = self.model_source.bundled_dir.clone();
+        self.model_source = ModelSource {
+            bundled_dir,
+            ..source
+        };
+    }
+
+    /// Register the directory of models bundled with the app. Only triggers a
+    /// load if no model is available yet (e.g. the download failed offline).
+    pub fn set_bundled_model_dir(&mut self, dir: std::path::PathBuf) -> SemanticStatus {
+        self.model_source.bundled_dir = Some(dir);
+        if self.embedding_generator.is_none() {
+            return self.reload_embedding_generator();
+        }
+        self.semantic_status(None)
+    }
+
+    /// Set the directory downloaded models are stored in
+    pub fn set_model_cache_dir(&mut self, dir: std::path::PathBuf) {
+        self.model_source.cache_dir = Some(dir);
+    }
+
+    /// Current model selection and source, used to drive background downloads
+    pub fn model_source(&self) -> &ModelSource {
+        &self.model_source
+    }
+
+    /// The embedding backend semantic search is configured to use
+    pub fn embedding_backend(&self) -> &EmbeddingBackendConfig {
+        &self.backend_config
+    }
+
+    /// Pick the embedding model and backend without loading them; the next
+    /// `reload_embedding_generator` uses them
+    pub fn select_embeddings(&mut self, model: EmbeddingModel, config: EmbeddingBackendConfig) {
+        self.embedding_model = model;
+        self.backend_config = config;
+    }
+
+    /// Switch between local inference and a hosted embedding API.
+    /// Vectors from a different model are discarded, requiring a re-index.
+    pub fn set_embedding_backend(&mut self, config: EmbeddingBackendConfig) -> SemanticStatus {
+        self.backend_config = config;
+        self.reload_embedding_generator()
+    }
+
+    fn load_backend(
+        &self,
+        model: EmbeddingModel,
+        preference: DevicePreference,
+    ) -> Result<Box<dyn EmbeddingBackend>, String> {
+        match self.backend_config {
+            EmbeddingBackendConfig::Local => Ok(Box::new(EmbeddingGenerator::load(
+                model,
+                preference,
+                &self.model_source,
+            )?)),
+            #[cfg(feature = "onnx")]
+            EmbeddingBackendConfig::Onnx => Ok(Box::new(OnnxEmbedder::new(
+                model,
+                self.model_source.cache_dir.clone(),
+            )?)),
+            #[cfg(not(feature = "onnx"))]
+            EmbeddingBackendConfig::Onnx => {
+                Err("This build does not include the ONNX backend (enable the `onnx` feature)".to_string())
+            }
+            EmbeddingBackendConfig::Remote(ref config) => {
+                Ok(Box::new(RemoteEmbedder::new(config.clone())?))
+            }
+        }
+    }
+
+    /// Reload the embedding model from the configured source
+    pub fn reload_embedding_generator(&mut self) -> SemanticStatus {
+        match self.load_backend(self.embedding_model, self.device_preference) {
+            Ok(generator) => {
+                let store_matches = self.vector_store.as_ref().is_some_and(|store| {
+                    store.model_id() == generator.model_id()
+                });
+                if !store_matches {
+                    self.vector_store =
+                        VectorStore::with_config(generator.model_id(), generator.embedding_dim(), &self.hnsw_config)
+                            .ok();
+                    self.file_vector_store = None;
+                }
+                self.embedding_generator = Some(generator);
+                self.semantic_status(None)
+            }
+            Err(e) => {
+                eprintln!("Semantic search disabled: {}", e);
+                self.embedding_generator = None;
+                self.vector_store = None;
+                self.file_vector_store = None;
+                self.semantic_status(Some(e))
+            }
+        }
+    }
+
+    /// Whether semantic search is available and which model/device backs it
+    pub fn semantic_status(&self, error: Option<String>) -> SemanticStatus {
+        SemanticStatus {
+            available: self.embedding_generator.is_some() && self.vector_store.is_some(),
+            model_id: self
+                .embedding_generator
+                .as_ref()
+                .map(|g| g.model_id().to_string())
+                .unwrap_or_else(|| self.embedding_model.model_id().to_string()),
+            device: self.embedding_device().map(String::from),
+            offline: self.model_source.offline,
+            error,
+        }
+    }
+
+    /// The embedding model selected for semantic search
+    pub fn embedding_model(&self) -> EmbeddingModel {
+        self.embedding_model
+    }
+
+    /// Switch to a different embedding model.
+    /// Existing vectors are incompatible, so the vector store starts empty
+    /// and the codebase must be re-indexed.
+    pub fn set_embedding_model(&mut self, model: EmbeddingModel) -> Result<(), String> {
+        if model == self.embedding_model && self.embedding_generator.is_some() {
+            return Ok(());
+        }
+
+        let generator = self.load_backend(model, self.device_preference)?;
+        self.vector_store = Some(VectorStore::with_config(
+            generator.model_id(),
+            generator.embedding_dim(),
+            &self.hnsw_config,
+        )?);
+        self.file_vector_store = None;
+        self.embedding_generator = Some(generator);
+        self.embedding_model = model;
+
         Ok(())
     }
 
+    /// HNSW parameters used for new vector indexes
+    pub fn hnsw_config(&self) -> &HnswConfig {
+        &self.hnsw_config
+    }
+
+    /// Update HNSW parameters. `expansion_search` applies immediately; the
+    /// build parameters take effect on the next index, and cached indexes
+    /// built differently are rebuilt on load. Returns whether a re-index is
+    /// needed for the change to take full effect.
+    pub fn set_hnsw_config(&mut self, config: HnswConfig) -> Result<bool, String> {
+        config.validate()?;
+
+        let mut needs_reindex = false;
+        for store in [&mut self.vector_store, &mut self.file_vector_store].into_iter().flatten() {
+            store.set_expansion_search(config.expansion_search);
+            needs_reindex |= !store.hnsw_config().is_build_compatible(&config);
+        }
+        self.hnsw_config = config;
+
+        Ok(needs_reindex)
+    }
+
+    /// What each vector in the symbol store embeds
+    pub fn vector_metadata(&self) -> Vec<VectorMetadata> {
+        self.vector_store
+            .as_ref()
+            .map(|store| store.all_metadata().map(|(_, metadata)| metadata.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn normalizer(&self) -> &TextNormalizer {
+        &self.normalizer
+    }
+
+    /// Refill the full-text index from already parsed symbols
+    pub fn rebuild_full_text(&mut self, index: &CodebaseIndex) -> Result<(), String> {
+        let Some(ref mut tantivy) = self.tantivy_indexer else {
+            return Ok(());
+        };
+
+        tantivy.clear()?;
+        for file in index.files.values() {
+            for symbol in &file.symbols {
+                tantivy.add_symbol(symbol, &file.language)?;
+            }
+        }
+        tantivy.commit()
+    }
+
+    /// Vectors in the symbol and file stores
+    pub fn vector_counts(&self) -> (usize, usize) {
+        (
+            self.vector_store.as_ref().map_or(0, VectorStore::len),
+            self.file_vector_store.as_ref().map_or(0, VectorStore::len),
+        )
+    }
+
+    /// Name of the device embeddings are computed on, if a model is loaded
+    pub fn embedding_device(&self) -> Option<&'static str> {
+        self.embedding_generator.as_ref().map(|g| g.device_name())
+    }
+
     /// Save vector store to disk
     pub fn save_vector_store<P: AsRef<Path>>(
         &self,
@@ -73,6 +468,36 @@ impl TreeSitterIndexer {
         Ok(())
     }
 
+    /// Save the file-level vector store to disk
+    pub fn save_file_vector_store<P: AsRef<Path>>(
+        &self,
+        index_path: P,
+        metadata_path: P,
+    ) -> Result<(), String> {
+        if let Some(ref store) = self.file_vector_store {
+            store.save(index_path, metadata_path)?;
+        }
+        Ok(())
+    }
+
+    /// Load the file-level vector store from disk
+    pub fn load_file_vector_store<P: AsRef<Path>>(
+        &mut self,
+        index_path: P,
+        metadata_path: P,
+    ) -> Result<(), String> {
+        if let Some(ref gen) = self.embedding_generator {
+            self.file_vector_store = Some(VectorStore::load(
+                index_path,
+                metadata_path,
+                gen.model_id(),
+                gen.embedding_dim(),
+                &self.hnsw_config,
+            )?);
+        }
+        Ok(())
+    }
+
     /// Load vector store from disk
     pub fn load_vector_store<P: AsRef<Path>>(
         &mut self,
@@ -81,11 +506,141 @@ impl TreeSitterIndexer {
     ) -> Result<(), String> {
         if let Some(ref gen) = self.embedding_generator {
             let dimensions = gen.embedding_dim();
-            self.vector_store = Some(VectorStore::load(index_path, metadata_path, dimensions)?);
+            self.vector_store = Some(VectorStore::load(
+                index_path,
+                metadata_path,
+                gen.model_id(),
+                dimensions,
+                &self.hnsw_config,
+            )?);
+        }
+        Ok(())
+    }
+
+    /// Empty symbol and file vector stores for the current embedding model
+    pub fn empty_vector_stores(&self) -> Result<(VectorStore, VectorStore), String> {
+        let gen = self.embedding_generator.as_ref()
+            .ok_or_else(|| "Semantic search not available".to_string())?;
+        Ok((
+            VectorStore::with_config(gen.model_id(), gen.embedding_dim(), &self.hnsw_config)?,
+            VectorStore::with_config(gen.model_id(), gen.embedding_dim(), &self.hnsw_config)?,
+        ))
+    }
+
+    /// Re-embed an already indexed file into the given stores without re-parsing it.
+    /// Unreadable files are skipped; only a model switch mid-migration is an error.
+    pub fn reembed_file(
+        &self,
+        file: &IndexedFile,
+        store: &mut VectorStore,
+        file_store: &mut VectorStore,
+    ) -> Result<(), String> {
+        let gen = self.embedding_generator.as_deref()
+            .ok_or_else(|| "Semantic search not available".to_string())?;
+        if gen.model_id() != store.model_id() {
+            return Err("Embedding model changed during migration".to_string());
+        }
+
+        match fs::read_to_string(&file.path) {
+            Ok(source_code) => embed_file(gen, file, &source_code, Some(store), Some(file_store)),
+            Err(e) => eprintln!("Failed to read {}: {}", file.path, e),
+        }
+        Ok(())
+    }
+
+    /// Re-embed files whose vectors in the loaded stores are outdated, e.g.
+    /// ones re-indexed after the stores were saved. Files no longer in the
+    /// index just lose their vectors.
+    pub fn refresh_file_vectors(
+        &mut self,
+        index: &CodebaseIndex,
+        paths: &BTreeSet<String>,
+    ) -> Result<(), String> {
+        for path in paths {
+            for store in [self.vector_store.as_mut(), self.file_vector_store.as_mut()]
+                .into_iter()
+                .flatten()
+            {
+                store.remove_by_file(path)?;
+            }
+
+            let (Some(file), Some(gen)) = (index.files.get(path), self.embedding_generator.as_deref())
+            else {
+                continue;
+            };
+            match fs::read_to_string(path) {
+                Ok(source_code) => embed_file(
+                    gen,
+                    file,
+                    &source_code,
+                    self.vector_store.as_mut(),
+                    self.file_vector_store.as_mut(),
+                ),
+                Err(e) => eprintln!("Failed to read {}: {}", path, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Swap in vector stores built by a re-embed migration
+    pub fn install_vector_stores(
+        &mut self,
+        store: VectorStore,
+        file_store: VectorStore,
+    ) -> Result<(), String> {
+        let gen = self.embedding_generator.as_ref()
+            .ok_or_else(|| "Semantic search not available".to_string())?;
+        if gen.model_id() != store.model_id() {
+            return Err("Embedding model changed during migration".to_string());
         }
+
+        self.vector_store = Some(store);
+        self.file_vector_store = Some(file_store);
         Ok(())
     }
 
+    /// Drop the loaded vector stores, e.g. while they are being rebuilt
+    pub fn clear_vector_stores(&mut self) {
+        self.vector_store = None;
+        self.file_vector_store = None;
+    }
+
+    /// Drop everything held for the open project: the Tantivy writer (and
+    /// its directory lock), vector stores, churn and cached rankings.
+    /// Models and settings stay loaded.
+    pub fn close_project(&mut self) {
+        drop(self.park_project());
+    }
+
+    /// Take the open project's search stores out of the indexer so another
+    /// project can be opened while this one stays loaded
+    pub fn park_project(&mut self) -> ProjectStores {
+        if let Ok(mut ranked) = self.ranked_results.lock() {
+            *ranked = None;
+        }
+        ProjectStores {
+            tantivy_indexer: self.tantivy_indexer.take(),
+            tantivy_path: self.tantivy_path.take(),
+            vector_store: self.vector_store.take(),
+            file_vector_store: self.file_vector_store.take(),
+            file_churn: std::mem::take(&mut self.file_churn),
+        }
+    }
+
+    /// Reopen a parked project, replacing the open one. Vectors built with
+    /// a model that has since been switched out are dropped.
+    pub fn resume_project(&mut self, stores: ProjectStores) {
+        self.close_project();
+
+        let model_id = self.embedding_generator.as_ref().map(|gen| gen.model_id().to_string());
+        let current = |store: &VectorStore| Some(store.model_id()) == model_id.as_deref();
+        self.tantivy_indexer = stores.tantivy_indexer;
+        self.tantivy_path = stores.tantivy_path;
+        self.vector_store = stores.vector_store.filter(current);
+        self.file_vector_store = stores.file_vector_store.filter(current);
+        self.file_churn = stores.file_churn;
+    }
+
     fn init_parser(&mut self, lang: &str, language: Language) -> Result<(), String> {
         let mut parser = Parser::new();
         parser
@@ -110,17 +665,43 @@ impl TreeSitterIndexer {
         self.queries.insert("python".to_string(), "function_definition,class_definition,import_statement,import_from_statement".to_string());
     }
 
-    /// Main indexing function
-    pub fn index_codebase(&mut self, root_path: &str) -> Result<CodebaseIndex, String> {
+    /// Main indexing function. With a checkpoint, progress is saved
+    /// periodically and files saved by an interrupted run are reused.
+    pub fn index_codebase(
+        &mut self,
+        root_path: &str,
+        checkpoint: Option<&IndexCheckpoint>,
+    ) -> Result<CodebaseIndex, String> {
         let start_time = std::time::Instant::now();
         let mut index = CodebaseIndex::new(root_path.to_string());
 
-        // Walk directory respecting .gitignore
-        let walker = WalkBuilder::new(root_path)
-            .hidden(false)
-            .git_ignore(true)
-            .git_exclude(true)
-            .build();
+        // A full index run rebuilds the full-text and vector stores from scratch
+        if let Some(ref mut tantivy) = self.tantivy_indexer {
+            tantivy.clear()?;
+        }
+        if let Some(ref gen) = self.embedding_generator {
+            self.vector_store =
+                VectorStore::with_config(gen.model_id(), gen.embedding_dim(), &self.hnsw_config).ok();
+            self.file_vector_store =
+                VectorStore::with_config(gen.model_id(), gen.embedding_dim(), &self.hnsw_config).ok();
+        }
+
+        let embedding_model = self
+            .embedding_generator
+            .as_ref()
+            .map(|gen| gen.model_id().to_string());
+        let mut resumed = checkpoint
+            .map(|checkpoint| checkpoint.load(embedding_model.as_deref()))
+            .unwrap_or_default();
+        if !resumed.is_empty() {
+            println!("Resuming from checkpoint with {} indexed files", resumed.len());
+        }
+        let mut pending = Vec::new();
+        let mut last_checkpoint = std::time::Instant::now();
+        let mut current_dir = None;
+
+        // Walk directory respecting .gitignore and the ignore globs
+        let walker = project_walker(root_path, &self.ignore_globs)?;
 
         for entry in walker.filter_map(Result::ok) {
             let path = entry.path();
@@ -129,10 +710,32 @@ impl TreeSitterIndexer {
                 continue;
             }
 
+            // Shards end where a directory does
+            if current_dir.as_deref() != path.parent() {
+                if let Some(checkpoint) = checkpoint {
+                    if !pending.is_empty() && last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                        let files = std::mem::take(&mut pending);
+                        if let Err(e) = checkpoint.save_shard(embedding_model.as_deref(), files) {
+                            eprintln!("Failed to save indexing checkpoint: {}", e);
+                        }
+                        last_checkpoint = std::time::Instant::now();
+                    }
+                }
+                current_dir = path.parent().map(Path::to_path_buf);
+            }
+
             // Determine language from extension
             if let Some(language) = self.detect_language(path) {
+                let saved = resumed
+                    .remove(path.to_string_lossy().as_ref())
+                    .filter(|saved| saved.file.last_modified == modified_secs(path));
+                if let Some(saved) = saved {
+                    self.restore_file(&mut index, saved);
+                    continue;
+                }
+
                 match self.index_file(path, &language) {
-                    Ok(indexed_file) => {
+                    Ok((indexed_file, source_code)) => {
                         // Add to Tantivy
                         if let Some(ref mut tantivy) = self.tantivy_indexer {
                             for symbol in &indexed_file.symbols {
@@ -145,33 +748,21 @@ impl TreeSitterIndexer {
                             }
                         }
 
-                        // Generate embeddings and add to vector store
-                        if let (Some(ref mut gen), Some(ref mut store)) =
-                            (&mut self.embedding_generator, &mut self.vector_store)
-                        {
-                            for symbol in &indexed_file.symbols {
-                                let text = symbol_to_text(symbol);
-                                match gen.embed(&text) {
-                                    Ok(embedding) => {
-                                        let metadata = VectorMetadata {
-                                            symbol_name: symbol.name.clone(),
-                                            file_path: symbol.file_path.clone(),
-                                            language: indexed_file.language.clone(),
-                                            start_line: symbol.start_line,
-                                            end_line: symbol.end_line,
-                                            signature: symbol.signature.clone(),
-                                            doc_comment: symbol.doc_comment.clone(),
-                                        };
-                                        if let Err(e) = store.add(&embedding, metadata) {
-                                            eprintln!("Vector store add failed: {}", e);
-                                        }
-                                    }
-                                    Err(e) => eprintln!("Embedding generation failed: {}", e),
-                                }
-                            }
+                        // Generate embeddings and add to the vector stores
+                        if let Some(gen) = self.embedding_generator.as_deref() {
+                            embed_file(
+                                gen,
+                                &indexed_file,
+                                &source_code,
+                                self.vector_store.as_mut(),
+                                self.file_vector_store.as_mut(),
+                            );
                         }
 
-                        index.add_file(indexed_file);
+                        if checkpoint.is_some() {
+                            pending.push(self.checkpointed_file(indexed_file.clone()));
+                        }
+                        index.add_file(indexed_file, &self.normalizer);
                     }
                     Err(e) => {
                         eprintln!("Failed to index {}: {}", path.display(), e);
@@ -185,6 +776,8 @@ impl TreeSitterIndexer {
             tantivy.commit()?;
         }
 
+        self.refresh_file_churn(root_path);
+
         println!(
             "Indexed {} files in {:?}",
             index.total_files,
@@ -194,8 +787,177 @@ impl TreeSitterIndexer {
         Ok(index)
     }
 
-    /// Index a single file
-    fn index_file(&mut self, path: &Path, language: &str) -> Result<IndexedFile, String> {
+    /// A just-indexed file with the vectors embedded for it
+    fn checkpointed_file(&self, file: IndexedFile) -> CheckpointedFile {
+        let stored = |store: Option<&VectorStore>| -> Vec<StoredVector> {
+            let Some(store) = store else {
+                return Vec::new();
+            };
+            store
+                .ids_for_file(&file.path)
+                .iter()
+                .filter_map(|&id| Some((store.get_vector(id)?, store.get_metadata(id)?.clone())))
+                .collect()
+        };
+        let symbol_vectors = stored(self.vector_store.as_ref());
+        let file_vectors = stored(self.file_vector_store.as_ref());
+        CheckpointedFile {
+            file,
+            symbol_vectors,
+            file_vectors,
+        }
+    }
+
+    /// Add a file saved by an interrupted run without parsing or embedding
+    /// it again
+    fn restore_file(&mut self, index: &mut CodebaseIndex, saved: CheckpointedFile) {
+        if let Some(ref mut tantivy) = self.tantivy_indexer {
+            for symbol in &saved.file.symbols {
+                if let Err(e) = tantivy.add_symbol(symbol, &saved.file.language) {
+                    eprintln!("Tantivy add failed: {}", e);
+                }
+            }
+        }
+        for (store, vectors) in [
+            (self.vector_store.as_mut(), saved.symbol_vectors),
+            (self.file_vector_store.as_mut(), saved.file_vectors),
+        ] {
+            let Some(store) = store else { continue };
+            for (vector, metadata) in vectors {
+                if let Err(e) = store.add(&vector, metadata) {
+                    eprintln!("Vector store add failed: {}", e);
+                }
+            }
+        }
+        index.add_file(saved.file, &self.normalizer);
+    }
+
+    /// Re-parse one file and patch it into the index, the full-text index
+    /// and the vector stores. A file that is gone or no longer a supported
+    /// source file is removed instead. Returns the file's new entry.
+    pub fn reindex_file(
+        &mut self,
+        index: &mut CodebaseIndex,
+        path: &str,
+    ) -> Result<Option<IndexedFile>, String> {
+        // Parse before removing anything so a failure keeps the old entry
+        let file_path = Path::new(path);
+        let parsed = match self.detect_language(file_path) {
+            Some(language) if file_path.is_file() => Some(self.index_file(file_path, &language)?),
+            _ => None,
+        };
+
+        index.remove_file(path, &self.normalizer);
+        if let Some(ref mut tantivy) = self.tantivy_indexer {
+            tantivy.remove_file(path)?;
+        }
+        if let Some(ref mut store) = self.vector_store {
+            store.remove_by_file(path)?;
+        }
+        if let Some(ref mut store) = self.file_vector_store {
+            store.remove_by_file(path)?;
+        }
+
+        let indexed_file = match parsed {
+            Some((indexed_file, source_code)) => {
+                if let Some(ref mut tantivy) = self.tantivy_indexer {
+                    for symbol in &indexed_file.symbols {
+                        tantivy.add_symbol(symbol, &indexed_file.language)?;
+                    }
+                }
+                if let Some(gen) = self.embedding_generator.as_deref() {
+                    embed_file(
+                        gen,
+                        &indexed_file,
+                        &source_code,
+                        self.vector_store.as_mut(),
+                        self.file_vector_store.as_mut(),
+                    );
+                }
+                index.add_file(indexed_file.clone(), &self.normalizer);
+                Some(indexed_file)
+            }
+            None => None,
+        };
+
+        if let Some(ref mut tantivy) = self.tantivy_indexer {
+            tantivy.commit()?;
+        }
+        // Cached rankings may point at the old symbols
+        if let Ok(mut ranked) = self.ranked_results.lock() {
+            *ranked = None;
+        }
+        Ok(indexed_file)
+    }
+
+    /// Give undocumented symbols the docs written for them, updating the
+    /// full-text index and vectors to match. Symbols with a doc comment of
+    /// their own keep it. Docs last until the file is re-parsed. Returns the
+    /// paths of the files changed.
+    pub fn annotate_symbols(
+        &mut self,
+        index: &mut CodebaseIndex,
+        docs: &[SymbolDoc],
+    ) -> Result<Vec<String>, String> {
+        let mut by_file: HashMap<&str, Vec<&SymbolDoc>> = HashMap::new();
+        for doc in docs {
+            by_file.entry(doc.file_path.as_str()).or_default().push(doc);
+        }
+
+        let mut changed = Vec::new();
+        for (path, docs) in by_file {
+            let Some(mut file) = index.files.get(path).cloned() else {
+                continue;
+            };
+            let mut annotated = false;
+            for symbol in file.symbols.iter_mut().filter(|s| s.doc_comment.is_none()) {
+                if let Some(doc) = docs
+                    .iter()
+                    .find(|d| d.name == symbol.name && d.start_line == symbol.start_line)
+                {
+                    symbol.doc_comment = Some(doc.doc.trim().to_string());
+                    annotated = true;
+                }
+            }
+            if !annotated {
+                continue;
+            }
+
+            index.remove_file(path, &self.normalizer);
+            if let Some(ref mut tantivy) = self.tantivy_indexer {
+                tantivy.remove_file(path)?;
+                for symbol in &file.symbols {
+                    tantivy.add_symbol(symbol, &file.language)?;
+                }
+            }
+            index.add_file(file, &self.normalizer);
+            changed.push(path.to_string());
+        }
+
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+        if let Some(ref mut tantivy) = self.tantivy_indexer {
+            tantivy.commit()?;
+        }
+        let paths: BTreeSet<String> = changed.iter().cloned().collect();
+        self.refresh_file_vectors(index, &paths)?;
+        if let Ok(mut ranked) = self.ranked_results.lock() {
+            *ranked = None;
+        }
+        Ok(changed)
+    }
+
+    /// Whether the open full-text index has no documents, e.g. after its
+    /// schema changed and it was recreated
+    pub fn full_text_is_empty(&self) -> bool {
+        self.tantivy_indexer
+            .as_ref()
+            .is_some_and(|tantivy| tantivy.num_docs() == 0)
+    }
+
+    /// Index a single file, also returning its source for body embeddings
+    fn index_file(&mut self, path: &Path, language: &str) -> Result<(IndexedFile, String), String> {
         let source_code = fs::read_to_string(path)
             .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
 
@@ -211,19 +973,17 @@ impl TreeSitterIndexer {
         let symbols = self.extract_symbols(&tree, &source_code, language, path);
         let imports = self.extract_imports(tree.root_node(), &source_code, language);
 
-        Ok(IndexedFile {
+        let indexed_file = IndexedFile {
             path: path.to_string_lossy().to_string(),
             language: language.to_string(),
             symbols,
             imports,
             exports: Vec::new(),
-            last_modified: fs::metadata(path)
-                .ok()
-                .and_then(|m| m.modified().ok())
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs())
-                .unwrap_or(0),
-        })
+            last_modified: modified_secs(path),
+            line_count: source_code.lines().count(),
+        };
+
+        Ok((indexed_file, source_code))
     }
 
     /// Extract symbols using tree-sitter queries
@@ -309,6 +1069,11 @@ impl TreeSitterIndexer {
         } else {
             Some(text.to_string())
         };
+        let calls = if matches!(kind, SymbolKind::Function | SymbolKind::Method) {
+            collect_calls(node, source_code)
+        } else {
+            Vec::new()
+        };
 
         Some(CodeSymbol {
             name,
@@ -319,6 +1084,7 @@ impl TreeSitterIndexer {
             signature,
             doc_comment: None,
             parent: None,
+            calls,
         })
     }
 
@@ -381,43 +1147,106 @@ impl TreeSitterIndexer {
         &self,
         index: &CodebaseIndex,
         query: &IndexQuery,
+        filter: &VectorFilter,
     ) -> Vec<CodeChunk> {
         let mut results = Vec::new();
         let max_results = query.max_results.unwrap_or(50);
 
-        // Three-tier search with normalization
-        for keyword in &query.keywords {
-            // 1. Exact match (score 1.0)
-            if let Some(symbols) = index.symbol_map.get(keyword) {
-                for symbol in symbols {
-                    let mut chunk = self.symbol_to_chunk(symbol, &index.files);
-                    chunk.relevance_score = 1.0;
-                    results.push(chunk);
-                }
+        let allowed = |symbol: &CodeSymbol| {
+            let language = index.files.get(&symbol.file_path)
+                .map(|f| f.language.as_str())
+                .unwrap_or("");
+            filter.matches_fields(language, &symbol.file_path, Some(&symbol.kind))
+        };
+
+        // Tiered search with normalization, over the keywords plus their
+        // synonyms and stems. Expanded terms score lower than typed ones.
+        for expanded in self.normalizer.expand_query(&query.keywords) {
+            let keyword = &expanded.term;
+            let keyword_lower = keyword.to_lowercase();
+            let weight = if expanded.original { 1.0 } else { EXPANDED_TERM_WEIGHT };
+            let mut matches: Vec<(&CodeSymbol, MatchType)> = Vec::new();
+
+            // 1. Exact match
+            if let Some(symbols) = index.symbol_map.get(keyword) {
+                matches.extend(symbols.iter().map(|s| (s, MatchType::Exact)));
             }
 
-            // 2. Normalized match (score 0.8)
+            // 2. Normalized match; a normalized form equal to the whole name
+            // counts as exact
             let normalized_terms = self.normalizer.normalize(keyword);
-            for term in normalized_terms {
-                if let Some(symbols) = index.normalized_symbol_map.get(&term) {
-                    for symbol in symbols {
-                        let mut chunk = self.symbol_to_chunk(symbol, &index.files);
-                        chunk.relevance_score = 0.8;
-                        results.push(chunk);
-                    }
+            for term in &normalized_terms {
+                if let Some(symbols) = index.normalized_symbol_map.get(term) {
+                    matches.extend(symbols.iter().map(|s| {
+                        let exact = normalized_terms.len() == 1 && s.name.to_lowercase() == *term;
+                        (s, if exact { MatchType::NormalizedExact } else { MatchType::Normalized })
+                    }));
                 }
             }
 
-            // 3. Partial match (score 0.5)
-            for (name, symbols) in &index.symbol_map {
-                if name.to_lowercase().contains(&keyword.to_lowercase()) && name != keyword {
-                    for symbol in symbols {
-                        let mut chunk = self.symbol_to_chunk(symbol, &index.files);
-                        chunk.relevance_score = 0.5;
-                        results.push(chunk);
+            // 3. Acronym match, like IDE symbol pickers ("hsc" -> HybridSearchConfig)
+            if (2..=MAX_ACRONYM_LEN).contains(&keyword.len())
+                && keyword.chars().all(|c| c.is_ascii_alphabetic())
+            {
+                for name in index.acronym_map.get(&keyword_lower).into_iter().flatten() {
+                    if let Some(symbols) = index.symbol_map.get(name) {
+                        matches.extend(symbols.iter().map(|s| (s, MatchType::Acronym)));
                     }
                 }
             }
+
+            // 4. Partial match: every word of the keyword starts a word of
+            // the name, found through the token index
+            let mut keyword_tokens = self.normalizer.symbol_tokens(keyword);
+            keyword_tokens.retain(|t| !t.is_empty());
+            let mut candidates: Option<HashSet<&String>> = None;
+            for token in &keyword_tokens {
+                let names: HashSet<&String> = index.names_with_token_prefix(token).collect();
+                candidates = Some(match candidates {
+                    Some(previous) => previous.intersection(&names).copied().collect(),
+                    None => names,
+                });
+            }
+
+            for name in candidates.unwrap_or_default() {
+                if name == keyword {
+                    continue;
+                }
+                let Some(symbols) = index.symbol_map.get(name) else { continue };
+                let match_type = if name.to_lowercase().starts_with(&keyword_lower) {
+                    MatchType::Prefix
+                } else {
+                    MatchType::Contains
+                };
+                matches.extend(symbols.iter().map(|s| (s, match_type)));
+            }
+
+            let term_frequency = index.corpus_stats.frequency(&self.normalizer.normalize_symbol(keyword));
+            for (symbol, match_type) in matches {
+                if !allowed(symbol) {
+                    continue;
+                }
+                let symbol_score = RelevanceScorer::score_symbol_match(
+                    &symbol.name,
+                    keyword,
+                    match_type,
+                    index.corpus_stats.total_symbols,
+                    term_frequency,
+                );
+                let score = RelevanceScorer::calculate_final_score(
+                    symbol_score,
+                    RelevanceScorer::score_symbol_kind(&symbol.kind),
+                    symbol.doc_comment.is_some(),
+                );
+
+                let mut chunk = self.symbol_to_chunk(symbol, &index.files);
+                chunk.relevance_score = score * weight;
+                chunk.score_breakdown = Some(ScoreBreakdown {
+                    match_type: Some(match_type),
+                    ..Default::default()
+                });
+                results.push(chunk);
+            }
         }
 
         // Deduplicate
@@ -434,44 +1263,320 @@ impl TreeSitterIndexer {
         results
     }
 
-    /// Main query method using hybrid search with RRF
+    /// Main query method using hybrid search with RRF. Returns the page
+    /// starting at `query.offset`.
     pub fn query_index(
         &self,
         index: &CodebaseIndex,
         query: &IndexQuery,
     ) -> Vec<CodeChunk> {
+        self.query_page(index, query, |ranked_query| self.rank_query(index, ranked_query))
+    }
+
+    /// Results per page: the query's max_results, else the search config's
+    fn page_size(&self, query: &IndexQuery) -> usize {
+        query.max_results.unwrap_or_else(|| {
+            query.hybrid_config.as_ref()
+                .or(self.default_hybrid_config.as_ref())
+                .or(self.app_hybrid_config.as_ref())
+                .map(|config| config.max_results)
+                .unwrap_or_else(|| HybridConfig::default().max_results)
+        })
+    }
+
+    /// Slice one page out of a query's ranking. A first page always ranks
+    /// afresh, at least `RANKED_POOL_SIZE` deep; later pages reuse that
+    /// ranking while it reaches far enough.
+    fn query_page(
+        &self,
+        index: &CodebaseIndex,
+        query: &IndexQuery,
+        rank: impl FnOnce(&IndexQuery) -> Vec<CodeChunk>,
+    ) -> Vec<CodeChunk> {
+        let offset = query.offset.unwrap_or(0);
+        let end = offset + self.page_size(query);
+        let key = format!(
+            "{}@{}:{:?}",
+            index.root_path,
+            index.indexed_at,
+            IndexQuery { max_results: None, offset: None, ..query.clone() }
+        );
+
+        let page = |results: &[CodeChunk]| -> Vec<CodeChunk> {
+            results.iter().skip(offset).take(end - offset).cloned().collect()
+        };
+
+        if offset > 0 {
+            if let Ok(ranked) = self.ranked_results.lock() {
+                if let Some(ref ranked) = *ranked {
+                    if ranked.key == key && (ranked.exhausted || ranked.results.len() >= end) {
+                        return page(&ranked.results);
+                    }
+                }
+            }
+        }
+
+        let depth = end.max(RANKED_POOL_SIZE);
+        let results = rank(&IndexQuery {
+            max_results: Some(depth),
+            offset: None,
+            ..query.clone()
+        });
+        let first_page = page(&results);
+
+        if let Ok(mut ranked) = self.ranked_results.lock() {
+            *ranked = Some(RankedResults {
+                key,
+                exhausted: results.len() < depth,
+                results,
+            });
+        }
+
+        first_page
+    }
+
+    /// Rank up to `query.max_results` results for a query
+    fn rank_query(
+        &self,
+        index: &CodebaseIndex,
+        query: &IndexQuery,
+    ) -> Vec<CodeChunk> {
+        // Field filters like kind:function apply to every engine; the
+        // remaining terms are the free-text query
+        let mut parsed = QueryAnalyzer::parse_query(&query.keywords.join(" "));
+
+        // The frontend's kind filter; combined with kind: in the query text,
+        // only kinds allowed by both remain
+        if let Some(kinds) = query.symbol_kinds.as_ref().filter(|kinds| !kinds.is_empty()) {
+            let allowed = parsed.filter.symbol_kinds.get_or_insert_with(|| kinds.clone());
+            allowed.retain(|kind| kinds.contains(kind));
+        }
+
+        // Glob patterns resolve to the set of indexed files they select
+        if let Some(patterns) = query.file_patterns.as_ref().filter(|p| !p.is_empty()) {
+            match files_matching_patterns(&index.root_path, index.files.keys(), patterns) {
+                Ok(files) => parsed.filter.file_paths = Some(files.into_iter().collect()),
+                Err(e) => eprintln!("Ignoring file patterns: {}", e),
+            }
+        }
+        if let Some(scope) = query.changed_scope.as_ref().filter(|scope| scope.restrict) {
+            let files = match parsed.filter.file_paths.take() {
+                Some(files) => files.into_iter().filter(|f| scope.files.contains(f)).collect(),
+                None => scope.files.iter().cloned().collect(),
+            };
+            parsed.filter.file_paths = Some(files);
+        }
+        let filter = &parsed.filter;
+        let query = &IndexQuery {
+            keywords: parsed.terms.clone(),
+            ..query.clone()
+        };
+
         let query_text = query.keywords.join(" ");
-        let query_type = QueryAnalyzer::analyze_query(&query_text);
-        let config = query.hybrid_config
+        if query_text.is_empty() {
+            return Vec::new();
+        }
+
+        let classification = QueryAnalyzer::classify_query(&query_text);
+        let query_type = classification.query_type.clone();
+        let mut config = query.hybrid_config
             .clone()
-            .unwrap_or_else(|| QueryAnalyzer::get_config_for_query(&query_type));
+            .or_else(|| self.default_hybrid_config.clone())
+            .or_else(|| self.app_hybrid_config.clone())
+            .unwrap_or_else(|| QueryAnalyzer::config_for_classification(&classification));
+        if let Some(depth) = query.max_results {
+            config.max_results = depth;
+        }
+
+        // Regexes and globs are matched against names; tokenizing them for
+        // the other engines only produces noise
+        if query_type == QueryType::Pattern {
+            if let Some(pattern) = QueryAnalyzer::pattern_regex(&query_text) {
+                let results = self.query_pattern(index, &pattern, config.max_results, filter);
+                return HybridSearcher.search(
+                    &query_text,
+                    results,
+                    Vec::new(),
+                    Vec::new(),
+                    &config,
+                    &RankingSignals::default(),
+                );
+            }
+        }
 
         // Execute all searches
-        let traditional_results = self.query_traditional(index, query);
+        let traditional_results = self.query_traditional(index, query, filter);
 
         let full_text_results = if self.tantivy_indexer.is_some() {
-            self.query_full_text(query)
+            self.query_full_text(query, filter)
         } else {
             Vec::new()
         };
 
         let semantic_results = if self.embedding_generator.is_some() {
-            self.search_semantic(&query_text, config.max_results)
+            self.search_semantic_filtered(&query_text, config.max_results, filter)
+                .map(|results| {
+                    results.into_iter()
+                        .filter(|chunk| chunk.relevance_score >= config.min_similarity)
+                        .collect()
+                })
                 .unwrap_or_else(|_| Vec::new())
         } else {
             Vec::new()
         };
 
+        // Negative terms drop results from every list before fusion
+        let exclude = |results: Vec<CodeChunk>| -> Vec<CodeChunk> {
+            results.into_iter()
+                .filter(|chunk| !mentions_any(chunk, &parsed.exclude_terms))
+                .collect()
+        };
+
         // Combine with hybrid search using RRF
         let hybrid_searcher = HybridSearcher;
         hybrid_searcher.search(
-            traditional_results,
-            full_text_results,
-            semantic_results,
+            &query_text,
+            exclude(traditional_results),
+            exclude(full_text_results),
+            exclude(semantic_results),
             &config,
+            &RankingSignals {
+                file_boosts: self.file_boosts(index, &config, query.changed_scope.as_ref()),
+                reranker: self.reranker.as_ref().map(|r| r as &dyn Reranker),
+            },
         )
     }
 
+    /// Per-file score boosts for fusion, from how recently each file changed
+    /// and how often it was committed to lately, plus a flat boost for the
+    /// files of a non-restricting changed scope
+    fn file_boosts(
+        &self,
+        index: &CodebaseIndex,
+        config: &HybridConfig,
+        changed: Option<&ChangedScope>,
+    ) -> HashMap<String, f32> {
+        let mut boosts = self.history_boosts(index, config);
+        if let Some(scope) = changed.filter(|scope| !scope.restrict) {
+            for path in &scope.files {
+                *boosts.entry(path.clone()).or_insert(0.0) += CHANGED_FILE_BOOST;
+            }
+        }
+        boosts
+    }
+
+    fn history_boosts(&self, index: &CodebaseIndex, config: &HybridConfig) -> HashMap<String, f32> {
+        if config.recency_weight <= 0.0 && config.churn_weight <= 0.0 {
+            return HashMap::new();
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        index.files.values()
+            .map(|file| {
+                let churn = self.file_churn.get(&file.path).copied().unwrap_or(0.0);
+                let boost = recency_boost(file.last_modified, now, config)
+                    + config.churn_weight * churn;
+                (file.path.clone(), boost)
+            })
+            .filter(|(_, boost)| *boost > 0.0)
+            .collect()
+    }
+
+    /// Run the hybrid query once per phrasing (the original keywords first)
+    /// and fuse the result lists. Later pages of the same query are served
+    /// from the fused ranking.
+    pub fn query_index_multi(
+        &self,
+        index: &CodebaseIndex,
+        query: &IndexQuery,
+        rewrites: &[String],
+    ) -> Vec<CodeChunk> {
+        self.query_page(index, query, |ranked_query| {
+            self.rank_query_variants(index, ranked_query, rewrites)
+        })
+    }
+
+    fn rank_query_variants(
+        &self,
+        index: &CodebaseIndex,
+        query: &IndexQuery,
+        rewrites: &[String],
+    ) -> Vec<CodeChunk> {
+        // Rewrites are free text only, so carry the original field filters over
+        let filter_tokens = QueryAnalyzer::parse_query(&query.keywords.join(" ")).filter_tokens;
+
+        let mut result_lists = vec![self.rank_query(index, query)];
+        for rewrite in rewrites {
+            let variant = IndexQuery {
+                keywords: filter_tokens.iter()
+                    .cloned()
+                    .chain(rewrite.split_whitespace().map(String::from))
+                    .collect(),
+                ..query.clone()
+            };
+            result_lists.push(self.rank_query(index, &variant));
+        }
+
+        let config = HybridConfig {
+            max_results: query.max_results.unwrap_or(50),
+            ..Default::default()
+        };
+        HybridSearcher.fuse_query_variants(result_lists, &config)
+    }
+
+    /// Symbols whose names match a regex or glob. Patterns containing `/`
+    /// match the file path relative to the root instead. Whole-name matches
+    /// rank above partial ones.
+    fn query_pattern(
+        &self,
+        index: &CodebaseIndex,
+        pattern: &regex::Regex,
+        max_results: usize,
+        filter: &VectorFilter,
+    ) -> Vec<CodeChunk> {
+        let match_paths = pattern.as_str().contains('/');
+        let mut results = Vec::new();
+
+        for (name, symbols) in &index.symbol_map {
+            for symbol in symbols {
+                let haystack = if match_paths {
+                    symbol.file_path
+                        .strip_prefix(&index.root_path)
+                        .unwrap_or(&symbol.file_path)
+                        .trim_start_matches(['/', '\\'])
+                } else {
+                    name.as_str()
+                };
+                let Some(found) = pattern.find(haystack) else { continue };
+
+                let language = index.files.get(&symbol.file_path)
+                    .map(|f| f.language.as_str())
+                    .unwrap_or("");
+                if !filter.matches_fields(language, &symbol.file_path, Some(&symbol.kind)) {
+                    continue;
+                }
+
+                let mut chunk = self.symbol_to_chunk(symbol, &index.files);
+                chunk.relevance_score = if found.len() == haystack.len() { 1.0 } else { 0.5 };
+                results.push(chunk);
+            }
+        }
+
+        results = self.deduplicate_results(results);
+        results.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.symbols.cmp(&b.symbols))
+        });
+        results.truncate(max_results);
+        results
+    }
+
     fn symbol_to_chunk(
         &self,
         symbol: &CodeSymbol,
@@ -488,19 +1593,27 @@ impl TreeSitterIndexer {
                 .unwrap_or_else(|| "unknown".to_string()),
             symbols: vec![symbol.name.clone()],
             relevance_score: 1.0,
+            score_breakdown: None,
         }
     }
 
-    fn query_full_text(&self, query: &IndexQuery) -> Vec<CodeChunk> {
+    fn query_full_text(&self, query: &IndexQuery, filter: &VectorFilter) -> Vec<CodeChunk> {
         let tantivy = match self.tantivy_indexer.as_ref() {
             Some(t) => t,
             None => return Vec::new(),
         };
 
-        let query_str = query.keywords.join(" OR ");
+        let query_str = self.normalizer
+            .expand_query(&query.keywords)
+            .into_iter()
+            .map(|t| t.term)
+            .collect::<Vec<_>>()
+            .join(" OR ");
         let max_results = query.max_results.unwrap_or(50);
 
-        let results = match tantivy.search(&query_str, max_results) {
+        // Filters are applied afterwards, so fetch extra candidates
+        let fetch = if filter.is_empty() { max_results } else { max_results * 4 };
+        let results = match tantivy.search(&query_str, fetch) {
             Ok(r) => r,
             Err(e) => {
                 eprintln!("Tantivy search failed: {}", e);
@@ -509,6 +1622,14 @@ impl TreeSitterIndexer {
         };
 
         results.into_iter()
+            .filter(|r| {
+                filter.matches_fields(
+                    &r.language,
+                    &r.file_path,
+                    SymbolKind::parse(&r.symbol_kind).as_ref(),
+                )
+            })
+            .take(max_results)
             .map(|r| CodeChunk {
                 file_path: r.file_path,
                 start_line: r.start_line,
@@ -517,6 +1638,7 @@ impl TreeSitterIndexer {
                 language: r.language,
                 symbols: vec![r.symbol_name],
                 relevance_score: r.score,
+                score_breakdown: None,
             })
             .collect()
     }
@@ -574,11 +1696,173 @@ impl TreeSitterIndexer {
         matches.into_iter().map(|(path, _)| path).collect()
     }
 
+    /// Read pinned chunks from disk for prompt context. A pin on a symbol
+    /// follows the symbol if its lines moved; pins on files that can no
+    /// longer be read are skipped.
+    pub fn pinned_chunks(&self, index: &CodebaseIndex, pins: &[PinnedChunk]) -> Vec<CodeChunk> {
+        pins.iter()
+            .filter_map(|pin| {
+                let file = index.files.get(&pin.file_path);
+                let (start_line, end_line) = pin
+                    .symbol_name
+                    .as_ref()
+                    .and_then(|name| file?.symbols.iter().find(|s| &s.name == name))
+                    .map(|s| (s.start_line, s.end_line))
+                    .unwrap_or((pin.start_line, pin.end_line));
+
+                let source_code = match fs::read_to_string(&pin.file_path) {
+                    Ok(source_code) => source_code,
+                    Err(e) => {
+                        eprintln!("Failed to read pinned file {}: {}", pin.file_path, e);
+                        return None;
+                    }
+                };
+                let content = source_code
+                    .lines()
+                    .skip(start_line.saturating_sub(1))
+                    .take((end_line + 1).saturating_sub(start_line.max(1)))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+
+                Some(CodeChunk {
+                    file_path: pin.file_path.clone(),
+                    start_line,
+                    end_line,
+                    content,
+                    language: file
+                        .map(|f| f.language.clone())
+                        .or_else(|| self.detect_language(Path::new(&pin.file_path)))
+                        .unwrap_or_else(|| "unknown".to_string()),
+                    symbols: pin.symbol_name.iter().cloned().collect(),
+                    relevance_score: 1.0,
+                    score_breakdown: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Symbol name completions. Names starting with the prefix rank first,
+    /// looked up in the full-text index's edge-ngram field, then names
+    /// whose words start with the prefix's words, then fuzzy (in-order
+    /// subsequence) matches from the in-memory index; shorter names win
+    /// within a tier.
+    pub fn suggest_symbols(
+        &self,
+        index: &CodebaseIndex,
+        prefix: &str,
+        limit: usize,
+    ) -> Vec<SymbolSuggestion> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+        let prefix_lower = prefix.to_lowercase();
+        let length_bonus = |name: &str| prefix.len() as f32 / name.len().max(prefix.len()) as f32 * 0.1;
+
+        // Names are scanned instead when the prefix is longer than the
+        // field indexes or there is no full-text index
+        let indexed = self
+            .tantivy_indexer
+            .as_ref()
+            .filter(|_| prefix.chars().count() <= MAX_PREFIX_LEN)
+            .and_then(|tantivy| match tantivy.suggest_symbols(prefix, limit) {
+                Ok(suggestions) => Some(suggestions),
+                Err(e) => {
+                    eprintln!("Prefix lookup failed: {}", e);
+                    None
+                }
+            });
+        let prefix_names: Vec<&String> = match &indexed {
+            Some(suggestions) => suggestions
+                .iter()
+                .filter_map(|s| index.symbol_map.get_key_value(&s.name).map(|(name, _)| name))
+                .collect(),
+            None => index
+                .symbol_map
+                .keys()
+                .filter(|name| name.to_lowercase().starts_with(&prefix_lower))
+                .collect(),
+        };
+
+        let mut scores: HashMap<&String, f32> = HashMap::new();
+        for name in prefix_names {
+            if name.to_lowercase() == prefix_lower {
+                scores.insert(name, 1.0);
+            } else {
+                scores.insert(name, 0.9 + length_bonus(name));
+            }
+        }
+
+        let mut prefix_tokens = self.normalizer.symbol_tokens(prefix);
+        prefix_tokens.retain(|t| !t.is_empty());
+        let mut candidates: Option<HashSet<&String>> = None;
+        for token in &prefix_tokens {
+            let names: HashSet<&String> = index.names_with_token_prefix(token).collect();
+            candidates = Some(match candidates {
+                Some(previous) => previous.intersection(&names).copied().collect(),
+                None => names,
+            });
+        }
+        for name in candidates.unwrap_or_default() {
+            scores.entry(name).or_insert_with(|| 0.7 + length_bonus(name));
+        }
+
+        // Fuzzy matches only matter when the better tiers leave room
+        if scores.len() < limit {
+            for name in index.symbol_map.keys() {
+                if scores.contains_key(name) {
+                    continue;
+                }
+                if let Some(tightness) = RelevanceScorer::fuzzy_match_score(prefix, name) {
+                    scores.insert(name, 0.5 * tightness + length_bonus(name));
+                }
+            }
+        }
+
+        let mut ranked: Vec<(&String, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.len().cmp(&b.0.len()))
+                .then_with(|| a.0.cmp(b.0))
+        });
+
+        ranked
+            .into_iter()
+            .filter_map(|(name, score)| {
+                let symbols = index.symbol_map.get(name)?;
+                // Point at a definition rather than an import where there is one
+                let symbol = symbols
+                    .iter()
+                    .find(|s| !matches!(s.kind, SymbolKind::Import | SymbolKind::Export))
+                    .or_else(|| symbols.first())?;
+                Some(SymbolSuggestion {
+                    name: name.clone(),
+                    kind: symbol.kind.clone(),
+                    file_path: symbol.file_path.clone(),
+                    start_line: symbol.start_line,
+                    score,
+                })
+            })
+            .take(limit)
+            .collect()
+    }
+
     /// Semantic search using embeddings
     pub fn search_semantic(
         &self,
         query: &str,
         max_results: usize,
+    ) -> Result<Vec<CodeChunk>, String> {
+        self.search_semantic_filtered(query, max_results, &VectorFilter::default())
+    }
+
+    /// Semantic search restricted to vectors matching a metadata filter
+    pub fn search_semantic_filtered(
+        &self,
+        query: &str,
+        max_results: usize,
+        filter: &VectorFilter,
     ) -> Result<Vec<CodeChunk>, String> {
         let generator = self.embedding_generator.as_ref()
             .ok_or_else(|| "Embedding generator not available".to_string())?;
@@ -587,13 +1871,103 @@ impl TreeSitterIndexer {
             .ok_or_else(|| "Vector store not available".to_string())?;
 
         // Generate embedding for query
-        let query_embedding = generator.embed(query)?;
+        let query_embedding = generator.embed_query(query)?;
+
+        // Search vector store. Symbols own several name and body vectors,
+        // so over-fetch and fuse the hits per parent symbol.
+        let results = vector_store.search_filtered(&query_embedding, max_results * 3, filter)?;
+        let mut chunks = fuse_symbol_hits(results, is_identifier_query(query));
+        chunks.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        chunks.truncate(max_results);
+
+        Ok(chunks)
+    }
+
+    /// Two-stage semantic search: find the files most similar to the query,
+    /// then rank symbols within them. Symbol scores are weighted by their
+    /// file's similarity so strong files lift their symbols.
+    pub fn search_coarse_to_fine(
+        &self,
+        query: &str,
+        max_results: usize,
+        candidate_files: usize,
+    ) -> Result<Vec<CodeChunk>, String> {
+        let generator = self.embedding_generator.as_ref()
+            .ok_or_else(|| "Embedding generator not available".to_string())?;
 
-        // Search vector store
-        let results = vector_store.search(&query_embedding, max_results)?;
+        let file_store = self.file_vector_store.as_ref()
+            .ok_or_else(|| "File vectors not available, re-index the codebase".to_string())?;
 
-        // Convert to CodeChunk
-        Ok(results.into_iter()
+        let vector_store = self.vector_store.as_ref()
+            .ok_or_else(|| "Vector store not available".to_string())?;
+
+        let query_embedding = generator.embed_query(query)?;
+
+        // Stage 1: candidate files
+        let file_scores: HashMap<String, f32> = file_store
+            .search(&query_embedding, candidate_files)?
+            .into_iter()
+            .map(|r| (r.metadata.file_path, r.similarity))
+            .collect();
+
+        if file_scores.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Stage 2: symbols within those files
+        let filter = VectorFilter {
+            file_paths: Some(file_scores.keys().cloned().collect()),
+            ..Default::default()
+        };
+        let results = vector_store.search_filtered(&query_embedding, max_results * 3, &filter)?;
+
+        let mut chunks = fuse_symbol_hits(results, is_identifier_query(query));
+        for chunk in &mut chunks {
+            let file_score = file_scores.get(&chunk.file_path).copied().unwrap_or(0.0);
+            chunk.relevance_score = 0.7 * chunk.relevance_score + 0.3 * file_score;
+        }
+        chunks.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        chunks.truncate(max_results);
+
+        Ok(chunks)
+    }
+
+    /// Symbols whose embeddings are closest to an already indexed symbol
+    pub fn find_similar_symbols(
+        &self,
+        file_path: &str,
+        symbol_name: &str,
+        k: usize,
+    ) -> Result<Vec<CodeChunk>, String> {
+        let store = self.vector_store.as_ref()
+            .ok_or_else(|| "Vector store not available".to_string())?;
+
+        let id = store.find_symbol(file_path, symbol_name)
+            .ok_or_else(|| format!("Symbol {} not found in {}", symbol_name, file_path))?;
+        let source = store.get_metadata(id)
+            .ok_or_else(|| format!("Symbol {} not found in {}", symbol_name, file_path))?;
+        let vector = store.get_vector(id)
+            .ok_or_else(|| format!("No vector stored for {}", symbol_name))?;
+
+        // Compare whole symbols only; name and body vectors crowd the
+        // neighborhood, so over-fetch
+        let results = store.search(&vector, (k + 1) * 4)?;
+
+        let chunks: Vec<CodeChunk> = results.into_iter()
+            .filter(|r| r.metadata.is_whole_symbol())
+            .filter(|r| {
+                !(r.metadata.file_path == source.file_path
+                    && r.metadata.start_line == source.start_line
+                    && r.metadata.end_line == source.end_line)
+            })
             .map(|r| CodeChunk {
                 file_path: r.metadata.file_path,
                 start_line: r.metadata.start_line,
@@ -602,21 +1976,45 @@ impl TreeSitterIndexer {
                 language: r.metadata.language,
                 symbols: vec![r.metadata.symbol_name],
                 relevance_score: r.similarity,
+                score_breakdown: None,
             })
-            .collect())
+            .collect();
+
+        let mut chunks = self.deduplicate_results(chunks);
+        chunks.sort_by(|a, b| {
+            b.relevance_score
+                .partial_cmp(&a.relevance_score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        chunks.truncate(k);
+
+        Ok(chunks)
+    }
+
+    /// Clusters of near-identical symbols, for refactoring and for
+    /// deduplicating prompt context
+    pub fn find_duplicates(&self, threshold: f32) -> Result<Vec<DuplicateCluster>, String> {
+        self.vector_store.as_ref()
+            .ok_or_else(|| "Vector store not available".to_string())?
+            .find_duplicates(threshold)
+    }
+
+    /// Group symbols into semantic clusters for a map of the codebase
+    pub fn cluster_symbols(&self, k: Option<usize>) -> Result<Vec<SemanticCluster>, String> {
+        let store = self.vector_store.as_ref()
+            .ok_or_else(|| "Vector store not available".to_string())?;
+        cluster_symbols(store, k)
     }
 
-    /// Collect file timestamps for cache validation
+    /// Collect file timestamps for cache validation, skipping the same
+    /// files indexing does
     pub fn collect_file_timestamps(
         root_path: &str,
+        ignore_globs: &[String],
     ) -> Result<HashMap<String, u64>, String> {
         let mut timestamps = HashMap::new();
 
-        let walker = WalkBuilder::new(root_path)
-            .hidden(false)
-            .git_ignore(true)
-            .git_exclude(true)
-            .build();
+        let walker = project_walker(root_path, ignore_globs)?;
 
         for entry in walker.filter_map(Result::ok) {
             let path = entry.path();
@@ -643,3 +2041,293 @@ impl TreeSitterIndexer {
         Ok(timestamps)
     }
 }
+
+/// Embed a file's symbols, the body chunks of long symbols and a file summary
+fn embed_file(
+    gen: &dyn EmbeddingBackend,
+    indexed_file: &IndexedFile,
+    source_code: &str,
+    store: Option<&mut VectorStore>,
+    file_store: Option<&mut VectorStore>,
+) {
+    if let Some(store) = store {
+        let mut texts = Vec::new();
+        let mut entries = Vec::new();
+
+        for symbol in &indexed_file.symbols {
+            let metadata = VectorMetadata {
+                symbol_name: symbol.name.clone(),
+                file_path: symbol.file_path.clone(),
+                language: indexed_file.language.clone(),
+                start_line: symbol.start_line,
+                end_line: symbol.end_line,
+                signature: symbol.signature.clone(),
+                doc_comment: symbol.doc_comment.clone(),
+                symbol_kind: Some(symbol.kind.clone()),
+                chunk_lines: None,
+                facet: VectorFacet::Symbol,
+            };
+
+            // The symbol itself, then its bare name when docs would dilute it
+            texts.push(symbol_to_text(symbol));
+            entries.push(metadata.clone());
+            if symbol.doc_comment.is_some() {
+                texts.push(symbol_name_text(symbol));
+                entries.push(VectorMetadata {
+                    facet: VectorFacet::Name,
+                    ..metadata.clone()
+                });
+            }
+
+            // Then its source: whole for short symbols, overlapping slices of
+            // long bodies whose logic the truncated signature doesn't reach
+            if let Some(body) = symbol_body_text(symbol, source_code) {
+                texts.push(body);
+                entries.push(VectorMetadata {
+                    facet: VectorFacet::Body,
+                    ..metadata.clone()
+                });
+            }
+            for chunk in symbol_body_chunks(symbol, source_code) {
+                texts.push(chunk.text);
+                entries.push(VectorMetadata {
+                    chunk_lines: Some((chunk.start_line, chunk.end_line)),
+                    facet: VectorFacet::Body,
+                    ..metadata.clone()
+                });
+            }
+        }
+
+        // One batch per file keeps remote backends to a few requests
+        match gen.embed_batch(&texts) {
+            Ok(embeddings) => {
+                for (embedding, metadata) in embeddings.iter().zip(entries) {
+                    if let Err(e) = store.add(embedding, metadata) {
+                        eprintln!("Vector store add failed: {}", e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Embedding generation failed: {}", e),
+        }
+    }
+
+    // One coarse vector per file, summarizing what it contains
+    if let Some(store) = file_store {
+        let metadata = VectorMetadata {
+            symbol_name: Path::new(&indexed_file.path)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            file_path: indexed_file.path.clone(),
+            language: indexed_file.language.clone(),
+            start_line: 1,
+            end_line: source_code.lines().count().max(1),
+            signature: None,
+            doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
+        };
+        match gen.embed(&file_to_text(indexed_file, source_code)) {
+            Ok(embedding) => {
+                if let Err(e) = store.add(&embedding, metadata) {
+                    eprintln!("File vector add failed: {}", e);
+                }
+            }
+            Err(e) => eprintln!("File embedding failed: {}", e),
+        }
+    }
+}
+
+/// A file's modification time in seconds, 0 if unknown
+pub fn modified_secs(path: &Path) -> u64 {
+    fs::metadata(path)
+        .ok()
+        .and_then(|m| m.modified().ok())
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Whether a result mentions any of the (lowercase) terms in its symbol
+/// names, signature or file path
+fn mentions_any(chunk: &CodeChunk, terms: &[String]) -> bool {
+    if terms.is_empty() {
+        return false;
+    }
+
+    let haystack = format!(
+        "{} {} {}",
+        chunk.symbols.join(" "),
+        chunk.content,
+        chunk.file_path
+    )
+    .to_lowercase();
+    terms.iter().any(|term| haystack.contains(term.as_str()))
+}
+
+/// Walk a project's files, honoring .gitignore and skipping files matched
+/// by any of the gitignore-style `ignore_globs`
+fn project_walker(root_path: &str, ignore_globs: &[String]) -> Result<ignore::Walk, String> {
+    let mut builder = OverrideBuilder::new(root_path);
+    for glob in ignore_globs {
+        builder
+            .add(&format!("!{}", glob))
+            .map_err(|e| format!("Invalid ignore glob '{}': {}", glob, e))?;
+    }
+    let overrides = builder
+        .build()
+        .map_err(|e| format!("Failed to build ignore globs: {}", e))?;
+
+    Ok(WalkBuilder::new(root_path)
+        .hidden(false)
+        .git_ignore(true)
+        .git_exclude(true)
+        .overrides(overrides)
+        .build())
+}
+
+/// Select the files matched by gitignore-style globs relative to the root.
+/// `!` patterns exclude; when only exclusions are given every other file is kept.
+pub fn files_matching_patterns<'a>(
+    root: &str,
+    files: impl Iterator<Item = &'a String>,
+    patterns: &[String],
+) -> Result<Vec<String>, String> {
+    let mut builder = OverrideBuilder::new(root);
+    for pattern in patterns {
+        builder
+            .add(pattern)
+            .map_err(|e| format!("Invalid file pattern '{}': {}", pattern, e))?;
+    }
+    let overrides = builder
+        .build()
+        .map_err(|e| format!("Failed to build file patterns: {}", e))?;
+
+    Ok(files
+        .filter(|path| !overrides.matched(Path::new(path.as_str()), false).is_ignore())
+        .cloned()
+        .collect())
+}
+
+/// Node kinds that start a symbol of their own, whose calls are not
+/// attributed to the enclosing definition
+const DEFINITION_NODE_KINDS: &[&str] = &[
+    "function_item",
+    "function_declaration",
+    "function_definition",
+    "method_definition",
+    "class_declaration",
+    "class_definition",
+    "impl_item",
+];
+
+/// Names of the functions and methods called inside a definition, in
+/// order of first call
+fn collect_calls(node: Node, source_code: &str) -> Vec<String> {
+    fn visit(node: Node, source_code: &str, calls: &mut Vec<String>) {
+        if matches!(node.kind(), "call_expression" | "call") {
+            let callee = node
+                .child_by_field_name("function")
+                .and_then(|function| callee_name(function, source_code));
+            if let Some(name) = callee {
+                if !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            if !DEFINITION_NODE_KINDS.contains(&child.kind()) {
+                visit(child, source_code, calls);
+            }
+        }
+    }
+
+    let mut calls = Vec::new();
+    visit(node, source_code, &mut calls);
+    calls
+}
+
+/// The called name in a call's function position: `foo` for `foo()`,
+/// `a::foo()`, `obj.foo()` and `foo::<T>()`
+fn callee_name(node: Node, source_code: &str) -> Option<String> {
+    let name = match node.kind() {
+        "identifier" => node,
+        "scoped_identifier" => node.child_by_field_name("name")?,
+        "field_expression" => node.child_by_field_name("field")?,
+        "member_expression" => node.child_by_field_name("property")?,
+        "attribute" => node.child_by_field_name("attribute")?,
+        "generic_function" => return callee_name(node.child_by_field_name("function")?, source_code),
+        _ => return None,
+    };
+    Some(source_code[name.byte_range()].to_string())
+}
+
+/// Minimum depth ranked for a query's first page, so "load more" pages can
+/// be served without searching again
+const RANKED_POOL_SIZE: usize = 200;
+
+/// Minimum time between checkpoints of a full index run
+const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Longest keyword tried as an acronym
+const MAX_ACRONYM_LEN: usize = 6;
+
+/// Score factor for matches on synonyms and stems rather than typed keywords
+const EXPANDED_TERM_WEIGHT: f32 = 0.8;
+
+/// File boost for results in files changed on the working tree, doubling
+/// their score so work in progress outranks the rest of the codebase
+const CHANGED_FILE_BOOST: f32 = 1.0;
+
+/// Whether a query reads like an identifier rather than a description
+fn is_identifier_query(query: &str) -> bool {
+    matches!(
+        QueryAnalyzer::analyze_query(query),
+        QueryType::ExactSymbol | QueryType::CodeContent
+    )
+}
+
+/// Share of a fused score taken from the average over matched facets
+const FACET_AGREEMENT_WEIGHT: f32 = 0.2;
+
+/// Merge the name, body and whole-symbol hits of each symbol into one
+/// result. The best weighted facet dominates; agreement between several
+/// facets lifts the score slightly.
+fn fuse_symbol_hits(results: Vec<SearchResult>, identifier_query: bool) -> Vec<CodeChunk> {
+    let mut order: Vec<(String, usize, usize)> = Vec::new();
+    let mut grouped: HashMap<(String, usize, usize), (VectorMetadata, Vec<f32>)> = HashMap::new();
+
+    for r in results {
+        let key = (r.metadata.file_path.clone(), r.metadata.start_line, r.metadata.end_line);
+        let score = r.similarity * r.metadata.facet.query_weight(identifier_query);
+        match grouped.get_mut(&key) {
+            Some((_, scores)) => scores.push(score),
+            None => {
+                order.push(key.clone());
+                grouped.insert(key, (r.metadata, vec![score]));
+            }
+        }
+    }
+
+    order
+        .into_iter()
+        .filter_map(|key| grouped.remove(&key))
+        .map(|(metadata, scores)| {
+            let best = scores.iter().cloned().fold(f32::MIN, f32::max);
+            let mean = scores.iter().sum::<f32>() / scores.len() as f32;
+            CodeChunk {
+                file_path: metadata.file_path,
+                start_line: metadata.start_line,
+                end_line: metadata.end_line,
+                content: metadata.signature.unwrap_or_default(),
+                language: metadata.language,
+                symbols: vec![metadata.symbol_name],
+                relevance_score: (1.0 - FACET_AGREEMENT_WEIGHT) * best + FACET_AGREEMENT_WEIGHT * mean,
+                score_breakdown: None,
+            }
+        })
+        .collect()
+}
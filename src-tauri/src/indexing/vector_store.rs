@@ -1,5 +1,9 @@
+use crate::indexing::compression::{compress, decompress};
+use crate::indexing::index_export::rebase_path;
+use crate::models::code_index::SymbolKind;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use usearch::ffi::{IndexOptions, MetricKind, ScalarKind};
 use usearch::Index as UsearchIndex;
 
@@ -13,6 +17,188 @@ pub struct VectorMetadata {
     pub end_line: usize,
     pub signature: Option<String>,
     pub doc_comment: Option<String>,
+    pub symbol_kind: Option<SymbolKind>,
+    /// Lines of the body chunk this vector embeds; None when it embeds the
+    /// whole symbol. start_line/end_line always describe the parent symbol.
+    pub chunk_lines: Option<(usize, usize)>,
+    /// Which view of the symbol the vector embeds
+    pub facet: VectorFacet,
+}
+
+impl VectorMetadata {
+    /// True for the one vector per symbol that embeds it as a whole
+    pub fn is_whole_symbol(&self) -> bool {
+        self.facet == VectorFacet::Symbol && self.chunk_lines.is_none()
+    }
+}
+
+/// Which view of a symbol a vector embeds. A symbol has one `Symbol`
+/// vector and may add `Name` and `Body` vectors so identifier-like and
+/// natural-language queries each have a close match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorFacet {
+    /// Name, kind, signature and docs together
+    #[default]
+    Symbol,
+    /// Name, kind and signature only
+    Name,
+    /// Doc comment and source lines
+    Body,
+}
+
+impl VectorFacet {
+    /// How much a hit on this facet counts towards its symbol's score
+    pub fn query_weight(&self, identifier_query: bool) -> f32 {
+        match (self, identifier_query) {
+            (VectorFacet::Symbol, _) => 1.0,
+            (VectorFacet::Name, true) | (VectorFacet::Body, false) => 1.0,
+            (VectorFacet::Name, false) | (VectorFacet::Body, true) => 0.85,
+        }
+    }
+}
+
+/// Metadata layout written before symbol kinds were tracked
+#[derive(Deserialize)]
+struct LegacyVectorMetadata {
+    symbol_name: String,
+    file_path: String,
+    language: String,
+    start_line: usize,
+    end_line: usize,
+    signature: Option<String>,
+    doc_comment: Option<String>,
+}
+
+impl From<LegacyVectorMetadata> for VectorMetadata {
+    fn from(legacy: LegacyVectorMetadata) -> Self {
+        Self {
+            symbol_name: legacy.symbol_name,
+            file_path: legacy.file_path,
+            language: legacy.language,
+            start_line: legacy.start_line,
+            end_line: legacy.end_line,
+            signature: legacy.signature,
+            doc_comment: legacy.doc_comment,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
+        }
+    }
+}
+
+/// Metadata predicates applied to semantic search results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorFilter {
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
+    #[serde(default)]
+    pub path_prefix: Option<String>,
+    #[serde(default)]
+    pub symbol_kinds: Option<Vec<SymbolKind>>,
+    /// Restrict results to these exact files
+    #[serde(default)]
+    pub file_paths: Option<HashSet<String>>,
+    /// Drop results in these languages
+    #[serde(default)]
+    pub exclude_languages: Option<Vec<String>>,
+    /// Drop results under any of these path prefixes
+    #[serde(default)]
+    pub exclude_paths: Option<Vec<String>>,
+    /// Drop results of these symbol kinds
+    #[serde(default)]
+    pub exclude_symbol_kinds: Option<Vec<SymbolKind>>,
+}
+
+/// Whether a path lies under a prefix. Paths are absolute, so the prefix
+/// may start at any directory boundary, but must also end at one: `src`
+/// covers `/repo/src/main.rs` and not `/repo/srcfoo/main.rs`.
+fn has_path_prefix(file_path: &str, prefix: &str) -> bool {
+    let path = file_path.replace('\\', "/");
+    let prefix = prefix.replace('\\', "/");
+    let prefix = prefix.trim_start_matches("./").trim_end_matches('/');
+    if prefix.is_empty() {
+        return true;
+    }
+    path == prefix
+        || path.starts_with(&format!("{}/", prefix))
+        || path.ends_with(&format!("/{}", prefix))
+        || path.contains(&format!("/{}/", prefix))
+}
+
+impl VectorFilter {
+    /// True when no predicate is set
+    pub fn is_empty(&self) -> bool {
+        self.languages.is_none()
+            && self.path_prefix.is_none()
+            && self.symbol_kinds.is_none()
+            && self.file_paths.is_none()
+            && self.exclude_languages.is_none()
+            && self.exclude_paths.is_none()
+            && self.exclude_symbol_kinds.is_none()
+    }
+
+    /// Check whether a vector's metadata satisfies every predicate
+    pub fn matches(&self, metadata: &VectorMetadata) -> bool {
+        self.matches_fields(
+            &metadata.language,
+            &metadata.file_path,
+            metadata.symbol_kind.as_ref(),
+        )
+    }
+
+    /// Check a result from any engine by its language, file and symbol kind
+    pub fn matches_fields(
+        &self,
+        language: &str,
+        file_path: &str,
+        symbol_kind: Option<&SymbolKind>,
+    ) -> bool {
+        if let Some(ref languages) = self.languages {
+            if !languages.iter().any(|l| l.eq_ignore_ascii_case(language)) {
+                return false;
+            }
+        }
+
+        if let Some(ref prefix) = self.path_prefix {
+            if !has_path_prefix(file_path, prefix) {
+                return false;
+            }
+        }
+
+        if let Some(ref kinds) = self.symbol_kinds {
+            match symbol_kind {
+                Some(kind) if kinds.contains(kind) => {}
+                _ => return false,
+            }
+        }
+
+        if let Some(ref paths) = self.file_paths {
+            if !paths.contains(file_path) {
+                return false;
+            }
+        }
+
+        if let Some(ref languages) = self.exclude_languages {
+            if languages.iter().any(|l| l.eq_ignore_ascii_case(language)) {
+                return false;
+            }
+        }
+
+        if let Some(ref prefixes) = self.exclude_paths {
+            if prefixes.iter().any(|prefix| has_path_prefix(file_path, prefix)) {
+                return false;
+            }
+        }
+
+        if let (Some(ref kinds), Some(kind)) = (&self.exclude_symbol_kinds, symbol_kind) {
+            if kinds.contains(kind) {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 /// Result from a vector search
@@ -22,42 +208,299 @@ pub struct SearchResult {
     pub similarity: f32,
 }
 
+/// A group of symbols whose embeddings are nearly identical
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateCluster {
+    pub members: Vec<VectorMetadata>,
+    /// Highest similarity between any two linked members
+    pub max_similarity: f32,
+}
+
+/// Neighbors inspected per vector when looking for duplicates
+const DUPLICATE_NEIGHBORS: usize = 10;
+
+/// Removals before compaction is considered at all
+const COMPACT_MIN_REMOVALS: usize = 256;
+
+/// Compact once removals exceed this fraction of live vectors
+const COMPACT_REMOVAL_RATIO: f32 = 0.25;
+
+/// Model used for every cache written before the model id was recorded
+const LEGACY_MODEL_ID: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// HNSW graph parameters. Larger values improve recall at the cost of
+/// memory, build time and query latency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HnswConfig {
+    /// Links per node (M); fixed once the index is built
+    pub connectivity: usize,
+    /// Candidate list size while inserting (efConstruction); fixed once built
+    pub expansion_add: usize,
+    /// Candidate list size while searching (ef); can change at any time
+    pub expansion_search: usize,
+}
+
+impl Default for HnswConfig {
+    fn default() -> Self {
+        Self {
+            connectivity: 16,
+            expansion_add: 128,
+            expansion_search: 64,
+        }
+    }
+}
+
+impl HnswConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=128).contains(&self.connectivity) {
+            return Err(format!("connectivity must be between 2 and 128, got {}", self.connectivity));
+        }
+        if self.expansion_add == 0 || self.expansion_search == 0 {
+            return Err("expansion_add and expansion_search must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether an index built with `other` can be reused under this config
+    pub fn is_build_compatible(&self, other: &HnswConfig) -> bool {
+        self.connectivity == other.connectivity && self.expansion_add == other.expansion_add
+    }
+}
+
+/// On-disk layout of the metadata file
+#[derive(Serialize, Deserialize)]
+struct StoredMetadata {
+    model_id: String,
+    dimensions: usize,
+    hnsw: HnswConfig,
+    next_id: u64,
+    entries: HashMap<u64, VectorMetadata>,
+}
+
 /// HNSW-based vector store for semantic code search
 pub struct VectorStore {
     index: UsearchIndex,
-    metadata: Vec<VectorMetadata>,
+    metadata: HashMap<u64, VectorMetadata>, // Keyed by usearch id, survives removals
+    file_ids: HashMap<String, Vec<u64>>,    // Reverse lookup for per-file removal
+    model_id: String,                       // Embedding model that produced the vectors
     dimensions: usize,
+    hnsw: HnswConfig,
     next_id: u64,
+    removed_since_compact: usize, // Tombstoned slots left behind in the HNSW graph
+    mapped_from: Option<PathBuf>, // Set while the index is a read-only memory-mapped view
 }
 
 impl VectorStore {
     /// Create a new vector store with specified dimensions
     pub fn new(dimensions: usize) -> Result<Self, String> {
+        Self::for_model(LEGACY_MODEL_ID, dimensions)
+    }
+
+    /// Create a new vector store for embeddings produced by a specific model
+    pub fn for_model(model_id: &str, dimensions: usize) -> Result<Self, String> {
+        Self::with_config(model_id, dimensions, &HnswConfig::default())
+    }
+
+    /// Create a new vector store with custom HNSW parameters
+    pub fn with_config(model_id: &str, dimensions: usize, hnsw: &HnswConfig) -> Result<Self, String> {
         println!("Creating vector store with {} dimensions", dimensions);
 
-        let options = IndexOptions {
+        hnsw.validate()?;
+        let index = UsearchIndex::new(&Self::index_options(dimensions, hnsw))
+            .map_err(|e| format!("Failed to create index: {}", e))?;
+
+        Ok(Self {
+            index,
+            metadata: HashMap::new(),
+            file_ids: HashMap::new(),
+            model_id: model_id.to_string(),
+            dimensions,
+            hnsw: hnsw.clone(),
+            next_id: 0,
+            removed_since_compact: 0,
+            mapped_from: None,
+        })
+    }
+
+    /// Id of the embedding model the stored vectors were produced with
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// HNSW parameters the index was built with
+    pub fn hnsw_config(&self) -> &HnswConfig {
+        &self.hnsw
+    }
+
+    /// Change the search-time candidate list size without rebuilding
+    pub fn set_expansion_search(&mut self, expansion_search: usize) {
+        self.index.change_expansion_search(expansion_search);
+        self.hnsw.expansion_search = expansion_search;
+    }
+
+    fn index_options(dimensions: usize, hnsw: &HnswConfig) -> IndexOptions {
+        IndexOptions {
             dimensions,
             metric: MetricKind::Cos, // Cosine similarity
             quantization: ScalarKind::F32,
-            connectivity: 16, // HNSW M parameter
-            expansion_add: 128, // HNSW efConstruction
-            expansion_search: 64, // HNSW ef
+            connectivity: hnsw.connectivity, // HNSW M parameter
+            expansion_add: hnsw.expansion_add, // HNSW efConstruction
+            expansion_search: hnsw.expansion_search, // HNSW ef
             multi: false,
+        }
+    }
+
+    /// Add a vector with associated metadata to the store, returning its id
+    pub fn add(&mut self, vector: &[f32], metadata: VectorMetadata) -> Result<u64, String> {
+        let id = self.next_id;
+        self.insert(id, vector, metadata)?;
+        self.next_id += 1;
+
+        Ok(id)
+    }
+
+    /// Replace the vector and metadata stored under an existing id
+    pub fn update(
+        &mut self,
+        id: u64,
+        vector: &[f32],
+        metadata: VectorMetadata,
+    ) -> Result<(), String> {
+        if !self.metadata.contains_key(&id) {
+            return Err(format!("Unknown vector id: {}", id));
+        }
+        // Checked before the old entry goes, so a bad vector leaves it intact
+        if vector.len() != self.dimensions {
+            return Err(format!(
+                "Vector dimension mismatch: expected {}, got {}",
+                self.dimensions,
+                vector.len()
+            ));
+        }
+
+        self.remove_entry(id)?;
+        self.insert(id, vector, metadata)?;
+        self.compact_if_needed()
+    }
+
+    /// Remove a single vector by id. Returns false if the id was unknown.
+    pub fn remove(&mut self, id: u64) -> Result<bool, String> {
+        let removed = self.remove_entry(id)?;
+        self.compact_if_needed()?;
+        Ok(removed)
+    }
+
+    fn remove_entry(&mut self, id: u64) -> Result<bool, String> {
+        if !self.metadata.contains_key(&id) {
+            return Ok(false);
+        }
+
+        self.ensure_writable()?;
+        let metadata = match self.metadata.remove(&id) {
+            Some(m) => m,
+            None => return Ok(false),
         };
 
-        let index = UsearchIndex::new(&options)
+        self.index
+            .remove(id)
+            .map_err(|e| format!("Failed to remove vector: {}", e))?;
+
+        if let Some(ids) = self.file_ids.get_mut(&metadata.file_path) {
+            ids.retain(|&other| other != id);
+            if ids.is_empty() {
+                self.file_ids.remove(&metadata.file_path);
+            }
+        }
+
+        self.removed_since_compact += 1;
+        Ok(true)
+    }
+
+    /// Remove every vector belonging to a file, returning how many were removed
+    pub fn remove_by_file(&mut self, file_path: &str) -> Result<usize, String> {
+        let ids = self.file_ids.get(file_path).cloned().unwrap_or_default();
+
+        let mut removed = 0;
+        for id in ids {
+            if self.remove_entry(id)? {
+                removed += 1;
+            }
+        }
+
+        self.compact_if_needed()?;
+        Ok(removed)
+    }
+
+    /// Whether enough vectors have been removed to make a rebuild worthwhile
+    pub fn needs_compaction(&self) -> bool {
+        self.removed_since_compact >= COMPACT_MIN_REMOVALS
+            && self.removed_since_compact as f32 > self.len() as f32 * COMPACT_REMOVAL_RATIO
+    }
+
+    fn compact_if_needed(&mut self) -> Result<(), String> {
+        if self.needs_compaction() {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rebuild the HNSW index from live vectors only, dropping the tombstoned
+    /// slots that removals and updates leave behind. Ids are preserved.
+    /// Returns the number of slots reclaimed.
+    pub fn compact(&mut self) -> Result<usize, String> {
+        let index = UsearchIndex::new(&Self::index_options(self.dimensions, &self.hnsw))
             .map_err(|e| format!("Failed to create index: {}", e))?;
+        index
+            .reserve(self.metadata.len().max(1024))
+            .map_err(|e| format!("Failed to reserve capacity: {}", e))?;
 
-        Ok(Self {
-            index,
-            metadata: Vec::new(),
-            dimensions,
-            next_id: 0,
-        })
+        let mut ids: Vec<u64> = self.metadata.keys().copied().collect();
+        ids.sort_unstable();
+
+        let mut vector = vec![0.0f32; self.dimensions];
+        for id in ids {
+            let found = self
+                .index
+                .get(id, &mut vector)
+                .map_err(|e| format!("Failed to read vector {}: {}", id, e))?;
+
+            if found == 0 {
+                // Metadata without a vector can never be returned; drop it
+                self.metadata.remove(&id);
+                continue;
+            }
+
+            index
+                .add(id, &vector)
+                .map_err(|e| format!("Failed to add vector: {}", e))?;
+        }
+
+        self.file_ids.clear();
+        for (&id, metadata) in &self.metadata {
+            self.file_ids
+                .entry(metadata.file_path.clone())
+                .or_default()
+                .push(id);
+        }
+
+        self.index = index;
+        self.mapped_from = None;
+        let reclaimed = std::mem::take(&mut self.removed_since_compact);
+        println!("Vector store compacted ({} slots reclaimed)", reclaimed);
+
+        Ok(reclaimed)
     }
 
-    /// Add a vector with associated metadata to the store
-    pub fn add(&mut self, vector: &[f32], metadata: VectorMetadata) -> Result<(), String> {
+    /// Ids of all vectors stored for a file
+    pub fn ids_for_file(&self, file_path: &str) -> &[u64] {
+        self.file_ids
+            .get(file_path)
+            .map(|ids| ids.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn insert(&mut self, id: u64, vector: &[f32], metadata: VectorMetadata) -> Result<(), String> {
         if vector.len() != self.dimensions {
             return Err(format!(
                 "Vector dimension mismatch: expected {}, got {}",
@@ -66,13 +509,25 @@ impl VectorStore {
             ));
         }
 
-        let id = self.next_id;
+        self.ensure_writable()?;
+
+        // usearch requires capacity to be reserved ahead of insertions
+        if self.index.size() >= self.index.capacity() {
+            let capacity = (self.index.capacity() * 2).max(1024);
+            self.index
+                .reserve(capacity)
+                .map_err(|e| format!("Failed to reserve capacity: {}", e))?;
+        }
+
         self.index
             .add(id, vector)
             .map_err(|e| format!("Failed to add vector: {}", e))?;
 
-        self.metadata.push(metadata);
-        self.next_id += 1;
+        self.file_ids
+            .entry(metadata.file_path.clone())
+            .or_default()
+            .push(id);
+        self.metadata.insert(id, metadata);
 
         Ok(())
     }
@@ -94,16 +549,16 @@ impl VectorStore {
 
         let mut search_results = Vec::new();
         for i in 0..results.keys.len() {
-            let id = results.keys[i] as usize;
+            let id = results.keys[i];
             let distance = results.distances[i];
 
             // Convert distance to similarity (cosine distance -> similarity)
             // For cosine: similarity = 1 - distance
             let similarity = 1.0 - distance;
 
-            if id < self.metadata.len() {
+            if let Some(metadata) = self.metadata.get(&id) {
                 search_results.push(SearchResult {
-                    metadata: self.metadata[id].clone(),
+                    metadata: metadata.clone(),
                     similarity,
                 });
             }
@@ -115,6 +570,145 @@ impl VectorStore {
         Ok(search_results)
     }
 
+    /// Search for k nearest neighbors that satisfy a metadata filter.
+    ///
+    /// HNSW cannot pre-filter, so candidates are over-fetched and the window
+    /// grows until k matches are found or the whole store has been scanned.
+    pub fn search_filtered(
+        &self,
+        query: &[f32],
+        k: usize,
+        filter: &VectorFilter,
+    ) -> Result<Vec<SearchResult>, String> {
+        if filter.is_empty() {
+            return self.search(query, k);
+        }
+
+        let mut fetch = (k * 4).max(32);
+        loop {
+            let mut results: Vec<SearchResult> = self
+                .search(query, fetch)?
+                .into_iter()
+                .filter(|r| filter.matches(&r.metadata))
+                .collect();
+
+            if results.len() >= k || fetch >= self.len() {
+                results.truncate(k);
+                return Ok(results);
+            }
+
+            fetch *= 4;
+        }
+    }
+
+    /// Id of the whole-symbol vector for a named symbol in a file
+    pub fn find_symbol(&self, file_path: &str, symbol_name: &str) -> Option<u64> {
+        self.ids_for_file(file_path).iter().copied().find(|id| {
+            self.metadata
+                .get(id)
+                .is_some_and(|m| m.symbol_name == symbol_name && m.is_whole_symbol())
+        })
+    }
+
+    /// Read back the stored vector for an id
+    pub fn get_vector(&self, id: u64) -> Option<Vec<f32>> {
+        if !self.metadata.contains_key(&id) {
+            return None;
+        }
+
+        let mut vector = vec![0.0f32; self.dimensions];
+        match self.index.get(id, &mut vector) {
+            Ok(found) if found > 0 => Some(vector),
+            _ => None,
+        }
+    }
+
+    /// Group whole-symbol vectors whose cosine similarity is at least
+    /// `threshold`. Each vector is linked to its nearest neighbors above the
+    /// threshold and linked vectors are merged into clusters, largest first.
+    pub fn find_duplicates(&self, threshold: f32) -> Result<Vec<DuplicateCluster>, String> {
+        // Name and body vectors would match their own parent symbol, so only compare symbols
+        let mut ids: Vec<u64> = self
+            .metadata
+            .iter()
+            .filter(|(_, m)| m.is_whole_symbol())
+            .map(|(&id, _)| id)
+            .collect();
+        ids.sort_unstable();
+
+        let mut parent: HashMap<u64, u64> = ids.iter().map(|&id| (id, id)).collect();
+        let mut best: HashMap<u64, f32> = HashMap::new();
+
+        fn find(parent: &mut HashMap<u64, u64>, id: u64) -> u64 {
+            let mut root = id;
+            while parent[&root] != root {
+                root = parent[&root];
+            }
+            // Path compression
+            let mut current = id;
+            while parent[&current] != root {
+                let next = parent[&current];
+                parent.insert(current, root);
+                current = next;
+            }
+            root
+        }
+
+        for &id in &ids {
+            let vector = match self.get_vector(id) {
+                Some(v) => v,
+                None => continue,
+            };
+
+            let matches = self
+                .index
+                .search(&vector, DUPLICATE_NEIGHBORS + 1)
+                .map_err(|e| format!("Search failed: {}", e))?;
+
+            for (&other, &distance) in matches.keys.iter().zip(matches.distances.iter()) {
+                let similarity = 1.0 - distance;
+                if other == id || similarity < threshold || !parent.contains_key(&other) {
+                    continue;
+                }
+
+                let (a, b) = (find(&mut parent, id), find(&mut parent, other));
+                let mut max_similarity = similarity.max(best.get(&a).copied().unwrap_or(0.0));
+                if a != b {
+                    parent.insert(b, a);
+                    max_similarity = max_similarity.max(best.remove(&b).unwrap_or(0.0));
+                }
+                best.insert(a, max_similarity);
+            }
+        }
+
+        let mut groups: HashMap<u64, Vec<u64>> = HashMap::new();
+        for &id in &ids {
+            let root = find(&mut parent, id);
+            groups.entry(root).or_default().push(id);
+        }
+
+        let mut clusters: Vec<DuplicateCluster> = groups
+            .into_iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(root, members)| DuplicateCluster {
+                members: members
+                    .iter()
+                    .filter_map(|id| self.metadata.get(id).cloned())
+                    .collect(),
+                max_similarity: best.get(&root).copied().unwrap_or(threshold),
+            })
+            .collect();
+
+        clusters.sort_by(|a, b| {
+            b.members
+                .len()
+                .cmp(&a.members.len())
+                .then(b.max_similarity.partial_cmp(&a.max_similarity).unwrap_or(std::cmp::Ordering::Equal))
+        });
+
+        Ok(clusters)
+    }
+
     /// Get the number of vectors in the store
     pub fn len(&self) -> usize {
         self.metadata.len()
@@ -125,16 +719,63 @@ impl VectorStore {
         self.metadata.is_empty()
     }
 
+    /// Whether the index is still a memory-mapped view of its file
+    pub fn is_memory_mapped(&self) -> bool {
+        self.mapped_from.is_some()
+    }
+
+    /// Replace a memory-mapped view with an in-memory copy before the first
+    /// modification, since views are read-only
+    fn ensure_writable(&mut self) -> Result<(), String> {
+        let path = match self.mapped_from.take() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        println!("Loading vector index into memory for updates...");
+        let index = UsearchIndex::new(&Self::index_options(self.dimensions, &self.hnsw))
+            .map_err(|e| format!("Failed to create index: {}", e))?;
+        let loaded = index
+            .load(path.to_str().unwrap())
+            .map_err(|e| format!("Failed to load index: {}", e));
+        if let Err(e) = loaded {
+            self.mapped_from = Some(path);
+            return Err(e);
+        }
+        index.change_expansion_search(self.hnsw.expansion_search);
+
+        self.index = index;
+        Ok(())
+    }
+
     /// Save the index and metadata to disk
     pub fn save<P: AsRef<Path>>(&self, index_path: P, metadata_path: P) -> Result<(), String> {
-        // Save HNSW index
-        self.index
-            .save(index_path.as_ref().to_str().unwrap())
-            .map_err(|e| format!("Failed to save index: {}", e))?;
+        // Save HNSW index. A mapped view is unchanged since it was loaded, and
+        // must not be overwritten in place while it is mapped.
+        match self.mapped_from {
+            Some(ref source) if source.as_path() == index_path.as_ref() => {}
+            Some(ref source) => {
+                std::fs::copy(source, index_path.as_ref())
+                    .map_err(|e| format!("Failed to save index: {}", e))?;
+            }
+            None => {
+                self.index
+                    .save(index_path.as_ref().to_str().unwrap())
+                    .map_err(|e| format!("Failed to save index: {}", e))?;
+            }
+        }
 
-        // Save metadata using bincode
-        let metadata_bytes = bincode::serialize(&self.metadata)
+        // Save metadata using bincode, compressed
+        let stored = StoredMetadata {
+            model_id: self.model_id.clone(),
+            dimensions: self.dimensions,
+            hnsw: self.hnsw.clone(),
+            next_id: self.next_id,
+            entries: self.metadata.clone(),
+        };
+        let metadata_bytes = bincode::serialize(&stored)
             .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+        let metadata_bytes = compress(&metadata_bytes)?;
 
         std::fs::write(metadata_path, metadata_bytes)
             .map_err(|e| format!("Failed to write metadata: {}", e))?;
@@ -143,80 +784,136 @@ impl VectorStore {
         Ok(())
     }
 
-    /// Load the index and metadata from disk
+    /// Move the file paths in a saved metadata file from under project root
+    /// `from` to under `to`, e.g. for a cache built on another machine
+    pub fn rebase_saved_metadata(metadata_path: &Path, from: &str, to: &str) -> Result<(), String> {
+        let metadata_bytes = std::fs::read(metadata_path)
+            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+        let mut stored: StoredMetadata = bincode::deserialize(&decompress(metadata_bytes)?)
+            .map_err(|e| format!("Failed to deserialize metadata: {}", e))?;
+
+        for entry in stored.entries.values_mut() {
+            entry.file_path = rebase_path(&entry.file_path, from, to);
+        }
+
+        let metadata_bytes = bincode::serialize(&stored)
+            .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+        std::fs::write(metadata_path, compress(&metadata_bytes)?)
+            .map_err(|e| format!("Failed to write metadata: {}", e))
+    }
+
+    /// Load the index and metadata from disk.
+    ///
+    /// The index is memory-mapped rather than read into RAM, so startup is
+    /// near-instant even for millions of vectors. It is copied into memory
+    /// on the first modification.
+    ///
+    /// Fails if the vectors were produced by a different model or dimension,
+    /// or with different HNSW build parameters, in which case the caller
+    /// should rebuild the store. A different `expansion_search` is applied
+    /// to the loaded index.
     pub fn load<P: AsRef<Path>>(
         index_path: P,
         metadata_path: P,
+        model_id: &str,
         dimensions: usize,
+        hnsw: &HnswConfig,
     ) -> Result<Self, String> {
         println!("Loading vector store from disk...");
 
-        // Load HNSW index
-        let options = IndexOptions {
-            dimensions,
-            metric: MetricKind::Cos,
-            quantization: ScalarKind::F32,
-            connectivity: 16,
-            expansion_add: 128,
-            expansion_search: 64,
-            multi: false,
+        // Load metadata first so a model mismatch is caught before usearch complains
+        let metadata_bytes = std::fs::read(metadata_path)
+            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+        let metadata_bytes = decompress(metadata_bytes)?;
+
+        let stored = match bincode::deserialize::<StoredMetadata>(&metadata_bytes) {
+            Ok(stored) => stored,
+            Err(_) => {
+                // Older caches stored a plain Vec where the position was the id
+                let legacy: Vec<LegacyVectorMetadata> = bincode::deserialize(&metadata_bytes)
+                    .map_err(|e| format!("Failed to deserialize metadata: {}", e))?;
+                StoredMetadata {
+                    model_id: LEGACY_MODEL_ID.to_string(),
+                    dimensions: 384,
+                    hnsw: HnswConfig::default(),
+                    next_id: legacy.len() as u64,
+                    entries: legacy
+                        .into_iter()
+                        .enumerate()
+                        .map(|(id, m)| (id as u64, m.into()))
+                        .collect(),
+                }
+            }
         };
 
-        let index = UsearchIndex::new(&options)
-            .map_err(|e| format!("Failed to create index: {}", e))?;
+        if stored.model_id != model_id || stored.dimensions != dimensions {
+            return Err(format!(
+                "Vector index was built with {} ({} dims) but the current model is {} ({} dims)",
+                stored.model_id, stored.dimensions, model_id, dimensions
+            ));
+        }
 
-        index
-            .load(index_path.as_ref().to_str().unwrap())
-            .map_err(|e| format!("Failed to load index: {}", e))?;
+        if !stored.hnsw.is_build_compatible(hnsw) {
+            return Err(format!(
+                "Vector index was built with connectivity {} / expansion_add {} but {} / {} is configured",
+                stored.hnsw.connectivity, stored.hnsw.expansion_add, hnsw.connectivity, hnsw.expansion_add
+            ));
+        }
 
-        // Load metadata
-        let metadata_bytes = std::fs::read(metadata_path)
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+        // Memory-map the HNSW index
+        let index = UsearchIndex::new(&Self::index_options(dimensions, hnsw))
+            .map_err(|e| format!("Failed to create index: {}", e))?;
 
-        let metadata: Vec<VectorMetadata> = bincode::deserialize(&metadata_bytes)
-            .map_err(|e| format!("Failed to deserialize metadata: {}", e))?;
+        index
+            .view(index_path.as_ref().to_str().unwrap())
+            .map_err(|e| format!("Failed to map index: {}", e))?;
+        index.change_expansion_search(hnsw.expansion_search);
 
-        let next_id = metadata.len() as u64;
+        let mut file_ids: HashMap<String, Vec<u64>> = HashMap::new();
+        for (&id, metadata) in &stored.entries {
+            file_ids
+                .entry(metadata.file_path.clone())
+                .or_default()
+                .push(id);
+        }
 
-        println!("Vector store loaded ({} vectors)", metadata.len());
+        println!("Vector store loaded ({} vectors)", stored.entries.len());
 
         Ok(Self {
             index,
-            metadata,
+            metadata: stored.entries,
+            file_ids,
+            model_id: stored.model_id,
             dimensions,
-            next_id,
+            hnsw: hnsw.clone(),
+            next_id: stored.next_id,
+            removed_since_compact: 0,
+            mapped_from: Some(index_path.as_ref().to_path_buf()),
         })
     }
 
     /// Clear all vectors and metadata
     pub fn clear(&mut self) {
         // Recreate the index
-        let options = IndexOptions {
-            dimensions: self.dimensions,
-            metric: MetricKind::Cos,
-            quantization: ScalarKind::F32,
-            connectivity: 16,
-            expansion_add: 128,
-            expansion_search: 64,
-            multi: false,
-        };
-
-        if let Ok(new_index) = UsearchIndex::new(&options) {
+        if let Ok(new_index) = UsearchIndex::new(&Self::index_options(self.dimensions, &self.hnsw)) {
             self.index = new_index;
+            self.mapped_from = None;
         }
 
         self.metadata.clear();
+        self.file_ids.clear();
         self.next_id = 0;
+        self.removed_since_compact = 0;
     }
 
-    /// Get metadata by index
-    pub fn get_metadata(&self, index: usize) -> Option<&VectorMetadata> {
-        self.metadata.get(index)
+    /// Get metadata by id
+    pub fn get_metadata(&self, id: u64) -> Option<&VectorMetadata> {
+        self.metadata.get(&id)
     }
 
-    /// Get all metadata
-    pub fn all_metadata(&self) -> &[VectorMetadata] {
-        &self.metadata
+    /// Iterate over all live (id, metadata) entries
+    pub fn all_metadata(&self) -> impl Iterator<Item = (u64, &VectorMetadata)> {
+        self.metadata.iter().map(|(&id, m)| (id, m))
     }
 }
 
@@ -245,6 +942,9 @@ mod tests {
             end_line: 10,
             signature: None,
             doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
         };
 
         let vector1 = vec![1.0, 0.0, 0.0];
@@ -270,6 +970,9 @@ mod tests {
             end_line: 10,
             signature: None,
             doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
         };
 
         let wrong_vector = vec![1.0, 0.0]; // Wrong dimension
@@ -294,6 +997,9 @@ mod tests {
             end_line: 10,
             signature: None,
             doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
         };
 
         let meta2 = VectorMetadata {
@@ -304,6 +1010,9 @@ mod tests {
             end_line: 30,
             signature: None,
             doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
         };
 
         let meta3 = VectorMetadata {
@@ -314,6 +1023,9 @@ mod tests {
             end_line: 10,
             signature: None,
             doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
         };
 
         store.add(&vector1, meta1).unwrap();
@@ -329,4 +1041,162 @@ mod tests {
         // First result should have higher similarity
         assert!(results[0].similarity > results[1].similarity);
     }
+
+    fn metadata_for(symbol_name: &str, file_path: &str) -> VectorMetadata {
+        VectorMetadata {
+            symbol_name: symbol_name.to_string(),
+            file_path: file_path.to_string(),
+            language: "rust".to_string(),
+            start_line: 1,
+            end_line: 10,
+            signature: None,
+            doc_comment: None,
+            symbol_kind: None,
+            chunk_lines: None,
+            facet: VectorFacet::Symbol,
+        }
+    }
+
+    #[test]
+    fn test_remove_by_file() {
+        let mut store = VectorStore::new(3).unwrap();
+
+        store.add(&[1.0, 0.0, 0.0], metadata_for("login", "auth.rs")).unwrap();
+        store.add(&[0.9, 0.1, 0.0], metadata_for("logout", "auth.rs")).unwrap();
+        let kept = store.add(&[0.0, 0.0, 1.0], metadata_for("parse", "utils.rs")).unwrap();
+
+        assert_eq!(store.remove_by_file("auth.rs").unwrap(), 2);
+        assert_eq!(store.len(), 1);
+        assert!(store.ids_for_file("auth.rs").is_empty());
+        assert_eq!(store.get_metadata(kept).unwrap().symbol_name, "parse");
+
+        // Ids are never reused after removal
+        let next = store.add(&[1.0, 0.0, 0.0], metadata_for("login", "auth.rs")).unwrap();
+        assert!(next > kept);
+    }
+
+    #[test]
+    fn test_compaction_threshold() {
+        let mut store = VectorStore::new(3).unwrap();
+
+        for i in 0..COMPACT_MIN_REMOVALS {
+            store.add(&[1.0, 0.0, 0.0], metadata_for(&format!("f{}", i), "a.rs")).unwrap();
+        }
+        store.add(&[0.0, 1.0, 0.0], metadata_for("keep", "b.rs")).unwrap();
+
+        // Removing 10% of vectors leaves the graph alone
+        for id in 0..(COMPACT_MIN_REMOVALS as u64 / 10) {
+            store.remove_entry(id).unwrap();
+        }
+        assert!(!store.needs_compaction());
+
+        // Removing a whole file pushes removals past the threshold and compacts
+        store.remove_by_file("a.rs").unwrap();
+        assert!(!store.needs_compaction());
+        assert_eq!(store.removed_since_compact, 0);
+    }
+
+    #[test]
+    fn test_find_symbol_skips_body_chunks() {
+        let mut store = VectorStore::new(3).unwrap();
+
+        let chunk = VectorMetadata {
+            chunk_lines: Some((20, 60)),
+            facet: VectorFacet::Body,
+            ..metadata_for("handle_request", "server.rs")
+        };
+        store.add(&[0.0, 1.0, 0.0], chunk).unwrap();
+        let id = store.add(&[1.0, 0.0, 0.0], metadata_for("handle_request", "server.rs")).unwrap();
+
+        assert_eq!(store.find_symbol("server.rs", "handle_request"), Some(id));
+        assert_eq!(store.find_symbol("client.rs", "handle_request"), None);
+    }
+
+    #[test]
+    fn test_hnsw_config_validation() {
+        assert!(HnswConfig::default().validate().is_ok());
+
+        let sparse = HnswConfig {
+            connectivity: 1,
+            ..Default::default()
+        };
+        assert!(sparse.validate().is_err());
+        assert!(VectorStore::with_config("model", 3, &sparse).is_err());
+
+        // Only build-time parameters affect compatibility
+        let faster = HnswConfig {
+            expansion_search: 16,
+            ..Default::default()
+        };
+        assert!(HnswConfig::default().is_build_compatible(&faster));
+        assert!(!HnswConfig::default().is_build_compatible(&HnswConfig {
+            connectivity: 32,
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_update_replaces_metadata() {
+        let mut store = VectorStore::new(3).unwrap();
+
+        let id = store.add(&[1.0, 0.0, 0.0], metadata_for("login", "auth.rs")).unwrap();
+        store.update(id, &[0.0, 1.0, 0.0], metadata_for("sign_in", "session.rs")).unwrap();
+
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get_metadata(id).unwrap().symbol_name, "sign_in");
+        assert!(store.ids_for_file("auth.rs").is_empty());
+        assert_eq!(store.ids_for_file("session.rs"), &[id]);
+
+        assert!(store.update(99, &[0.0, 1.0, 0.0], metadata_for("x", "x.rs")).is_err());
+
+        // A wrong-length vector is rejected without losing the entry
+        assert!(store.update(id, &[1.0, 0.0], metadata_for("x", "x.rs")).is_err());
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.get_metadata(id).unwrap().symbol_name, "sign_in");
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let mut metadata = metadata_for("handler", "/repo/services/api/handler.py");
+        metadata.language = "python".to_string();
+        metadata.symbol_kind = Some(SymbolKind::Function);
+
+        let filter = VectorFilter {
+            languages: Some(vec!["python".to_string()]),
+            path_prefix: Some("services/".to_string()),
+            symbol_kinds: None,
+            file_paths: None,
+            ..Default::default()
+        };
+        assert!(filter.matches(&metadata));
+
+        let filter = VectorFilter {
+            symbol_kinds: Some(vec![SymbolKind::Class]),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&metadata));
+
+        let filter = VectorFilter {
+            path_prefix: Some("frontend/".to_string()),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&metadata));
+
+        let filter = VectorFilter {
+            file_paths: Some(HashSet::from(["/repo/services/api/handler.py".to_string()])),
+            ..Default::default()
+        };
+        assert!(filter.matches(&metadata));
+        assert!(VectorFilter::default().matches(&metadata));
+    }
+
+    #[test]
+    fn test_path_prefix_stops_at_directory_boundary() {
+        assert!(has_path_prefix("/repo/src/main.rs", "src"));
+        assert!(has_path_prefix("/repo/src/main.rs", "src/"));
+        assert!(has_path_prefix("/repo/src/main.rs", "/repo/src"));
+        assert!(has_path_prefix("/repo/src/main.rs", "src/main.rs"));
+        assert!(!has_path_prefix("/repo/srcfoo/main.rs", "src"));
+        assert!(!has_path_prefix("/repo/services-old/api.py", "services/"));
+    }
 }
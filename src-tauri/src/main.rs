@@ -5,11 +5,19 @@ mod anthropic;
 mod commands;
 mod indexing;
 mod models;
+mod settings;
 
 use commands::index_commands::*;
 use commands::anthropic_commands::*;
+use commands::file_commands::*;
+use commands::settings_commands::*;
+use commands::session_commands::*;
+use commands::batch_commands::*;
 use indexing::tree_sitter_indexer::TreeSitterIndexer;
+use settings::AppSettings;
+use std::collections::HashMap;
 use std::sync::Mutex;
+use tauri::Manager;
 
 fn main() {
     // Initialize indexer state
@@ -18,22 +26,155 @@ fn main() {
     let indexer_state = IndexerState {
         indexer: Mutex::new(indexer),
         current_index: Mutex::new(None),
-        persistence: Mutex::new(None), // Will be initialized on first index_codebase call
+        persistence: Mutex::new(None), // Set up with the settings, or on first use
+        project_searchers: Mutex::new(HashMap::new()),
+        loaded_projects: Mutex::new(Vec::new()),
+        settings: Mutex::new(AppSettings::default()), // Loaded from disk in setup
+        cache_gc_report: Mutex::new(None),
     };
 
     tauri::Builder::default()
         .manage(indexer_state)
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            {
+                let state = app.state::<IndexerState>();
+                if let Ok(mut indexer) = state.indexer.lock() {
+                    // Models shipped in the app bundle let semantic search work offline
+                    if let Ok(resource_dir) = app.path().resource_dir() {
+                        indexer.set_bundled_model_dir(resource_dir.join("models"));
+                    }
+                };
+            }
+
+            if let Err(e) = init_settings(app.handle()) {
+                eprintln!("Using default settings: {}", e);
+            }
+
+            // Fetch the embedding model in the background instead of blocking startup
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = ensure_embedding_model(&handle).await {
+                    eprintln!("Embedding model unavailable: {}", e);
+                }
+            });
+
+            // The re-ranking model, when turned on, loads in the background too
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Err(e) = init_reranking(&handle) {
+                    eprintln!("Re-ranking unavailable: {}", e);
+                }
+            });
+
+            // Drop caches of projects that were deleted or moved since the last run
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Err(e) = collect_cache_garbage(&handle) {
+                    eprintln!("Cache cleanup failed: {}", e);
+                }
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             index_codebase,
+            reindex_file,
             query_index,
+            query_changed_code,
             get_index_stats,
+            export_index,
+            import_index,
+            export_cache_bundle,
+            import_cache_bundle,
+            clear_project_cache,
+            get_cached_projects,
+            get_cache_gc_report,
+            clear_all_caches,
+            get_loaded_projects,
+            switch_project,
+            get_query_history,
+            clear_query_history,
+            pin_chunk,
+            unpin_chunk,
+            list_pins,
+            get_pinned_chunks,
+            unload_project,
             get_file_symbols,
+            goto_definition,
+            get_call_hierarchy,
+            get_symbols_in_range,
+            get_file_content,
+            open_in_editor,
+            get_editor_config,
+            set_editor_config,
+            detect_editor,
+            reveal_in_file_manager,
             search_files,
+            grep_project,
             search_semantic,
+            search_coarse_to_fine,
+            find_similar_symbols,
+            find_duplicates,
+            get_semantic_map,
+            suggest_symbols,
+            search_all_projects,
+            get_full_text_config,
+            set_full_text_config,
+            get_embedding_device,
+            set_embedding_device,
+            get_embedding_model,
+            set_embedding_model,
+            get_semantic_status,
+            set_model_source,
+            download_embedding_model,
+            set_model_cache_dir,
+            set_reranking_enabled,
+            get_reranking_enabled,
+            set_embedding_backend,
+            get_embedding_backend,
+            get_hnsw_config,
+            set_hnsw_config,
+            get_default_search_config,
+            set_default_search_config,
+            get_normalizer_config,
+            set_normalizer_config,
+            get_project_system_prompts,
+            set_project_system_prompts,
+            get_query_synonyms,
+            set_query_synonyms,
+            set_multi_query_enabled,
+            get_multi_query_enabled,
             analyze_intent,
+            enhance_prompt,
+            ask_codebase,
+            create_session,
+            list_sessions,
+            get_session,
+            delete_session,
+            chat,
+            submit_batch_job,
+            list_batch_jobs,
+            poll_batch_job,
+            cancel_batch_job,
             extract_patterns,
+            extract_project_patterns,
+            generate_codebase_summary,
+            generate_docstring,
+            explain_symbol,
+            generate_change_description,
+            suggest_tests,
+            count_tokens,
+            list_models,
+            get_usage_report,
+            clear_response_cache,
+            list_ollama_models,
+            check_ollama,
+            get_default_system_prompts,
+            set_api_key,
+            get_api_status,
+            get_settings,
+            update_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use crate::indexing::hybrid_search::HybridConfig;
+use crate::indexing::relevance_scorer::MatchType;
+use crate::indexing::text_normalizer::TextNormalizer;
 
 /// Represents a code symbol (function, class, method, etc.)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,6 +15,19 @@ pub struct CodeSymbol {
     pub signature: Option<String>,
     pub doc_comment: Option<String>,
     pub parent: Option<String>, // For nested symbols
+    /// Names of the functions and methods called in the body
+    #[serde(default)]
+    pub calls: Vec<String>,
+}
+
+/// Documentation written for a symbol outside its source, e.g. generated by
+/// an LLM. The symbol is found by file, name and first line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolDoc {
+    pub file_path: String,
+    pub name: String,
+    pub start_line: usize,
+    pub doc: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -29,6 +44,25 @@ pub enum SymbolKind {
     Export,
 }
 
+impl SymbolKind {
+    /// Parse a kind name as written in queries, accepting common aliases
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "function" | "fn" | "func" => Some(SymbolKind::Function),
+            "method" => Some(SymbolKind::Method),
+            "class" => Some(SymbolKind::Class),
+            "struct" => Some(SymbolKind::Struct),
+            "interface" | "trait" => Some(SymbolKind::Interface),
+            "enum" => Some(SymbolKind::Enum),
+            "constant" | "const" => Some(SymbolKind::Constant),
+            "variable" | "var" => Some(SymbolKind::Variable),
+            "import" => Some(SymbolKind::Import),
+            "export" => Some(SymbolKind::Export),
+            _ => None,
+        }
+    }
+}
+
 /// Represents a file in the codebase
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedFile {
@@ -38,6 +72,8 @@ pub struct IndexedFile {
     pub imports: Vec<String>,
     pub exports: Vec<String>,
     pub last_modified: u64,
+    #[serde(default)]
+    pub line_count: usize,
 }
 
 /// The main index structure
@@ -54,9 +90,70 @@ pub struct CodebaseIndex {
     // Normalized search index
     pub normalized_symbol_map: HashMap<String, Vec<CodeSymbol>>,
 
+    // Lowercase name word -> names of the symbols containing it, sorted
+    // so partial matches are a prefix range lookup
+    pub token_index: BTreeMap<String, BTreeSet<String>>,
+
+    // Lowercase initials of multi-word names ("hsc" for HybridSearchConfig)
+    pub acronym_map: HashMap<String, BTreeSet<String>>,
+
+    // Called name -> names of the symbols calling it
+    pub callers: HashMap<String, BTreeSet<String>>,
+
     pub language_stats: HashMap<String, usize>, // File count per language
     pub total_files: usize,
     pub indexed_at: u64,
+
+    // Term statistics for IDF scoring
+    pub corpus_stats: CorpusStats,
+}
+
+/// How many symbols' names contain each normalized term
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CorpusStats {
+    pub total_symbols: usize,
+    pub term_frequency: HashMap<String, usize>,
+}
+
+impl CorpusStats {
+    /// Count one symbol by the normalized terms of its name
+    pub fn add_symbol(&mut self, terms: &[String]) {
+        self.total_symbols += 1;
+
+        let mut seen = HashSet::new();
+        for term in terms {
+            if seen.insert(term) {
+                *self.term_frequency.entry(term.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Undo `add_symbol` for a symbol being removed
+    pub fn remove_symbol(&mut self, terms: &[String]) {
+        self.total_symbols = self.total_symbols.saturating_sub(1);
+
+        let mut seen = HashSet::new();
+        for term in terms {
+            if seen.insert(term) {
+                if let Some(count) = self.term_frequency.get_mut(term) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.term_frequency.remove(term);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Symbols containing the rarest of the terms, and at least 1 so IDF
+    /// stays finite
+    pub fn frequency(&self, terms: &[String]) -> usize {
+        terms.iter()
+            .map(|t| self.term_frequency.get(t).copied().unwrap_or(0))
+            .min()
+            .unwrap_or(0)
+            .max(1)
+    }
 }
 
 impl CodebaseIndex {
@@ -68,7 +165,11 @@ impl CodebaseIndex {
             file_paths: Vec::new(),
             file_path_components: HashMap::new(),
             normalized_symbol_map: HashMap::new(),
+            token_index: BTreeMap::new(),
+            acronym_map: HashMap::new(),
+            callers: HashMap::new(),
             language_stats: HashMap::new(),
+            corpus_stats: CorpusStats::default(),
             total_files: 0,
             indexed_at: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -77,27 +178,308 @@ impl CodebaseIndex {
         }
     }
 
-    pub fn add_file(&mut self, file: IndexedFile) {
+    pub fn add_file(&mut self, file: IndexedFile, normalizer: &TextNormalizer) {
         // Update language stats
         *self.language_stats.entry(file.language.clone()).or_insert(0) += 1;
         self.total_files += 1;
 
-        // Add symbols to symbol map
+        // Add symbols to the symbol map, the normalized term map and term statistics
         for symbol in &file.symbols {
             self.symbol_map
                 .entry(symbol.name.clone())
                 .or_insert_with(Vec::new)
                 .push(symbol.clone());
+
+            let tokens = normalizer.symbol_tokens(&symbol.name);
+            if tokens.len() > 1 {
+                let acronym: String = tokens.iter().filter_map(|t| t.chars().next()).collect();
+                self.acronym_map
+                    .entry(acronym)
+                    .or_default()
+                    .insert(symbol.name.clone());
+            }
+            for token in tokens {
+                self.token_index
+                    .entry(token)
+                    .or_default()
+                    .insert(symbol.name.clone());
+            }
+
+            let terms = normalizer.normalize_symbol(&symbol.name);
+            for term in &terms {
+                self.normalized_symbol_map
+                    .entry(term.clone())
+                    .or_insert_with(Vec::new)
+                    .push(symbol.clone());
+            }
+            self.corpus_stats.add_symbol(&terms);
+
+            for call in &symbol.calls {
+                self.callers
+                    .entry(call.clone())
+                    .or_default()
+                    .insert(symbol.name.clone());
+            }
         }
 
         // Store indexed file
         self.files.insert(file.path.clone(), file);
     }
 
-    /// Save the index to disk using bincode
+    /// Take a file and its symbols out of every lookup table, e.g. before
+    /// adding a re-parsed version of it
+    pub fn remove_file(&mut self, path: &str, normalizer: &TextNormalizer) -> Option<IndexedFile> {
+        let file = self.files.remove(path)?;
+
+        if let Some(count) = self.language_stats.get_mut(&file.language) {
+            *count -= 1;
+            if *count == 0 {
+                self.language_stats.remove(&file.language);
+            }
+        }
+        self.total_files = self.total_files.saturating_sub(1);
+
+        for symbol in &file.symbols {
+            if let Some(symbols) = self.symbol_map.get_mut(&symbol.name) {
+                symbols.retain(|s| s.file_path != path);
+                if symbols.is_empty() {
+                    self.symbol_map.remove(&symbol.name);
+                }
+            }
+
+            let terms = normalizer.normalize_symbol(&symbol.name);
+            for term in &terms {
+                if let Some(symbols) = self.normalized_symbol_map.get_mut(term) {
+                    symbols.retain(|s| s.file_path != path);
+                    if symbols.is_empty() {
+                        self.normalized_symbol_map.remove(term);
+                    }
+                }
+            }
+            self.corpus_stats.remove_symbol(&terms);
+        }
+
+        // Name-keyed tables only lose a name once no other file defines it
+        for symbol in &file.symbols {
+            let name_remains = self.symbol_map.contains_key(&symbol.name);
+            if !name_remains {
+                let tokens = normalizer.symbol_tokens(&symbol.name);
+                if tokens.len() > 1 {
+                    let acronym: String = tokens.iter().filter_map(|t| t.chars().next()).collect();
+                    remove_name(&mut self.acronym_map, &acronym, &symbol.name);
+                }
+                for token in tokens {
+                    if let Some(names) = self.token_index.get_mut(&token) {
+                        names.remove(&symbol.name);
+                        if names.is_empty() {
+                            self.token_index.remove(&token);
+                        }
+                    }
+                }
+            }
+
+            for call in &symbol.calls {
+                let still_calls = self.symbol_map.get(&symbol.name).is_some_and(|symbols| {
+                    symbols.iter().any(|s| s.calls.contains(call))
+                });
+                if !still_calls {
+                    remove_name(&mut self.callers, call, &symbol.name);
+                }
+            }
+        }
+
+        Some(file)
+    }
+
+    /// Names of symbols with a word starting with `prefix` (lowercase)
+    pub fn names_with_token_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a String> {
+        self.token_index
+            .range::<str, _>((std::ops::Bound::Included(prefix), std::ops::Bound::Unbounded))
+            .take_while(move |(token, _)| token.starts_with(prefix))
+            .flat_map(|(_, names)| names)
+    }
+
+    /// Definitions of a symbol, best first. `name` may be qualified with
+    /// its parent (`Searcher::search` or `Searcher.search`); exact-case
+    /// matches win over case-insensitive ones. Definitions in or near
+    /// `file_path` rank higher, and imports/exports are only returned
+    /// when nothing else matches.
+    pub fn find_definitions(
+        &self,
+        name: &str,
+        file_path: Option<&str>,
+        kind: Option<&SymbolKind>,
+    ) -> Vec<CodeSymbol> {
+        let name = name.trim();
+        let (parent, name) = match name.rsplit_once("::").or_else(|| name.rsplit_once('.')) {
+            Some((parent, name)) if !parent.is_empty() && !name.is_empty() => (Some(parent), name),
+            _ => (None, name),
+        };
+
+        let mut candidates: Vec<&CodeSymbol> = match self.symbol_map.get(name) {
+            Some(symbols) => symbols.iter().collect(),
+            None => self
+                .symbol_map
+                .iter()
+                .filter(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+                .flat_map(|(_, symbols)| symbols)
+                .collect(),
+        };
+        candidates.retain(|s| kind.is_none_or(|kind| &s.kind == kind));
+        if let Some(parent) = parent {
+            candidates.retain(|s| s.parent.as_deref() == Some(parent));
+        }
+        if candidates.iter().any(|s| !Self::is_reference(s)) {
+            candidates.retain(|s| !Self::is_reference(s));
+        }
+
+        // Rank by how many leading path components are shared with the
+        // file the lookup came from
+        let proximity = |symbol: &CodeSymbol| {
+            file_path.map_or(0, |from| {
+                std::path::Path::new(from)
+                    .components()
+                    .zip(std::path::Path::new(&symbol.file_path).components())
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            })
+        };
+        candidates.sort_by(|a, b| {
+            proximity(b)
+                .cmp(&proximity(a))
+                .then_with(|| a.file_path.cmp(&b.file_path))
+                .then_with(|| a.start_line.cmp(&b.start_line))
+        });
+
+        candidates.into_iter().cloned().collect()
+    }
+
+    fn is_reference(symbol: &CodeSymbol) -> bool {
+        matches!(symbol.kind, SymbolKind::Import | SymbolKind::Export)
+    }
+
+    /// The callers or callees of a symbol as a tree `depth` levels deep.
+    /// Calls are matched by name, resolving to the definition nearest the
+    /// calling file. A symbol already on the path from the root is marked
+    /// recursive instead of being expanded again.
+    pub fn call_hierarchy(
+        &self,
+        root: &CodeSymbol,
+        direction: CallDirection,
+        depth: usize,
+    ) -> CallHierarchyNode {
+        let mut path = Vec::new();
+        let mut budget = MAX_CALL_HIERARCHY_NODES;
+        self.expand_calls(root, direction, depth.min(MAX_CALL_HIERARCHY_DEPTH), &mut path, &mut budget)
+    }
+
+    fn expand_calls(
+        &self,
+        symbol: &CodeSymbol,
+        direction: CallDirection,
+        depth: usize,
+        path: &mut Vec<(String, usize)>,
+        budget: &mut usize,
+    ) -> CallHierarchyNode {
+        let key = (symbol.file_path.clone(), symbol.start_line);
+        let recursive = path.contains(&key);
+        let mut node = CallHierarchyNode {
+            symbol: symbol.clone(),
+            children: Vec::new(),
+            recursive,
+        };
+        if recursive || depth == 0 {
+            return node;
+        }
+
+        let related: Vec<CodeSymbol> = match direction {
+            CallDirection::Outgoing => symbol
+                .calls
+                .iter()
+                .filter_map(|call| {
+                    self.find_definitions(call, Some(&symbol.file_path), None)
+                        .into_iter()
+                        .find(|s| !Self::is_reference(s))
+                })
+                .collect(),
+            CallDirection::Incoming => self.direct_callers(symbol),
+        };
+
+        path.push(key);
+        for next in related {
+            if *budget == 0 {
+                break;
+            }
+            *budget -= 1;
+            node.children.push(self.expand_calls(&next, direction, depth - 1, path, budget));
+        }
+        path.pop();
+        node
+    }
+
+    /// Symbols whose bodies call `symbol` by name
+    pub fn direct_callers(&self, symbol: &CodeSymbol) -> Vec<CodeSymbol> {
+        self.callers
+            .get(&symbol.name)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| self.symbol_map.get(name))
+            .flatten()
+            .filter(|caller| caller.calls.contains(&symbol.name))
+            .cloned()
+            .collect()
+    }
+
+    /// The symbols around and inside lines `start_line..=end_line` of a
+    /// file, with the callers of all of them. None if the file isn't indexed.
+    pub fn symbols_in_range(
+        &self,
+        file_path: &str,
+        start_line: usize,
+        end_line: usize,
+    ) -> Option<SelectionSymbols> {
+        let file = self.files.get(file_path)?;
+        let definitions = file.symbols.iter().filter(|s| !Self::is_reference(s));
+
+        let (mut enclosing, mut contained): (Vec<CodeSymbol>, Vec<CodeSymbol>) = definitions
+            .filter(|s| s.start_line <= end_line && s.end_line >= start_line)
+            .cloned()
+            .partition(|s| s.start_line <= start_line && s.end_line >= end_line);
+        contained.retain(|s| s.start_line >= start_line && s.end_line <= end_line);
+        enclosing.sort_by_key(|s| (std::cmp::Reverse(s.end_line - s.start_line), s.start_line));
+        contained.sort_by_key(|s| s.start_line);
+
+        // Calls made from inside the selection itself aren't interesting
+        let is_selected = |caller: &CodeSymbol| {
+            caller.file_path == file_path
+                && enclosing
+                    .iter()
+                    .chain(&contained)
+                    .any(|s| s.start_line == caller.start_line)
+        };
+        let mut seen = HashSet::new();
+        let callers = enclosing
+            .iter()
+            .chain(&contained)
+            .flat_map(|symbol| self.direct_callers(symbol))
+            .filter(|caller| !is_selected(caller))
+            .filter(|caller| seen.insert((caller.file_path.clone(), caller.start_line)))
+            .take(MAX_SELECTION_CALLERS)
+            .collect();
+
+        Some(SelectionSymbols {
+            enclosing,
+            contained,
+            callers,
+        })
+    }
+
+    /// Save the index to disk using bincode, compressed
+    #[cfg(not(feature = "sqlite"))]
     pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), String> {
         let bytes = bincode::serialize(self)
             .map_err(|e| format!("Failed to serialize index: {}", e))?;
+        let bytes = crate::indexing::compression::compress(&bytes)?;
 
         std::fs::write(path, bytes)
             .map_err(|e| format!("Failed to write index: {}", e))?;
@@ -107,9 +489,11 @@ impl CodebaseIndex {
     }
 
     /// Load the index from disk
+    #[cfg(not(feature = "sqlite"))]
     pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self, String> {
         let bytes = std::fs::read(path)
             .map_err(|e| format!("Failed to read index: {}", e))?;
+        let bytes = crate::indexing::compression::decompress(bytes)?;
 
         let index: Self = bincode::deserialize(&bytes)
             .map_err(|e| format!("Failed to deserialize index: {}", e))?;
@@ -119,8 +503,56 @@ impl CodebaseIndex {
     }
 }
 
+/// Drop `name` from the set under `key`, and the key once its set is empty
+fn remove_name(map: &mut HashMap<String, BTreeSet<String>>, key: &str, name: &str) {
+    if let Some(names) = map.get_mut(key) {
+        names.remove(name);
+        if names.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+/// Most callers returned for a selection
+pub const MAX_SELECTION_CALLERS: usize = 50;
+
+/// Symbols related to a selected line range
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelectionSymbols {
+    /// Symbols spanning the whole selection, outermost first
+    pub enclosing: Vec<CodeSymbol>,
+    /// Symbols lying entirely inside the selection, in source order
+    pub contained: Vec<CodeSymbol>,
+    /// Symbols elsewhere that call any of the above
+    pub callers: Vec<CodeSymbol>,
+}
+
+/// Deepest call hierarchy that is expanded
+const MAX_CALL_HIERARCHY_DEPTH: usize = 10;
+
+/// Most nodes in one call hierarchy, so heavily shared helpers stay cheap
+const MAX_CALL_HIERARCHY_NODES: usize = 500;
+
+/// Which way a call hierarchy follows calls
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CallDirection {
+    /// Symbols calling the root
+    Incoming,
+    /// Symbols the root calls
+    Outgoing,
+}
+
+/// A symbol in a call hierarchy with the calls one level further out
+#[derive(Debug, Clone, Serialize)]
+pub struct CallHierarchyNode {
+    pub symbol: CodeSymbol,
+    pub children: Vec<CallHierarchyNode>,
+    /// Already expanded higher up this branch, so children are omitted
+    pub recursive: bool,
+}
+
 /// Result of indexing operation
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexResult {
     pub success: bool,
     pub total_files: usize,
@@ -130,6 +562,26 @@ pub struct IndexResult {
     pub errors: Vec<String>,
 }
 
+/// Symbol name completion for the as-you-type picker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolSuggestion {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub file_path: String,
+    pub start_line: usize,
+    pub score: f32,
+}
+
+/// Availability of semantic search after loading the embedding model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SemanticStatus {
+    pub available: bool,
+    pub model_id: String,
+    pub device: Option<String>,
+    pub offline: bool,
+    pub error: Option<String>,
+}
+
 /// Code chunk for context injection
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeChunk {
@@ -140,10 +592,39 @@ pub struct CodeChunk {
     pub language: String,
     pub symbols: Vec<String>, // Symbol names in this chunk
     pub relevance_score: f32, // For ranking
+    /// Per-engine contributions, set on hybrid search results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+/// Why a hybrid search result surfaced: each engine's rank and score for it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub traditional: Option<EngineScore>,
+    pub full_text: Option<EngineScore>,
+    pub semantic: Option<EngineScore>,
+    /// Reranker probability when the result was re-scored
+    pub reranker: Option<f32>,
+    /// Multiplier applied for the file (1 + boost), e.g. for recent edits
+    pub file_boost: Option<f32>,
+    /// How the symbol name matched, when traditional search found it
+    #[serde(default)]
+    pub match_type: Option<MatchType>,
+}
+
+/// A result's standing in one engine's list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngineScore {
+    /// 1-based position in the engine's results
+    pub rank: usize,
+    /// The engine's own score (match score, BM25 or cosine similarity)
+    pub score: f32,
+    /// What this engine added to the fused score
+    pub contribution: f32,
 }
 
 /// Query request from frontend
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct IndexQuery {
     pub keywords: Vec<String>,
     #[serde(default)]
@@ -152,6 +633,9 @@ pub struct IndexQuery {
     pub file_patterns: Option<Vec<String>>,
     #[serde(default)]
     pub max_results: Option<usize>,
+    /// Results to skip, for paging through a ranking
+    #[serde(default)]
+    pub offset: Option<usize>,
     #[serde(default)]
     pub use_full_text: Option<bool>,
     #[serde(default)]
@@ -160,4 +644,15 @@ pub struct IndexQuery {
     pub search_comments: Option<bool>,
     #[serde(default)]
     pub hybrid_config: Option<HybridConfig>,
+    #[serde(default)]
+    pub changed_scope: Option<ChangedScope>,
+}
+
+/// Files being worked on, which a query is restricted to or boosts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedScope {
+    pub files: Vec<String>,
+    /// Drop results outside the files instead of boosting those inside
+    #[serde(default)]
+    pub restrict: bool,
 }
@@ -0,0 +1,237 @@
+use crate::anthropic::credentials::{
+    load_embedding_api_key, load_llm_provider_api_key, store_embedding_api_key,
+    store_llm_provider_api_key,
+};
+use crate::anthropic::http::{HttpClientSettings, NetworkSettings};
+use crate::anthropic::prompts::SystemPrompts;
+use crate::anthropic::provider::LlmProviderConfig;
+use crate::anthropic::response_cache::DEFAULT_RESPONSE_CACHE_HOURS;
+use crate::anthropic::catalog::DEFAULT_MODEL;
+use crate::indexing::embedding_backend::EmbeddingBackendConfig;
+use crate::indexing::embedding_generator::{DevicePreference, EmbeddingModel};
+use crate::indexing::hybrid_search::HybridConfig;
+use crate::indexing::tantivy_indexer::FullTextConfig;
+use crate::indexing::text_normalizer::TextNormalizer;
+use crate::indexing::vector_store::HnswConfig;
+use ignore::overrides::OverrideBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Projects kept in memory, the active one included, unless settings say otherwise
+pub const DEFAULT_MAX_LOADED_PROJECTS: usize = 3;
+
+/// App-wide preferences, saved as JSON in the app data dir. Search defaults
+/// saved for a project (ProjectSettings) take precedence over these.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    /// Gitignore-style globs left out of every index, on top of .gitignore
+    pub ignore_globs: Vec<String>,
+    pub embedding_model: EmbeddingModel,
+    /// Compute device the embedding model runs on
+    pub embedding_device: DevicePreference,
+    /// A remote backend's API key is kept in the OS keyring, not the file
+    pub embedding_backend: EmbeddingBackendConfig,
+    /// Search config for projects without one of their own; None uses the
+    /// query-type presets
+    pub hybrid_config: Option<HybridConfig>,
+    /// Field boosts and score normalization of full-text search
+    pub full_text_config: FullTextConfig,
+    /// Re-rank the top search results with a cross-encoder model
+    pub reranking: bool,
+    /// HNSW graph parameters of the vector indexes; caches built with other
+    /// build parameters are rebuilt on load
+    pub hnsw: HnswConfig,
+    /// Synonym and abbreviation table for query expansion and symbol name
+    /// normalization; None uses the built-in table
+    pub query_synonyms: Option<HashMap<String, Vec<String>>>,
+    /// Editor command template; `{file}`, `{line}` and `{column}` are
+    /// substituted per argument. None detects an installed editor.
+    pub editor_command: Option<String>,
+    /// Projects kept in memory for fast switching, the active one included
+    pub max_loaded_projects: usize,
+    /// Disk space for cached indexes; the least recently indexed projects
+    /// are evicted past it. None is unlimited.
+    pub max_cache_bytes: Option<u64>,
+    /// Absolute path cached indexes are kept under, e.g. on a fast scratch
+    /// disk or outside backups. None uses the app data dir.
+    pub cache_dir: Option<String>,
+    /// Absolute path downloaded embedding models are stored under. None uses
+    /// the models directory in the app data dir.
+    pub model_cache_dir: Option<String>,
+    /// Absolute path of a directory holding the embedding model's files,
+    /// used in place of downloaded or bundled ones
+    pub model_local_path: Option<String>,
+    /// Never download models; only local, bundled or cached files are used
+    pub model_offline: bool,
+    /// Service for intent analysis, pattern extraction, query rewriting and
+    /// chat. Another provider's API key is kept in the OS keyring.
+    pub llm_provider: LlmProviderConfig,
+    /// Anthropic model for intent analysis, pattern extraction and query rewriting
+    pub llm_model: String,
+    /// Have the LLM rewrite each search query into alternate phrasings; one
+    /// API call per search
+    pub multi_query: bool,
+    /// How long LLM replies are reused for identical requests; 0 turns
+    /// caching off
+    pub response_cache_hours: u64,
+    /// Replacements for the built-in system prompts; a project's own take
+    /// precedence
+    pub system_prompts: SystemPrompts,
+    /// Proxy and extra CA certificates for the LLM providers, remote
+    /// embedding APIs and model downloads
+    pub network: NetworkSettings,
+    /// Timeouts and connection pooling of requests to the LLM provider
+    pub llm_http: HttpClientSettings,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            ignore_globs: Vec::new(),
+            embedding_model: EmbeddingModel::default(),
+            embedding_device: DevicePreference::default(),
+            embedding_backend: EmbeddingBackendConfig::default(),
+            hybrid_config: None,
+            full_text_config: FullTextConfig::default(),
+            reranking: false,
+            hnsw: HnswConfig::default(),
+            query_synonyms: None,
+            editor_command: None,
+            max_loaded_projects: DEFAULT_MAX_LOADED_PROJECTS,
+            max_cache_bytes: None,
+            cache_dir: None,
+            model_cache_dir: None,
+            model_local_path: None,
+            model_offline: false,
+            llm_provider: LlmProviderConfig::default(),
+            llm_model: DEFAULT_MODEL.to_string(),
+            multi_query: false,
+            response_cache_hours: DEFAULT_RESPONSE_CACHE_HOURS,
+            system_prompts: SystemPrompts::default(),
+            network: NetworkSettings::default(),
+            llm_http: HttpClientSettings::default(),
+        }
+    }
+}
+
+impl AppSettings {
+    fn path(app_data_dir: &Path) -> PathBuf {
+        app_data_dir.join("settings.json")
+    }
+
+    /// Load saved settings, or defaults when none were saved
+    pub fn load(app_data_dir: &Path) -> Result<Self, String> {
+        let path = Self::path(app_data_dir);
+        let mut settings = if path.exists() {
+            let json = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read settings: {}", e))?;
+            serde_json::from_str(&json).map_err(|e| format!("Failed to parse settings: {}", e))?
+        } else {
+            Self::default()
+        };
+
+        if let EmbeddingBackendConfig::Remote(ref mut remote) = settings.embedding_backend {
+            remote.api_key = load_embedding_api_key()?.unwrap_or_default();
+        }
+        if let Some(secret) = settings.llm_provider.secret_mut() {
+            *secret = load_llm_provider_api_key()?.unwrap_or_default();
+        }
+        Ok(settings)
+    }
+
+    /// Save settings, moving remote embedding and LLM provider API keys to
+    /// the keyring
+    pub fn save(&self, app_data_dir: &Path) -> Result<(), String> {
+        let mut settings = self.clone();
+        if let EmbeddingBackendConfig::Remote(ref mut remote) = settings.embedding_backend {
+            store_embedding_api_key(&remote.api_key)?;
+            remote.api_key.clear();
+        }
+        if let Some(secret) = settings.llm_provider.secret_mut() {
+            store_llm_provider_api_key(secret)?;
+            secret.clear();
+        }
+
+        fs::create_dir_all(app_data_dir)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+        let json = serde_json::to_string_pretty(&settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(Self::path(app_data_dir), json)
+            .map_err(|e| format!("Failed to write settings: {}", e))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let mut builder = OverrideBuilder::new("/");
+        for glob in &self.ignore_globs {
+            builder
+                .add(&format!("!{}", glob))
+                .map_err(|e| format!("Invalid ignore glob '{}': {}", glob, e))?;
+        }
+        if let Some(ref config) = self.hybrid_config {
+            config.validate()?;
+        }
+        self.full_text_config.validate()?;
+        self.hnsw.validate()?;
+        if self
+            .editor_command
+            .as_deref()
+            .is_some_and(|command| !command.contains("{file}"))
+        {
+            return Err("Editor command must contain {file}".to_string());
+        }
+        if self.max_loaded_projects == 0 {
+            return Err("At least one project must stay loaded".to_string());
+        }
+        if self
+            .cache_dir
+            .as_deref()
+            .is_some_and(|dir| !Path::new(dir).is_absolute())
+        {
+            return Err("Cache directory must be an absolute path".to_string());
+        }
+        if self
+            .model_cache_dir
+            .as_deref()
+            .is_some_and(|dir| !Path::new(dir).is_absolute())
+        {
+            return Err("Model cache directory must be an absolute path".to_string());
+        }
+        if self
+            .model_local_path
+            .as_deref()
+            .is_some_and(|dir| !Path::new(dir).is_absolute())
+        {
+            return Err("Model path must be an absolute path".to_string());
+        }
+        self.llm_provider.validate()?;
+        if self.llm_model.trim().is_empty() {
+            return Err("LLM model must not be empty".to_string());
+        }
+        self.system_prompts.validate()?;
+        self.network.validate()?;
+        self.llm_http.validate()?;
+        Ok(())
+    }
+
+    /// The synonym table to use: the saved one, or the built-in one
+    pub fn synonym_table(&self) -> HashMap<String, Vec<String>> {
+        self.query_synonyms
+            .clone()
+            .unwrap_or_else(TextNormalizer::default_synonyms)
+    }
+
+    /// Settings as shown to the frontend, with any API key blanked out
+    pub fn redacted(&self) -> Self {
+        let mut settings = self.clone();
+        if let EmbeddingBackendConfig::Remote(ref mut remote) = settings.embedding_backend {
+            remote.api_key.clear();
+        }
+        if let Some(secret) = settings.llm_provider.secret_mut() {
+            secret.clear();
+        }
+        settings
+    }
+}
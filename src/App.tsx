@@ -1,5 +1,6 @@
 import React from 'react';
 import { useAppStore } from './store/app-store';
+import { getApiStatus, setApiKey as storeApiKey } from './lib/tauri-api';
 import { ProjectSelector } from './components/project-selector/ProjectSelector';
 import { PromptEditor } from './components/prompt-editor/PromptEditor';
 import { OptimizedPromptViewer } from './components/prompt-editor/OptimizedPromptViewer';
@@ -13,21 +14,40 @@ function App() {
   const { agent, initializeAgent, tryLoadCachedIndex } = useAppStore();
   const [apiKey, setApiKey] = React.useState('');
 
-  const handleSetApiKey = () => {
+  const handleSetApiKey = async () => {
     if (apiKey.trim()) {
-      initializeAgent(apiKey);
-      // Store in localStorage for development convenience
-      localStorage.setItem('anthropic_api_key', apiKey);
+      try {
+        // Kept in the OS keyring; the backend reads it from there
+        await storeApiKey(apiKey.trim());
+        setApiKey('');
+        initializeAgent();
+      } catch (e) {
+        console.error('Failed to store API key:', e);
+      }
     }
   };
 
-  // Load API key from localStorage on mount
+  // Skip the key screen when a key is already stored
   React.useEffect(() => {
-    const stored = localStorage.getItem('anthropic_api_key');
-    if (stored) {
-      setApiKey(stored);
-      initializeAgent(stored);
+    // Earlier versions kept the key in localStorage; move it to the keyring
+    const legacy = localStorage.getItem('anthropic_api_key');
+    if (legacy) {
+      storeApiKey(legacy)
+        .then(() => {
+          localStorage.removeItem('anthropic_api_key');
+          initializeAgent();
+        })
+        .catch((e) => console.error('Failed to move API key to keyring:', e));
+      return;
     }
+
+    getApiStatus()
+      .then((status) => {
+        if (status.configured) {
+          initializeAgent();
+        }
+      })
+      .catch((e) => console.error('Failed to check API key:', e));
   }, [initializeAgent]);
 
   // Try to load cached index when agent is initialized
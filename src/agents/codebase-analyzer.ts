@@ -24,10 +24,16 @@ export interface ProjectStructure {
 
 export class CodebaseAnalyzer {
   private stats: IndexStats | null = null;
+  private project = '';
+
+  setProject(path: string) {
+    this.project = path;
+    this.stats = null;
+  }
 
   async initialize(): Promise<void> {
     try {
-      this.stats = await getIndexStats();
+      this.stats = await getIndexStats(this.project);
     } catch (error) {
       console.error('Failed to initialize codebase analyzer:', error);
     }
@@ -80,12 +86,12 @@ export class CodebaseAnalyzer {
   private async categorizeFiles(): Promise<ProjectStructure['filesByType']> {
     try {
       const [components, services, utilities, types, tests, configs] = await Promise.all([
-        searchFiles('component', 20),
-        searchFiles('service', 20),
-        searchFiles('util', 20),
-        searchFiles('type', 20),
-        searchFiles('test', 20),
-        searchFiles('config', 20),
+        searchFiles(this.project, 'component', 20),
+        searchFiles(this.project, 'service', 20),
+        searchFiles(this.project, 'util', 20),
+        searchFiles(this.project, 'type', 20),
+        searchFiles(this.project, 'test', 20),
+        searchFiles(this.project, 'config', 20),
       ]);
 
       return {
@@ -112,7 +118,7 @@ export class CodebaseAnalyzer {
     try {
       // Search for files matching each keyword
       for (const term of allTerms) {
-        const files = await searchFiles(term, 10);
+        const files = await searchFiles(this.project, term, 10);
         files.forEach((file) => uniqueFiles.add(file));
       }
 
@@ -138,7 +144,7 @@ export class CodebaseAnalyzer {
       // Get symbols from related files
       for (const filePath of relatedFiles.slice(0, 5)) {
         try {
-          const symbols = await getFileSymbols(filePath);
+          const symbols = await getFileSymbols(this.project, filePath);
 
           // Filter symbols that match keywords
           const relevantSymbols = symbols.filter((symbol) => {
@@ -195,12 +201,12 @@ export class CodebaseAnalyzer {
         tauriFiles,
         electronFiles,
       ] = await Promise.all([
-        searchFiles('react', 5),
-        searchFiles('vue', 5),
-        searchFiles('angular', 5),
-        searchFiles('next', 5),
-        searchFiles('tauri', 5),
-        searchFiles('electron', 5),
+        searchFiles(this.project, 'react', 5),
+        searchFiles(this.project, 'vue', 5),
+        searchFiles(this.project, 'angular', 5),
+        searchFiles(this.project, 'next', 5),
+        searchFiles(this.project, 'tauri', 5),
+        searchFiles(this.project, 'electron', 5),
       ]);
 
       if (reactFiles.length > 0) patterns.push('Uses React framework');
@@ -212,10 +218,10 @@ export class CodebaseAnalyzer {
 
       // Detect architectural patterns
       const [serviceFiles, componentFiles, storeFiles, apiFiles] = await Promise.all([
-        searchFiles('service', 5),
-        searchFiles('component', 5),
-        searchFiles('store', 5),
-        searchFiles('api', 5),
+        searchFiles(this.project, 'service', 5),
+        searchFiles(this.project, 'component', 5),
+        searchFiles(this.project, 'store', 5),
+        searchFiles(this.project, 'api', 5),
       ]);
 
       if (serviceFiles.length > 2) patterns.push('Service layer architecture');
@@ -239,7 +245,7 @@ export class CodebaseAnalyzer {
       const fileName = targetFile.split('/').pop()?.replace(/\.[^.]+$/, '') || '';
 
       // Search for files that might import this file
-      const potentialDependents = await searchFiles(fileName, 20);
+      const potentialDependents = await searchFiles(this.project, fileName, 20);
 
       // Filter out the target file itself
       return potentialDependents.filter((file) => file !== targetFile);
@@ -254,7 +260,7 @@ export class CodebaseAnalyzer {
    */
   async findSemanticallySimilarCode(description: string, maxResults = 10): Promise<CodeChunk[]> {
     try {
-      return await searchSemantic(description, maxResults);
+      return await searchSemantic(this.project, description, maxResults);
     } catch (error) {
       console.error('Failed semantic search:', error);
       return [];
@@ -2,29 +2,12 @@ import { invoke } from '@tauri-apps/api/core';
 import type { PromptIntent } from '../types/agent';
 
 export class IntentAnalyzer {
-  private apiKey: string;
-
-  constructor(apiKey: string) {
-    this.apiKey = apiKey;
-  }
-
   async analyzeIntent(rawPrompt: string): Promise<PromptIntent> {
     try {
-      // Call Tauri backend instead of direct API call
-      const response = await invoke<string>('analyze_intent', {
-        apiKey: this.apiKey,
+      // The backend parses and validates the model's reply
+      return await invoke<PromptIntent>('analyze_intent', {
         prompt: rawPrompt,
       });
-
-      // Clean up the response to handle markdown code blocks
-      let jsonText = response.trim();
-      if (jsonText.startsWith('```')) {
-        // Remove markdown code block markers
-        jsonText = jsonText.replace(/^```(?:json)?\n?/, '').replace(/\n?```$/, '');
-      }
-
-      const intent = JSON.parse(jsonText);
-      return intent;
     } catch (error) {
       console.error('Failed to parse intent:', error);
       // Fallback to basic intent extraction
@@ -12,19 +12,18 @@ import type {
 } from '../types/agent';
 
 export class PromptOptimizerAgent {
-  private apiKey: string;
   private intentAnalyzer: IntentAnalyzer;
   private codebaseAnalyzer: CodebaseAnalyzer;
   private indexedPath: string | null = null;
 
-  constructor(apiKey: string) {
-    this.apiKey = apiKey;
-    this.intentAnalyzer = new IntentAnalyzer(apiKey);
+  constructor() {
+    this.intentAnalyzer = new IntentAnalyzer();
     this.codebaseAnalyzer = new CodebaseAnalyzer();
   }
 
   setIndexedPath(path: string) {
     this.indexedPath = path;
+    this.codebaseAnalyzer.setProject(path);
   }
 
   async optimizePrompt(rawPrompt: string): Promise<OptimizedPrompt> {
@@ -92,7 +91,7 @@ export class PromptOptimizerAgent {
       }
 
       // Query Rust backend for relevant code (request more results for ranking)
-      const results = await queryIndex({
+      const results = await queryIndex(this.indexedPath!, {
         keywords: searchTerms,
         max_results: 20,
       });
@@ -400,7 +399,6 @@ ${ctx.content}
     try {
       // Call Tauri backend instead of direct API call
       const patterns = await invoke<string>('extract_patterns', {
-        apiKey: this.apiKey,
         codeSnippets,
       });
       return patterns;
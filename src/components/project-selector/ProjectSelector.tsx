@@ -16,9 +16,10 @@ export function ProjectSelector() {
 
   const isIndexing = indexStatus === 'indexing';
   const isLoadingCache = indexStatus === 'loading_cache';
+  const isUpgradingCache = indexStatus === 'upgrading_cache';
   const isComplete = indexStatus === 'complete';
   const isError = indexStatus === 'error';
-  const isBusy = isIndexing || isLoadingCache;
+  const isBusy = isIndexing || isLoadingCache || isUpgradingCache;
 
   // Format quick summary
   const formatNumber = (num: number) => num.toLocaleString();
@@ -46,7 +47,7 @@ export function ProjectSelector() {
             {isBusy ? (
               <>
                 <Loader2 className="h-4 w-4 animate-spin" />
-                {isLoadingCache ? 'Loading cache...' : 'Indexing...'}
+                {isLoadingCache ? 'Loading cache...' : isUpgradingCache ? 'Rebuilding...' : 'Indexing...'}
               </>
             ) : (
               <>
@@ -59,11 +60,12 @@ export function ProjectSelector() {
             <div className="flex-1 flex items-center gap-2 text-sm">
               {isComplete && <CheckCircle2 className="h-4 w-4 text-green-500" />}
               {isError && <XCircle className="h-4 w-4 text-red-500" />}
-              {isLoadingCache && <Loader2 className="h-4 w-4 animate-spin text-blue-500" />}
+              {(isLoadingCache || isUpgradingCache) && <Loader2 className="h-4 w-4 animate-spin text-blue-500" />}
               <span className="font-medium">
                 {isComplete && 'Indexed: '}
                 {isError && 'Failed: '}
                 {isLoadingCache && 'Loading: '}
+                {isUpgradingCache && 'Rebuilding after cache upgrade: '}
               </span>
               <span className="text-muted-foreground truncate">{indexedPath}</span>
             </div>
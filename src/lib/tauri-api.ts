@@ -1,6 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-dialog';
-import type { IndexResult, IndexStats, CodeChunk, IndexQuery, CodeSymbol } from '../types/agent';
+import type { IndexResult, IndexStats, CodeChunk, IndexQuery, CodeSymbol, ApiStatus, CacheUpgrade } from '../types/agent';
 
 export async function selectDirectory(): Promise<string | null> {
   const selected = await open({
@@ -15,22 +16,34 @@ export async function indexCodebase(path: string): Promise<IndexResult> {
   return invoke<IndexResult>('index_codebase', { path });
 }
 
-export async function queryIndex(query: IndexQuery): Promise<CodeChunk[]> {
-  return invoke<CodeChunk[]>('query_index', { query });
+export async function queryIndex(project: string, query: IndexQuery): Promise<CodeChunk[]> {
+  return invoke<CodeChunk[]>('query_index', { query, project });
 }
 
-export async function getIndexStats(): Promise<IndexStats> {
-  return invoke<IndexStats>('get_index_stats');
+export async function getIndexStats(project: string): Promise<IndexStats> {
+  return invoke<IndexStats>('get_index_stats', { project });
 }
 
-export async function getFileSymbols(filePath: string): Promise<CodeSymbol[]> {
-  return invoke<CodeSymbol[]>('get_file_symbols', { filePath });
+export async function getFileSymbols(project: string, filePath: string): Promise<CodeSymbol[]> {
+  return invoke<CodeSymbol[]>('get_file_symbols', { filePath, project });
 }
 
-export async function searchFiles(query: string, maxResults?: number): Promise<string[]> {
-  return invoke<string[]>('search_files', { query, maxResults });
+export async function searchFiles(project: string, query: string, maxResults?: number): Promise<string[]> {
+  return invoke<string[]>('search_files', { query, maxResults, project });
 }
 
-export async function searchSemantic(query: string, maxResults?: number): Promise<CodeChunk[]> {
-  return invoke<CodeChunk[]>('search_semantic', { query, maxResults });
+export async function searchSemantic(project: string, query: string, maxResults?: number): Promise<CodeChunk[]> {
+  return invoke<CodeChunk[]>('search_semantic', { query, maxResults, project });
+}
+
+export async function setApiKey(apiKey: string): Promise<void> {
+  return invoke<void>('set_api_key', { apiKey });
+}
+
+export async function getApiStatus(): Promise<ApiStatus> {
+  return invoke<ApiStatus>('get_api_status');
+}
+
+export async function onCacheUpgrade(handler: (upgrade: CacheUpgrade) => void): Promise<UnlistenFn> {
+  return listen<CacheUpgrade>('cache-upgrade', (event) => handler(event.payload));
 }
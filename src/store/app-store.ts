@@ -1,12 +1,12 @@
 import { create } from 'zustand';
 import { PromptOptimizerAgent } from '../agents/prompt-optimizer';
-import { indexCodebase, getIndexStats } from '../lib/tauri-api';
+import { indexCodebase, getIndexStats, onCacheUpgrade } from '../lib/tauri-api';
 import type { OptimizedPrompt, IndexStats, IndexResult } from '../types/agent';
 
 interface AppState {
   // Indexing state
   indexedPath: string | null;
-  indexStatus: 'idle' | 'indexing' | 'complete' | 'error' | 'loading_cache';
+  indexStatus: 'idle' | 'indexing' | 'complete' | 'error' | 'loading_cache' | 'upgrading_cache';
   indexStats: IndexStats | null;
   indexResult: IndexResult | null;
   indexError: string | null;
@@ -19,10 +19,9 @@ interface AppState {
 
   // Agent instance
   agent: PromptOptimizerAgent | null;
-  apiKey: string | null;
 
   // Actions
-  initializeAgent: (apiKey: string) => void;
+  initializeAgent: () => void;
   setIndexedPath: (path: string) => void;
   indexCodebase: (path: string) => Promise<void>;
   setRawPrompt: (prompt: string) => void;
@@ -68,12 +67,11 @@ export const useAppStore = create<AppState>((set, get) => ({
   isOptimizing: false,
   optimizeError: null,
   agent: null,
-  apiKey: null,
 
-  // Initialize agent with API key
-  initializeAgent: (apiKey: string) => {
-    const agent = new PromptOptimizerAgent(apiKey);
-    set({ agent, apiKey });
+  // Initialize agent once an API key is stored
+  initializeAgent: () => {
+    const agent = new PromptOptimizerAgent();
+    set({ agent });
 
     // Restore indexed path if it exists
     const { indexedPath } = get();
@@ -95,6 +93,7 @@ export const useAppStore = create<AppState>((set, get) => ({
   // Index codebase
   indexCodebase: async (path: string) => {
     set({ indexStatus: 'indexing', indexError: null });
+    const unlisten = await onCacheUpgrade(() => set({ indexStatus: 'upgrading_cache' }));
 
     try {
       console.log('Starting indexing for:', path);
@@ -131,13 +130,20 @@ export const useAppStore = create<AppState>((set, get) => ({
         indexStatus: 'error',
         indexError: error instanceof Error ? error.message : String(error),
       });
+    } finally {
+      unlisten();
     }
   },
 
   // Get index statistics
   getIndexStats: async () => {
+    const { indexedPath } = get();
+    if (!indexedPath) {
+      return;
+    }
+
     try {
-      const stats = await getIndexStats();
+      const stats = await getIndexStats(indexedPath);
       set({ indexStats: stats });
     } catch (error) {
       console.error('Failed to get index stats:', error);
@@ -213,6 +219,8 @@ export const useAppStore = create<AppState>((set, get) => ({
 
     console.log('Attempting to load cached index for:', indexedPath);
     set({ indexStatus: 'loading_cache' });
+    // An outdated cache is rebuilt, which takes as long as a fresh index
+    const unlisten = await onCacheUpgrade(() => set({ indexStatus: 'upgrading_cache' }));
 
     try {
       // Try to index with cache (force_reindex: false)
@@ -248,6 +256,8 @@ export const useAppStore = create<AppState>((set, get) => ({
         indexedPath: null,
       });
       storeIndexedPath(null);
+    } finally {
+      unlisten();
     }
   },
 }));
@@ -26,7 +26,7 @@ export interface CodeContext {
 export interface PromptIntent {
   action: 'create' | 'modify' | 'fix' | 'explain' | 'refactor' | 'other';
   keywords: string[];
-  scope: 'file' | 'function' | 'class' | 'project';
+  scope: 'file' | 'function' | 'class' | 'module' | 'project';
   entities: string[]; // Functions, classes, files mentioned
 }
 
@@ -40,6 +40,17 @@ export interface CodeSymbol {
   signature?: string;
   doc_comment?: string;
   parent?: string;
+  calls: string[];
+}
+
+export interface IndexedFile {
+  path: string;
+  language: string;
+  symbols: CodeSymbol[];
+  imports: string[];
+  exports: string[];
+  last_modified: number;
+  line_count: number;
 }
 
 export type SymbolKind =
@@ -54,6 +65,62 @@ export type SymbolKind =
   | 'Import'
   | 'Export';
 
+export interface SymbolSuggestion {
+  name: string;
+  kind: SymbolKind;
+  file_path: string;
+  start_line: number;
+  score: number;
+}
+
+export interface SelectionSymbols {
+  enclosing: CodeSymbol[];
+  contained: CodeSymbol[];
+  callers: CodeSymbol[];
+}
+
+export type CallDirection = 'Incoming' | 'Outgoing';
+
+export interface CallHierarchyNode {
+  symbol: CodeSymbol;
+  children: CallHierarchyNode[];
+  recursive: boolean;
+}
+
+export type ExportFormat = 'json' | 'sqlite';
+
+export interface PinnedChunk {
+  id: number;
+  file_path: string;
+  start_line: number;
+  end_line: number;
+  symbol_name: string | null;
+  note: string | null;
+  pinned_at: number;
+}
+
+export type QueryMode = 'keyword' | 'semantic' | 'coarse_to_fine' | 'files';
+
+export interface VectorFilter {
+  languages?: string[] | null;
+  path_prefix?: string | null;
+  symbol_kinds?: SymbolKind[] | null;
+  file_paths?: string[] | null;
+  exclude_languages?: string[] | null;
+  exclude_paths?: string[] | null;
+  exclude_symbol_kinds?: SymbolKind[] | null;
+}
+
+export interface QueryHistoryEntry {
+  query: string;
+  mode: QueryMode;
+  symbol_kinds: SymbolKind[] | null;
+  file_patterns: string[] | null;
+  filter: VectorFilter | null;
+  result_count: number;
+  timestamp: number;
+}
+
 export interface IndexResult {
   success: boolean;
   total_files: number;
@@ -63,11 +130,29 @@ export interface IndexResult {
   errors: string[];
 }
 
+export interface ApiStatus {
+  configured: boolean;
+}
+
+export interface ProjectReady {
+  project_path: string;
+  result: IndexResult | null;
+  error: string | null;
+}
+
 export interface IndexStats {
   total_files: number;
   languages: Record<string, number>;
   root_path: string;
   indexed_at: number;
+  total_symbols: number;
+  total_lines: number;
+  symbol_kinds: Partial<Record<SymbolKind, number>>;
+  directories: Record<string, { files: number; symbols: number }>;
+  largest_files: { path: string; lines: number; symbols: number }[];
+  symbol_vectors: number;
+  file_vectors: number;
+  cache_size_bytes: number;
 }
 
 export interface CodeChunk {
@@ -78,11 +163,416 @@ export interface CodeChunk {
   language: string;
   symbols: string[];
   relevance_score: number;
+  score_breakdown?: ScoreBreakdown;
+}
+
+export interface FileContent {
+  file_path: string;
+  content: string;
+  start_line: number;
+  end_line: number;
+  total_lines: number;
+}
+
+export interface EditorConfig {
+  command: string | null;
+}
+
+export type EmbeddingModel = 'all_mini_lm_l6_v2' | 'bge_small_en' | 'jina_v2_base_code';
+
+export type EmbeddingBackendConfig =
+  | { kind: 'local' }
+  | { kind: 'onnx' }
+  | {
+      kind: 'remote';
+      provider: 'voyage' | 'open_ai';
+      api_key: string;
+      model?: string | null;
+      base_url?: string | null;
+    };
+
+export interface HybridConfig {
+  traditional_weight: number;
+  full_text_weight: number;
+  semantic_weight: number;
+  rrf_k: number;
+  max_results: number;
+  rerank_top_n?: number;
+  min_similarity?: number;
+  min_fused_score?: number;
+  fusion_strategy?: 'rrf' | 'weighted_score';
+  recency_weight?: number;
+  recency_half_life_days?: number;
+  churn_weight?: number;
+}
+
+export interface FullTextConfig {
+  symbol_name_boost: number;
+  file_path_boost: number;
+  signature_boost: number;
+  doc_comment_boost: number;
+  normalization: 'none' | 'max_score' | 'saturating';
+  saturation: number;
+}
+
+export interface HnswConfig {
+  connectivity: number; // Fixed once an index is built
+  expansion_add: number; // Fixed once an index is built
+  expansion_search: number;
+}
+
+export interface AppSettings {
+  ignore_globs: string[];
+  embedding_model: EmbeddingModel;
+  embedding_device: 'auto' | 'cpu' | 'cuda' | 'metal';
+  embedding_backend: EmbeddingBackendConfig;
+  hybrid_config: HybridConfig | null;
+  full_text_config: FullTextConfig;
+  reranking: boolean;
+  hnsw: HnswConfig;
+  query_synonyms: Record<string, string[]> | null; // Null uses the built-in table
+  editor_command: string | null;
+  max_loaded_projects: number;
+  max_cache_bytes: number | null;
+  cache_dir: string | null;
+  model_cache_dir: string | null; // Null uses the app data dir
+  model_local_path: string | null; // Directory with the embedding model's files
+  model_offline: boolean; // Never download models
+  llm_provider: LlmProviderConfig;
+  llm_model: string;
+  multi_query: boolean; // One extra LLM call per search
+  response_cache_hours: number; // 0 turns caching off
+  system_prompts: SystemPrompts;
+  network: NetworkSettings;
+  llm_http: HttpClientSettings;
+}
+
+export interface HttpClientSettings {
+  request_timeout_secs: number;
+  connect_timeout_secs: number;
+  read_timeout_secs: number; // Longest wait for more of a response
+  pool_idle_timeout_secs: number;
+  pool_max_idle_per_host: number;
+  tcp_keepalive_secs: number; // 0 turns keep-alive probes off
+}
+
+export interface NetworkSettings {
+  proxy: string | null; // Null uses HTTPS_PROXY/HTTP_PROXY
+  no_proxy: string | null; // Comma-separated, as in NO_PROXY
+  ca_bundle: string | null; // PEM file trusted on top of the system's CAs
+}
+
+export type LlmProviderConfig =
+  | { kind: 'anthropic' }
+  | {
+      kind: 'open_ai_compatible';
+      base_url: string;
+      api_key: string; // Blank when read; kept in the OS keyring
+      model: string;
+    }
+  | { kind: 'ollama'; base_url: string; model: string }
+  | {
+      kind: 'bedrock';
+      region: string;
+      model: string;
+      access_key_id: string; // Blank to use the AWS_* environment variables
+      secret_access_key: string; // Blank when read; kept in the OS keyring
+    }
+  | {
+      kind: 'azure_open_ai';
+      endpoint: string;
+      deployment: string;
+      api_version: string;
+      api_key: string; // Blank when read; kept in the OS keyring
+    };
+
+export type ModelUse = 'intent' | 'enhancement' | 'chat';
+
+// Metadata is null where the catalog doesn't know the model
+export interface ModelInfo {
+  id: string;
+  name: string;
+  context_window: number | null;
+  max_output_tokens: number | null;
+  input_price_per_mtok: number | null; // USD; 0 for local models
+  output_price_per_mtok: number | null;
+  recommended_for: ModelUse[];
+}
+
+export type UsageFeature =
+  | 'intent'
+  | 'patterns'
+  | 'query_rewrite'
+  | 'chat'
+  | 'batch'
+  | 'codebase_summary'
+  | 'docstring'
+  | 'change_description'
+  | 'test_suggestions';
+
+export interface UsageEntry {
+  date: string; // UTC, YYYY-MM-DD
+  project_path: string; // Empty when no project was open
+  feature: UsageFeature;
+  model: string;
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number | null; // Null for models without a known price
+}
+
+export interface UsageTotals {
+  requests: number;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+  unpriced_requests: number;
+}
+
+export interface UsageReport {
+  entries: UsageEntry[];
+  by_feature: Partial<Record<UsageFeature, UsageTotals>>;
+  total: UsageTotals;
+}
+
+export interface ConventionsProfile {
+  project_path: string;
+  profile: string;
+  languages: string[];
+  files_sampled: string[];
+  chunks: number;
+  sample_tokens: number;
+}
+
+export interface CodebaseSummary {
+  project_path: string;
+  summary: string;
+  model: string;
+  generated_at: number;
+  indexed_at: number;
+  files: number;
+}
+
+export interface DocstringEdit {
+  file_path: string;
+  line: number;
+  replaced_lines: number;
+  original: string;
+  text: string;
+  doc: string;
+}
+
+export interface ExplanationEvent {
+  explanation_id: string;
+  text: string;
+}
+
+export type ChangeDescriptionKind = 'commit_message' | 'pull_request';
+
+export interface ChangeDescription {
+  kind: ChangeDescriptionKind;
+  text: string;
+  files: string[];
+  symbols: CodeSymbol[];
+  diff_truncated: boolean;
+}
+
+export interface TestSuggestion {
+  name: string;
+  description: string;
+  code: string;
+}
+
+export interface TestSuggestions {
+  symbol: CodeSymbol;
+  language: string;
+  existing_tests: CodeSymbol[];
+  conventions_used: boolean; // Whether the project's conventions profile was followed
+  suggestions: TestSuggestion[];
+}
+
+export type BatchJobKind = 'docstrings' | 'summaries';
+
+export interface BatchJob {
+  id: string;
+  project_path: string;
+  kind: BatchJobKind;
+  model: string;
+  created_at: number;
+  status: 'in_progress' | 'canceling' | 'ended';
+  counts: {
+    processing: number;
+    succeeded: number;
+    errored: number;
+    canceled: number;
+    expired: number;
+  };
+  symbols: { file_path: string; name: string; start_line: number }[];
+  ingested_at: number | null;
+  ingested: number;
+}
+
+export interface OllamaModel {
+  name: string;
+  size: number;
+  modified_at: string;
+}
+
+export interface OllamaStatus {
+  running: boolean;
+  version: string | null;
+  model_available: boolean;
+  error: string | null;
+}
+
+// A null prompt keeps the built-in one
+export interface SystemPrompts {
+  intent_analysis: string | null;
+  pattern_extraction: string | null;
+}
+
+export interface EngineScore {
+  rank: number;
+  score: number;
+  contribution: number;
+}
+
+export interface ScoreBreakdown {
+  traditional: EngineScore | null;
+  full_text: EngineScore | null;
+  semantic: EngineScore | null;
+  reranker: number | null;
+  file_boost: number | null;
+  match_type: MatchType | null;
 }
 
+export type MatchType = 'Exact' | 'NormalizedExact' | 'Prefix' | 'Contains' | 'Normalized' | 'Acronym';
+
 export interface IndexQuery {
   keywords: string[];
   symbol_kinds?: SymbolKind[];
   file_patterns?: string[];
   max_results?: number;
+  offset?: number;
+  changed_scope?: ChangedScope;
+}
+
+export interface ChangedScope {
+  files: string[];
+  restrict?: boolean;
+}
+
+export interface GrepOptions {
+  regex?: boolean;
+  case_sensitive?: boolean;
+  whole_word?: boolean;
+  file_patterns?: string[];
+  max_results?: number;
+}
+
+export interface GrepMatch {
+  file_path: string;
+  line_number: number;
+  line: string;
+  ranges: [number, number][];
+}
+
+export interface GrepMatchesEvent {
+  search_id: string;
+  matches: GrepMatch[];
+}
+
+export interface GrepSummary {
+  search_id: string;
+  files_searched: number;
+  total_matches: number;
+  truncated: boolean;
+}
+
+export interface CacheInfo {
+  project_path: string;
+  branch: string | null;
+  cached_at: number;
+  last_accessed: number;
+  file_count: number;
+  size_bytes: number;
+}
+
+export interface CacheUsage {
+  projects: CacheInfo[];
+  total_bytes: number;
+  max_bytes: number | null;
+}
+
+export interface RemovedCache {
+  project_path: string | null;
+  reason: string;
+  size_bytes: number;
+}
+
+export interface CacheGcReport {
+  ran_at: number;
+  scanned: number;
+  removed: RemovedCache[];
+  freed_bytes: number;
+  errors: string[];
+}
+
+export interface CacheUpgrade {
+  project_path: string;
+  from_version: number;
+  to_version: number;
+}
+
+export interface BundleManifest {
+  format_version: number;
+  cache_format_version: number;
+  root_path: string;
+  file_count: number;
+}
+
+export interface EnhancedPrompt {
+  original: string;
+  prompt: string;
+  intent: PromptIntent;
+  chunks: CodeChunk[];
+  context_tokens: number;
+  tokens_estimated: boolean;
+  attachments: ContentBlock[];
+  attachment_tokens: number;
+}
+
+export type ContentBlock =
+  | { type: 'text'; text: string }
+  | { type: 'tool_use'; id: string; name: string; input: unknown }
+  | { type: 'tool_result'; tool_use_id: string; content: string; is_error?: boolean }
+  | { type: 'image'; source: MediaSource }
+  | { type: 'document'; source: MediaSource; title?: string };
+
+export type MediaSource =
+  | { type: 'base64'; media_type: string; data: string }
+  | { type: 'text'; media_type: string; data: string }
+  | { type: 'url'; url: string };
+
+export interface ChatMessage {
+  role: 'user' | 'assistant';
+  content: string | ContentBlock[];
+}
+
+export interface ChatSession {
+  id: number;
+  project_path: string;
+  title: string;
+  created_at: number;
+  updated_at: number;
+  messages: ChatMessage[];
+  summary: string | null;
+}
+
+export interface SessionInfo {
+  id: number;
+  title: string;
+  message_count: number;
+  created_at: number;
+  updated_at: number;
 }